@@ -10,5 +10,6 @@ mod load_input_data_hash_cell;
 mod non_contextual_block_txs_verify;
 mod reward;
 mod truncate;
+mod tx_pool;
 mod uncle;
 mod util;
@@ -0,0 +1,253 @@
+use crate::tests::dep_cell::build_tx;
+use crate::tests::util::start_chain_with_relay_receiver;
+use ckb_app_config::{ConsumedDepPolicy, TxPoolConfig};
+use ckb_shared::Shared;
+use ckb_store::ChainStore;
+use ckb_tx_pool::service::TxVerificationResult;
+use ckb_types::{
+    core::{
+        tx_pool::{RBFRule, TxStatus},
+        Capacity, TransactionView,
+    },
+    packed::{Block, CellDep, OutPoint},
+    prelude::*,
+};
+use ckb_verification_traits::Switch;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// the txs of the genesis block of the default test chain, the first one holds the always success
+// cell, each of the others one output spendable with it
+fn genesis_txs(shared: &Shared) -> Vec<TransactionView> {
+    let snapshot = shared.snapshot();
+    let hash = snapshot.get_block_hash(0).unwrap();
+    snapshot.get_block(&hash).unwrap().transactions()
+}
+
+// a tx spending the output of the genesis tx at `index`
+fn spend_genesis(shared: &Shared, index: usize, fee: Capacity) -> TransactionView {
+    let txs = genesis_txs(shared);
+    build_tx((&txs[index], &[0]), (&txs[0], &[0]), 1, fee)
+}
+
+fn is_relayed(result: TxVerificationResult, tx: &TransactionView) -> bool {
+    matches!(
+        result,
+        TxVerificationResult::Ok { original_peer: None, tx_hash, .. } if tx_hash == tx.hash()
+    )
+}
+
+// polls the status of the tx until `expected` returns true, the last status is returned
+fn wait_tx_status(
+    shared: &Shared,
+    tx: &TransactionView,
+    expected: impl Fn(&TxStatus) -> bool,
+) -> TxStatus {
+    let tx_pool = shared.tx_pool_controller();
+    let started = Instant::now();
+    loop {
+        let (status, _) = tx_pool.get_tx_status(tx.hash()).unwrap().unwrap();
+        if expected(&status) || started.elapsed() > Duration::from_secs(10) {
+            return status;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn test_rebroadcast_local_txs() {
+    let config = TxPoolConfig {
+        rebroadcast_min_age_secs: 1,
+        rebroadcast_batch_size: 10,
+        ..Default::default()
+    };
+    let (_chain_controller, shared, _parent, relay_receiver) =
+        start_chain_with_relay_receiver(None, config);
+    let tx = spend_genesis(&shared, 1, Capacity::shannons(1000));
+    let tx_pool = shared.tx_pool_controller();
+    tx_pool.submit_local_tx(tx.clone()).unwrap().unwrap();
+
+    // announced when admitted, then again once older than the min age
+    for _ in 0..2 {
+        let result = relay_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(is_relayed(result, &tx));
+    }
+}
+
+#[test]
+fn test_rebroadcast_disabled_by_default() {
+    let (_chain_controller, shared, _parent, relay_receiver) =
+        start_chain_with_relay_receiver(None, TxPoolConfig::default());
+    let tx = spend_genesis(&shared, 1, Capacity::shannons(1000));
+    let tx_pool = shared.tx_pool_controller();
+    tx_pool.submit_local_tx(tx.clone()).unwrap().unwrap();
+
+    let result = relay_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(is_relayed(result, &tx));
+    assert!(relay_receiver.recv_timeout(Duration::from_secs(3)).is_err());
+}
+
+#[test]
+fn test_local_tx_committed_after_proposal_window() {
+    let (chain_controller, shared, _parent, _relay_receiver) =
+        start_chain_with_relay_receiver(None, TxPoolConfig::default());
+    let tx = spend_genesis(&shared, 2, Capacity::shannons(1000));
+    let tx_pool = shared.tx_pool_controller();
+    tx_pool.submit_local_tx(tx.clone()).unwrap().unwrap();
+
+    // the blocks built from the templates are only checked against the two-phase commit rule
+    let switch = Switch::DISABLE_ALL - Switch::DISABLE_TWO_PHASE_COMMIT;
+    let started = Instant::now();
+    let mut proposed_at = None;
+    loop {
+        assert!(started.elapsed() < Duration::from_secs(120));
+        let tip_number = shared.snapshot().tip_number();
+        let block_template = shared
+            .get_block_template(None, None, None)
+            .unwrap()
+            .unwrap();
+        if Into::<u64>::into(block_template.number) != tip_number + 1 {
+            continue;
+        }
+        let block: Block = block_template.into();
+        let block = block.into_view();
+        chain_controller
+            .internal_process_block(Arc::new(block.clone()), switch)
+            .unwrap();
+
+        if block.union_proposal_ids().contains(&tx.proposal_short_id()) {
+            proposed_at.get_or_insert(block.number());
+        }
+        if block
+            .transactions()
+            .iter()
+            .any(|committed| committed.hash() == tx.hash())
+        {
+            let proposed_at = proposed_at.expect("proposed before committed");
+            let closest = shared.consensus().tx_proposal_window().closest();
+            assert!(block.number() >= proposed_at + closest);
+            break;
+        }
+    }
+}
+
+#[test]
+fn test_verifying_tx_status() {
+    // the always success script takes 537 cycles, over the limit the tx is verified in chunks
+    let config = TxPoolConfig {
+        max_tx_verify_cycles: 500,
+        ..Default::default()
+    };
+    let (_chain_controller, shared, _parent, _relay_receiver) =
+        start_chain_with_relay_receiver(None, config);
+    let tx = spend_genesis(&shared, 1, Capacity::shannons(1000));
+    let tx_pool = shared.tx_pool_controller();
+    tx_pool.suspend_chunk_process().unwrap();
+    thread::sleep(Duration::from_millis(100));
+    tx_pool.notify_txs(vec![tx.clone()]).unwrap();
+
+    let status = wait_tx_status(&shared, &tx, |status| status != &TxStatus::Unknown);
+    assert_eq!(status, TxStatus::Verifying);
+    let with_status = tx_pool
+        .get_transaction_with_status(tx.hash())
+        .unwrap()
+        .unwrap();
+    assert_eq!(with_status.tx_status, TxStatus::Verifying);
+    assert_eq!(
+        with_status.transaction.map(|verifying| verifying.hash()),
+        Some(tx.hash())
+    );
+
+    // verified, the tx leaves the queue for the pool
+    tx_pool.continue_chunk_process().unwrap();
+    let status = wait_tx_status(&shared, &tx, |status| status != &TxStatus::Verifying);
+    assert_eq!(status, TxStatus::Pending);
+    assert!(tx_pool.pending_verification().unwrap().is_empty());
+}
+
+#[test]
+fn test_acceptance_latency_measured() {
+    let (_chain_controller, shared, _parent, _relay_receiver) =
+        start_chain_with_relay_receiver(None, TxPoolConfig::default());
+    let tx = spend_genesis(&shared, 1, Capacity::shannons(1000));
+    let tx_pool = shared.tx_pool_controller();
+    tx_pool.submit_local_tx(tx).unwrap().unwrap();
+
+    // resolved and verified by the service, the time it took is recorded with the entry
+    let histogram = tx_pool.acceptance_latency_histogram().unwrap();
+    assert_eq!(histogram.measured(), 1);
+    assert_eq!(histogram.unmeasured, 0);
+    assert!(histogram.max > Duration::ZERO);
+    assert_eq!(histogram.total, histogram.max);
+}
+
+#[test]
+fn test_check_rbf_through_service() {
+    let (_chain_controller, shared, _parent, _relay_receiver) =
+        start_chain_with_relay_receiver(None, TxPoolConfig::default());
+    let tx = spend_genesis(&shared, 1, Capacity::shannons(1000));
+    let tx_pool = shared.tx_pool_controller();
+    tx_pool.submit_local_tx(tx).unwrap().unwrap();
+
+    // nothing in the pool to replace
+    let unrelated = spend_genesis(&shared, 2, Capacity::shannons(1000));
+    assert!(tx_pool.check_rbf(unrelated).unwrap().unwrap().is_empty());
+
+    let replacement = spend_genesis(&shared, 1, Capacity::shannons(100_000));
+    let rules = tx_pool.check_rbf(replacement).unwrap().unwrap();
+    assert!(!rules.is_empty());
+    assert!(rules.iter().all(|(_, passed)| *passed));
+
+    // pays for the replaced tx, not for the relay of the replacement, the pool is untouched
+    let underpaying = spend_genesis(&shared, 1, Capacity::shannons(1001));
+    let rules = tx_pool.check_rbf(underpaying).unwrap().unwrap();
+    assert_eq!(rules.last(), Some(&(RBFRule::PaysMinRbfFee, false)));
+    assert_eq!(tx_pool.get_tx_pool_info().unwrap().pending_size, 1);
+}
+
+#[test]
+fn test_evict_dep_consumers_through_service() {
+    let config = TxPoolConfig {
+        consumed_dep_policy: ConsumedDepPolicy::Evict,
+        ..Default::default()
+    };
+    let (_chain_controller, shared, _parent, _relay_receiver) =
+        start_chain_with_relay_receiver(None, config);
+    let txs = genesis_txs(&shared);
+    // depends on the output spent by `spender`
+    let consumer = spend_genesis(&shared, 1, Capacity::shannons(1000))
+        .as_advanced_builder()
+        .cell_dep(
+            CellDep::new_builder()
+                .out_point(OutPoint::new(txs[2].hash(), 0))
+                .build(),
+        )
+        .build();
+    let spender = spend_genesis(&shared, 2, Capacity::shannons(100_000));
+    let tx_pool = shared.tx_pool_controller();
+    tx_pool.submit_local_tx(consumer.clone()).unwrap().unwrap();
+    tx_pool.submit_local_tx(spender.clone()).unwrap().unwrap();
+
+    // the reject callback took the evicted tx off the totals, once
+    let entries = tx_pool.get_all_entry_info().unwrap();
+    assert!(!entries.pending.contains_key(&consumer.hash()));
+    let entry = &entries.pending[&spender.hash()];
+    let info = tx_pool.get_tx_pool_info().unwrap();
+    assert_eq!(info.pending_size, 1);
+    assert_eq!(info.total_tx_size as u64, entry.size);
+    assert_eq!(info.total_tx_cycles, entry.cycles);
+}
+
+#[test]
+fn test_entry_info_reports_tip_hash() {
+    let (_chain_controller, shared, _parent, _relay_receiver) =
+        start_chain_with_relay_receiver(None, TxPoolConfig::default());
+    let tx = spend_genesis(&shared, 1, Capacity::shannons(1000));
+    let tx_pool = shared.tx_pool_controller();
+    tx_pool.submit_local_tx(tx.clone()).unwrap().unwrap();
+
+    let info = tx_pool.get_all_entry_info().unwrap();
+    assert_eq!(info.tip_hash, shared.snapshot().tip_hash());
+    assert!(info.pending.contains_key(&tx.hash()));
+}
@@ -2,6 +2,7 @@ use crate::chain::{ChainController, ChainService};
 use ckb_app_config::TxPoolConfig;
 use ckb_app_config::{BlockAssemblerConfig, NetworkConfig};
 use ckb_chain_spec::consensus::{Consensus, ConsensusBuilder};
+use ckb_channel::Receiver;
 use ckb_dao::DaoCalculator;
 use ckb_dao_utils::genesis_dao_data;
 use ckb_jsonrpc_types::ScriptHashType;
@@ -13,6 +14,7 @@ pub use ckb_test_chain_utils::MockStore;
 use ckb_test_chain_utils::{
     always_success_cell, load_input_data_hash_cell, load_input_one_byte_cell,
 };
+use ckb_tx_pool::service::TxVerificationResult;
 use ckb_types::prelude::*;
 use ckb_types::{
     bytes::Bytes,
@@ -86,6 +88,22 @@ pub(crate) fn start_chain_with_tx_pool_config(
     consensus: Option<Consensus>,
     tx_pool_config: TxPoolConfig,
 ) -> (ChainController, Shared, HeaderView) {
+    let (chain_controller, shared, parent, _) =
+        start_chain_with_relay_receiver(consensus, tx_pool_config);
+    (chain_controller, shared, parent)
+}
+
+/// Starts the chain like `start_chain_with_tx_pool_config`, returns the channel on which the
+/// tx-pool reports the txs to relay as well
+pub(crate) fn start_chain_with_relay_receiver(
+    consensus: Option<Consensus>,
+    tx_pool_config: TxPoolConfig,
+) -> (
+    ChainController,
+    Shared,
+    HeaderView,
+    Receiver<TxVerificationResult>,
+) {
     let builder = SharedBuilder::with_temp_db();
     let (_, _, always_success_script) = always_success_cell();
     let consensus = consensus.unwrap_or_else(|| {
@@ -141,6 +159,7 @@ pub(crate) fn start_chain_with_tx_pool_config(
         .unwrap();
     let network = dummy_network(&shared);
     pack.take_tx_pool_builder().start(network);
+    let relay_receiver = pack.take_relay_tx_receiver();
 
     let chain_service = ChainService::new(shared.clone(), pack.take_proposal_table());
     let chain_controller = chain_service.start::<&str>(None);
@@ -152,7 +171,7 @@ pub(crate) fn start_chain_with_tx_pool_config(
             .unwrap()
     };
 
-    (chain_controller, shared, parent)
+    (chain_controller, shared, parent, relay_receiver)
 }
 
 pub(crate) fn calculate_reward(
@@ -114,6 +114,12 @@ pub enum RPCError {
     PoolRejectedTransactionBySizeLimit = -1110,
     /// (-1111): The transaction is rejected for RBF checking.
     PoolRejectedRBF = -1111,
+    /// (-1112): The transaction is added as proposed but the recent blocks did not propose it.
+    PoolRejectedNotProposed = -1112,
+    /// (-1113): The pool is no longer pinned at the requested sequence number, the view expired.
+    PoolViewExpired = -1113,
+    /// (-1114): The tx-pool is read-only for maintenance, the transaction can be sent again later.
+    PoolInMaintenance = -1114,
     /// (-1200): The indexer error.
     Indexer = -1200,
 }
@@ -169,17 +175,30 @@ impl RPCError {
             Reject::ExceededMaximumAncestorsCount => {
                 RPCError::PoolRejectedTransactionByMaxAncestorsCountLimit
             }
+            Reject::ExceededPackageLimit(..) => {
+                RPCError::PoolRejectedTransactionByMaxAncestorsCountLimit
+            }
             Reject::Full(_) => RPCError::PoolIsFull,
             Reject::Duplicated(_) => RPCError::PoolRejectedDuplicatedTransaction,
             Reject::Malformed(_, _) => RPCError::PoolRejectedMalformedTransaction,
             Reject::DeclaredWrongCycles(..) => RPCError::PoolRejectedMalformedTransaction,
             Reject::Resolve(_) => RPCError::TransactionFailedToResolve,
+            Reject::Conflict { .. } => RPCError::TransactionFailedToResolve,
             Reject::Verification(_) => RPCError::TransactionFailedToVerify,
             Reject::RBFRejected(_) => RPCError::PoolRejectedRBF,
+            Reject::ExceededMaximumHeaderDeps(..) => RPCError::PoolRejectedTransactionBySizeLimit,
             Reject::ExceededTransactionSizeLimit(_, _) => {
                 RPCError::PoolRejectedTransactionBySizeLimit
             }
             Reject::Expiry(_) => RPCError::TransactionExpired,
+            Reject::NotProposed(_) => RPCError::PoolRejectedNotProposed,
+            Reject::VerificationQueueFull(_) => RPCError::PoolIsFull,
+            Reject::RateLimited(_) => RPCError::PoolIsFull,
+            Reject::NonStandard(_) => RPCError::PoolRejectedTransactionByOutputsValidator,
+            Reject::DepConsumed(_) => RPCError::TransactionFailedToResolve,
+            Reject::Maintenance => RPCError::PoolInMaintenance,
+            Reject::ReorgCleared(_) => RPCError::TransactionExpired,
+            Reject::OutputPolicy(_) => RPCError::PoolRejectedTransactionByOutputsValidator,
         };
         RPCError::custom_with_error(code, reject)
     }
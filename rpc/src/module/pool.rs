@@ -1,9 +1,13 @@
 use crate::error::RPCError;
 use ckb_chain_spec::consensus::Consensus;
 use ckb_constant::hardfork::{mainnet, testnet};
-use ckb_jsonrpc_types::{OutputsValidator, RawTxPool, Script, Transaction, TxPoolInfo};
+use ckb_jsonrpc_types::{
+    OutputsValidator, PoolTxFilter, RBFRuleCheck, RawTxPool, Script, Transaction, TxPoolEntry,
+    TxPoolInfo, Uint64,
+};
 use ckb_logger::error;
 use ckb_shared::shared::Shared;
+use ckb_tx_pool::error::ViewError;
 use ckb_types::{core, packed, prelude::*, H256};
 use ckb_verification::{Since, SinceMetric};
 use jsonrpc_core::Result;
@@ -32,6 +36,7 @@ pub trait PoolRpc {
     /// * [`PoolRejectedTransactionByMaxAncestorsCountLimit (-1105)`](../enum.RPCError.html#variant.PoolRejectedTransactionByMaxAncestorsCountLimit) - The ancestors count must be greater than or equal to the config option `tx_pool.max_ancestors_count`.
     /// * [`PoolIsFull (-1106)`](../enum.RPCError.html#variant.PoolIsFull) - Pool is full.
     /// * [`PoolRejectedDuplicatedTransaction (-1107)`](../enum.RPCError.html#variant.PoolRejectedDuplicatedTransaction) - The transaction is already in the pool.
+    /// * [`PoolInMaintenance (-1114)`](../enum.RPCError.html#variant.PoolInMaintenance) - The tx-pool is read-only for maintenance.
     /// * [`TransactionFailedToResolve (-301)`](../enum.RPCError.html#variant.TransactionFailedToResolve) - Failed to resolve the referenced cells and headers used in the transaction, as inputs or dependencies.
     /// * [`TransactionFailedToVerify (-302)`](../enum.RPCError.html#variant.TransactionFailedToVerify) - Failed to verify the transaction.
     ///
@@ -164,13 +169,19 @@ pub trait PoolRpc {
     ///   "id": 42,
     ///   "jsonrpc": "2.0",
     ///   "result": {
+    ///     "gap_quota": { "count": "0x0", "max_count": null, "max_size": null, "size": "0x0" },
     ///     "last_txs_updated_at": "0x0",
     ///     "min_fee_rate": "0x3e8",
     ///     "min_rbf_rate": "0x5dc",
+    ///     "max_tx_pool_entries": "0xf4240",
     ///     "max_tx_pool_size": "0xaba9500",
     ///     "orphan": "0x0",
     ///     "pending": "0x1",
+    ///     "pending_quota": { "count": "0x1", "max_count": null, "max_size": null, "size": "0x112" },
     ///     "proposed": "0x0",
+    ///     "proposed_quota": { "count": "0x0", "max_count": null, "max_size": null, "size": "0x0" },
+    ///     "read_only": false,
+    ///     "seq": "0x1",
     ///     "tip_hash": "0xa5f5c85987a15de25661e5a214f2c1449cd803f071acc7999820f25246471f40",
     ///     "tip_number": "0x400",
     ///     "total_tx_cycles": "0x219",
@@ -210,9 +221,15 @@ pub trait PoolRpc {
     fn clear_tx_pool(&self) -> Result<()>;
 
     /// Returns all transaction ids in tx pool as a json array of string transaction ids.
+    ///
+    /// The result carries the sequence number of the last pool membership change it was read at.
+    /// The plain ids also pin the pool at that number, see `get_pool_entries_at`.
+    ///
     /// ## Params
     ///
     /// * `verbose` - True for a json object, false for array of transaction ids, default=false
+    /// * `annotated` - When `verbose` is false, true to return each transaction id along with its
+    ///   fee, size and fee rate under an `annotated` field, default=false
     ///
     /// ## Examples
     ///
@@ -240,18 +257,227 @@ pub trait PoolRpc {
     ///                "cycles": "0x219",
     ///                "size": "0x112",
     ///                "fee": "0x16923f7dcf",
+    ///                "weight": "0x112",
     ///                "ancestors_size": "0x112",
     ///                "ancestors_cycles": "0x219",
     ///                "ancestors_count": "0x1",
-    ///                "timestamp": "0x17c983e6e44"
+    ///                "timestamp": "0x17c983e6e44",
+    ///                "double_spend_attempts": "0x0",
+    ///                "double_spend_tx_hashes": []
     ///            }
     ///        },
-    ///        "proposed": {}
+    ///        "proposed": {},
+    ///        "seq": "0x1",
+    ///        "tip_hash": "0xa5f5c85987a15de25661e5a214f2c1449cd803f071acc7999820f25246471f40"
     ///    }
     /// }
     /// ```
     #[rpc(name = "get_raw_tx_pool")]
-    fn get_raw_tx_pool(&self, verbose: Option<bool>) -> Result<RawTxPool>;
+    fn get_raw_tx_pool(&self, verbose: Option<bool>, annotated: Option<bool>) -> Result<RawTxPool>;
+
+    /// Returns a page of the ids of the transactions in tx pool.
+    ///
+    /// The ids are ordered by the score the pool ranks the transactions with, highest first, so
+    /// the pages of a pool which doesn't change neither overlap nor miss any transaction.
+    ///
+    /// ## Params
+    ///
+    /// * `filter` - Which transactions the ids are taken from
+    /// * `offset` - The number of ids skipped
+    /// * `limit` - The maximum number of ids returned
+    ///
+    /// ## Examples
+    ///
+    /// Request
+    ///
+    /// ```json
+    /// {
+    ///   "id": 42,
+    ///   "jsonrpc": "2.0",
+    ///   "method": "get_tx_pool_ids",
+    ///   "params": ["pending", "0x0", "0x10"]
+    /// }
+    /// ```
+    ///
+    /// Response
+    ///
+    /// ```json
+    /// {
+    ///   "id": 42,
+    ///   "jsonrpc": "2.0",
+    ///   "result": [
+    ///     "0xa0ef4eb5f4ceeb08a4c8524d84c5da95dce2f608e0ca2ec8091191b0f330c6e3"
+    ///   ]
+    /// }
+    /// ```
+    #[rpc(name = "get_tx_pool_ids")]
+    fn get_tx_pool_ids(
+        &self,
+        filter: PoolTxFilter,
+        offset: Uint64,
+        limit: Uint64,
+    ) -> Result<Vec<H256>>;
+
+    /// Returns the entries of the transactions as they were when the pool was at the sequence
+    /// number `seq`.
+    ///
+    /// The ids returned by `get_raw_tx_pool` pin the pool at the sequence number they carry, the
+    /// entries looked up at that number are consistent with the ids whatever happened to the pool
+    /// in between, until the pin is older than the config option `tx_pool.max_view_age_millis`.
+    ///
+    /// ## Params
+    ///
+    /// * `seq` - The sequence number the pool was pinned at
+    /// * `tx_hashes` - The hashes of the transactions
+    ///
+    /// ## Returns
+    ///
+    /// The entry of each transaction in the order of `tx_hashes`, null for the ones which were not
+    /// in the pool.
+    ///
+    /// ## Errors
+    ///
+    /// * [`PoolViewExpired (-1113)`](../enum.RPCError.html#variant.PoolViewExpired) - The pool is
+    ///   no longer pinned at `seq`, the ids have to be read again.
+    ///
+    /// ## Examples
+    ///
+    /// Request
+    ///
+    /// ```json
+    /// {
+    ///   "id": 42,
+    ///   "jsonrpc": "2.0",
+    ///   "method": "get_pool_entries_at",
+    ///   "params": [
+    ///     "0x1",
+    ///     [
+    ///       "0xa0ef4eb5f4ceeb08a4c8524d84c5da95dce2f608e0ca2ec8091191b0f330c6e3",
+    ///       "0x0000000000000000000000000000000000000000000000000000000000000000"
+    ///     ]
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// Response
+    ///
+    /// ```json
+    /// {
+    ///   "id": 42,
+    ///   "jsonrpc": "2.0",
+    ///   "result": [
+    ///     {
+    ///       "cycles": "0x219",
+    ///       "size": "0x112",
+    ///       "fee": "0x16923f7dcf",
+    ///       "weight": "0x112",
+    ///       "ancestors_size": "0x112",
+    ///       "ancestors_cycles": "0x219",
+    ///       "ancestors_count": "0x1",
+    ///       "timestamp": "0x17c983e6e44",
+    ///       "double_spend_attempts": "0x0",
+    ///       "double_spend_tx_hashes": []
+    ///     },
+    ///     null
+    ///   ]
+    /// }
+    /// ```
+    #[rpc(name = "get_pool_entries_at")]
+    fn get_pool_entries_at(
+        &self,
+        seq: Uint64,
+        tx_hashes: Vec<H256>,
+    ) -> Result<Vec<Option<TxPoolEntry>>>;
+
+    /// Checks whether a transaction could replace the transactions it conflicts with in the pool,
+    /// without submitting it.
+    ///
+    /// The replace-by-fee rules are checked in order and the first one failing stops the check.
+    ///
+    /// ## Params
+    ///
+    /// * `tx` - The replacement transaction.
+    ///
+    /// ## Returns
+    ///
+    /// The rules checked with whether each one passed, ending with the failing rule if any.
+    /// Empty if the transaction conflicts with no transaction in the pool.
+    ///
+    /// ## Errors
+    ///
+    /// * [`PoolRejectedRBF (-1111)`](../enum.RPCError.html#variant.PoolRejectedRBF) - RBF is
+    ///   disabled.
+    /// * [`PoolRejectedDuplicatedTransaction (-1107)`](../enum.RPCError.html#variant.PoolRejectedDuplicatedTransaction) -
+    ///   The transaction is already in the pool.
+    /// * [`TransactionFailedToResolve (-301)`](../enum.RPCError.html#variant.TransactionFailedToResolve) -
+    ///   The transaction inputs or dependencies can't be resolved.
+    /// * [`PoolRejectedTransactionByMinFeeRate (-1104)`](../enum.RPCError.html#variant.PoolRejectedTransactionByMinFeeRate) -
+    ///   The transaction fee rate is lower than `min_fee_rate`.
+    ///
+    /// ## Examples
+    ///
+    /// Request
+    ///
+    /// ```json
+    /// {
+    ///   "id": 42,
+    ///   "jsonrpc": "2.0",
+    ///   "method": "check_tx_replacement",
+    ///   "params": [
+    ///     {
+    ///       "cell_deps": [
+    ///         {
+    ///           "dep_type": "code",
+    ///           "out_point": {
+    ///             "index": "0x0",
+    ///             "tx_hash": "0xa4037a893eb48e18ed4ef61034ce26eba9c585f15c9cee102ae58505565eccc3"
+    ///           }
+    ///         }
+    ///       ],
+    ///       "header_deps": [
+    ///         "0x7978ec7ce5b507cfb52e149e36b1a23f6062ed150503c85bbf825da3599095ed"
+    ///       ],
+    ///       "inputs": [
+    ///         {
+    ///           "previous_output": {
+    ///             "index": "0x1",
+    ///             "tx_hash": "0x365698b50ca0da75dca2c87f9e7b563811d3b5813736b8cc62cc3b106faceb17"
+    ///           },
+    ///           "since": "0x0"
+    ///         }
+    ///       ],
+    ///       "outputs": [
+    ///         {
+    ///           "capacity": "0x2540be400",
+    ///           "lock": {
+    ///             "code_hash": "0x28e83a1277d48add8e72fadaa9248559e1b632bab2bd60b27955ebc4c03800a5",
+    ///             "hash_type": "data",
+    ///             "args": "0x"
+    ///           },
+    ///           "type": null
+    ///         }
+    ///       ],
+    ///       "outputs_data": [
+    ///         "0x"
+    ///       ],
+    ///       "version": "0x0",
+    ///       "witnesses": []
+    ///     }
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// Response
+    ///
+    /// ```json
+    /// {
+    ///   "id": 42,
+    ///   "jsonrpc": "2.0",
+    ///   "result": []
+    /// }
+    /// ```
+    #[rpc(name = "check_tx_replacement")]
+    fn check_tx_replacement(&self, tx: Transaction) -> Result<Vec<RBFRuleCheck>>;
 
     /// Returns whether tx-pool service is started, ready for request.
     ///
@@ -466,7 +692,7 @@ impl PoolRpc for PoolRpcImpl {
         Ok(())
     }
 
-    fn get_raw_tx_pool(&self, verbose: Option<bool>) -> Result<RawTxPool> {
+    fn get_raw_tx_pool(&self, verbose: Option<bool>, annotated: Option<bool>) -> Result<RawTxPool> {
         let tx_pool = self.shared.tx_pool_controller();
 
         let raw = if verbose.unwrap_or(false) {
@@ -474,14 +700,74 @@ impl PoolRpc for PoolRpcImpl {
                 .get_all_entry_info()
                 .map_err(|err| RPCError::custom(RPCError::CKBInternalError, err.to_string()))?;
             RawTxPool::Verbose(info.into())
-        } else {
+        } else if annotated.unwrap_or(false) {
             let ids = tx_pool
-                .get_all_ids()
+                .get_all_annotated_ids()
                 .map_err(|err| RPCError::custom(RPCError::CKBInternalError, err.to_string()))?;
-            RawTxPool::Ids(ids.into())
+            RawTxPool::Annotated {
+                annotated: ids.into(),
+            }
+        } else {
+            // pins the pool at the seq of the ids, for `get_pool_entries_at`
+            let view = tx_pool
+                .consistent_view()
+                .map_err(|err| RPCError::custom(RPCError::CKBInternalError, err.to_string()))?;
+            let ids = view
+                .get_ids()
+                .map_err(|err| RPCError::custom(RPCError::PoolViewExpired, err.to_string()))?;
+            RawTxPool::Ids(ids.clone().into())
         };
         Ok(raw)
     }
+
+    fn get_tx_pool_ids(
+        &self,
+        filter: PoolTxFilter,
+        offset: Uint64,
+        limit: Uint64,
+    ) -> Result<Vec<H256>> {
+        let offset = usize::try_from(offset.value()).unwrap_or(usize::MAX);
+        let limit = usize::try_from(limit.value()).unwrap_or(usize::MAX);
+        let ids = self
+            .shared
+            .tx_pool_controller()
+            .get_ids_paginated(filter.into(), offset, limit)
+            .map_err(|err| RPCError::custom(RPCError::CKBInternalError, err.to_string()))?;
+        Ok(ids.iter().map(Unpack::unpack).collect())
+    }
+
+    fn get_pool_entries_at(
+        &self,
+        seq: Uint64,
+        tx_hashes: Vec<H256>,
+    ) -> Result<Vec<Option<TxPoolEntry>>> {
+        let expired = |err: ViewError| RPCError::custom(RPCError::PoolViewExpired, err.to_string());
+        let view = self
+            .shared
+            .tx_pool_controller()
+            .view_at(seq.value())
+            .map_err(|err| RPCError::custom(RPCError::CKBInternalError, err.to_string()))?
+            .map_err(expired)?;
+        tx_hashes
+            .iter()
+            .map(|tx_hash| {
+                view.get_entry_info(&tx_hash.pack())
+                    .map(|info| info.map(Into::into))
+                    .map_err(expired)
+            })
+            .collect()
+    }
+
+    fn check_tx_replacement(&self, tx: Transaction) -> Result<Vec<RBFRuleCheck>> {
+        let tx: packed::Transaction = tx.into();
+        let rules = self
+            .shared
+            .tx_pool_controller()
+            .check_rbf(tx.into_view())
+            .map_err(|err| RPCError::custom(RPCError::CKBInternalError, err.to_string()))?
+            .map_err(|reject| RPCError::from_submit_transaction_reject(&reject))?;
+        Ok(rules.into_iter().map(Into::into).collect())
+    }
 }
 
 pub(crate) struct WellKnownScriptsOnlyValidator<'a> {
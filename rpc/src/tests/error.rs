@@ -58,6 +58,12 @@ fn test_submit_transaction_error() {
         "PoolRejectedTransactionBySizeLimit: Transaction size 10 exceeded maximum limit 9",
         RPCError::from_submit_transaction_reject(&reject).message
     );
+
+    let reject = Reject::Maintenance;
+    assert_eq!(
+        "PoolInMaintenance: The tx-pool is in maintenance mode, try send it later",
+        RPCError::from_submit_transaction_reject(&reject).message
+    );
 }
 
 #[test]
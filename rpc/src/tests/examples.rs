@@ -633,7 +633,8 @@ fn mock_rpc_response(example: &RpcTestExample, response: &mut RpcTestResponse) {
         }
         "tx_pool_info" => {
             response.result["last_txs_updated_at"] =
-                example.response.result["last_txs_updated_at"].clone()
+                example.response.result["last_txs_updated_at"].clone();
+            response.result["seq"] = example.response.result["seq"].clone()
         }
         "get_blockchain_info" => {
             response.result["chain"] = example.response.result["chain"].clone()
@@ -641,7 +642,12 @@ fn mock_rpc_response(example: &RpcTestExample, response: &mut RpcTestResponse) {
         "send_alert" => response.error["data"] = example.response.error["data"].clone(),
         "get_raw_tx_pool" => {
             response.result["pending"][example_tx_hash.as_str()]["timestamp"] =
-                example.response.result["pending"][example_tx_hash.as_str()]["timestamp"].clone()
+                example.response.result["pending"][example_tx_hash.as_str()]["timestamp"].clone();
+            response.result["tip_hash"] = example.response.result["tip_hash"].clone();
+            response.result["seq"] = example.response.result["seq"].clone()
+        }
+        "get_pool_entries_at" => {
+            response.result[0]["timestamp"] = example.response.result[0]["timestamp"].clone()
         }
         "generate_block_with_template" => replace_rpc_response::<H256>(example, response),
         "generate_block" => replace_rpc_response::<H256>(example, response),
@@ -672,6 +678,16 @@ fn before_rpc_example(suite: &RpcTestSuite, example: &mut RpcTestExample) -> boo
         ("notify_transaction", 42) => return false,
         ("truncate", 42) => return false,
         ("get_block_template", 42) => suite.wait_block_template_update(),
+        ("get_pool_entries_at", 42) => {
+            // pins the pool at its current seq, the example one is only valid on a fresh pool
+            let ids = suite.rpc(&RpcTestRequest {
+                id: 42,
+                jsonrpc: "2.0".to_string(),
+                method: "get_raw_tx_pool".to_string(),
+                params: vec![],
+            });
+            example.request.params[0] = ids.result["seq"].clone();
+        }
         _ => return true,
     }
 
@@ -209,9 +209,9 @@ impl RpcClient {
             .expect("rpc call remove_transaction")
     }
 
-    pub fn get_raw_tx_pool(&self, verbose: Option<bool>) -> RawTxPool {
+    pub fn get_raw_tx_pool(&self, verbose: Option<bool>, annotated: Option<bool>) -> RawTxPool {
         self.inner
-            .get_raw_tx_pool(verbose)
+            .get_raw_tx_pool(verbose, annotated)
             .expect("rpc call get_raw_tx_pool")
     }
 
@@ -340,7 +340,7 @@ jsonrpc!(pub struct Inner {
     pub fn send_transaction(&self, tx: Transaction, outputs_validator: Option<String>) -> H256;
     pub fn remove_transaction(&self, tx_hash: H256) -> bool;
     pub fn tx_pool_info(&self) -> TxPoolInfo;
-    pub fn get_raw_tx_pool(&self, verbose: Option<bool>) -> RawTxPool;
+    pub fn get_raw_tx_pool(&self, verbose: Option<bool>, annotated: Option<bool>) -> RawTxPool;
 
     pub fn send_alert(&self, alert: Alert) -> ();
 
@@ -246,7 +246,7 @@ impl Spec for TransactionRelayConflict {
             .err()
             .unwrap()
             .to_string()
-            .contains("TransactionFailedToResolve: Resolve failed Dead"));
+            .contains("TransactionFailedToResolve: Conflict with in-pool transactions"));
 
         let relayed = wait_until(20, || {
             [tx1.hash()].iter().all(|hash| {
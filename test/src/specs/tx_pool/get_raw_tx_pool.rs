@@ -23,7 +23,7 @@ impl Spec for GetRawTxPool {
 
         let mut pending: Vec<H256> = txs_hash.iter().map(Unpack::unpack).collect();
         pending.sort();
-        let result = node0.rpc_client().get_raw_tx_pool(None);
+        let result = node0.rpc_client().get_raw_tx_pool(None, None);
         match result {
             RawTxPool::Ids(ids) => {
                 assert_eq!(0, ids.proposed.len());
@@ -33,13 +33,19 @@ impl Spec for GetRawTxPool {
             }
             _ => panic!("get_raw_tx_pool(true) should return entries"),
         }
-        match node0.rpc_client().get_raw_tx_pool(Some(true)) {
-            RawTxPool::Ids(_ids) => {
-                panic!("get_raw_tx_pool(true) should return entries");
-            }
+        match node0.rpc_client().get_raw_tx_pool(Some(true), None) {
             RawTxPool::Verbose(entries) => {
                 assert_eq!(6, entries.pending.len());
             }
+            _ => panic!("get_raw_tx_pool(true) should return entries"),
+        }
+        match node0.rpc_client().get_raw_tx_pool(None, Some(true)) {
+            RawTxPool::Annotated { annotated } => {
+                let mut ids: Vec<H256> = annotated.pending.into_iter().map(|id| id.hash).collect();
+                ids.sort();
+                assert_eq!(ids, pending);
+            }
+            _ => panic!("get_raw_tx_pool(false, true) should return annotated ids"),
         }
     }
 }
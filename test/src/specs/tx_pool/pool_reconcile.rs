@@ -170,7 +170,7 @@ impl Spec for PoolResolveConflictAfterReorg {
             .send_transaction_result(conflict_tx.data().into());
         assert!(ret.is_err());
         let err_msg = ret.err().unwrap().to_string();
-        assert!(err_msg.contains("Resolve failed Dead"));
+        assert!(err_msg.contains("Conflict with in-pool transactions"));
     }
 
     fn modify_app_config(&self, config: &mut ckb_app_config::CKBAppConfig) {
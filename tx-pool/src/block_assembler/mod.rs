@@ -193,6 +193,7 @@ impl BlockAssembler {
             let max_block_cycles = consensus.max_block_cycles();
             let (txs, _txs_size, _cycles) =
                 tx_pool_reader.package_txs(max_block_cycles, txs_size_limit);
+            tx_pool_reader.template_built(&txs, txs_size_limit);
             (proposals, txs, basic_size)
         };
 
@@ -402,8 +403,10 @@ impl BlockAssembler {
             }
 
             let max_block_cycles = consensus.max_block_cycles();
-            let (txs, _txs_size, _cycles) = tx_pool_reader
-                .package_txs(max_block_cycles, txs_size_limit.expect("overflow checked"));
+            let txs_size_limit = txs_size_limit.expect("overflow checked");
+            let (txs, _txs_size, _cycles) =
+                tx_pool_reader.package_txs(max_block_cycles, txs_size_limit);
+            tx_pool_reader.template_built(&txs, txs_size_limit);
             txs
         };
 
@@ -1,6 +1,7 @@
 use super::component::TxEntry;
 use crate::error::Reject;
 use crate::pool::TxPool;
+use ckb_types::{core::TransactionView, packed::ProposalShortId};
 
 /// Callback boxed fn pointer wrapper
 pub type Callback = Box<dyn Fn(&mut TxPool, &TxEntry) + Sync + Send>;
@@ -8,6 +9,11 @@ pub type Callback = Box<dyn Fn(&mut TxPool, &TxEntry) + Sync + Send>;
 pub type ProposedCallback = Box<dyn Fn(&mut TxPool, &TxEntry, bool) + Sync + Send>;
 /// Reject Callback boxed fn pointer wrapper
 pub type RejectCallback = Box<dyn Fn(&mut TxPool, &TxEntry, Reject) + Sync + Send>;
+/// Pool miss Callback boxed fn pointer wrapper
+pub type PoolMissCallback = Box<dyn Fn(&ProposalShortId) + Sync + Send>;
+/// Double spend Callback boxed fn pointer wrapper, called with the pool clock's now in
+/// milliseconds
+pub type DoubleSpendCallback = Box<dyn Fn(&TransactionView, Reject, u64) + Sync + Send>;
 
 /// Struct hold callbacks
 pub struct Callbacks {
@@ -15,6 +21,8 @@ pub struct Callbacks {
     pub(crate) proposed: Option<ProposedCallback>,
     pub(crate) committed: Option<Callback>,
     pub(crate) reject: Option<RejectCallback>,
+    pub(crate) pool_miss: Option<PoolMissCallback>,
+    pub(crate) double_spend: Option<DoubleSpendCallback>,
 }
 
 impl Default for Callbacks {
@@ -31,6 +39,8 @@ impl Callbacks {
             proposed: None,
             committed: None,
             reject: None,
+            pool_miss: None,
+            double_spend: None,
         }
     }
 
@@ -54,6 +64,16 @@ impl Callbacks {
         self.reject = Some(callback);
     }
 
+    /// Register a new pool miss callback
+    pub fn register_pool_miss(&mut self, callback: PoolMissCallback) {
+        self.pool_miss = Some(callback);
+    }
+
+    /// Register a new double spend callback
+    pub fn register_double_spend(&mut self, callback: DoubleSpendCallback) {
+        self.double_spend = Some(callback);
+    }
+
     /// Call on after pending
     pub fn call_pending(&self, tx_pool: &mut TxPool, entry: &TxEntry) {
         if let Some(call) = &self.pending {
@@ -81,4 +101,18 @@ impl Callbacks {
             call(tx_pool, entry, reject)
         }
     }
+
+    /// Call on a tx lookup falling back to the store
+    pub fn call_pool_miss(&self, proposal_id: &ProposalShortId) {
+        if let Some(call) = &self.pool_miss {
+            call(proposal_id)
+        }
+    }
+
+    /// Call on a tx rejected at `now_ms` for double spending pool entries it could not replace
+    pub fn call_double_spend(&self, tx: &TransactionView, reject: Reject, now_ms: u64) {
+        if let Some(call) = &self.double_spend {
+            call(tx, reject, now_ms)
+        }
+    }
 }
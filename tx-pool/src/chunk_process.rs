@@ -1,6 +1,8 @@
-use crate::component::chunk::Entry;
+use crate::component::chunk::{ChunkProgress, Entry};
 use crate::component::entry::TxEntry;
+use crate::component::trace::TxEvent;
 use crate::try_or_return_with_snapshot;
+use crate::util::origin_of;
 use crate::{error::Reject, service::TxPoolService};
 use ckb_chain_spec::consensus::Consensus;
 use ckb_error::Error;
@@ -14,7 +16,7 @@ use ckb_types::{
 };
 use ckb_verification::cache::TxVerificationCache;
 use ckb_verification::{
-    cache::{CacheEntry, Completed},
+    cache::{CacheEntry, Completed, ForkContext},
     ContextualWithoutScriptTransactionVerifier, DaoScriptSizeVerifier, ScriptError, ScriptVerifier,
     ScriptVerifyResult, ScriptVerifyState, TimeRelativeTransactionVerifier, TransactionSnapshot,
     TxVerifyEnv,
@@ -127,6 +129,7 @@ impl ChunkProcess {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn loop_resume<
         DL: CellDataProvider + HeaderProvider + ExtensionProvider + Send + Sync + Clone + 'static,
     >(
@@ -137,10 +140,16 @@ impl ChunkProcess {
         max_cycles: Cycle,
         consensus: Arc<Consensus>,
         tx_env: Arc<TxVerifyEnv>,
+        progress: &ChunkProgress,
     ) -> Result<State, Reject> {
         let script_verifier = ScriptVerifier::new(rtx, data_loader, consensus, tx_env);
         let mut tmp_state: Option<ScriptVerifyState> = None;
 
+        // progress is counted in steps of MIN_STEP_CYCLE, the total assumes the worst case
+        let chunks_total = max_cycles.saturating_add(MIN_STEP_CYCLE - 1) / MIN_STEP_CYCLE;
+        let resumed_cycles = init_snap.as_ref().map_or(0, |snap| snap.current_cycles);
+        progress.update(resumed_cycles / MIN_STEP_CYCLE, chunks_total);
+
         let completed: Cycle = loop {
             if self.signal.is_cancelled() {
                 return Ok(State::Stopped);
@@ -202,6 +211,7 @@ impl ChunkProcess {
                         );
                         return Err(Reject::Verification(error));
                     }
+                    progress.update(state.current_cycles / MIN_STEP_CYCLE, chunks_total);
                     tmp_state = Some(state);
                 }
             }
@@ -214,10 +224,15 @@ impl ChunkProcess {
         &mut self,
         entry: Entry,
     ) -> Option<(Result<Stop, Reject>, Arc<Snapshot>)> {
-        let Entry { tx, remote } = entry;
+        let Entry {
+            tx,
+            remote,
+            progress,
+            ..
+        } = entry;
         let tx_hash = tx.hash();
 
-        let (ret, snapshot) = self.service.pre_check(&tx).await;
+        let (ret, snapshot) = self.service.pre_check(&tx, origin_of(&remote)).await;
         let (tip_hash, rtx, status, fee, tx_size, conflicts) =
             try_or_return_with_snapshot!(ret, snapshot);
 
@@ -231,7 +246,7 @@ impl ChunkProcess {
 
         if let Some(ref cached) = cached {
             match cached {
-                CacheEntry::Completed(completed) => {
+                CacheEntry::Completed(completed) | CacheEntry::Prewarmed(completed, _) => {
                     let ret = TimeRelativeTransactionVerifier::new(
                         Arc::clone(&rtx),
                         Arc::clone(&consensus),
@@ -243,7 +258,15 @@ impl ChunkProcess {
                     .map_err(Reject::Verification);
                     let completed = try_or_return_with_snapshot!(ret, snapshot);
 
-                    let entry = TxEntry::new(rtx, completed.cycles, fee, tx_size);
+                    let entry = TxEntry::new_with_timestamp(
+                        rtx,
+                        completed.cycles,
+                        fee,
+                        tx_size,
+                        self.service.clock.now_ms(),
+                    )
+                    .with_origin(origin_of(&remote))
+                    .with_fork_context(ForkContext::new(&consensus, &tx_env));
                     let (ret, submit_snapshot) = self
                         .service
                         .submit_entry(tip_hash, entry, status, conflicts)
@@ -295,6 +318,7 @@ impl ChunkProcess {
             max_cycles,
             Arc::clone(&consensus),
             Arc::clone(&tx_env),
+            &progress,
         );
         let state = try_or_return_with_snapshot!(ret, snapshot);
 
@@ -311,6 +335,12 @@ impl ChunkProcess {
             }
             State::Completed(cycles) => Completed { cycles, fee },
         };
+        self.service.tracer.record(
+            &tx_hash,
+            TxEvent::Verified {
+                cycles: completed.cycles,
+            },
+        );
 
         if let Some((declared_cycle, _peer)) = remote {
             if declared_cycle != completed.cycles {
@@ -324,12 +354,20 @@ impl ChunkProcess {
             }
         }
 
-        let entry = TxEntry::new(rtx, completed.cycles, fee, tx_size);
+        let entry = TxEntry::new_with_timestamp(
+            rtx,
+            completed.cycles,
+            fee,
+            tx_size,
+            self.service.clock.now_ms(),
+        )
+        .with_origin(origin_of(&remote))
+        .with_fork_context(ForkContext::new(&consensus, &tx_env));
         let (ret, submit_snapshot) = self
             .service
             .submit_entry(tip_hash, entry, status, conflicts)
             .await;
-        try_or_return_with_snapshot!(ret, snapshot);
+        let status = try_or_return_with_snapshot!(ret, snapshot);
 
         self.service.notify_block_assembler(status).await;
 
@@ -0,0 +1,50 @@
+//! Time source of the tx-pool
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Source of the unix timestamps, in milliseconds, the tx-pool reads for entry timestamps,
+/// expiry and re-broadcast
+pub trait Clock: Send + Sync {
+    /// Returns the current unix timestamp in milliseconds
+    fn now_ms(&self) -> u64;
+}
+
+/// The system clock, used by default
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        ckb_systemtime::unix_time_as_millis()
+    }
+}
+
+/// A clock which only moves when it is told to, for tests and replays
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now: AtomicU64,
+}
+
+impl ManualClock {
+    /// Creates a clock stopped at `now`
+    pub fn new(now: u64) -> Self {
+        ManualClock {
+            now: AtomicU64::new(now),
+        }
+    }
+
+    /// Moves the clock to `now`
+    pub fn set(&self, now: u64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    /// Moves the clock forward by `ms` milliseconds
+    pub fn advance(&self, ms: u64) {
+        self.now.fetch_add(ms, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_ms(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
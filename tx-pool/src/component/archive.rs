@@ -0,0 +1,92 @@
+use crate::component::entry::TxEntry;
+use ckb_types::core::FeeRate;
+use ckb_types::packed::ProposalShortId;
+use std::collections::{BTreeMap, HashMap};
+
+/// Bounded archive of the entries evicted by the size limit, kept to be re-admitted once the
+/// pool has room again.
+///
+/// When full, the entry with the lowest fee rate is dropped to make room for a better paying one.
+/// Entries are indexed by fee rate, ties are broken by the order they were archived in.
+#[derive(Debug, Clone)]
+pub(crate) struct EvictedArchive {
+    entries: HashMap<ProposalShortId, (u64, TxEntry)>,
+    by_fee_rate: BTreeMap<(FeeRate, u64), ProposalShortId>,
+    next_seq: u64,
+    capacity: usize,
+}
+
+impl EvictedArchive {
+    pub(crate) fn new(capacity: usize) -> Self {
+        EvictedArchive {
+            entries: HashMap::default(),
+            by_fee_rate: BTreeMap::default(),
+            next_seq: 0,
+            capacity,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn contains_key(&self, id: &ProposalShortId) -> bool {
+        self.entries.contains_key(id)
+    }
+
+    /// Archives `entry`. Returns the entry which didn't fit: `entry` itself if it pays a lower
+    /// fee rate than every archived entry while the archive is full, or the lowest paying one it
+    /// pushed out.
+    pub(crate) fn insert(&mut self, entry: TxEntry) -> Option<TxEntry> {
+        if self.capacity == 0 {
+            return Some(entry);
+        }
+        let id = entry.proposal_short_id();
+        let mut dropped = self.remove(&id);
+        if self.entries.len() >= self.capacity {
+            match self.by_fee_rate.keys().next() {
+                Some((fee_rate, _)) if *fee_rate < entry.fee_rate() => {
+                    dropped = self.pop_lowest();
+                }
+                _ => return Some(entry),
+            }
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.by_fee_rate.insert((entry.fee_rate(), seq), id.clone());
+        self.entries.insert(id, (seq, entry));
+        dropped
+    }
+
+    fn remove(&mut self, id: &ProposalShortId) -> Option<TxEntry> {
+        let (seq, entry) = self.entries.remove(id)?;
+        self.by_fee_rate.remove(&(entry.fee_rate(), seq));
+        Some(entry)
+    }
+
+    fn pop_lowest(&mut self) -> Option<TxEntry> {
+        let (_, id) = self.by_fee_rate.pop_first()?;
+        self.entries.remove(&id).map(|(_, entry)| entry)
+    }
+
+    /// Takes all the archived entries out, highest fee rate first
+    pub(crate) fn take_sorted(&mut self) -> Vec<TxEntry> {
+        let mut entries: Vec<_> = std::mem::take(&mut self.by_fee_rate)
+            .into_values()
+            .rev()
+            .filter_map(|id| self.entries.remove(&id).map(|(_, entry)| entry))
+            .collect();
+        // entries of the same fee rate, the parents first
+        entries.sort_by(|a, b| {
+            b.fee_rate()
+                .cmp(&a.fee_rate())
+                .then_with(|| a.ancestors_count.cmp(&b.ancestors_count))
+        });
+        entries
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.by_fee_rate.clear();
+    }
+}
@@ -0,0 +1,133 @@
+use ckb_types::{core::Capacity, packed::Byte32};
+use std::collections::VecDeque;
+
+/// Max number of changes kept in memory, older ones are dropped
+pub(crate) const DEFAULT_CHANGELOG_CAPACITY: usize = 10_000;
+
+/// Why an entry left the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoveReason {
+    /// Committed in a block
+    Committed,
+    /// Stayed in the pool longer than the expiry
+    Expired,
+    /// Evicted to keep the pool within its size limit
+    Evicted,
+    /// Dropped by a reorg deeper than `reorg_clear_depth`
+    Cleared,
+    /// Spends or depends on a cell consumed by a committed tx
+    Conflicted,
+    /// Depends on a header which was detached
+    InvalidHeaderDep,
+    /// Replaced by a tx paying a higher fee
+    Replaced,
+    /// Moved back from gap or proposed after its proposal was detached, re-added as pending
+    Detached,
+    /// Removed on demand
+    Removed,
+}
+
+/// A pool membership change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolChange {
+    /// A tx was added to the pool
+    Added {
+        /// Transaction hash
+        hash: Byte32,
+        /// Transaction fee
+        fee: Capacity,
+        /// Transaction size
+        size: usize,
+    },
+    /// A tx was removed from the pool
+    Removed {
+        /// Transaction hash
+        hash: Byte32,
+        /// Why it was removed
+        reason: RemoveReason,
+    },
+}
+
+/// The pool changes after a sequence number, see `TxPool::changes_since`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolChanges {
+    /// Changes with their sequence numbers, in order, the next call should start from the last one
+    /// if the limit was hit
+    pub changes: Vec<(u64, PoolChange)>,
+    /// Current sequence number of the pool
+    pub current: u64,
+    /// The requested changes are no longer buffered, the caller must resync from the full pool
+    pub truncated: bool,
+}
+
+/// Bounded log of pool membership changes, each one numbered by a monotonically increasing
+/// sequence number starting from 1.
+pub(crate) struct Changelog {
+    seq: u64,
+    changes: VecDeque<(u64, PoolChange)>,
+    capacity: usize,
+}
+
+impl Changelog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Changelog {
+            seq: 0,
+            changes: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub(crate) fn record_added(&mut self, hash: Byte32, fee: Capacity, size: usize) {
+        self.push(PoolChange::Added { hash, fee, size });
+    }
+
+    pub(crate) fn record_removed(&mut self, hash: Byte32, reason: RemoveReason) {
+        self.push(PoolChange::Removed { hash, reason });
+    }
+
+    fn push(&mut self, change: PoolChange) {
+        self.seq += 1;
+        if self.changes.len() == self.capacity {
+            self.changes.pop_front();
+        }
+        self.changes.push_back((self.seq, change));
+    }
+
+    /// The sequence number of the last change
+    pub(crate) fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Drops all buffered changes, the sequence number moves on so every caller has to resync
+    pub(crate) fn reset(&mut self) {
+        self.seq += 1;
+        self.changes.clear();
+    }
+
+    pub(crate) fn changes_since(&self, seq: u64, limit: usize) -> PoolChanges {
+        let first_retained = self
+            .changes
+            .front()
+            .map(|(seq, _)| *seq)
+            .unwrap_or(self.seq + 1);
+        if seq.saturating_add(1) < first_retained {
+            return PoolChanges {
+                changes: Vec::new(),
+                current: self.seq,
+                truncated: true,
+            };
+        }
+        let changes = self
+            .changes
+            .iter()
+            .skip_while(|(change_seq, _)| *change_seq <= seq)
+            .take(limit)
+            .cloned()
+            .collect();
+        PoolChanges {
+            changes,
+            current: self.seq,
+            truncated: false,
+        }
+    }
+}
@@ -1,17 +1,66 @@
 use ckb_network::PeerIndex;
 use ckb_types::{
     core::{Cycle, TransactionView},
-    packed::ProposalShortId,
+    packed::{Byte32, ProposalShortId},
 };
 use ckb_util::{shrink_to_fit, LinkedHashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 const SHRINK_THRESHOLD: usize = 100;
-pub(crate) const DEFAULT_MAX_CHUNK_TRANSACTIONS: usize = 100;
 
-#[derive(Debug, Clone, Eq)]
+/// A tx waiting for or going through the chunked verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyingTx {
+    /// Transaction hash
+    pub tx_hash: Byte32,
+    /// Unix timestamp in milliseconds when the tx was queued
+    pub enqueued_at: u64,
+    /// Number of verified chunks
+    pub chunks_done: u64,
+    /// Number of chunks needed in the worst case, 0 until the verification starts
+    pub chunks_total: u64,
+}
+
+/// Verification progress shared between the queue and the chunk process
+#[derive(Debug, Default)]
+pub(crate) struct ChunkProgress {
+    done: AtomicU64,
+    total: AtomicU64,
+}
+
+impl ChunkProgress {
+    pub(crate) fn update(&self, done: u64, total: u64) {
+        self.done.store(done, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> (u64, u64) {
+        (
+            self.done.load(Ordering::Relaxed),
+            self.total.load(Ordering::Relaxed),
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
 pub(crate) struct Entry {
     pub(crate) tx: TransactionView,
     pub(crate) remote: Option<(Cycle, PeerIndex)>,
+    pub(crate) enqueued_at: u64,
+    pub(crate) progress: Arc<ChunkProgress>,
+}
+
+impl Entry {
+    fn verifying_tx(&self) -> VerifyingTx {
+        let (chunks_done, chunks_total) = self.progress.get();
+        VerifyingTx {
+            tx_hash: self.tx.hash(),
+            enqueued_at: self.enqueued_at,
+            chunks_done,
+            chunks_total,
+        }
+    }
 }
 
 impl PartialEq for Entry {
@@ -20,21 +69,28 @@ impl PartialEq for Entry {
     }
 }
 
-#[derive(Default)]
+impl Eq for Entry {}
+
 pub(crate) struct ChunkQueue {
     inner: LinkedHashMap<ProposalShortId, Entry>,
     // memory last pop value for atomic reset
     front: Option<Entry>,
+    max_len: usize,
 }
 
 impl ChunkQueue {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(max_len: usize) -> Self {
         ChunkQueue {
             inner: LinkedHashMap::default(),
             front: None,
+            max_len,
         }
     }
 
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
     pub fn len(&self) -> usize {
         self.inner.len()
     }
@@ -44,8 +100,9 @@ impl ChunkQueue {
         self.inner.is_empty()
     }
 
+    /// The tx being verified counts against the limit as well
     pub fn is_full(&self) -> bool {
-        self.len() > DEFAULT_MAX_CHUNK_TRANSACTIONS
+        self.len() + usize::from(self.front.is_some()) >= self.max_len
     }
 
     pub fn contains_key(&self, id: &ProposalShortId) -> bool {
@@ -57,6 +114,23 @@ impl ChunkQueue {
             || self.inner.contains_key(id)
     }
 
+    pub fn get_tx(&self, id: &ProposalShortId) -> Option<TransactionView> {
+        self.front
+            .iter()
+            .chain(self.inner.get(id))
+            .find(|entry| &entry.tx.proposal_short_id() == id)
+            .map(|entry| entry.tx.clone())
+    }
+
+    /// Lists the txs under verification, the one being verified first
+    pub fn pending_verification(&self) -> Vec<VerifyingTx> {
+        self.front
+            .iter()
+            .chain(self.inner.values())
+            .map(Entry::verifying_tx)
+            .collect()
+    }
+
     pub fn shrink_to_fit(&mut self) {
         shrink_to_fit!(self.inner, SHRINK_THRESHOLD);
     }
@@ -92,15 +166,27 @@ impl ChunkQueue {
         self.shrink_to_fit();
     }
 
+    /// Queues the tx at `now`, by the pool clock.
     /// If the queue did not have this tx present, true is returned.
     /// If the queue did have this tx present, false is returned.
-    pub fn add_tx(&mut self, tx: TransactionView, remote: Option<(Cycle, PeerIndex)>) -> bool {
+    pub fn add_tx(
+        &mut self,
+        tx: TransactionView,
+        remote: Option<(Cycle, PeerIndex)>,
+        now: u64,
+    ) -> bool {
         if self.contains_key(&tx.proposal_short_id()) {
             return false;
         }
 
+        let entry = Entry {
+            tx,
+            remote,
+            enqueued_at: now,
+            progress: Arc::default(),
+        };
         self.inner
-            .insert(tx.proposal_short_id(), Entry { tx, remote })
+            .insert(entry.tx.proposal_short_id(), entry)
             .is_none()
     }
 
@@ -1,10 +1,11 @@
 extern crate slab;
 use crate::component::pool_map::PoolMap;
 use crate::component::{entry::TxEntry, sort_key::AncestorsScoreSortKey};
-use ckb_types::{core::Cycle, packed::ProposalShortId};
+use ckb_types::{core::Cycle, packed::ProposalShortId, prelude::*};
 use ckb_util::LinkedHashMap;
 use multi_index_map::MultiIndexMap;
 use std::collections::HashSet;
+use std::time::{Duration, Instant};
 
 // A template data struct used to store modified entries when package txs
 #[derive(MultiIndexMap, Clone)]
@@ -59,6 +60,10 @@ pub struct CommitTxsScanner<'a> {
     fetched_txs: HashSet<ProposalShortId>,
     // Keep track of entries that failed inclusion, to avoid duplicate work
     failed_txs: HashSet<ProposalShortId>,
+    // modified entries which failed inclusion, candidates again for the next block
+    deferred_entries: Vec<TxEntry>,
+    // stop selecting once the total cycles reach it, below the hard cycles limit
+    soft_cycles_target: Option<Cycle>,
 }
 
 impl<'a> CommitTxsScanner<'a> {
@@ -69,23 +74,78 @@ impl<'a> CommitTxsScanner<'a> {
             modified_entries: MultiIndexModifiedTxMap::default(),
             fetched_txs: HashSet::default(),
             failed_txs: HashSet::default(),
+            deferred_entries: Vec::new(),
+            soft_cycles_target: None,
         }
     }
 
-    /// find txs to commit, return TxEntry vector, total_size and total_cycles.
+    /// Stop selecting txs once the selected cycles reach `target`, the cycles limit passed to
+    /// `txs_to_commit` is still never exceeded.
+    pub fn with_soft_cycles_target(mut self, target: Option<Cycle>) -> Self {
+        self.soft_cycles_target = target;
+        self
+    }
+
+    /// find txs to commit, return TxEntry vector, total_size, total_cycles and whether it timed out.
+    ///
+    /// Once `max_duration` elapsed the scanner stops selecting and returns what it has gathered,
+    /// at least one package is always selected if there is any candidate.
     pub fn txs_to_commit(
         mut self,
         size_limit: usize,
         cycles_limit: Cycle,
-    ) -> (Vec<TxEntry>, usize, Cycle) {
+        max_duration: Option<Duration>,
+    ) -> (Vec<TxEntry>, usize, Cycle, bool) {
+        let deadline = max_duration.and_then(|duration| Instant::now().checked_add(duration));
+        let (size, cycles, timed_out) = self.fill(size_limit, cycles_limit, deadline);
+        (self.entries, size, cycles, timed_out)
+    }
+
+    /// Same as `txs_to_commit` for the block following the txs selected by the previous calls,
+    /// which are assumed committed, the first call selects the same txs as `txs_to_commit`.
+    pub fn next_block(
+        &mut self,
+        size_limit: usize,
+        cycles_limit: Cycle,
+        deadline: Option<Instant>,
+    ) -> (Vec<TxEntry>, usize, Cycle, bool) {
+        // what did not fit in the previous block may fit in this one
+        self.failed_txs.clear();
+        for entry in std::mem::take(&mut self.deferred_entries) {
+            self.modified_entries.insert_entry(entry);
+        }
+        let (size, cycles, timed_out) = self.fill(size_limit, cycles_limit, deadline);
+        (std::mem::take(&mut self.entries), size, cycles, timed_out)
+    }
+
+    fn fill(
+        &mut self,
+        size_limit: usize,
+        cycles_limit: Cycle,
+        deadline: Option<Instant>,
+    ) -> (usize, Cycle, bool) {
         let mut size: usize = 0;
         let mut cycles: Cycle = 0;
         let mut consecutive_failed = 0;
+        let mut timed_out = false;
 
         let mut iter = self.pool_map.sorted_proposed_iter().peekable();
         loop {
             let mut using_modified = false;
 
+            if let Some(deadline) = deadline {
+                if !self.entries.is_empty() && Instant::now() >= deadline {
+                    timed_out = true;
+                    break;
+                }
+            }
+
+            if let Some(target) = self.soft_cycles_target {
+                if cycles >= target {
+                    break;
+                }
+            }
+
             if let Some(entry) = iter.peek() {
                 if self.skip_proposed_entry(&entry.proposal_short_id()) {
                     iter.next();
@@ -119,13 +179,23 @@ impl<'a> CommitTxsScanner<'a> {
             };
 
             let short_id = tx_entry.proposal_short_id();
+            if self.is_do_not_mine(&tx_entry) {
+                if using_modified {
+                    self.modified_entries.remove(&short_id);
+                }
+                self.failed_txs.insert(short_id);
+                continue;
+            }
+
             let next_size = size.saturating_add(tx_entry.ancestors_size);
             let next_cycles = cycles.saturating_add(tx_entry.ancestors_cycles);
 
             if next_cycles > cycles_limit || next_size > size_limit {
                 consecutive_failed += 1;
                 if using_modified {
-                    self.modified_entries.remove(&short_id);
+                    if let Some(entry) = self.modified_entries.remove(&short_id) {
+                        self.deferred_entries.push(entry);
+                    }
                     self.failed_txs.insert(short_id.clone());
                 }
                 if consecutive_failed > MAX_CONSECUTIVE_FAILURES {
@@ -154,8 +224,15 @@ impl<'a> CommitTxsScanner<'a> {
                 .collect::<Vec<TxEntry>>();
 
             // sort ancestors by ancestors_count,
-            // if A is an ancestor of B, B.ancestors_count must large than A
-            ancestors.sort_unstable_by_key(|entry| entry.ancestors_count);
+            // if A is an ancestor of B, B.ancestors_count must large than A,
+            // ties are broken by id so the selection does not depend on the hash set order
+            ancestors.sort_unstable_by(|a, b| {
+                a.ancestors_count.cmp(&b.ancestors_count).then_with(|| {
+                    a.proposal_short_id()
+                        .as_slice()
+                        .cmp(b.proposal_short_id().as_slice())
+                })
+            });
             ancestors.push(tx_entry.to_owned());
 
             let ancestors: LinkedHashMap<ProposalShortId, TxEntry> = ancestors
@@ -175,7 +252,7 @@ impl<'a> CommitTxsScanner<'a> {
 
             self.update_modified_entries(&ancestors);
         }
-        (self.entries, size, cycles)
+        (size, cycles, timed_out)
     }
 
     fn retrieve_entry(&self, short_id: &ProposalShortId) -> Option<&TxEntry> {
@@ -194,11 +271,19 @@ impl<'a> CommitTxsScanner<'a> {
             || self.failed_txs.contains(short_id)
     }
 
+    // A tx flagged do-not-mine is left out, and so are its descendants which can't be committed
+    // without it
+    fn is_do_not_mine(&self, entry: &TxEntry) -> bool {
+        self.pool_map.is_do_not_mine(&entry.proposal_short_id())
+    }
+
     /// Add descendants of given transactions to `modified_entries` with ancestor
     /// state updated assuming given transactions are inBlock.
     fn update_modified_entries(&mut self, already_added: &LinkedHashMap<ProposalShortId, TxEntry>) {
         for (id, entry) in already_added {
-            let descendants = self.pool_map.calc_descendants(id);
+            // visited by id, the insertion order breaks the ties between equal scores
+            let mut descendants: Vec<_> = self.pool_map.calc_descendants(id).into_iter().collect();
+            descendants.sort_unstable_by(|a, b| a.as_slice().cmp(b.as_slice()));
             for desc_id in descendants
                 .iter()
                 .filter(|id| !already_added.contains_key(id) && self.pool_map.has_proposed(id))
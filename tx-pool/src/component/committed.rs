@@ -0,0 +1,66 @@
+use crate::component::entry::TxEntry;
+use crate::pool::CommittedInfo;
+use ckb_types::{core::BlockNumber, packed::ProposalShortId};
+use ckb_util::LinkedHashMap;
+
+/// Most committed entries kept, the oldest ones are dropped first
+pub(crate) const MAX_COMMITTED_ENTRIES: usize = 10_000;
+
+/// A committed entry kept queryable, with the block it was committed in
+#[derive(Clone)]
+pub(crate) struct CommittedEntry {
+    pub(crate) entry: TxEntry,
+    pub(crate) info: CommittedInfo,
+}
+
+/// Committed entries kept queryable with the committed status until enough blocks are built on
+/// top of theirs, see `keep_committed_confirmations`.
+///
+/// They are held apart from the pool index, so they are never packaged, evicted or counted in the
+/// pool limits. At most `MAX_COMMITTED_ENTRIES` are kept.
+#[derive(Default)]
+pub(crate) struct CommittedEntries {
+    entries: LinkedHashMap<ProposalShortId, CommittedEntry>,
+}
+
+impl CommittedEntries {
+    pub(crate) fn insert(&mut self, id: ProposalShortId, entry: TxEntry, info: CommittedInfo) {
+        // moved to the back if committed again after a reorg
+        self.entries.remove(&id);
+        self.entries.insert(id, CommittedEntry { entry, info });
+        while self.entries.len() > MAX_COMMITTED_ENTRIES {
+            self.entries.pop_front();
+        }
+    }
+
+    pub(crate) fn get(&self, id: &ProposalShortId) -> Option<&CommittedEntry> {
+        self.entries.get(id)
+    }
+
+    pub(crate) fn remove(&mut self, id: &ProposalShortId) {
+        self.entries.remove(id);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Drops the entries with at least `confirmations` blocks on top of theirs at `tip`
+    pub(crate) fn prune(&mut self, tip: BlockNumber, confirmations: u64) {
+        let confirmed: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(_, committed)| {
+                tip.saturating_sub(committed.info.block_number) >= confirmations
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in confirmed {
+            self.entries.remove(&id);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
@@ -0,0 +1,85 @@
+use crate::error::Reject;
+use ckb_types::packed::{Byte32, ProposalShortId};
+use lru::LruCache;
+use std::collections::HashSet;
+
+/// Max number of rejected double-spend txs remembered
+pub(crate) const CONFLICT_CACHE_SIZE: usize = 10_000;
+/// A cached rejection is re-evaluated after 10 minutes
+pub(crate) const CONFLICT_CACHE_TTL_MS: u64 = 10 * 60 * 1000;
+
+/// Counters of the conflict cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConflictCacheStats {
+    /// Submissions rejected from the cache without resolution
+    pub hits: u64,
+    /// Cached rejections dropped because a conflicting entry left the pool or the record expired
+    pub invalidations: u64,
+}
+
+#[derive(Debug, Clone)]
+struct ConflictRecord {
+    conflicts: HashSet<ProposalShortId>,
+    reject: Reject,
+    timestamp: u64,
+}
+
+/// Rejections of txs which double spend an in-pool entry, keyed by tx hash, so that the same
+/// losing tx relayed again by other peers is rejected without resolving it.
+pub(crate) struct ConflictCache {
+    records: LruCache<Byte32, ConflictRecord>,
+    stats: ConflictCacheStats,
+}
+
+impl ConflictCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        ConflictCache {
+            records: LruCache::new(capacity),
+            stats: ConflictCacheStats::default(),
+        }
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        tx_hash: Byte32,
+        conflicts: HashSet<ProposalShortId>,
+        reject: Reject,
+        now: u64,
+    ) {
+        self.records.put(
+            tx_hash,
+            ConflictRecord {
+                conflicts,
+                reject,
+                timestamp: now,
+            },
+        );
+    }
+
+    /// Returns the cached rejection of `tx_hash` if all its conflicting entries are still in the pool
+    pub(crate) fn check<F>(&mut self, tx_hash: &Byte32, now: u64, in_pool: F) -> Option<Reject>
+    where
+        F: Fn(&ProposalShortId) -> bool,
+    {
+        let record = self.records.get(tx_hash)?;
+        if record.conflicts.iter().all(in_pool)
+            && now.saturating_sub(record.timestamp) < CONFLICT_CACHE_TTL_MS
+        {
+            let reject = record.reject.clone();
+            self.stats.hits += 1;
+            Some(reject)
+        } else {
+            self.records.pop(tx_hash);
+            self.stats.invalidations += 1;
+            None
+        }
+    }
+
+    pub(crate) fn stats(&self) -> ConflictCacheStats {
+        self.stats
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.records.clear();
+    }
+}
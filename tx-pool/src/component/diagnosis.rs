@@ -0,0 +1,77 @@
+use ckb_types::{
+    core::{BlockNumber, FeeRate},
+    packed::Byte32,
+};
+
+/// Why a tx is, or is not, on its way into a block, see `TxPool::diagnose`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Diagnosis {
+    /// The tx is in the pool
+    InPool(PoolDiagnosis),
+    /// The tx was committed recently
+    Committed {
+        /// Hash of the committing block
+        block_hash: Byte32,
+        /// Number of the committing block
+        block_number: BlockNumber,
+    },
+    /// The tx was rejected recently, with the reason
+    Rejected(String),
+    /// The pool knows nothing about the tx
+    Unknown,
+}
+
+/// Where a tx is in the pool
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolStage {
+    /// Waiting to be proposed
+    Pending,
+    /// Proposed, the proposal window is not open yet
+    Gap,
+    /// Proposed, may be committed
+    Proposed,
+}
+
+/// Why a pool tx is not selected for the next block
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Blocker {
+    /// The tx has not been proposed yet
+    NotProposed,
+    /// The tx waits for the proposal window to open
+    AwaitingProposalWindow,
+    /// In-pool ancestors of the tx are not proposed yet, their hashes
+    AncestorsUnproposed(Vec<Byte32>),
+    /// The since or header deps constraints of the tx are not satisfied yet, with the reason
+    SinceLocked(String),
+    /// The next block is full of txs paying more
+    FeeBelowCutoff {
+        /// Fee rate of the tx
+        fee_rate: FeeRate,
+        /// Lowest fee rate selected for the next block
+        cutoff: FeeRate,
+    },
+}
+
+/// State of a pool tx on its way into a block
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PoolDiagnosis {
+    /// Where the tx is in the pool
+    pub stage: PoolStage,
+    /// Position of the tx in the packaging order of the next block, `None` if not selected
+    pub position: Option<usize>,
+    /// Why the tx is not selected for the next block, `None` if it is
+    pub blocker: Option<Blocker>,
+    /// Milliseconds left before the tx expires
+    pub expires_in: u64,
+    /// Whether less than a tenth of the pool expiry is left
+    pub expiring_soon: bool,
+    /// Fee rate of the tx
+    pub fee_rate: FeeRate,
+    /// Fee rate floor of the pool, raised by the recent evictions, see
+    /// `TxPool::dynamic_min_fee_rate`
+    pub eviction_floor: FeeRate,
+    /// Whether the tx pays less than the eviction floor, so it goes first when the pool is full
+    pub eviction_risk: bool,
+    /// Number of rejected txs which tried to double spend the tx
+    pub double_spend_attempts: u64,
+}
@@ -0,0 +1,53 @@
+use ckb_types::packed::{Byte32, ProposalShortId};
+use std::collections::{HashMap, VecDeque};
+
+/// Max number of attempting tx hashes kept per entry, older ones are dropped
+pub(crate) const MAX_DOUBLE_SPEND_HASHES: usize = 16;
+
+/// Rejected attempts to double spend a pool entry
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DoubleSpendAttempts {
+    /// Number of rejected txs which conflicted with the entry
+    pub count: u64,
+    /// Hashes of the most recent attempting txs, oldest first
+    pub tx_hashes: VecDeque<Byte32>,
+}
+
+/// Double spend attempts keyed by the pool entry they conflicted with, an entry's record is
+/// dropped once it leaves the pool.
+#[derive(Debug, Default)]
+pub(crate) struct DoubleSpendLedger {
+    attempts: HashMap<ProposalShortId, DoubleSpendAttempts>,
+}
+
+impl DoubleSpendLedger {
+    /// Counts `tx_hash` as a rejected attempt against each of `conflicts`
+    pub(crate) fn record<'a>(
+        &mut self,
+        conflicts: impl Iterator<Item = &'a ProposalShortId>,
+        tx_hash: &Byte32,
+    ) {
+        for id in conflicts {
+            let attempts = self.attempts.entry(id.clone()).or_default();
+            attempts.count = attempts.count.saturating_add(1);
+            if !attempts.tx_hashes.contains(tx_hash) {
+                if attempts.tx_hashes.len() == MAX_DOUBLE_SPEND_HASHES {
+                    attempts.tx_hashes.pop_front();
+                }
+                attempts.tx_hashes.push_back(tx_hash.clone());
+            }
+        }
+    }
+
+    pub(crate) fn get(&self, id: &ProposalShortId) -> Option<&DoubleSpendAttempts> {
+        self.attempts.get(id)
+    }
+
+    pub(crate) fn remove(&mut self, id: &ProposalShortId) {
+        self.attempts.remove(id);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.attempts.clear();
+    }
+}
@@ -9,6 +9,8 @@ pub(crate) struct Edges {
     pub(crate) deps: HashMap<OutPoint, HashSet<ProposalShortId>>,
     /// dep-set<txid-headers> map represent in-pool tx's header deps
     pub(crate) header_deps: HashMap<ProposalShortId, Vec<Byte32>>,
+    /// header-set<txid> map, the reverse index of `header_deps`
+    pub(crate) header_dep_txs: HashMap<Byte32, HashSet<ProposalShortId>>,
 }
 
 impl Edges {
@@ -61,9 +63,41 @@ impl Edges {
         }
     }
 
+    pub(crate) fn insert_header_deps(&mut self, txid: ProposalShortId, headers: Vec<Byte32>) {
+        for header in &headers {
+            self.header_dep_txs
+                .entry(header.to_owned())
+                .or_default()
+                .insert(txid.clone());
+        }
+        self.header_deps.insert(txid, headers);
+    }
+
+    pub(crate) fn remove_header_deps(&mut self, txid: &ProposalShortId) -> Option<Vec<Byte32>> {
+        let headers = self.header_deps.remove(txid)?;
+        for header in &headers {
+            if let Entry::Occupied(mut occupied) = self.header_dep_txs.entry(header.to_owned()) {
+                let ids = occupied.get_mut();
+                ids.remove(txid);
+                if ids.is_empty() {
+                    occupied.remove();
+                }
+            }
+        }
+        Some(headers)
+    }
+
+    pub(crate) fn get_txids_by_header_dep(
+        &self,
+        header: &Byte32,
+    ) -> Option<&HashSet<ProposalShortId>> {
+        self.header_dep_txs.get(header)
+    }
+
     pub(crate) fn clear(&mut self) {
         self.inputs.clear();
         self.deps.clear();
         self.header_deps.clear();
+        self.header_dep_txs.clear();
     }
 }
@@ -3,17 +3,21 @@ use ckb_systemtime::unix_time_as_millis;
 use ckb_types::{
     core::{
         cell::ResolvedTransaction,
-        tx_pool::{get_transaction_weight, TxEntryInfo},
+        tx_pool::{
+            get_transaction_weight_with_ratio, Origin, TxEntryInfo, DEFAULT_BYTES_PER_CYCLES,
+        },
         Capacity, Cycle, FeeRate, TransactionView,
     },
-    packed::{OutPoint, ProposalShortId},
+    packed::{CellOutput, OutPoint, ProposalShortId},
 };
+use ckb_verification::cache::ForkContext;
 use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
+use std::time::Duration;
 
 /// An entry in the transaction pool.
-#[derive(Debug, Clone, Eq)]
+#[derive(Debug, Clone)]
 pub struct TxEntry {
     /// Transaction
     pub rtx: Arc<ResolvedTransaction>,
@@ -41,6 +45,16 @@ pub struct TxEntry {
     pub descendants_count: usize,
     /// The unix timestamp when entering the Txpool, unit: Millisecond
     pub timestamp: u64,
+    /// Where the transaction was submitted from, remote unless set with `with_origin`
+    pub origin: Origin,
+    /// Bytes each cycle weighs when computing the fee rates, set by the pool the entry is added to
+    pub bytes_per_cycles: f64,
+    /// Time spent resolving and verifying the tx before it was added, if measured
+    pub verify_latency: Option<Duration>,
+    /// The script execution rules the tx was verified under, if known
+    pub fork_context: Option<ForkContext>,
+    /// Kept in the pool and relayed, but left out of the local proposals and block templates
+    pub do_not_mine: bool,
 }
 
 impl TxEntry {
@@ -71,9 +85,32 @@ impl TxEntry {
             descendants_cycles: cycles,
             descendants_count: 1,
             ancestors_count: 1,
+            origin: Origin::default(),
+            bytes_per_cycles: DEFAULT_BYTES_PER_CYCLES,
+            verify_latency: None,
+            fork_context: None,
+            do_not_mine: false,
         }
     }
 
+    /// Set where the transaction was submitted from
+    pub fn with_origin(mut self, origin: Origin) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Set the time spent resolving and verifying the transaction
+    pub fn with_verify_latency(mut self, latency: Duration) -> Self {
+        self.verify_latency = Some(latency);
+        self
+    }
+
+    /// Set the script execution rules the transaction was verified under
+    pub fn with_fork_context(mut self, fork_context: ForkContext) -> Self {
+        self.fork_context = Some(fork_context);
+        self
+    }
+
     /// Create dummy entry from tx, skip resolve
     pub fn dummy_resolve(tx: TransactionView, cycles: Cycle, fee: Capacity, size: usize) -> Self {
         let rtx = ResolvedTransaction::dummy_resolve(tx);
@@ -85,6 +122,21 @@ impl TxEntry {
         self.rtx.related_dep_out_points()
     }
 
+    /// Return the out points spent by the inputs
+    pub fn input_out_points(&self) -> impl Iterator<Item = OutPoint> {
+        self.transaction().input_pts_iter()
+    }
+
+    /// Return the out points of the outputs
+    pub fn output_out_points(&self) -> impl Iterator<Item = OutPoint> {
+        self.transaction().output_pts_iter()
+    }
+
+    /// Return the cells created by the transaction, the out point of each output with the output
+    pub fn created_out_points(&self) -> impl Iterator<Item = (OutPoint, CellOutput)> {
+        self.output_out_points().zip(self.transaction().outputs())
+    }
+
     /// Return reference of transaction
     pub fn transaction(&self) -> &TransactionView {
         &self.rtx.transaction
@@ -111,10 +163,33 @@ impl TxEntry {
         EvictKey::from(self)
     }
 
+    fn weight_of(&self, size: usize, cycles: Cycle) -> u64 {
+        get_transaction_weight_with_ratio(size, cycles, self.bytes_per_cycles)
+    }
+
+    /// Returns the weight the fee rate is computed with
+    pub fn weight(&self) -> u64 {
+        self.weight_of(self.size, self.cycles)
+    }
+
+    /// Returns the weight of the package made of the tx and its in-pool ancestors
+    pub fn ancestors_weight(&self) -> u64 {
+        self.weight_of(self.ancestors_size, self.ancestors_cycles)
+    }
+
     /// Returns fee rate
     pub fn fee_rate(&self) -> FeeRate {
-        let weight = get_transaction_weight(self.size, self.cycles);
-        FeeRate::calculate(self.fee, weight)
+        FeeRate::calculate(self.fee, self.weight())
+    }
+
+    /// Returns the fee rate of the package made of the tx and its in-pool descendants, or the fee
+    /// rate of the tx itself if higher, so a low-fee child can't drag down its parent.
+    ///
+    /// This is the rate eviction ranks txs by.
+    pub fn package_fee_rate(&self) -> FeeRate {
+        let descendants_weight = self.weight_of(self.descendants_size, self.descendants_cycles);
+        let descendants_fee_rate = FeeRate::calculate(self.descendants_fee, descendants_weight);
+        descendants_fee_rate.max(self.fee_rate())
     }
 
     /// Update ancestor state for add an entry
@@ -184,12 +259,16 @@ impl TxEntry {
             cycles: self.cycles,
             size: self.size as u64,
             fee: self.fee,
+            weight: self.weight(),
             ancestors_size: self.ancestors_size as u64,
             ancestors_cycles: self.ancestors_cycles,
             descendants_size: self.descendants_size as u64,
             descendants_cycles: self.descendants_cycles,
             ancestors_count: self.ancestors_count as u64,
             timestamp: self.timestamp,
+            origin: self.origin,
+            double_spend_attempts: 0,
+            double_spend_tx_hashes: Vec::new(),
         }
     }
 }
@@ -206,6 +285,8 @@ impl PartialEq for TxEntry {
     }
 }
 
+impl Eq for TxEntry {}
+
 impl PartialOrd for TxEntry {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -220,27 +301,20 @@ impl Ord for TxEntry {
 
 impl From<&TxEntry> for AncestorsScoreSortKey {
     fn from(entry: &TxEntry) -> Self {
-        let weight = get_transaction_weight(entry.size, entry.cycles);
-        let ancestors_weight = get_transaction_weight(entry.ancestors_size, entry.ancestors_cycles);
         AncestorsScoreSortKey {
             fee: entry.fee,
-            weight,
+            weight: entry.weight(),
             ancestors_fee: entry.ancestors_fee,
-            ancestors_weight,
+            ancestors_weight: entry.ancestors_weight(),
         }
     }
 }
 
 impl From<&TxEntry> for EvictKey {
     fn from(entry: &TxEntry) -> Self {
-        let weight = get_transaction_weight(entry.size, entry.cycles);
-        let descendants_weight =
-            get_transaction_weight(entry.descendants_size, entry.descendants_cycles);
-
-        let descendants_feerate = FeeRate::calculate(entry.descendants_fee, descendants_weight);
-        let feerate = FeeRate::calculate(entry.fee, weight);
         EvictKey {
-            fee_rate: descendants_feerate.max(feerate),
+            zero_fee: entry.descendants_fee.as_u64() == 0,
+            fee_rate: entry.package_fee_rate(),
             timestamp: entry.timestamp,
             descendants_count: entry.descendants_count,
         }
@@ -0,0 +1,151 @@
+use crate::component::entry::TxEntry;
+use crate::component::pool_map::{PoolEntry, PoolMap, Status};
+use ckb_app_config::{EvictionStrategy, TxPoolConfig};
+use ckb_types::{
+    core::{Cycle, FeeRate},
+    packed::ProposalShortId,
+};
+use std::time::Duration;
+
+/// Number of the cheapest entries `PackageSizePolicy` picks from
+const PACKAGE_SIZE_CANDIDATES: usize = 32;
+/// Number of the cheapest entries `DescendantScorePolicy` picks from
+const DESCENDANT_SCORE_CANDIDATES: usize = 32;
+
+/// Picks the entries to evict when the pool is over its limits.
+///
+/// The selected entry is evicted together with its descendants. The caller takes care of the
+/// removal, the statistics and the callbacks, then asks again until the pool fits.
+pub(crate) trait EvictionPolicy: Send + Sync {
+    /// Selects the next entry in `status` to evict, `overage` is the number of bytes the pool or
+    /// the status quota is over, 0 if only a count limit is exceeded
+    fn select(&self, pool_map: &PoolMap, status: Status, overage: usize)
+        -> Option<ProposalShortId>;
+}
+
+/// Evicts the entry with the lowest package fee rate first, see `TxEntry::package_fee_rate`
+pub(crate) struct PackageFeeRatePolicy;
+
+impl EvictionPolicy for PackageFeeRatePolicy {
+    fn select(
+        &self,
+        pool_map: &PoolMap,
+        status: Status,
+        _overage: usize,
+    ) -> Option<ProposalShortId> {
+        pool_map.next_evict_entry(status)
+    }
+}
+
+/// Evicts, among the `DESCENDANT_SCORE_CANDIDATES` entries with the lowest package fee rate, the
+/// cheapest one whose package, the entry and its descendants, frees at least the overage, so one
+/// large cheap package goes instead of several small ones.
+///
+/// Falls back to the lowest package fee rate when no package is large enough, or when only a
+/// count limit is exceeded.
+pub(crate) struct DescendantScorePolicy;
+
+impl EvictionPolicy for DescendantScorePolicy {
+    fn select(
+        &self,
+        pool_map: &PoolMap,
+        status: Status,
+        overage: usize,
+    ) -> Option<ProposalShortId> {
+        pool_map
+            .iter_evict_order(status)
+            .take(DESCENDANT_SCORE_CANDIDATES)
+            .find(|entry| entry.inner.descendants_size >= overage)
+            .map(|entry| entry.id.clone())
+            .or_else(|| pool_map.next_evict_entry(status))
+    }
+}
+
+/// Evicts, among the `PACKAGE_SIZE_CANDIDATES` entries with the lowest package fee rate, the one
+/// whose package is the smallest still freeing the overage, or the cheapest one if no package is
+/// large enough, so a large descendant tree is not evicted when a smaller package would do.
+///
+/// Packages larger than `max_package_size` are skipped, unless there is nothing else to evict.
+pub(crate) struct PackageSizePolicy {
+    pub(crate) max_package_size: Option<usize>,
+}
+
+impl EvictionPolicy for PackageSizePolicy {
+    fn select(
+        &self,
+        pool_map: &PoolMap,
+        status: Status,
+        overage: usize,
+    ) -> Option<ProposalShortId> {
+        let mut cheapest = None;
+        let mut closest: Option<&PoolEntry> = None;
+        for entry in pool_map
+            .iter_evict_order(status)
+            .take(PACKAGE_SIZE_CANDIDATES)
+            .filter(|entry| {
+                self.max_package_size
+                    .map_or(true, |max_size| entry.inner.descendants_size <= max_size)
+            })
+        {
+            cheapest.get_or_insert(entry);
+            let size = entry.inner.descendants_size;
+            if size >= overage && closest.map_or(true, |best| size < best.inner.descendants_size) {
+                closest = Some(entry);
+            }
+        }
+        closest
+            .or(cheapest)
+            .map(|entry| entry.id.clone())
+            .or_else(|| pool_map.next_evict_entry(status))
+    }
+}
+
+/// What a run of the size limit evicted, see `TxPool::limit_size`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EvictionSummary {
+    /// Number of evicted txs, descendants included
+    pub removed_count: usize,
+    /// Total size of the evicted txs
+    pub removed_bytes: usize,
+    /// Total cycles of the evicted txs
+    pub removed_cycles: Cycle,
+    /// Lowest package fee rate a tx was evicted at, see `TxEntry::package_fee_rate`, `None` if
+    /// nothing was evicted
+    pub min_fee_rate_removed: Option<FeeRate>,
+    /// Highest package fee rate a tx was evicted at, `None` if nothing was evicted
+    pub max_fee_rate_removed: Option<FeeRate>,
+    /// Time spent selecting and removing the evicted txs
+    pub duration: Duration,
+}
+
+impl EvictionSummary {
+    /// Whether nothing was evicted
+    pub fn is_empty(&self) -> bool {
+        self.removed_count == 0
+    }
+
+    /// Counts `entry`, evicted with the package selected at `fee_rate`
+    pub(crate) fn record(&mut self, entry: &TxEntry, fee_rate: FeeRate) {
+        self.removed_count += 1;
+        self.removed_bytes = self.removed_bytes.saturating_add(entry.size);
+        self.removed_cycles = self.removed_cycles.saturating_add(entry.cycles);
+        self.min_fee_rate_removed = Some(
+            self.min_fee_rate_removed
+                .map_or(fee_rate, |min| min.min(fee_rate)),
+        );
+        self.max_fee_rate_removed = Some(
+            self.max_fee_rate_removed
+                .map_or(fee_rate, |max| max.max(fee_rate)),
+        );
+    }
+}
+
+pub(crate) fn eviction_policy(config: &TxPoolConfig) -> Box<dyn EvictionPolicy> {
+    match config.eviction_strategy {
+        EvictionStrategy::PackageFeeRate => Box::new(PackageFeeRatePolicy),
+        EvictionStrategy::DescendantScore => Box::new(DescendantScorePolicy),
+        EvictionStrategy::PackageSize => Box::new(PackageSizePolicy {
+            max_package_size: config.max_evict_package_size,
+        }),
+    }
+}
@@ -0,0 +1,140 @@
+use crate::component::changelog::RemoveReason;
+use ckb_types::core::Capacity;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Fees of the entries which left the pool, by outcome
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeeFlow {
+    /// Fees of the entries committed in a block
+    pub committed: Capacity,
+    /// Fees of the entries evicted by the size limit
+    pub evicted: Capacity,
+    /// Fees of the entries dropped by a reorg deeper than `reorg_clear_depth`
+    pub cleared: Capacity,
+    /// Fees of the entries which expired
+    pub expired: Capacity,
+    /// Fees of the entries replaced by a tx paying a higher fee
+    pub replaced: Capacity,
+    /// Fees of the entries removed because a cell they spend or depend on was consumed by a
+    /// committed tx or by a tx entering the pool
+    pub conflicted: Capacity,
+}
+
+impl FeeFlow {
+    /// Sum of all the outcomes, saturating
+    pub fn total(&self) -> Capacity {
+        Capacity::shannons(
+            [
+                self.committed,
+                self.evicted,
+                self.cleared,
+                self.expired,
+                self.replaced,
+                self.conflicted,
+            ]
+            .iter()
+            .fold(0u64, |total, fee| total.saturating_add(fee.as_u64())),
+        )
+    }
+
+    fn bucket(&mut self, reason: RemoveReason) -> Option<&mut Capacity> {
+        match reason {
+            RemoveReason::Committed => Some(&mut self.committed),
+            RemoveReason::Evicted => Some(&mut self.evicted),
+            RemoveReason::Cleared => Some(&mut self.cleared),
+            RemoveReason::Expired => Some(&mut self.expired),
+            RemoveReason::Replaced => Some(&mut self.replaced),
+            RemoveReason::Conflicted => Some(&mut self.conflicted),
+            _ => None,
+        }
+    }
+}
+
+/// Committed versus lost fees, see `TxPool::fee_flow_report`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeeFlowReport {
+    /// The current UTC day, in days since the unix epoch
+    pub day: u64,
+    /// Fees of the entries which left the pool during the current day
+    pub today: FeeFlow,
+    /// Fees of the entries which left the pool during the previous day, zero if the pool was not
+    /// running or nothing left
+    pub previous_day: FeeFlow,
+    /// Fees of the entries which left the pool since it started
+    pub since_start: FeeFlow,
+}
+
+/// Running fee totals, the current day ones roll over at midnight UTC
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FeeFlowTracker {
+    report: FeeFlowReport,
+}
+
+impl FeeFlowTracker {
+    /// Adds `fee` to the bucket of `reason`, the reasons without a bucket are ignored
+    pub(crate) fn record(&mut self, reason: RemoveReason, fee: Capacity, now_ms: u64) {
+        let report = &mut self.report;
+        Self::roll_over(report, now_ms);
+        for flow in [&mut report.today, &mut report.since_start] {
+            if let Some(bucket) = flow.bucket(reason) {
+                *bucket = Capacity::shannons(bucket.as_u64().saturating_add(fee.as_u64()));
+            }
+        }
+        if let Some(metrics) = ckb_metrics::handle() {
+            let outcome = match reason {
+                RemoveReason::Committed => &metrics.ckb_tx_pool_fee_flow.committed,
+                RemoveReason::Evicted => &metrics.ckb_tx_pool_fee_flow.evicted,
+                RemoveReason::Cleared => &metrics.ckb_tx_pool_fee_flow.cleared,
+                RemoveReason::Expired => &metrics.ckb_tx_pool_fee_flow.expired,
+                RemoveReason::Replaced => &metrics.ckb_tx_pool_fee_flow.replaced,
+                RemoveReason::Conflicted => &metrics.ckb_tx_pool_fee_flow.conflicted,
+                _ => return,
+            };
+            outcome.inc_by(fee.as_u64());
+        }
+    }
+
+    /// Takes `fee` back from the bucket of `reason` for an entry which came back to the pool,
+    /// from the current day first then from the previous one
+    pub(crate) fn undo(&mut self, reason: RemoveReason, fee: Capacity, now_ms: u64) {
+        let report = &mut self.report;
+        Self::roll_over(report, now_ms);
+        let mut left = fee.as_u64();
+        for flow in [&mut report.today, &mut report.previous_day] {
+            if let Some(bucket) = flow.bucket(reason) {
+                let taken = left.min(bucket.as_u64());
+                *bucket = Capacity::shannons(bucket.as_u64() - taken);
+                left -= taken;
+            }
+        }
+        if let Some(bucket) = report.since_start.bucket(reason) {
+            *bucket = Capacity::shannons(bucket.as_u64().saturating_sub(fee.as_u64()));
+        }
+        // the counters only go up, the readmitted fees are counted apart
+        if let Some(metrics) = ckb_metrics::handle() {
+            if reason == RemoveReason::Evicted {
+                metrics.ckb_tx_pool_fee_flow.readmitted.inc_by(fee.as_u64());
+            }
+        }
+    }
+
+    pub(crate) fn report(&self, now_ms: u64) -> FeeFlowReport {
+        let mut report = self.report;
+        Self::roll_over(&mut report, now_ms);
+        report
+    }
+
+    fn roll_over(report: &mut FeeFlowReport, now_ms: u64) {
+        let day = now_ms / DAY_MS;
+        if day > report.day {
+            report.previous_day = if day == report.day + 1 {
+                report.today
+            } else {
+                FeeFlow::default()
+            };
+            report.today = FeeFlow::default();
+            report.day = day;
+        }
+    }
+}
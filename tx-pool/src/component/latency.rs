@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+/// Upper bounds in milliseconds of the acceptance latency buckets, slower txs fall in a last
+/// bucket without bound
+pub const LATENCY_BUCKETS_MS: [u64; 5] = [1, 10, 100, 1_000, 10_000];
+
+/// How long the pool entries took to resolve and verify before they were added, see
+/// `TxPool::acceptance_latency_histogram`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// Number of entries per bucket, `counts[i]` holds the latencies up to `LATENCY_BUCKETS_MS[i]`
+    /// and above the previous bound, the last one the latencies above all bounds
+    pub counts: [usize; LATENCY_BUCKETS_MS.len() + 1],
+    /// Number of entries added without a measured latency: verified in chunks, re-added after a
+    /// reorg or restored
+    pub unmeasured: usize,
+    /// Sum of the measured latencies
+    pub total: Duration,
+    /// The highest measured latency
+    pub max: Duration,
+}
+
+impl LatencyHistogram {
+    pub(crate) fn record(&mut self, latency: Option<Duration>) {
+        let latency = match latency {
+            Some(latency) => latency,
+            None => {
+                self.unmeasured += 1;
+                return;
+            }
+        };
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| latency <= Duration::from_millis(*bound))
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.counts[bucket] += 1;
+        self.total = self.total.saturating_add(latency);
+        self.max = self.max.max(latency);
+    }
+
+    /// Number of entries with a measured latency
+    pub fn measured(&self) -> usize {
+        self.counts.iter().sum()
+    }
+}
@@ -1,5 +1,5 @@
 use ckb_types::packed::ProposalShortId;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 #[derive(Default, Debug, Clone)]
 pub struct TxLinks {
@@ -79,6 +79,37 @@ impl TxLinksMap {
         self.calc_relative_ids(short_id, Relation::Children)
     }
 
+    /// Breadth-first walk of the relatives of `short_id`, each one with its distance, at most
+    /// `max_depth` links away if set and at most `limit` of them
+    pub fn walk_relatives(
+        &self,
+        short_id: &ProposalShortId,
+        relation: Relation,
+        max_depth: Option<usize>,
+        limit: usize,
+    ) -> Vec<(ProposalShortId, usize)> {
+        let mut relatives = Vec::new();
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::from(vec![(short_id, 0)]);
+        visited.insert(short_id);
+        while let Some((id, depth)) = queue.pop_front() {
+            if max_depth.map_or(false, |max_depth| depth >= max_depth) {
+                continue;
+            }
+            let direct = self.inner.get(id).map(|link| link.get_direct_ids(relation));
+            for direct_id in direct.into_iter().flatten() {
+                if relatives.len() == limit {
+                    return relatives;
+                }
+                if visited.insert(direct_id) {
+                    relatives.push((direct_id.clone(), depth + 1));
+                    queue.push_back((direct_id, depth + 1));
+                }
+            }
+        }
+        relatives
+    }
+
     pub fn get_children(&self, short_id: &ProposalShortId) -> Option<&HashSet<ProposalShortId>> {
         self.inner.get(short_id).map(|link| &link.children)
     }
@@ -1,13 +1,31 @@
 pub mod commit_txs_scanner;
 pub mod entry;
 
+pub(crate) mod archive;
+pub(crate) mod changelog;
 pub(crate) mod chunk;
+pub(crate) mod committed;
+pub(crate) mod conflict_cache;
+pub(crate) mod diagnosis;
+pub(crate) mod double_spend;
 pub(crate) mod edges;
+pub(crate) mod eviction;
+pub(crate) mod fee_flow;
+pub(crate) mod latency;
 pub(crate) mod links;
 pub(crate) mod orphan;
 pub(crate) mod pool_map;
+pub(crate) mod rate_limit;
+pub(crate) mod rebroadcast;
 pub(crate) mod recent_reject;
+pub(crate) mod replacement;
+pub(crate) mod replay;
+pub(crate) mod resolve_cache;
+pub(crate) mod resubmit;
 pub(crate) mod sort_key;
+pub(crate) mod template_refresh;
+pub(crate) mod trace;
+pub(crate) mod view;
 
 #[cfg(test)]
 mod tests;
@@ -7,7 +7,7 @@ use ckb_types::{
     packed::{OutPoint, ProposalShortId},
 };
 use ckb_util::shrink_to_fit;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 const SHRINK_THRESHOLD: usize = 100;
 
@@ -161,4 +161,31 @@ impl OrphanPool {
             .flatten()
             .collect::<Vec<_>>()
     }
+
+    /// Removes the orphans spending outputs of the given txs, then the orphans spending outputs
+    /// of the removed ones in turn, used when the given txs can no longer enter the pool
+    pub fn remove_orphans_by_previous<'a>(
+        &mut self,
+        txs: impl Iterator<Item = &'a TransactionView>,
+    ) -> Vec<Entry> {
+        let mut queue: VecDeque<TransactionView> = txs.cloned().collect();
+        let mut removed = vec![];
+        while let Some(previous) = queue.pop_front() {
+            let ids: Vec<_> = self
+                .find_by_previous(&previous)
+                .into_iter()
+                .cloned()
+                .collect();
+            for id in ids {
+                if let Some(entry) = self.remove_orphan_tx(&id) {
+                    queue.push_back(entry.tx.clone());
+                    removed.push(entry);
+                }
+            }
+        }
+        if !removed.is_empty() {
+            self.shrink_to_fit();
+        }
+        removed
+    }
 }
@@ -1,10 +1,14 @@
 //! Top-level Pool type, methods, and tests
 extern crate rustc_hash;
 extern crate slab;
+use crate::clock::{Clock, SystemClock};
+use crate::component::changelog::{Changelog, RemoveReason, DEFAULT_CHANGELOG_CAPACITY};
+use crate::component::double_spend::DoubleSpendLedger;
 use crate::component::edges::Edges;
 use crate::component::links::{Relation, TxLinksMap};
 use crate::component::sort_key::{AncestorsScoreSortKey, EvictKey};
-use crate::error::Reject;
+use crate::component::trace::{TxEvent, TxTracer};
+use crate::error::{IntegrityError, Reject};
 use crate::TxEntry;
 
 use ckb_logger::{debug, trace};
@@ -13,11 +17,16 @@ use ckb_types::packed::OutPoint;
 use ckb_types::prelude::*;
 use ckb_types::{
     bytes::Bytes,
-    core::TransactionView,
+    core::{
+        cell::ResolvedTransaction, tx_pool::DEFAULT_BYTES_PER_CYCLES, Cycle, FeeRate,
+        TransactionView,
+    },
     packed::{Byte32, CellOutput, ProposalShortId},
 };
+use ckb_util::Mutex;
 use multi_index_map::MultiIndexMap;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use super::links::TxLinks;
 
@@ -48,6 +57,8 @@ pub struct PoolEntry {
     pub evict_key: EvictKey,
     // other sort key
     pub inner: TxEntry,
+    /// Unix timestamp in milliseconds the entry entered its current status
+    pub status_since: u64,
 }
 
 pub struct PoolMap {
@@ -57,7 +68,33 @@ pub struct PoolMap {
     pub(crate) edges: Edges,
     /// All the parent/children relationships
     pub(crate) links: TxLinksMap,
+    /// Full tx hash of every entry to its proposal short id
+    pub(crate) hashes: HashMap<Byte32, ProposalShortId>,
     pub(crate) max_ancestors_count: usize,
+    /// Max total size of an entry and its ancestors
+    pub(crate) max_package_size: usize,
+    /// Max total cycles of an entry and its ancestors
+    pub(crate) max_package_cycles: Cycle,
+    /// Max number of header deps of an entry
+    pub(crate) max_header_deps: Option<usize>,
+    /// Bytes each cycle weighs when scoring the entries
+    pub(crate) bytes_per_cycles: f64,
+    /// Membership changes for incremental sync
+    pub(crate) changelog: Changelog,
+    /// Rejected double spend attempts against the entries, recorded under the read lock
+    pub(crate) double_spends: Mutex<DoubleSpendLedger>,
+    /// Lifecycle traces of the watched and sampled txs, shared with the service for the events
+    /// happening outside the pool
+    pub(crate) tracer: Arc<TxTracer>,
+    /// Source of the status change timestamps
+    pub(crate) clock: Arc<dyn Clock>,
+    /// Total size and count of the entries in each status, kept along the entries
+    usage: HashMap<Status, (usize, usize)>,
+    /// The entries flagged do-not-mine, see `TxEntry::do_not_mine`
+    do_not_mine: HashSet<ProposalShortId>,
+    /// Bumped by every change to the entries, including the status moves the changelog doesn't
+    /// record, so a copy taken at a version is known to be current while the version holds
+    version: u64,
 }
 
 impl PoolMap {
@@ -66,10 +103,53 @@ impl PoolMap {
             entries: MultiIndexPoolEntryMap::default(),
             edges: Edges::default(),
             links: TxLinksMap::new(),
+            hashes: HashMap::default(),
             max_ancestors_count,
+            max_package_size: usize::MAX,
+            max_package_cycles: Cycle::MAX,
+            max_header_deps: None,
+            bytes_per_cycles: DEFAULT_BYTES_PER_CYCLES,
+            changelog: Changelog::new(DEFAULT_CHANGELOG_CAPACITY),
+            double_spends: Mutex::new(DoubleSpendLedger::default()),
+            tracer: Arc::new(TxTracer::new(Arc::new(SystemClock), 0.0)),
+            clock: Arc::new(SystemClock),
+            usage: HashMap::default(),
+            do_not_mine: HashSet::default(),
+            version: 0,
         }
     }
 
+    pub(crate) fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub(crate) fn with_tracer(mut self, tracer: TxTracer) -> Self {
+        self.tracer = Arc::new(tracer);
+        self
+    }
+
+    pub(crate) fn with_package_limits(mut self, max_size: usize, max_cycles: Cycle) -> Self {
+        self.max_package_size = max_size;
+        self.max_package_cycles = max_cycles;
+        self
+    }
+
+    pub(crate) fn with_max_header_deps(mut self, max_header_deps: Option<usize>) -> Self {
+        self.max_header_deps = max_header_deps;
+        self
+    }
+
+    pub(crate) fn with_bytes_per_cycles(mut self, bytes_per_cycles: f64) -> Self {
+        self.bytes_per_cycles = bytes_per_cycles;
+        self
+    }
+
+    /// The version of the entries, see `TxPool::consistent_view`
+    pub(crate) fn version(&self) -> u64 {
+        self.version
+    }
+
     #[cfg(test)]
     pub(crate) fn header_deps_len(&self) -> usize {
         self.edges.header_deps_len()
@@ -85,12 +165,10 @@ impl PoolMap {
         self.edges.inputs_len()
     }
 
-    #[cfg(test)]
     pub(crate) fn size(&self) -> usize {
         self.entries.len()
     }
 
-    #[cfg(test)]
     pub(crate) fn contains_key(&self, id: &ProposalShortId) -> bool {
         self.entries.get_by_id(id).is_some()
     }
@@ -111,6 +189,12 @@ impl PoolMap {
         self.entries.get_by_id(id)
     }
 
+    /// The entry of the tx `hash`, `None` if no entry has this full hash, even when another tx
+    /// with the same proposal short id is in the pool
+    pub(crate) fn get_by_hash(&self, hash: &Byte32) -> Option<&PoolEntry> {
+        self.hashes.get(hash).and_then(|id| self.get_by_id(id))
+    }
+
     fn get_by_id_checked(&self, id: &ProposalShortId) -> &PoolEntry {
         self.get_by_id(id).expect("unconsistent pool")
     }
@@ -120,12 +204,30 @@ impl PoolMap {
     }
 
     pub(crate) fn pending_size(&self) -> usize {
-        self.entries.get_by_status(&Status::Pending).len()
-            + self.entries.get_by_status(&Status::Gap).len()
+        self.status_usage(Status::Pending).1 + self.status_usage(Status::Gap).1
     }
 
     pub(crate) fn proposed_size(&self) -> usize {
-        self.entries.get_by_status(&Status::Proposed).len()
+        self.status_usage(Status::Proposed).1
+    }
+
+    /// Total size and count of the entries in `status`
+    pub(crate) fn status_usage(&self, status: Status) -> (usize, usize) {
+        self.usage.get(&status).copied().unwrap_or_default()
+    }
+
+    fn update_usage(&mut self, status: Status, size: usize, op: EntryOp) {
+        let (total_size, count) = self.usage.entry(status).or_default();
+        match op {
+            EntryOp::Add => {
+                *total_size = total_size.saturating_add(size);
+                *count += 1;
+            }
+            EntryOp::Remove => {
+                *total_size = total_size.saturating_sub(size);
+                *count = count.saturating_sub(1);
+            }
+        }
     }
 
     pub(crate) fn sorted_proposed_iter(&self) -> impl Iterator<Item = &TxEntry> {
@@ -152,6 +254,31 @@ impl PoolMap {
         self.links.calc_ancestors(short_id)
     }
 
+    /// Calculates the in-pool ancestors `tx` would have if added once the `removed` entries are
+    /// gone, the parents are found the same way as in `check_and_record_ancestors`
+    pub(crate) fn calc_tx_ancestors(
+        &self,
+        tx: &TransactionView,
+        removed: &HashSet<ProposalShortId>,
+    ) -> HashSet<ProposalShortId> {
+        let mut parents = HashSet::new();
+        for input_pt in tx.input_pts_iter() {
+            if let Some(deps) = self.edges.deps.get(&input_pt) {
+                parents.extend(deps.iter().cloned());
+            }
+            parents.insert(ProposalShortId::from_tx_hash(&input_pt.tx_hash()));
+        }
+        for cell_dep in tx.cell_deps_iter() {
+            parents.insert(ProposalShortId::from_tx_hash(
+                &cell_dep.out_point().tx_hash(),
+            ));
+        }
+        // the descendants of a removed entry are removed too, so the ancestors of the parents
+        // left are all kept
+        parents.retain(|id| self.links.inner.contains_key(id) && !removed.contains(id));
+        self.links.calc_relation_ids(parents, Relation::Parents)
+    }
+
     /// calculate all descendants from pool
     pub(crate) fn calc_descendants(&self, short_id: &ProposalShortId) -> HashSet<ProposalShortId> {
         self.links.calc_descendants(short_id)
@@ -172,24 +299,152 @@ impl PoolMap {
             return Ok(false);
         }
         trace!("pool_map.add_{:?} {}", status, entry.transaction().hash());
+        let header_deps_len = entry.transaction().header_deps().len();
+        if let Some(max_header_deps) = self.max_header_deps {
+            if header_deps_len > max_header_deps {
+                return Err(Reject::ExceededMaximumHeaderDeps(
+                    header_deps_len,
+                    max_header_deps,
+                ));
+            }
+        }
+        entry.bytes_per_cycles = self.bytes_per_cycles;
         self.check_and_record_ancestors(&mut entry)?;
         self.insert_entry(&entry, status);
         self.record_entry_edges(&entry);
         self.record_entry_descendants(&entry);
+        self.changelog
+            .record_added(entry.transaction().hash(), entry.fee, entry.size);
+        self.trace_status(&entry.transaction().hash(), None, status);
         Ok(true)
     }
 
+    /// Replaces the tx of an entry by a variant with the same hash but other witnesses.
+    ///
+    /// The entry keeps its status, timestamp, origin and links, only the size and cycles of the
+    /// entry and the package weights of its relatives change. Returns the replaced entry.
+    pub(crate) fn swap_witnesses(
+        &mut self,
+        rtx: Arc<ResolvedTransaction>,
+        cycles: Cycle,
+        size: usize,
+    ) -> Option<TxEntry> {
+        let id = rtx.transaction.proposal_short_id();
+        let old = self.get(&id)?.clone();
+        let mut new = old.clone();
+        new.rtx = rtx;
+        new.size = size;
+        new.cycles = cycles;
+        new.ancestors_size = old.ancestors_size.saturating_sub(old.size) + size;
+        new.ancestors_cycles = old.ancestors_cycles.saturating_sub(old.cycles) + cycles;
+        new.descendants_size = old.descendants_size.saturating_sub(old.size) + size;
+        new.descendants_cycles = old.descendants_cycles.saturating_sub(old.cycles) + cycles;
+
+        self.update_ancestors_index_key(&old, EntryOp::Remove);
+        self.update_ancestors_index_key(&new, EntryOp::Add);
+        self.update_descendants_index_key(&old, EntryOp::Remove);
+        self.update_descendants_index_key(&new, EntryOp::Add);
+        let mut status = None;
+        self.version += 1;
+        self.entries
+            .modify_by_id(&id, |e| {
+                e.score = new.as_score_key();
+                e.evict_key = new.as_evict_key();
+                e.inner = new.clone();
+                status = Some(e.status);
+            })
+            .expect("unconsistent pool");
+        if let Some(status) = status {
+            self.update_usage(status, old.size, EntryOp::Remove);
+            self.update_usage(status, size, EntryOp::Add);
+        }
+        Some(old)
+    }
+
+    /// Flags the entry to be left out of the local blocks, returns false if it is not in the pool
+    pub(crate) fn set_do_not_mine(&mut self, id: &ProposalShortId, do_not_mine: bool) -> bool {
+        if self
+            .entries
+            .modify_by_id(id, |e| e.inner.do_not_mine = do_not_mine)
+            .is_none()
+        {
+            return false;
+        }
+        self.version += 1;
+        if do_not_mine {
+            self.do_not_mine.insert(id.clone());
+        } else {
+            self.do_not_mine.remove(id);
+        }
+        true
+    }
+
+    /// Whether the entry is flagged do-not-mine or descends from one which is, it can't be
+    /// committed nor proposed by the local blocks then
+    pub(crate) fn is_do_not_mine(&self, id: &ProposalShortId) -> bool {
+        if self.do_not_mine.is_empty() {
+            return false;
+        }
+        self.do_not_mine.contains(id)
+            || (self
+                .get(id)
+                .map_or(false, |entry| entry.ancestors_count > 1)
+                && self
+                    .calc_ancestors(id)
+                    .iter()
+                    .any(|ancestor| self.do_not_mine.contains(ancestor)))
+    }
+
     /// Change the status of the entry, only used for `gap_rtx` and `proposed_rtx`
     pub(crate) fn set_entry(&mut self, short_id: &ProposalShortId, status: Status) {
+        let mut moved = None;
+        let now_ms = self.clock.now_ms();
+        self.version += 1;
         self.entries
             .modify_by_id(short_id, |e| {
+                moved = Some((e.inner.transaction().hash(), e.status, e.inner.size));
+                if e.status != status {
+                    e.status_since = now_ms;
+                }
                 e.status = status;
             })
             .expect("unconsistent pool");
+        if let Some((hash, from, size)) = moved {
+            if from != status {
+                self.update_usage(from, size, EntryOp::Remove);
+                self.update_usage(status, size, EntryOp::Add);
+            }
+            self.trace_status(&hash, Some(from), status);
+        }
+    }
+
+    /// Traces an entry admitted with `status` if `from` is `None`, or moved from `from` to it
+    fn trace_status(&self, hash: &Byte32, from: Option<Status>, status: Status) {
+        if !self.tracer.is_traced(hash) {
+            return;
+        }
+        if from.is_none() {
+            self.tracer.record(hash, TxEvent::Admitted);
+        }
+        match status {
+            Status::Gap if from != Some(Status::Gap) => self.tracer.record(hash, TxEvent::Gap),
+            Status::Proposed if from != Some(Status::Proposed) => {
+                self.tracer.record(hash, TxEvent::Proposed)
+            }
+            _ => {}
+        }
     }
 
-    pub(crate) fn remove_entry(&mut self, id: &ProposalShortId) -> Option<TxEntry> {
+    pub(crate) fn remove_entry(
+        &mut self,
+        id: &ProposalShortId,
+        reason: RemoveReason,
+    ) -> Option<TxEntry> {
         self.entries.remove_by_id(id).map(|entry| {
+            self.version += 1;
+            self.hashes.remove(&entry.inner.transaction().hash());
+            self.do_not_mine.remove(id);
+            self.update_usage(entry.status, entry.inner.size, EntryOp::Remove);
             debug!(
                 "remove entry {} from status: {:?}",
                 entry.inner.transaction().hash(),
@@ -199,11 +454,62 @@ impl PoolMap {
             self.update_descendants_index_key(&entry.inner, EntryOp::Remove);
             self.remove_entry_edges(&entry.inner);
             self.remove_entry_links(id);
+            self.changelog
+                .record_removed(entry.inner.transaction().hash(), reason);
+            self.tracer
+                .record(&entry.inner.transaction().hash(), TxEvent::Removed(reason));
+            // a detached entry is put back as pending right away, it keeps its record
+            if reason != RemoveReason::Detached {
+                self.double_spends.get_mut().remove(id);
+            }
             entry.inner
         })
     }
 
-    pub(crate) fn remove_entry_and_descendants(&mut self, id: &ProposalShortId) -> Vec<TxEntry> {
+    /// Checks that the input index matches the inputs of the live entries exactly
+    pub(crate) fn verify_inputs_index(&self) -> Result<(), IntegrityError> {
+        for entry in self.iter() {
+            for out_point in entry.inner.transaction().input_pts_iter() {
+                if self.edges.get_input_ref(&out_point) != Some(&entry.id) {
+                    return Err(IntegrityError::MissingInput(
+                        out_point.to_string(),
+                        entry.id.to_string(),
+                    ));
+                }
+            }
+        }
+        for (out_point, id) in self.edges.inputs.iter() {
+            let spends = self.entries.get_by_id(id).map_or(false, |entry| {
+                entry
+                    .inner
+                    .transaction()
+                    .input_pts_iter()
+                    .any(|input| &input == out_point)
+            });
+            if !spends {
+                return Err(IntegrityError::StaleInput(
+                    out_point.to_string(),
+                    id.to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // drops the entry but leaves its edges behind, to exercise the integrity checks
+    #[cfg(test)]
+    pub(crate) fn remove_entry_skip_index(&mut self, id: &ProposalShortId) -> Option<TxEntry> {
+        self.entries.remove_by_id(id).map(|entry| {
+            self.update_usage(entry.status, entry.inner.size, EntryOp::Remove);
+            entry.inner
+        })
+    }
+
+    pub(crate) fn remove_entry_and_descendants(
+        &mut self,
+        id: &ProposalShortId,
+        reason: RemoveReason,
+    ) -> Vec<TxEntry> {
         let mut removed_ids = vec![id.to_owned()];
         removed_ids.extend(self.calc_descendants(id));
 
@@ -214,10 +520,18 @@ impl PoolMap {
 
         removed_ids
             .iter()
-            .filter_map(|id| self.remove_entry(id))
+            .filter_map(|id| self.remove_entry(id, reason))
             .collect()
     }
 
+    /// Returns the entries which have `header` in their header deps
+    pub(crate) fn entries_with_header_dep(&self, header: &Byte32) -> Vec<&PoolEntry> {
+        self.edges
+            .get_txids_by_header_dep(header)
+            .map(|ids| ids.iter().map(|id| self.get_by_id_checked(id)).collect())
+            .unwrap_or_default()
+    }
+
     pub(crate) fn resolve_conflict_header_dep(
         &mut self,
         headers: &HashSet<Byte32>,
@@ -225,18 +539,17 @@ impl PoolMap {
         let mut conflicts = Vec::new();
 
         // invalid header deps
-        let mut ids = Vec::new();
-        for (tx_id, deps) in self.edges.header_deps.iter() {
-            for hash in deps {
-                if headers.contains(hash) {
-                    ids.push((hash.clone(), tx_id.clone()));
-                    break;
-                }
-            }
-        }
+        let ids: Vec<_> = headers
+            .iter()
+            .flat_map(|hash| {
+                self.entries_with_header_dep(hash)
+                    .into_iter()
+                    .map(move |entry| (hash.clone(), entry.id.clone()))
+            })
+            .collect();
 
         for (blk_hash, id) in ids {
-            let entries = self.remove_entry_and_descendants(&id);
+            let entries = self.remove_entry_and_descendants(&id, RemoveReason::InvalidHeaderDep);
             for entry in entries {
                 let reject = Reject::Resolve(OutPointError::InvalidHeader(blk_hash.to_owned()));
                 conflicts.push((entry, reject));
@@ -245,10 +558,64 @@ impl PoolMap {
         conflicts
     }
 
-    pub(crate) fn find_conflict_tx(&self, tx: &TransactionView) -> HashSet<ProposalShortId> {
-        tx.input_pts_iter()
-            .filter_map(|out_point| self.edges.get_input_ref(&out_point).cloned())
-            .collect()
+    /// Returns the entries spending `out_point`, either as input or cell dep, together with
+    /// their descendants: the txs which would be removed if `out_point` were consumed elsewhere
+    pub(crate) fn dependents_of_outpoint(&self, out_point: &OutPoint) -> HashSet<ProposalShortId> {
+        let mut dependents: HashSet<ProposalShortId> = self
+            .edges
+            .get_input_ref(out_point)
+            .into_iter()
+            .chain(self.edges.get_deps_ref(out_point).into_iter().flatten())
+            .cloned()
+            .collect();
+        let descendants: Vec<_> = dependents
+            .iter()
+            .flat_map(|id| self.calc_descendants(id))
+            .collect();
+        dependents.extend(descendants);
+        dependents
+    }
+
+    /// Returns the entries using a cell spent by `tx` as cell dep, plain or through a dep group,
+    /// with the cell. The ancestors of `tx` are left out, they come before it in a block anyway.
+    pub(crate) fn find_dep_consumers(
+        &self,
+        tx: &TransactionView,
+    ) -> Vec<(ProposalShortId, OutPoint)> {
+        let mut ancestors = HashSet::new();
+        let referenced = tx
+            .input_pts_iter()
+            .chain(tx.cell_deps_iter().map(|dep| dep.out_point()));
+        for out_point in referenced {
+            let id = ProposalShortId::from_tx_hash(&out_point.tx_hash());
+            if self.get_by_id(&id).is_some() && !ancestors.contains(&id) {
+                ancestors.extend(self.calc_ancestors(&id));
+                ancestors.insert(id);
+            }
+        }
+
+        let mut consumers = Vec::new();
+        for out_point in tx.input_pts_iter() {
+            for id in self.edges.get_deps_ref(&out_point).into_iter().flatten() {
+                if !ancestors.contains(id) {
+                    consumers.push((id.clone(), out_point.clone()));
+                }
+            }
+        }
+        consumers
+    }
+
+    /// Returns the entries spending the inputs of `tx`, in the order of the inputs
+    pub(crate) fn find_conflict_tx(&self, tx: &TransactionView) -> Vec<ProposalShortId> {
+        let mut conflicts = Vec::new();
+        for out_point in tx.input_pts_iter() {
+            if let Some(id) = self.edges.get_input_ref(&out_point) {
+                if !conflicts.contains(id) {
+                    conflicts.push(id.clone());
+                }
+            }
+        }
+        conflicts
     }
 
     pub(crate) fn resolve_conflict(&mut self, tx: &TransactionView) -> Vec<ConflictEntry> {
@@ -256,7 +623,7 @@ impl PoolMap {
 
         for i in tx.input_pts_iter() {
             if let Some(id) = self.edges.remove_input(&i) {
-                let entries = self.remove_entry_and_descendants(&id);
+                let entries = self.remove_entry_and_descendants(&id, RemoveReason::Conflicted);
                 if !entries.is_empty() {
                     let reject = Reject::Resolve(OutPointError::Dead(i.clone()));
                     let rejects = std::iter::repeat(reject).take(entries.len());
@@ -267,7 +634,7 @@ impl PoolMap {
             // deps consumed
             if let Some(x) = self.edges.remove_deps(&i) {
                 for id in x {
-                    let entries = self.remove_entry_and_descendants(&id);
+                    let entries = self.remove_entry_and_descendants(&id, RemoveReason::Conflicted);
                     if !entries.is_empty() {
                         let reject = Reject::Resolve(OutPointError::Dead(i.clone()));
                         let rejects = std::iter::repeat(reject).take(entries.len());
@@ -293,7 +660,7 @@ impl PoolMap {
                 break;
             }
             let id = entry.proposal_short_id();
-            if !exclusion.contains(&id) {
+            if !exclusion.contains(&id) && !self.is_do_not_mine(&id) {
                 proposals.insert(id);
             }
         }
@@ -303,17 +670,38 @@ impl PoolMap {
         self.entries.iter().map(|(_, entry)| entry)
     }
 
+    /// Returns the entry with the lowest `TxEntry::package_fee_rate` in `status`
     pub(crate) fn next_evict_entry(&self, status: Status) -> Option<ProposalShortId> {
+        self.iter_evict_order(status)
+            .next()
+            .map(|entry| entry.id.clone())
+    }
+
+    /// Iterates the entries in `status`, the lowest `TxEntry::package_fee_rate` first
+    pub(crate) fn iter_evict_order(&self, status: Status) -> impl Iterator<Item = &PoolEntry> {
         self.entries
             .iter_by_evict_key()
-            .find(move |entry| entry.status == status)
-            .map(|entry| entry.id.clone())
+            .filter(move |entry| entry.status == status)
+    }
+
+    /// Fee rate of the first entry to evict, whatever its status
+    pub(crate) fn min_evict_fee_rate(&self) -> Option<FeeRate> {
+        self.entries
+            .iter_by_evict_key()
+            .next()
+            .map(|entry| entry.evict_key.fee_rate)
     }
 
     pub(crate) fn clear(&mut self) {
         self.entries = MultiIndexPoolEntryMap::default();
         self.edges.clear();
         self.links.clear();
+        self.hashes.clear();
+        self.changelog.reset();
+        self.double_spends.get_mut().clear();
+        self.usage.clear();
+        self.do_not_mine.clear();
+        self.version += 1;
     }
 
     pub(crate) fn score_sorted_iter_by(
@@ -327,6 +715,27 @@ impl PoolMap {
             .map(|entry| &entry.inner)
     }
 
+    /// Same as `score_sorted_iter_by` but ordered by `score`, highest first, entries with the
+    /// same score keep the order of the built-in score
+    pub(crate) fn score_sorted_iter_with<F>(
+        &self,
+        statuses: Vec<Status>,
+        score: F,
+    ) -> impl Iterator<Item = &TxEntry>
+    where
+        F: Fn(&PoolEntry) -> i128,
+    {
+        let mut entries: Vec<_> = self
+            .entries
+            .iter_by_score()
+            .rev()
+            .filter(|entry| statuses.contains(&entry.status))
+            .map(|entry| (score(entry), entry))
+            .collect();
+        entries.sort_by(|a, b| b.0.cmp(&a.0));
+        entries.into_iter().map(|(_, entry)| &entry.inner)
+    }
+
     fn remove_entry_links(&mut self, id: &ProposalShortId) {
         if let Some(parents) = self.links.get_parents(id).cloned() {
             for parent in parents {
@@ -390,8 +799,7 @@ impl PoolMap {
         // record header_deps
         if !header_deps.is_empty() {
             self.edges
-                .header_deps
-                .insert(tx_short_id, header_deps.into_iter().collect());
+                .insert_header_deps(tx_short_id, header_deps.into_iter().collect());
         }
     }
 
@@ -463,6 +871,20 @@ impl PoolMap {
             debug!("debug: exceeded maximum ancestors count");
             return Err(Reject::ExceededMaximumAncestorsCount);
         }
+        if entry.ancestors_size > self.max_package_size {
+            return Err(Reject::ExceededPackageLimit(
+                "size".to_owned(),
+                entry.ancestors_size as u64,
+                self.max_package_size as u64,
+            ));
+        }
+        if entry.ancestors_cycles > self.max_package_cycles {
+            return Err(Reject::ExceededPackageLimit(
+                "cycles".to_owned(),
+                entry.ancestors_cycles,
+                self.max_package_cycles,
+            ));
+        }
 
         for parent in &parents {
             self.links.add_child(parent, short_id.clone());
@@ -488,19 +910,27 @@ impl PoolMap {
             self.edges.delete_txid_by_dep(d, &id);
         }
 
-        self.edges.header_deps.remove(&id);
+        self.edges.remove_header_deps(&id);
     }
 
     fn insert_entry(&mut self, entry: &TxEntry, status: Status) {
         let tx_short_id = entry.proposal_short_id();
         let score = entry.as_score_key();
         let evict_key = entry.as_evict_key();
+        self.hashes
+            .insert(entry.transaction().hash(), tx_short_id.clone());
+        if entry.do_not_mine {
+            self.do_not_mine.insert(tx_short_id.clone());
+        }
+        self.update_usage(status, entry.size, EntryOp::Add);
+        self.version += 1;
         self.entries.insert(PoolEntry {
             id: tx_short_id,
             score,
             status,
             inner: entry.clone(),
             evict_key,
+            status_since: self.clock.now_ms(),
         });
     }
 }
@@ -0,0 +1,91 @@
+use ckb_network::PeerIndex;
+use std::collections::HashMap;
+
+/// Buckets are pruned once more peers than this are tracked
+const MAX_TRACKED_PEERS: usize = 1024;
+/// A peer silent for 10 minutes has a full bucket again, it can be forgotten when pruning
+const PEER_IDLE_MS: u64 = 10 * 60 * 1000;
+// tokens are counted in thousandths so that the bucket refills every millisecond
+const TOKEN_SCALE: u64 = 1000;
+
+/// Admission counters of a peer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PeerAdmissionStats {
+    /// Txs let through the rate limit
+    pub admitted: u64,
+    /// Txs rejected with `Reject::RateLimited`
+    pub throttled: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Bucket {
+    tokens: u64,
+    // unix timestamp in milliseconds of the last refill
+    last_refill: u64,
+    stats: PeerAdmissionStats,
+}
+
+/// Token bucket rate limit of the txs relayed by each peer.
+///
+/// A peer may send `rate` txs per second on average, and up to `burst` txs at once after being
+/// idle. Local submissions don't go through the limiter.
+pub(crate) struct PeerRateLimiter {
+    buckets: HashMap<PeerIndex, Bucket>,
+    rate: u64,
+    burst: u64,
+}
+
+impl PeerRateLimiter {
+    pub(crate) fn new(rate: u64, burst: u64) -> Self {
+        PeerRateLimiter {
+            buckets: HashMap::default(),
+            rate,
+            burst,
+        }
+    }
+
+    /// The limit is disabled when either the rate or the burst is zero
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.rate > 0 && self.burst > 0
+    }
+
+    /// Takes a token from the bucket of `peer` at `now`, returns false if the peer is throttled
+    pub(crate) fn try_admit(&mut self, peer: PeerIndex, now: u64) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        if self.buckets.len() >= MAX_TRACKED_PEERS && !self.buckets.contains_key(&peer) {
+            self.buckets
+                .retain(|_, bucket| bucket.last_refill.saturating_add(PEER_IDLE_MS) > now);
+        }
+
+        let capacity = self.burst.saturating_mul(TOKEN_SCALE);
+        let bucket = self.buckets.entry(peer).or_insert_with(|| Bucket {
+            tokens: capacity,
+            last_refill: now,
+            stats: PeerAdmissionStats::default(),
+        });
+        let elapsed = now.saturating_sub(bucket.last_refill);
+        bucket.tokens = bucket
+            .tokens
+            .saturating_add(elapsed.saturating_mul(self.rate))
+            .min(capacity);
+        bucket.last_refill = bucket.last_refill.max(now);
+
+        if bucket.tokens >= TOKEN_SCALE {
+            bucket.tokens -= TOKEN_SCALE;
+            bucket.stats.admitted += 1;
+            true
+        } else {
+            bucket.stats.throttled += 1;
+            false
+        }
+    }
+
+    pub(crate) fn stats(&self) -> HashMap<PeerIndex, PeerAdmissionStats> {
+        self.buckets
+            .iter()
+            .map(|(peer, bucket)| (*peer, bucket.stats))
+            .collect()
+    }
+}
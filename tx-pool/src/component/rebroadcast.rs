@@ -0,0 +1,101 @@
+use ckb_types::packed::{Byte32, ProposalShortId};
+use std::collections::HashMap;
+
+/// Upper bound of the delay between two re-announcements of the same tx, 6 hours
+pub(crate) const MAX_REBROADCAST_BACKOFF: u64 = 6 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone)]
+struct ScheduleEntry {
+    tx_hash: Byte32,
+    // unix timestamp in milliseconds of the next re-announcement
+    next_attempt: u64,
+    // delay in milliseconds applied after the next re-announcement
+    backoff: u64,
+}
+
+/// Schedule of locally submitted txs which should be re-announced while they are still pending.
+///
+/// A tx becomes due `min_age` after it was submitted, then the delay doubles after every
+/// re-announcement until it reaches `MAX_REBROADCAST_BACKOFF`.
+#[derive(Debug, Clone)]
+pub(crate) struct RebroadcastSchedule {
+    entries: HashMap<ProposalShortId, ScheduleEntry>,
+    min_age: u64,
+    batch_size: usize,
+}
+
+impl RebroadcastSchedule {
+    pub(crate) fn new(min_age: u64, batch_size: usize) -> Self {
+        RebroadcastSchedule {
+            entries: HashMap::default(),
+            min_age,
+            batch_size,
+        }
+    }
+
+    /// Re-broadcast is disabled when either the min age or the batch size is zero
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.min_age > 0 && self.batch_size > 0
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn contains_key(&self, id: &ProposalShortId) -> bool {
+        self.entries.contains_key(id)
+    }
+
+    /// Start tracking a local tx submitted at `now`
+    pub(crate) fn track(&mut self, id: ProposalShortId, tx_hash: Byte32, now: u64) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.entries.insert(
+            id,
+            ScheduleEntry {
+                tx_hash,
+                next_attempt: now.saturating_add(self.min_age),
+                backoff: self.min_age.saturating_mul(2).min(MAX_REBROADCAST_BACKOFF),
+            },
+        );
+    }
+
+    pub(crate) fn untrack(&mut self, id: &ProposalShortId) {
+        self.entries.remove(id);
+    }
+
+    /// Returns the hashes of txs due for re-announcement at `now`, oldest first and at most
+    /// `batch_size` of them, and reschedules them with a doubled backoff.
+    ///
+    /// Entries for which `is_pending` returns false are dropped from the schedule.
+    pub(crate) fn due<F>(&mut self, now: u64, is_pending: F) -> Vec<Byte32>
+    where
+        F: Fn(&ProposalShortId) -> bool,
+    {
+        self.entries.retain(|id, _| is_pending(id));
+
+        let mut due: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.next_attempt <= now)
+            .map(|(id, entry)| (entry.next_attempt, id.clone()))
+            .collect();
+        due.sort_unstable_by_key(|(next_attempt, _)| *next_attempt);
+        due.truncate(self.batch_size);
+
+        let mut hashes = Vec::with_capacity(due.len());
+        for (_, id) in due {
+            if let Some(entry) = self.entries.get_mut(&id) {
+                entry.next_attempt = now.saturating_add(entry.backoff);
+                entry.backoff = entry.backoff.saturating_mul(2).min(MAX_REBROADCAST_BACKOFF);
+                hashes.push(entry.tx_hash.clone());
+            }
+        }
+        hashes
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
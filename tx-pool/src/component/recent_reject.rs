@@ -7,6 +7,63 @@ use rand::{thread_rng, Rng};
 use std::path::Path;
 
 const DEFAULT_SHARDS: u32 = 5;
+/// Length of the window the reject rate is measured over, 1 minute
+pub(crate) const TUNING_WINDOW_MS: u64 = 60 * 1000;
+// grow the count limit when one window of rejects would turn over more than 1/4 of it
+const GROW_RATIO: u64 = 4;
+// shrink it back when one window of rejects would turn over less than 1/16 of it
+const SHRINK_RATIO: u64 = 16;
+
+/// The record kept for a rejected tx, see `RecentReject::get_record`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RejectRecord {
+    /// The latest reject reason, a `ckb_jsonrpc_types::PoolTransactionReject` in JSON
+    pub reason: String,
+    /// Unix timestamp in milliseconds of the latest reject
+    pub timestamp: u64,
+    /// Number of times the tx was rejected while the record was kept
+    pub count: u64,
+}
+
+impl RejectRecord {
+    fn encode(&self) -> Result<String, AnyError> {
+        let value = serde_json::json!({
+            "reason": self.reason,
+            "timestamp": self.timestamp,
+            "count": self.count,
+        });
+        Ok(serde_json::to_string(&value)?)
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        let raw = String::from_utf8_lossy(bytes);
+        let value: Option<serde_json::Value> = serde_json::from_str(&raw).ok();
+        let field = |name: &str| value.as_ref().and_then(|value| value.get(name).cloned());
+        match field("reason").and_then(|reason| reason.as_str().map(ToOwned::to_owned)) {
+            Some(reason) => RejectRecord {
+                reason,
+                timestamp: field("timestamp")
+                    .and_then(|timestamp| timestamp.as_u64())
+                    .unwrap_or(0),
+                count: field("count").and_then(|count| count.as_u64()).unwrap_or(1),
+            },
+            // written before the records were counted, only the reason was stored
+            None => RejectRecord {
+                reason: raw.into_owned(),
+                timestamp: 0,
+                count: 1,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CountLimitTuning {
+    min: u64,
+    max: u64,
+    window_start: u64,
+    window_rejects: u64,
+}
 
 #[derive(Debug)]
 pub struct RecentReject {
@@ -15,6 +72,7 @@ pub struct RecentReject {
     pub(crate) count_limit: u64,
     pub(crate) total_keys_num: u64,
     pub(crate) db: DBWithTTL,
+    tuning: Option<CountLimitTuning>,
 }
 
 impl RecentReject {
@@ -49,16 +107,55 @@ impl RecentReject {
             ttl,
             db,
             total_keys_num,
+            tuning: None,
         })
     }
 
-    pub fn put(&mut self, hash: &Byte32, reject: Reject) -> Result<(), AnyError> {
+    /// Lets the count limit double up to `max_count_limit` when the reject rate spikes, and halve
+    /// back down to the initial limit when it calms down.
+    pub fn with_max_count_limit(mut self, max_count_limit: u64) -> Self {
+        if max_count_limit > self.count_limit {
+            self.tuning = Some(CountLimitTuning {
+                min: self.count_limit,
+                max: max_count_limit,
+                window_start: 0,
+                window_rejects: 0,
+            });
+        }
+        self
+    }
+
+    /// The count limit currently in effect
+    pub fn effective_count_limit(&self) -> u64 {
+        self.count_limit
+    }
+
+    /// Records the reject of the tx `hash` at `now`, by the pool clock. A tx rejected again keeps
+    /// a single record, updated with the latest reason and timestamp, and whose TTL restarts from
+    /// this reject.
+    pub fn put(&mut self, hash: &Byte32, reject: Reject, now: u64) -> Result<(), AnyError> {
+        self.tune_count_limit(now);
+
         let hash_slice = hash.as_slice();
         let shard = self.get_shard(hash_slice).to_string();
         let reject: ckb_jsonrpc_types::PoolTransactionReject = reject.into();
-        let json_string = serde_json::to_string(&reject)?;
-        self.db.put(&shard, hash_slice, json_string)?;
+        let previous = self
+            .db
+            .get_pinned(&shard, hash_slice)?
+            .map(|bytes| RejectRecord::decode(&bytes));
+        let record = RejectRecord {
+            reason: serde_json::to_string(&reject)?,
+            timestamp: now,
+            count: previous
+                .as_ref()
+                .map_or(1, |record| record.count.saturating_add(1)),
+        };
+        self.db.put(&shard, hash_slice, record.encode()?)?;
 
+        if previous.is_some() {
+            // updated in place, no key added
+            return Ok(());
+        }
         if let Some(total_keys_num) = self.total_keys_num.checked_add(1) {
             if total_keys_num > self.count_limit {
                 self.shrink()?;
@@ -70,11 +167,34 @@ impl RecentReject {
         Ok(())
     }
 
+    /// The latest reject reason of the tx `hash`, a `ckb_jsonrpc_types::PoolTransactionReject`
+    /// in JSON
     pub fn get(&self, hash: &Byte32) -> Result<Option<String>, AnyError> {
+        Ok(self.get_record(hash)?.map(|record| record.reason))
+    }
+
+    /// The record of the tx `hash`, with the latest reason and the number of rejects
+    pub fn get_record(&self, hash: &Byte32) -> Result<Option<RejectRecord>, AnyError> {
         let slice = hash.as_slice();
         let shard = self.get_shard(slice).to_string();
         let ret = self.db.get_pinned(&shard, slice)?;
-        Ok(ret.map(|bytes| unsafe { String::from_utf8_unchecked(bytes.to_vec()) }))
+        Ok(ret.map(|bytes| RejectRecord::decode(&bytes)))
+    }
+
+    fn tune_count_limit(&mut self, now: u64) {
+        if let Some(tuning) = self.tuning.as_mut() {
+            if now.saturating_sub(tuning.window_start) >= TUNING_WINDOW_MS {
+                let rejects = tuning.window_rejects;
+                if rejects.saturating_mul(GROW_RATIO) > self.count_limit {
+                    self.count_limit = self.count_limit.saturating_mul(2).min(tuning.max);
+                } else if rejects.saturating_mul(SHRINK_RATIO) < self.count_limit {
+                    self.count_limit = (self.count_limit / 2).max(tuning.min);
+                }
+                tuning.window_start = now;
+                tuning.window_rejects = 0;
+            }
+            tuning.window_rejects = tuning.window_rejects.saturating_add(1);
+        }
     }
 
     fn shrink(&mut self) -> Result<u64, AnyError> {
@@ -0,0 +1,69 @@
+use crate::component::entry::TxEntry;
+use ckb_types::{core::Capacity, packed::Byte32};
+use lru::LruCache;
+
+/// Max number of replacement txs remembered until they are committed
+pub(crate) const REPLACEMENT_LEDGER_SIZE: usize = 10_000;
+
+/// What a committed replacement tx displaced from the pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplaceSummary {
+    /// Hash of the committed replacement tx
+    pub tx_hash: Byte32,
+    /// Hashes of all txs displaced by it, including the ones displaced by the txs it replaced
+    pub replaced: Vec<Byte32>,
+    /// Sum of the fees of the displaced txs
+    pub displaced_fee: Capacity,
+}
+
+#[derive(Debug, Clone)]
+struct ReplaceRecord {
+    replaced: Vec<Byte32>,
+    displaced_fee: Capacity,
+}
+
+/// Remembers which txs were displaced by RBF, keyed by the replacement tx hash
+pub(crate) struct ReplacementLedger {
+    records: LruCache<Byte32, ReplaceRecord>,
+}
+
+impl ReplacementLedger {
+    pub(crate) fn new(capacity: usize) -> Self {
+        ReplacementLedger {
+            records: LruCache::new(capacity),
+        }
+    }
+
+    /// Records that `tx_hash` displaced the `removed` entries, a displaced tx which was itself a
+    /// replacement hands its ancestry over to `tx_hash`.
+    pub(crate) fn record(&mut self, tx_hash: Byte32, removed: &[TxEntry]) {
+        let mut record = self.records.pop(&tx_hash).unwrap_or(ReplaceRecord {
+            replaced: Vec::new(),
+            displaced_fee: Capacity::zero(),
+        });
+        for entry in removed {
+            let hash = entry.transaction().hash();
+            let mut fee = entry.fee.as_u64();
+            if let Some(ancestry) = self.records.pop(&hash) {
+                record.replaced.extend(ancestry.replaced);
+                fee = fee.saturating_add(ancestry.displaced_fee.as_u64());
+            }
+            record.replaced.push(hash);
+            record.displaced_fee =
+                Capacity::shannons(record.displaced_fee.as_u64().saturating_add(fee));
+        }
+        self.records.put(tx_hash, record);
+    }
+
+    pub(crate) fn take(&mut self, tx_hash: &Byte32) -> Option<ReplaceSummary> {
+        self.records.pop(tx_hash).map(|record| ReplaceSummary {
+            tx_hash: tx_hash.clone(),
+            replaced: record.replaced,
+            displaced_fee: record.displaced_fee,
+        })
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.records.clear();
+    }
+}
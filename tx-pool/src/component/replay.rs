@@ -0,0 +1,615 @@
+//! Append-only log of the inputs the tx-pool decides on, to re-run a node's pool in a test
+use crate::component::entry::TxEntry;
+use crate::component::pool_map::Status;
+use crate::pool::{TxPool, TxPoolThresholds};
+use crate::process::TxStatus;
+use ckb_app_config::TxPoolConfig;
+use ckb_channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use ckb_jsonrpc_types::{JsonBytes, TxPoolEntries};
+use ckb_logger::{error, info, warn};
+use ckb_snapshot::Snapshot;
+use ckb_store::ChainStore;
+use ckb_types::{
+    bytes::Bytes,
+    core::{
+        tx_pool::{Origin, Reject},
+        BlockNumber, BlockView, Capacity, Cycle, DepType, FeeRate, TransactionView,
+    },
+    packed::{Byte32, CellEntry, OutPoint, OutPointVec, ProposalShortId},
+    prelude::*,
+    H256,
+};
+use serde_json::json;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write as _};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long the records handed to the writer may wait in its buffer
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// One record of the replay log, written as a line of JSON along with the pool time
+#[derive(Clone, Debug)]
+pub(crate) enum ReplayEvent {
+    /// The pool started with this config, a replay starts over from an empty pool
+    Start(Box<TxPoolConfig>),
+    /// An attempt to admit a tx, once it was pre-checked against the pool
+    Attempt {
+        tx: TransactionView,
+        origin: Origin,
+        /// The chain cells the tx refers to, the replay resolves it against them
+        cells: Vec<ChainCell>,
+        /// Why the pre-check rejected the tx, `None` if the tx went on to be verified
+        reject: Option<String>,
+    },
+    /// The reject an attempt ended with, the replay only runs the pre-check of an attempt
+    Rejected { tx_hash: Byte32, reason: String },
+    /// A verified tx submitted to the pool
+    Submit {
+        tx: TransactionView,
+        origin: Origin,
+        status: TxStatus,
+        cycles: Cycle,
+        fee: Capacity,
+        size: usize,
+        /// When the entry was verified, by the pool clock
+        entered_at: u64,
+    },
+    /// An entry and its descendants removed for the replacement submitted next
+    Replaced(ProposalShortId),
+    /// The pool caught up with the chain, see `_update_tx_pool_for_reorg`
+    Commit {
+        /// Number and tx hashes of each attached block
+        blocks: Vec<(BlockNumber, Vec<Byte32>)>,
+        /// The committed txs which were not in the pool, the others are found in the log
+        foreign: Vec<TransactionView>,
+        /// Hashes of the detached headers
+        detached: Vec<Byte32>,
+        /// The entries the new tip moves to proposed or gap, when mining
+        proposed: Vec<ProposalShortId>,
+        gap: Vec<ProposalShortId>,
+    },
+    /// The thresholds were changed at runtime
+    Thresholds(TxPoolThresholds),
+    /// The pool was cleared
+    Clear,
+    /// The entries the pool held when the log was closed
+    Expect(Box<TxPoolEntries>),
+}
+
+/// A chain cell an attempted tx spends or depends on, added to the store of a replay
+#[derive(Clone, Debug)]
+pub(crate) struct ChainCell {
+    pub(crate) out_point: OutPoint,
+    pub(crate) entry: CellEntry,
+    pub(crate) data: Option<Bytes>,
+}
+
+// the chain cells `tx` refers to in `snapshot`, the members of its dep groups included
+fn chain_cells(snapshot: &Snapshot, tx: &TransactionView) -> Vec<ChainCell> {
+    let mut out_points: Vec<OutPoint> = tx
+        .input_pts_iter()
+        .chain(tx.cell_deps_iter().map(|dep| dep.out_point()))
+        .collect();
+    for dep in tx
+        .cell_deps_iter()
+        .filter(|dep| dep.dep_type() == DepType::DepGroup.into())
+    {
+        let members = snapshot
+            .get_cell_data(&dep.out_point())
+            .and_then(|(data, _)| OutPointVec::from_slice(&data).ok());
+        if let Some(members) = members {
+            out_points.extend(members.into_iter());
+        }
+    }
+    out_points
+        .into_iter()
+        .filter_map(|out_point| {
+            let cell = snapshot.get_cell(&out_point)?;
+            let info = cell.transaction_info?;
+            let entry = CellEntry::new_builder()
+                .output(cell.cell_output)
+                .block_hash(info.block_hash)
+                .block_number(info.block_number.pack())
+                .block_epoch(info.block_epoch.pack())
+                .index(info.index.pack())
+                .data_size(cell.data_bytes.pack())
+                .build();
+            let data = snapshot.get_cell_data(&out_point).map(|(data, _)| data);
+            Some(ChainCell {
+                out_point,
+                entry,
+                data,
+            })
+        })
+        .collect()
+}
+
+fn encode_tx(tx: &TransactionView) -> JsonBytes {
+    JsonBytes::from_bytes(tx.data().as_bytes())
+}
+
+fn encode_origin(origin: &Origin) -> &'static str {
+    match origin {
+        Origin::Local => "local",
+        Origin::Remote => "remote",
+        Origin::Reorg => "reorg",
+    }
+}
+
+fn encode_cell(cell: &ChainCell) -> serde_json::Value {
+    json!({
+        "out_point": ckb_jsonrpc_types::OutPoint::from(cell.out_point.clone()),
+        "entry": JsonBytes::from_bytes(cell.entry.as_bytes()),
+        "data": cell.data.clone().map(JsonBytes::from_bytes),
+    })
+}
+
+fn encode_hash(hash: &Byte32) -> H256 {
+    hash.unpack()
+}
+
+fn encode_hashes(hashes: &[Byte32]) -> Vec<H256> {
+    hashes.iter().map(encode_hash).collect()
+}
+
+fn encode_ids(ids: &[ProposalShortId]) -> Vec<ckb_jsonrpc_types::ProposalShortId> {
+    ids.iter().cloned().map(Into::into).collect()
+}
+
+impl ReplayEvent {
+    /// The record of an attempt to admit `tx`, the chain cells it refers to are looked up in
+    /// `snapshot`
+    pub(crate) fn attempt(
+        snapshot: &Snapshot,
+        tx: &TransactionView,
+        origin: Origin,
+        reject: Option<&Reject>,
+    ) -> Self {
+        ReplayEvent::Attempt {
+            tx: tx.clone(),
+            origin,
+            cells: chain_cells(snapshot, tx),
+            reject: reject.map(ToString::to_string),
+        }
+    }
+
+    /// The record of `entry` submitted with `status`
+    pub(crate) fn submit(entry: &TxEntry, status: TxStatus) -> Self {
+        ReplayEvent::Submit {
+            tx: entry.transaction().clone(),
+            origin: entry.origin,
+            status,
+            cycles: entry.cycles,
+            fee: entry.fee,
+            size: entry.size,
+            entered_at: entry.timestamp,
+        }
+    }
+
+    /// The record of the pool catching up with `snapshot`, taken before the pool is updated
+    pub(crate) fn commit(
+        tx_pool: &TxPool,
+        attached_blocks: &[BlockView],
+        detached_headers: &HashSet<Byte32>,
+        snapshot: &Snapshot,
+        mine_mode: bool,
+    ) -> Self {
+        let mut blocks = Vec::with_capacity(attached_blocks.len());
+        let mut foreign = Vec::new();
+        for block in attached_blocks {
+            let mut txs = Vec::new();
+            for tx in block.transactions().into_iter().skip(1) {
+                let in_pool = tx_pool
+                    .get_tx_from_pool(&tx.proposal_short_id())
+                    .map_or(false, |pool_tx| pool_tx.hash() == tx.hash());
+                txs.push(tx.hash());
+                if !in_pool {
+                    foreign.push(tx);
+                }
+            }
+            blocks.push((block.number(), txs));
+        }
+
+        let (mut proposed, mut gap) = (Vec::new(), Vec::new());
+        if mine_mode {
+            let proposals = snapshot.proposals();
+            for status in [Status::Pending, Status::Gap] {
+                for entry in tx_pool.pool_map.entries.get_by_status(&status) {
+                    let id = entry.inner.proposal_short_id();
+                    if proposals.contains_proposed(&id) {
+                        proposed.push(id);
+                    } else if status == Status::Pending && proposals.contains_gap(&id) {
+                        gap.push(id);
+                    }
+                }
+            }
+        }
+
+        ReplayEvent::Commit {
+            blocks,
+            foreign,
+            detached: detached_headers.iter().cloned().collect(),
+            proposed,
+            gap,
+        }
+    }
+
+    /// Encodes the event as one line of JSON, without the line break
+    pub(crate) fn encode(&self, timestamp: u64) -> String {
+        let mut record = match self {
+            ReplayEvent::Start(config) => json!({ "event": "start", "config": config }),
+            ReplayEvent::Attempt {
+                tx,
+                origin,
+                cells,
+                reject,
+            } => json!({
+                "event": "attempt",
+                "tx": encode_tx(tx),
+                "origin": encode_origin(origin),
+                "cells": cells.iter().map(encode_cell).collect::<Vec<_>>(),
+                "reject": reject,
+            }),
+            ReplayEvent::Rejected { tx_hash, reason } => json!({
+                "event": "rejected",
+                "tx_hash": encode_hash(tx_hash),
+                "reason": reason,
+            }),
+            ReplayEvent::Submit {
+                tx,
+                origin,
+                status,
+                cycles,
+                fee,
+                size,
+                entered_at,
+            } => json!({
+                "event": "submit",
+                "tx": encode_tx(tx),
+                "origin": encode_origin(origin),
+                "status": match status {
+                    TxStatus::Fresh => "fresh",
+                    TxStatus::Gap => "gap",
+                    TxStatus::Proposed => "proposed",
+                },
+                "cycles": cycles,
+                "fee": fee.as_u64(),
+                "size": size,
+                "entered_at": entered_at,
+            }),
+            ReplayEvent::Replaced(id) => json!({
+                "event": "replaced",
+                "id": ckb_jsonrpc_types::ProposalShortId::from(id.clone()),
+            }),
+            ReplayEvent::Commit {
+                blocks,
+                foreign,
+                detached,
+                proposed,
+                gap,
+            } => json!({
+                "event": "commit",
+                "blocks": blocks
+                    .iter()
+                    .map(|(number, txs)| json!({ "number": number, "txs": encode_hashes(txs) }))
+                    .collect::<Vec<_>>(),
+                "foreign": foreign.iter().map(encode_tx).collect::<Vec<_>>(),
+                "detached": encode_hashes(detached),
+                "proposed": encode_ids(proposed),
+                "gap": encode_ids(gap),
+            }),
+            ReplayEvent::Thresholds(thresholds) => json!({
+                "event": "thresholds",
+                "max_tx_pool_size": thresholds.max_tx_pool_size,
+                "min_fee_rate": thresholds.min_fee_rate.map(FeeRate::as_u64),
+                "min_rbf_rate": thresholds.min_rbf_rate.map(FeeRate::as_u64),
+                "expiry_hours": thresholds.expiry_hours,
+                "max_ancestors_count": thresholds.max_ancestors_count,
+            }),
+            ReplayEvent::Clear => json!({ "event": "clear" }),
+            ReplayEvent::Expect(entries) => json!({ "event": "expect", "entries": entries }),
+        };
+        record["timestamp"] = timestamp.into();
+        record.to_string()
+    }
+
+    /// Decodes a line written by `encode`, returns the event and its timestamp
+    #[cfg(test)]
+    pub(crate) fn decode(line: &str) -> Result<(u64, ReplayEvent), String> {
+        let record: serde_json::Value =
+            serde_json::from_str(line).map_err(|err| err.to_string())?;
+        macro_rules! field {
+            ($value:expr, $name:expr) => {
+                serde_json::from_value($value[$name].clone())
+                    .map_err(|err| format!("field {}: {}", $name, err))?
+            };
+        }
+        let decode_tx = |bytes: JsonBytes| {
+            ckb_types::packed::Transaction::from_slice(bytes.as_bytes())
+                .map(|tx| tx.into_view())
+                .map_err(|err| err.to_string())
+        };
+        let decode_hashes = |hashes: Vec<H256>| {
+            hashes
+                .iter()
+                .map(|hash| hash.pack())
+                .collect::<Vec<Byte32>>()
+        };
+        let decode_ids = |ids: Vec<ckb_jsonrpc_types::ProposalShortId>| {
+            ids.into_iter()
+                .map(Into::into)
+                .collect::<Vec<ProposalShortId>>()
+        };
+        let decode_origin = |origin: String| match origin.as_str() {
+            "local" => Ok(Origin::Local),
+            "remote" => Ok(Origin::Remote),
+            "reorg" => Ok(Origin::Reorg),
+            other => Err(format!("unknown origin {other}")),
+        };
+        let decode_cell = |cell: serde_json::Value| -> Result<ChainCell, String> {
+            let out_point: ckb_jsonrpc_types::OutPoint = field!(cell, "out_point");
+            let entry: JsonBytes = field!(cell, "entry");
+            let data: Option<JsonBytes> = field!(cell, "data");
+            Ok(ChainCell {
+                out_point: out_point.into(),
+                entry: CellEntry::from_slice(entry.as_bytes()).map_err(|err| err.to_string())?,
+                data: data.map(JsonBytes::into_bytes),
+            })
+        };
+
+        let timestamp: u64 = field!(record, "timestamp");
+        let event: String = field!(record, "event");
+        let event = match event.as_str() {
+            "start" => ReplayEvent::Start(Box::new(field!(record, "config"))),
+            "attempt" => {
+                let cells: Vec<serde_json::Value> = field!(record, "cells");
+                ReplayEvent::Attempt {
+                    tx: decode_tx(field!(record, "tx"))?,
+                    origin: decode_origin(field!(record, "origin"))?,
+                    cells: cells
+                        .into_iter()
+                        .map(decode_cell)
+                        .collect::<Result<_, _>>()?,
+                    reject: field!(record, "reject"),
+                }
+            }
+            "rejected" => {
+                let tx_hash: H256 = field!(record, "tx_hash");
+                ReplayEvent::Rejected {
+                    tx_hash: tx_hash.pack(),
+                    reason: field!(record, "reason"),
+                }
+            }
+            "submit" => {
+                let status: String = field!(record, "status");
+                ReplayEvent::Submit {
+                    tx: decode_tx(field!(record, "tx"))?,
+                    origin: decode_origin(field!(record, "origin"))?,
+                    status: match status.as_str() {
+                        "fresh" => TxStatus::Fresh,
+                        "gap" => TxStatus::Gap,
+                        "proposed" => TxStatus::Proposed,
+                        other => return Err(format!("unknown status {other}")),
+                    },
+                    cycles: field!(record, "cycles"),
+                    fee: Capacity::shannons(field!(record, "fee")),
+                    size: field!(record, "size"),
+                    entered_at: field!(record, "entered_at"),
+                }
+            }
+            "replaced" => {
+                let id: ckb_jsonrpc_types::ProposalShortId = field!(record, "id");
+                ReplayEvent::Replaced(id.into())
+            }
+            "commit" => {
+                let blocks: Vec<serde_json::Value> = field!(record, "blocks");
+                let blocks = blocks
+                    .into_iter()
+                    .map(|block| -> Result<(BlockNumber, Vec<Byte32>), String> {
+                        Ok((field!(block, "number"), decode_hashes(field!(block, "txs"))))
+                    })
+                    .collect::<Result<_, _>>()?;
+                let foreign: Vec<JsonBytes> = field!(record, "foreign");
+                ReplayEvent::Commit {
+                    blocks,
+                    foreign: foreign
+                        .into_iter()
+                        .map(decode_tx)
+                        .collect::<Result<_, _>>()?,
+                    detached: decode_hashes(field!(record, "detached")),
+                    proposed: decode_ids(field!(record, "proposed")),
+                    gap: decode_ids(field!(record, "gap")),
+                }
+            }
+            "thresholds" => {
+                let min_fee_rate: Option<u64> = field!(record, "min_fee_rate");
+                let min_rbf_rate: Option<u64> = field!(record, "min_rbf_rate");
+                ReplayEvent::Thresholds(TxPoolThresholds {
+                    max_tx_pool_size: field!(record, "max_tx_pool_size"),
+                    min_fee_rate: min_fee_rate.map(FeeRate::from_u64),
+                    min_rbf_rate: min_rbf_rate.map(FeeRate::from_u64),
+                    expiry_hours: field!(record, "expiry_hours"),
+                    max_ancestors_count: field!(record, "max_ancestors_count"),
+                })
+            }
+            "clear" => ReplayEvent::Clear,
+            "expect" => ReplayEvent::Expect(Box::new(field!(record, "entries"))),
+            other => return Err(format!("unknown event {other}")),
+        };
+        Ok((timestamp, event))
+    }
+}
+
+/// The replay log file, see `TxPoolConfig::replay_log`.
+///
+/// Records are only appended, a restarted node adds a new `Start` record to the same file. They
+/// are encoded by the caller, to be counted against the size bound, and written by a background
+/// thread which flushes them at least every `FLUSH_INTERVAL`. Half of the room left when the log
+/// is opened is kept for the `Expect` record, once the other records fill the rest the log is
+/// closed and nothing else is written.
+pub(crate) struct ReplayLog {
+    path: PathBuf,
+    sender: Sender<String>,
+    writer: JoinHandle<()>,
+    /// The size of the file when it was opened
+    start: u64,
+    /// The bytes in the file and handed to the writer
+    size: AtomicU64,
+    /// The bytes the records but `Expect` may take
+    limit: u64,
+    max_size: u64,
+    full: AtomicBool,
+}
+
+impl ReplayLog {
+    /// Opens the configured log, `None` if it's disabled, already full or can't be opened
+    pub(crate) fn open(config: &TxPoolConfig) -> Option<ReplayLog> {
+        let path = config.replay_log.clone()?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|file| file.metadata().map(|metadata| (file, metadata.len())));
+        let (file, size) = match file {
+            Ok((_, size)) if size >= config.replay_log_max_size => {
+                warn!("tx-pool replay log {:?} is full, nothing is recorded", path);
+                return None;
+            }
+            Ok(opened) => opened,
+            Err(err) => {
+                error!("failed to open the tx-pool replay log {:?}: {}", path, err);
+                return None;
+            }
+        };
+
+        // the writer works until the sender is dropped, the records queued are bounded by the
+        // size of the log
+        let (sender, receiver) = unbounded();
+        let writer = {
+            let path = path.clone();
+            thread::Builder::new()
+                .name("TxPoolReplayLog".to_string())
+                .spawn(move || write_records(path, file, receiver))
+        };
+        match writer {
+            Ok(writer) => {
+                info!("tx-pool replay log {:?} opened", path);
+                Some(ReplayLog {
+                    path,
+                    sender,
+                    writer,
+                    start: size,
+                    size: AtomicU64::new(size),
+                    limit: size + (config.replay_log_max_size - size) / 2,
+                    max_size: config.replay_log_max_size,
+                    full: AtomicBool::new(false),
+                })
+            }
+            Err(err) => {
+                error!(
+                    "failed to spawn the tx-pool replay log writer {:?}: {}",
+                    path, err
+                );
+                None
+            }
+        }
+    }
+
+    /// Whether a record didn't fit, the log must be closed then
+    pub(crate) fn is_full(&self) -> bool {
+        self.full.load(Ordering::Acquire)
+    }
+
+    /// Hands a record to the writer, returns false if the log is full or the writer failed
+    pub(crate) fn append(&self, record: &str) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        let len = record.len() as u64 + 1;
+        let fits = self
+            .size
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |size| {
+                (size + len <= self.limit).then_some(size + len)
+            })
+            .is_ok();
+        if !fits {
+            if !self.full.swap(true, Ordering::AcqRel) {
+                info!(
+                    "tx-pool replay log {:?} reached {} bytes, closing it",
+                    self.path, self.limit
+                );
+            }
+            return false;
+        }
+        // the writer stops on a write error, which it reported
+        if self.sender.send(record.to_owned()).is_err() {
+            self.full.store(true, Ordering::Release);
+            return false;
+        }
+        true
+    }
+
+    /// Hands the last record to the writer if it fits in `replay_log_max_size`, and waits for
+    /// the writer to flush everything. Nothing is written if no record fit before.
+    pub(crate) fn close(self, record: &str) {
+        let ReplayLog {
+            path,
+            sender,
+            writer,
+            start,
+            size,
+            max_size,
+            ..
+        } = self;
+        let size = size.load(Ordering::Acquire);
+        let len = record.len() as u64 + 1;
+        // if not even the `Start` record fit there is nothing to replay
+        if size > start && size + len <= max_size {
+            // a writer which stopped already reported why
+            let _ = sender.send(record.to_owned());
+        } else if size > start {
+            warn!(
+                "tx-pool replay log {:?} has no room left for the {} bytes of the expected \
+                 entries, the log ends without them",
+                path, len
+            );
+        }
+        drop(sender);
+        if writer.join().is_err() {
+            error!("the tx-pool replay log {:?} writer panicked", path);
+        }
+    }
+}
+
+// writes the records handed over until the log is closed, a write error stops the writer
+fn write_records(path: PathBuf, file: File, receiver: Receiver<String>) {
+    let mut writer = BufWriter::new(file);
+    let mut flushed = Instant::now();
+    loop {
+        let written = match receiver.recv_timeout(FLUSH_INTERVAL) {
+            Ok(record) => writer
+                .write_all(record.as_bytes())
+                .and_then(|_| writer.write_all(b"\n")),
+            Err(RecvTimeoutError::Timeout) => Ok(()),
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let ret = written.and_then(|_| {
+            if flushed.elapsed() < FLUSH_INTERVAL {
+                return Ok(());
+            }
+            flushed = Instant::now();
+            writer.flush()
+        });
+        if let Err(err) = ret {
+            error!("failed to write the tx-pool replay log {:?}: {}", path, err);
+            return;
+        }
+    }
+    if let Err(err) = writer.flush() {
+        error!("failed to flush the tx-pool replay log {:?}: {}", path, err);
+    }
+}
@@ -0,0 +1,66 @@
+use ckb_types::{core::cell::ResolvedTransaction, packed::Byte32};
+use lru::LruCache;
+use std::sync::Arc;
+
+struct CachedResolve {
+    tip_hash: Byte32,
+    pool_seq: u64,
+    rtx: Arc<ResolvedTransaction>,
+}
+
+/// Successful resolutions of `TxPool::resolve_tx_from_pool`, keyed by tx hash and RBF flag, so
+/// a tx resolved again, e.g. on retry, skips the cell lookups.
+///
+/// A result is only served against the snapshot tip and the pool changelog sequence number it
+/// was computed with, any block or pool membership change makes it stale.
+pub(crate) struct ResolveCache {
+    entries: LruCache<(Byte32, bool), CachedResolve>,
+}
+
+impl ResolveCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        ResolveCache {
+            entries: LruCache::new(capacity),
+        }
+    }
+
+    /// Returns the cached resolution of `tx_hash` if still valid, drops it if stale
+    pub(crate) fn get(
+        &mut self,
+        tx_hash: &Byte32,
+        rbf: bool,
+        tip_hash: &Byte32,
+        pool_seq: u64,
+    ) -> Option<Arc<ResolvedTransaction>> {
+        let key = (tx_hash.clone(), rbf);
+        let cached = self.entries.get(&key)?;
+        if &cached.tip_hash == tip_hash && cached.pool_seq == pool_seq {
+            Some(Arc::clone(&cached.rtx))
+        } else {
+            self.entries.pop(&key);
+            None
+        }
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        rbf: bool,
+        tip_hash: Byte32,
+        pool_seq: u64,
+        rtx: Arc<ResolvedTransaction>,
+    ) {
+        let key = (rtx.transaction.hash(), rbf);
+        self.entries.put(
+            key,
+            CachedResolve {
+                tip_hash,
+                pool_seq,
+                rtx,
+            },
+        );
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
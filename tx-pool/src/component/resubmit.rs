@@ -0,0 +1,112 @@
+use crate::component::entry::TxEntry;
+use crate::error::Reject;
+use ckb_types::{
+    core::{Capacity, Cycle, TransactionView},
+    packed::Byte32,
+};
+use ckb_util::LinkedHashMap;
+
+/// A local tx which expired in the pool or was dropped by a clearing reorg, kept to be submitted
+/// again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResubmitEntry {
+    /// The expired tx
+    pub transaction: TransactionView,
+    /// Fee paid by the tx
+    pub fee: Capacity,
+    /// Cycles consumed by its scripts
+    pub cycles: Cycle,
+    /// Unix timestamp in milliseconds the tx entered the pool
+    pub submitted_at: u64,
+    /// Unix timestamp in milliseconds the tx expired
+    pub expired_at: u64,
+}
+
+impl ResubmitEntry {
+    /// The record of the pool `entry` which left at `expired_at`
+    pub(crate) fn new(entry: &TxEntry, expired_at: u64) -> Self {
+        ResubmitEntry {
+            transaction: entry.transaction().clone(),
+            fee: entry.fee,
+            cycles: entry.cycles,
+            submitted_at: entry.timestamp,
+            expired_at,
+        }
+    }
+}
+
+/// Bounded list of the local txs which expired in the pool, the oldest expiry first.
+///
+/// When full, the oldest entry is dropped to make room, entries are dropped anyway once they
+/// expired more than `retention` milliseconds ago.
+pub(crate) struct ResubmitList {
+    entries: LinkedHashMap<Byte32, ResubmitEntry>,
+    capacity: usize,
+    retention: u64,
+}
+
+impl ResubmitList {
+    pub(crate) fn new(capacity: usize, retention: u64) -> Self {
+        ResubmitList {
+            entries: LinkedHashMap::default(),
+            capacity,
+            retention,
+        }
+    }
+
+    /// Keeps the expired `entry` to be submitted again
+    pub(crate) fn insert(&mut self, entry: &TxEntry, now_ms: u64) {
+        self.push(ResubmitEntry::new(entry, now_ms));
+    }
+
+    /// Puts back an entry taken out by `take`
+    pub(crate) fn push(&mut self, entry: ResubmitEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        let hash = entry.transaction.hash();
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&hash) {
+            self.entries.pop_front();
+        }
+        self.entries.insert(hash, entry);
+    }
+
+    /// Drops the entries which expired more than `retention` milliseconds before `now_ms`
+    pub(crate) fn prune(&mut self, now_ms: u64) {
+        while let Some((_, entry)) = self.entries.front() {
+            if entry.expired_at.saturating_add(self.retention) >= now_ms {
+                break;
+            }
+            self.entries.pop_front();
+        }
+    }
+
+    /// The kept entries, the oldest expiry first
+    pub(crate) fn entries(&self) -> Vec<ResubmitEntry> {
+        self.entries.values().cloned().collect()
+    }
+
+    /// Takes all the kept entries out, the oldest expiry first
+    pub(crate) fn take(&mut self) -> Vec<ResubmitEntry> {
+        let mut entries = Vec::with_capacity(self.entries.len());
+        while let Some((_, entry)) = self.entries.pop_front() {
+            entries.push(entry);
+        }
+        entries
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Outcome of submitting the expired local txs again, see `TxPoolController::resubmit_expired`
+#[derive(Debug, Default)]
+pub struct ResubmitReport {
+    /// Txs submitted to the pool again
+    pub resubmitted: Vec<Byte32>,
+    /// Txs dropped from the list with the reason, e.g. an input spent since they expired
+    pub dropped: Vec<(Byte32, Reject)>,
+    /// Txs which failed for another reason, kept in the list for a later attempt
+    pub kept: Vec<(Byte32, Reject)>,
+}
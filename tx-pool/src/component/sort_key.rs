@@ -33,6 +33,11 @@ impl PartialOrd for AncestorsScoreSortKey {
 
 impl Ord for AncestorsScoreSortKey {
     fn cmp(&self, other: &Self) -> Ordering {
+        // zero-fee txs sort last, whatever their weights
+        let (zero_fee, other_zero_fee) = (self.fee.as_u64() == 0, other.fee.as_u64() == 0);
+        if zero_fee != other_zero_fee {
+            return other_zero_fee.cmp(&zero_fee);
+        }
         // avoid division a_fee/a_weight > b_fee/b_weight
         let (fee, weight) = self.min_fee_and_weight();
         let (other_fee, other_weight) = other.min_fee_and_weight();
@@ -47,11 +52,13 @@ impl Ord for AncestorsScoreSortKey {
     }
 }
 
-/// First compare fee_rate, select the smallest fee_rate,
-/// and then select the latest timestamp, for eviction,
+/// First select the packages paying no fee at all, then compare fee_rate, select the smallest
+/// fee_rate, and then select the latest timestamp, for eviction,
 /// the latest timestamp which also means that the fewer descendants may exist.
 #[derive(Eq, PartialEq, Clone, Debug)]
 pub struct EvictKey {
+    /// Neither the tx nor its descendants pay any fee
+    pub zero_fee: bool,
     pub fee_rate: FeeRate,
     pub timestamp: u64,
     pub descendants_count: usize,
@@ -65,7 +72,9 @@ impl PartialOrd for EvictKey {
 
 impl Ord for EvictKey {
     fn cmp(&self, other: &Self) -> Ordering {
-        if self.fee_rate == other.fee_rate {
+        if self.zero_fee != other.zero_fee {
+            other.zero_fee.cmp(&self.zero_fee)
+        } else if self.fee_rate == other.fee_rate {
             if self.descendants_count == other.descendants_count {
                 self.timestamp.cmp(&other.timestamp)
             } else {
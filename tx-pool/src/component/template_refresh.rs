@@ -0,0 +1,63 @@
+use crate::component::entry::TxEntry;
+use ckb_types::core::FeeRate;
+
+/// Tracks how much the txs proposed since the last block template would improve it, to rebuild
+/// the template once worth it rather than on every pool change.
+///
+/// A tx improves the template by the fee it pays above the lowest fee rate of the template for
+/// its weight, the txs paying less would not make it in.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TemplateRefresh {
+    threshold: Option<u64>,
+    marginal_rate: FeeRate,
+    improvement: u64,
+    dirty: bool,
+    txs_size_limit: Option<usize>,
+}
+
+impl TemplateRefresh {
+    pub(crate) fn new(threshold: Option<u64>) -> Self {
+        TemplateRefresh {
+            threshold,
+            ..Default::default()
+        }
+    }
+
+    /// Starts over from a freshly built template holding `txs`, within `txs_size_limit` bytes
+    pub(crate) fn reset(&mut self, txs: &[TxEntry], txs_size_limit: usize) {
+        self.txs_size_limit = Some(txs_size_limit);
+        self.marginal_rate = txs
+            .iter()
+            .map(TxEntry::fee_rate)
+            .min()
+            .unwrap_or_else(FeeRate::zero);
+        self.improvement = 0;
+        self.dirty = false;
+    }
+
+    /// Accounts a newly proposed entry, returns true if it makes the template dirty
+    pub(crate) fn record(&mut self, entry: &TxEntry) -> bool {
+        let threshold = match self.threshold {
+            Some(threshold) if !self.dirty => threshold,
+            _ => return false,
+        };
+        if entry.fee_rate() <= self.marginal_rate {
+            return false;
+        }
+        let marginal_fee = self.marginal_rate.fee(entry.weight());
+        self.improvement = self
+            .improvement
+            .saturating_add(entry.fee.as_u64().saturating_sub(marginal_fee.as_u64()));
+        self.dirty = self.improvement >= threshold;
+        self.dirty
+    }
+
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Bytes left for the txs in the last template, `None` if none was built yet
+    pub(crate) fn txs_size_limit(&self) -> Option<usize> {
+        self.txs_size_limit
+    }
+}
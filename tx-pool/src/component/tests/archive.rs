@@ -0,0 +1,211 @@
+use crate::callback::Callbacks;
+use crate::component::archive::EvictedArchive;
+use crate::component::entry::TxEntry;
+use crate::component::tests::util::{build_block, build_tx, build_tx_pool_with_chain, MOCK_CYCLES};
+use crate::error::Reject;
+use crate::pool::TxPool;
+use ckb_app_config::{StatusQuota, TxPoolConfig};
+use ckb_proposal_table::ProposalView;
+use ckb_types::{
+    core::{Capacity, TransactionBuilder, TransactionView},
+    packed::{Byte32, CellInput, CellOutput, OutPoint},
+    prelude::*,
+};
+use ckb_verification::{cache::ForkContext, TxVerifyEnv};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+// a pool on top of a chain holding `funding`, so its outputs resolve
+fn build_funded_pool(config: TxPoolConfig, funding: &TransactionView) -> (TempDir, TxPool) {
+    let block = build_block(vec![funding.clone()], 1);
+    build_tx_pool_with_chain(config, &[block], ProposalView::default())
+}
+
+// an entry verified under the rules of the pool tip
+fn verified_entry(pool: &TxPool, tx: &TransactionView, fee: u64) -> TxEntry {
+    let tx_env = TxVerifyEnv::new_submit(pool.snapshot().tip_header());
+    let fork_context = ForkContext::new(pool.snapshot().consensus(), &tx_env);
+    TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, Capacity::shannons(fee), 1)
+        .with_fork_context(fork_context)
+}
+
+// callbacks keeping the pool statistics, collecting the hashes of the rejected and pending txs
+fn recording_callbacks() -> (Callbacks, Arc<Mutex<Vec<Byte32>>>, Arc<Mutex<Vec<Byte32>>>) {
+    let rejected = Arc::new(Mutex::new(Vec::new()));
+    let pending = Arc::new(Mutex::new(Vec::new()));
+    let mut callbacks = Callbacks::new();
+    let on_reject = Arc::clone(&rejected);
+    callbacks.register_reject(Box::new(move |pool, entry, reject| {
+        assert!(matches!(reject, Reject::Full(_)));
+        pool.update_statics_for_remove_tx(entry.size, entry.cycles);
+        on_reject.lock().unwrap().push(entry.transaction().hash());
+    }));
+    let on_pending = Arc::clone(&pending);
+    callbacks.register_pending(Box::new(move |pool, entry| {
+        pool.update_statics_for_add_tx(entry.size, entry.cycles);
+        on_pending.lock().unwrap().push(entry.transaction().hash());
+    }));
+    (callbacks, rejected, pending)
+}
+
+fn add_pending(pool: &mut TxPool, entry: TxEntry) {
+    pool.update_statics_for_add_tx(entry.size, entry.cycles);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+}
+
+#[test]
+fn test_archive_keeps_best_paying() {
+    let mut archive = EvictedArchive::new(2);
+    let txs: Vec<_> = (0..4u32)
+        .map(|i| build_tx(vec![(&Byte32::zero(), i)], 1))
+        .collect();
+    let entry = |index: usize, fee: u64| {
+        TxEntry::dummy_resolve(txs[index].clone(), MOCK_CYCLES, Capacity::shannons(fee), 1)
+    };
+
+    assert!(archive.insert(entry(0, 200)).is_none());
+    assert!(archive.insert(entry(1, 100)).is_none());
+    // full, a lower paying entry is declined, a better paying one pushes out the lowest
+    let declined = archive.insert(entry(2, 50)).unwrap();
+    assert_eq!(declined.transaction().hash(), txs[2].hash());
+    let dropped = archive.insert(entry(3, 300)).unwrap();
+    assert_eq!(dropped.transaction().hash(), txs[1].hash());
+    assert_eq!(archive.len(), 2);
+
+    let taken: Vec<_> = archive
+        .take_sorted()
+        .iter()
+        .map(|entry| entry.transaction().hash())
+        .collect();
+    assert_eq!(taken, vec![txs[3].hash(), txs[0].hash()]);
+    assert_eq!(archive.len(), 0);
+}
+
+#[test]
+fn test_evicted_archive() {
+    let config = TxPoolConfig {
+        max_tx_pool_entries: 2,
+        evicted_archive_size: 1,
+        ..Default::default()
+    };
+    let funding = build_tx(vec![(&Byte32::zero(), 0)], 4);
+    let (_tmp_dir, mut pool) = build_funded_pool(config, &funding);
+    let (callbacks, rejected, pending) = recording_callbacks();
+
+    let children: Vec<_> = (0..4u32)
+        .map(|i| build_tx(vec![(&funding.hash(), i)], 1))
+        .collect();
+    let fees = [300, 100, 400, 200];
+    for (tx, fee) in children.iter().zip(fees) {
+        let entry = verified_entry(&pool, tx, fee);
+        add_pending(&mut pool, entry);
+    }
+
+    // both the 100 and the 200 shannons children are evicted, the archive keeps the best one
+    pool.limit_size(&callbacks);
+    assert_eq!(pool.pool_map.size(), 2);
+    assert_eq!(pool.archived_count(), 1);
+    assert!(pool.archive.contains_key(&children[3].proposal_short_id()));
+    assert_eq!(rejected.lock().unwrap().len(), 2);
+    assert_eq!(
+        pool.fee_flow_report().since_start.evicted,
+        Capacity::shannons(300)
+    );
+
+    // no room yet
+    assert!(pool.reconsider_archived(&callbacks).is_empty());
+    assert_eq!(pool.archived_count(), 1);
+
+    assert!(pool.remove_tx(&children[2].proposal_short_id()));
+    pool.update_statics_for_remove_tx(1, MOCK_CYCLES);
+    let readmitted = pool.reconsider_archived(&callbacks);
+    assert_eq!(readmitted.len(), 1);
+    assert_eq!(
+        readmitted[0].proposal_short_id(),
+        children[3].proposal_short_id()
+    );
+    // announced again on return, and no longer counted as evicted
+    assert_eq!(*pending.lock().unwrap(), vec![children[3].hash()]);
+    assert_eq!(
+        pool.fee_flow_report().since_start.evicted,
+        Capacity::shannons(100)
+    );
+    assert!(pool
+        .pool_map
+        .get(&children[3].proposal_short_id())
+        .is_some());
+    assert_eq!(pool.archived_count(), 0);
+    assert_eq!(pool.pool_map.size(), 2);
+    pool.verify_integrity().unwrap();
+}
+
+#[test]
+fn test_evicted_archive_respects_pending_quota() {
+    let config = TxPoolConfig {
+        pending_quota: StatusQuota {
+            max_size: None,
+            max_count: Some(1),
+        },
+        evicted_archive_size: 1,
+        ..Default::default()
+    };
+    let funding = build_tx(vec![(&Byte32::zero(), 0)], 2);
+    let (_tmp_dir, mut pool) = build_funded_pool(config, &funding);
+    let (callbacks, _rejected, _pending) = recording_callbacks();
+    let high = build_tx(vec![(&funding.hash(), 0)], 1);
+    let low = build_tx(vec![(&funding.hash(), 1)], 1);
+    let entry = verified_entry(&pool, &high, 300);
+    add_pending(&mut pool, entry);
+    let entry = verified_entry(&pool, &low, 100);
+    add_pending(&mut pool, entry);
+
+    pool.limit_size(&callbacks);
+    assert!(pool.archive.contains_key(&low.proposal_short_id()));
+
+    // the pool has room, but pending is at its quota, the archived tx would only be evicted again
+    assert!(pool.reconsider_archived(&callbacks).is_empty());
+    assert_eq!(pool.archived_count(), 1);
+
+    assert!(pool.remove_tx(&high.proposal_short_id()));
+    pool.update_statics_for_remove_tx(1, MOCK_CYCLES);
+    assert_eq!(pool.reconsider_archived(&callbacks).len(), 1);
+    assert!(pool.pool_map.get(&low.proposal_short_id()).is_some());
+}
+
+#[test]
+fn test_evicted_archive_verifies_again() {
+    let config = TxPoolConfig {
+        max_tx_pool_entries: 1,
+        evicted_archive_size: 1,
+        ..Default::default()
+    };
+    let funding = build_tx(vec![(&Byte32::zero(), 0)], 2);
+    let (_tmp_dir, mut pool) = build_funded_pool(config, &funding);
+    let (callbacks, _rejected, pending) = recording_callbacks();
+    let high = build_tx(vec![(&funding.hash(), 0)], 1);
+    // locked until block 100, the tip is block 1
+    let locked = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(funding.hash(), 1), 100))
+        .output(
+            CellOutput::new_builder()
+                .capacity(Capacity::bytes(1).unwrap().pack())
+                .build(),
+        )
+        .output_data(Default::default())
+        .build();
+    let entry = verified_entry(&pool, &high, 300);
+    add_pending(&mut pool, entry);
+    let entry = verified_entry(&pool, &locked, 100);
+    add_pending(&mut pool, entry);
+
+    pool.limit_size(&callbacks);
+    assert!(pool.archive.contains_key(&locked.proposal_short_id()));
+    assert!(pool.remove_tx(&high.proposal_short_id()));
+    pool.update_statics_for_remove_tx(1, MOCK_CYCLES);
+
+    // the time lock is checked again, the archived tx is dropped rather than re-added
+    assert!(pool.reconsider_archived(&callbacks).is_empty());
+    assert_eq!(pool.archived_count(), 0);
+    assert!(pending.lock().unwrap().is_empty());
+    assert!(pool.pool_map.get(&locked.proposal_short_id()).is_none());
+}
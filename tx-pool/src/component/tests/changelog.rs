@@ -0,0 +1,101 @@
+use crate::component::changelog::{Changelog, PoolChange, RemoveReason};
+use crate::component::entry::TxEntry;
+use crate::component::tests::util::{build_tx, build_tx_pool, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE};
+use crate::pool::TxPool;
+use ckb_types::{core::Capacity, packed::Byte32};
+use std::collections::HashSet;
+
+// replays the changes after `seq` page by page into `members`, returns the new sequence number
+fn sync(pool: &TxPool, members: &mut HashSet<Byte32>, mut seq: u64) -> u64 {
+    loop {
+        let delta = pool.changes_since(seq, 2);
+        assert!(!delta.truncated);
+        for (change_seq, change) in delta.changes.iter() {
+            match change {
+                PoolChange::Added { hash, .. } => assert!(members.insert(hash.clone())),
+                PoolChange::Removed { hash, .. } => assert!(members.remove(hash)),
+            }
+            seq = *change_seq;
+        }
+        if seq == delta.current {
+            return seq;
+        }
+    }
+}
+
+fn pool_members(pool: &TxPool) -> HashSet<Byte32> {
+    pool.pool_map
+        .iter()
+        .map(|entry| entry.inner.transaction().hash())
+        .collect()
+}
+
+#[test]
+fn test_changes_since_rebuilds_membership() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let mut members = HashSet::new();
+    let mut seq = 0;
+
+    let tx1 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let tx2 = build_tx(vec![(&tx1.hash(), 0)], 1);
+    let tx3 = build_tx(vec![(&tx2.hash(), 0)], 1);
+    let tx4 = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    for tx in [&tx1, &tx2, &tx3] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        pool.add_pending(entry).unwrap();
+    }
+    seq = sync(&pool, &mut members, seq);
+    assert_eq!(members, pool_members(&pool));
+
+    // removing tx2 takes its descendant tx3 along
+    assert!(pool.remove_tx(&tx2.proposal_short_id()));
+    let entry = TxEntry::dummy_resolve(tx4.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    pool.add_pending(entry).unwrap();
+    seq = sync(&pool, &mut members, seq);
+    assert_eq!(members, pool_members(&pool));
+    assert_eq!(members.len(), 2);
+
+    let delta = pool.changes_since(seq, 10);
+    assert!(delta.changes.is_empty());
+    assert_eq!(delta.current, seq);
+
+    let removed: Vec<_> = pool
+        .changes_since(3, 10)
+        .changes
+        .into_iter()
+        .filter_map(|(_, change)| match change {
+            PoolChange::Removed { hash, reason } => Some((hash, reason)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(removed.len(), 2);
+    assert!(removed
+        .iter()
+        .all(|(_, reason)| *reason == RemoveReason::Removed));
+
+    // clearing the pool forces a resync
+    pool.clear(pool.cloned_snapshot());
+    assert!(pool.changes_since(seq, 10).truncated);
+}
+
+#[test]
+fn test_changes_since_truncated() {
+    let mut changelog = Changelog::new(3);
+    for i in 0..5u64 {
+        changelog.record_added(Byte32::zero(), Capacity::shannons(i), 0);
+    }
+
+    // 1 and 2 were dropped
+    assert!(changelog.changes_since(0, 10).truncated);
+    assert!(changelog.changes_since(1, 10).truncated);
+
+    let delta = changelog.changes_since(2, 10);
+    assert!(!delta.truncated);
+    assert_eq!(delta.current, 5);
+    let seqs: Vec<_> = delta.changes.iter().map(|(seq, _)| *seq).collect();
+    assert_eq!(seqs, vec![3, 4, 5]);
+
+    let delta = changelog.changes_since(5, 10);
+    assert!(!delta.truncated);
+    assert!(delta.changes.is_empty());
+}
@@ -1,22 +1,77 @@
 use ckb_types::core::TransactionBuilder;
+use ckb_types::packed::Byte32;
 
-use crate::component::chunk::{ChunkQueue, Entry};
+use crate::component::chunk::{ChunkQueue, VerifyingTx};
+use crate::component::tests::util::build_tx;
 
 #[test]
 fn basic() {
     let tx = TransactionBuilder::default().build();
-    let entry = Entry {
-        tx: tx.clone(),
-        remote: None,
-    };
     let id = tx.proposal_short_id();
-    let mut queue = ChunkQueue::new();
+    let mut queue = ChunkQueue::new(100);
 
-    assert!(queue.add_tx(tx.clone(), None));
-    assert_eq!(queue.pop_front().as_ref(), Some(&entry));
+    assert!(queue.add_tx(tx.clone(), None, 0));
+    assert_eq!(queue.pop_front().map(|entry| entry.tx), Some(tx.clone()));
     assert!(queue.contains_key(&id));
-    assert!(!queue.add_tx(tx, None));
+    assert!(!queue.add_tx(tx, None, 0));
 
     queue.clean_front();
     assert!(!queue.contains_key(&id));
 }
+
+#[test]
+fn test_pending_verification() {
+    let tx1 = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let tx2 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let tx3 = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    let mut queue = ChunkQueue::new(3);
+
+    assert!(queue.add_tx(tx1.clone(), None, 100));
+    assert!(queue.add_tx(tx2.clone(), None, 200));
+    assert!(!queue.is_full());
+    assert_eq!(queue.max_len(), 3);
+
+    // the front entry is verifying, its progress is visible through the queue
+    let front = queue.pop_front().unwrap();
+    front.progress.update(3, 10);
+    assert_eq!(queue.get_tx(&tx1.proposal_short_id()), Some(tx1.clone()));
+    assert!(!queue.is_full());
+    assert!(queue.add_tx(tx3.clone(), None, 300));
+    // the verifying tx still takes its place in the queue
+    assert!(queue.is_full());
+
+    assert_eq!(
+        queue.pending_verification(),
+        vec![
+            VerifyingTx {
+                tx_hash: tx1.hash(),
+                enqueued_at: 100,
+                chunks_done: 3,
+                chunks_total: 10,
+            },
+            VerifyingTx {
+                tx_hash: tx2.hash(),
+                enqueued_at: 200,
+                chunks_done: 0,
+                chunks_total: 0,
+            },
+            VerifyingTx {
+                tx_hash: tx3.hash(),
+                enqueued_at: 300,
+                chunks_done: 0,
+                chunks_total: 0,
+            },
+        ]
+    );
+
+    // verified, the tx leaves the queue
+    queue.clean_front();
+    let hashes: Vec<_> = queue
+        .pending_verification()
+        .into_iter()
+        .map(|tx| tx.tx_hash)
+        .collect();
+    assert_eq!(hashes, vec![tx2.hash(), tx3.hash()]);
+    assert_eq!(queue.get_tx(&tx1.proposal_short_id()), None);
+    assert!(!queue.is_full());
+}
@@ -0,0 +1,259 @@
+use crate::callback::Callbacks;
+use crate::component::committed::{CommittedEntries, MAX_COMMITTED_ENTRIES};
+use crate::component::entry::TxEntry;
+use crate::component::tests::util::{
+    build_block, build_tx, build_tx_pool, build_tx_pool_with_chain, MOCK_CYCLES, MOCK_FEE,
+    MOCK_SIZE,
+};
+use crate::error::Reject;
+use crate::pool::CommittedInfo;
+use crate::process::{ReorgUpdate, _update_tx_pool_for_reorg};
+use ckb_app_config::TxPoolConfig;
+use ckb_proposal_table::ProposalView;
+use ckb_types::{
+    core::{tx_pool::Origin, BlockBuilder, Capacity, TransactionBuilder},
+    packed::{Byte32, CellInput},
+    prelude::*,
+};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_invalidate_committed_cache_on_reorg() {
+    let tx = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(1))
+        .build();
+    let block = BlockBuilder::default()
+        .number(1u64.pack())
+        .transactions(vec![cellbase, tx.clone()])
+        .build();
+    let (_tmp_dir, mut pool) = build_tx_pool_with_chain(
+        Default::default(),
+        &[block.clone()],
+        ProposalView::default(),
+    );
+    let callbacks = Callbacks::new();
+    let short_id = tx.proposal_short_id();
+
+    pool.remove_committed_txs(&[block.clone()], &callbacks, &HashSet::new());
+    assert_eq!(pool.cached_committed_hash(&short_id), Some(tx.hash()));
+    assert_eq!(
+        pool.get_tx_from_pool_or_store(&short_id, &callbacks),
+        Some(tx.clone())
+    );
+
+    // the block is detached and nothing is attached
+    let snapshot = pool.cloned_snapshot();
+    _update_tx_pool_for_reorg(
+        &mut pool,
+        ReorgUpdate {
+            attached_blocks: &[],
+            uncommitted: &[tx],
+            detached_headers: &HashSet::from_iter([block.hash()]),
+            detached_proposal_id: HashSet::new(),
+            snapshot,
+        },
+        &callbacks,
+        false,
+    );
+    assert_eq!(pool.cached_committed_hash(&short_id), None);
+    assert_eq!(pool.get_tx_from_pool_or_store(&short_id, &callbacks), None);
+}
+
+#[test]
+fn test_committed_info() {
+    let tx = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let short_id = tx.proposal_short_id();
+    let block = build_block(vec![tx.clone()], 1);
+    let (_tmp_dir, mut pool) = build_tx_pool_with_chain(
+        Default::default(),
+        &[block.clone()],
+        ProposalView::default(),
+    );
+    let callbacks = Callbacks::new();
+    assert_eq!(pool.committed_info(&short_id), None);
+
+    let snapshot = pool.cloned_snapshot();
+    _update_tx_pool_for_reorg(
+        &mut pool,
+        ReorgUpdate {
+            attached_blocks: &[block.clone()],
+            uncommitted: &[],
+            detached_headers: &HashSet::new(),
+            detached_proposal_id: HashSet::new(),
+            snapshot: Arc::clone(&snapshot),
+        },
+        &callbacks,
+        false,
+    );
+    let info = pool.committed_info(&short_id).unwrap();
+    assert_eq!(info.tx_hash, tx.hash());
+    assert_eq!(info.block_hash, block.hash());
+    assert_eq!(info.block_number, 1);
+
+    // detached without being attached again
+    _update_tx_pool_for_reorg(
+        &mut pool,
+        ReorgUpdate {
+            attached_blocks: &[],
+            uncommitted: &[tx.clone()],
+            detached_headers: &HashSet::from_iter([block.hash()]),
+            detached_proposal_id: HashSet::new(),
+            snapshot: Arc::clone(&snapshot),
+        },
+        &callbacks,
+        false,
+    );
+    assert_eq!(pool.committed_info(&short_id), None);
+
+    // committed again by a block of the new fork
+    let fork_block = build_block(vec![tx.clone()], 2);
+    _update_tx_pool_for_reorg(
+        &mut pool,
+        ReorgUpdate {
+            attached_blocks: &[fork_block.clone()],
+            uncommitted: &[],
+            detached_headers: &HashSet::new(),
+            detached_proposal_id: HashSet::new(),
+            snapshot,
+        },
+        &callbacks,
+        false,
+    );
+    let info = pool.committed_info(&short_id).unwrap();
+    assert_eq!(info.block_hash, fork_block.hash());
+    assert_eq!(info.block_number, 2);
+}
+
+#[test]
+fn test_keep_committed_confirmations() {
+    let config = TxPoolConfig {
+        keep_committed_confirmations: 2,
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+    let callbacks = Callbacks::new();
+    let tx = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let id = tx.proposal_short_id();
+    let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+
+    let block = build_block(vec![tx.clone()], 1);
+    pool.remove_committed_txs(&[block.clone()], &callbacks, &HashSet::new());
+    assert!(!pool.contains_proposal_id(&id));
+    let committed = pool.committed_entry(&id).unwrap();
+    assert_eq!(committed.entry.transaction().hash(), tx.hash());
+    assert_eq!(committed.info.block_hash, block.hash());
+
+    // one block on top, still kept
+    pool.remove_committed_txs(&[build_block(vec![], 2)], &callbacks, &HashSet::new());
+    assert!(pool.committed_entry(&id).is_some());
+
+    // two blocks on top, pruned
+    pool.remove_committed_txs(&[build_block(vec![], 3)], &callbacks, &HashSet::new());
+    assert!(pool.committed_entry(&id).is_none());
+    assert_eq!(pool.committed_count(), 0);
+}
+
+#[test]
+fn test_committed_entries_bounded() {
+    let mut committed = CommittedEntries::default();
+    let txs: Vec<_> = (0..=MAX_COMMITTED_ENTRIES as u32)
+        .map(|i| build_tx(vec![(&Byte32::zero(), i)], 1))
+        .collect();
+    for tx in txs.iter() {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        let info = CommittedInfo {
+            tx_hash: tx.hash(),
+            block_hash: Byte32::zero(),
+            block_number: 1,
+        };
+        committed.insert(tx.proposal_short_id(), entry, info);
+    }
+    // the oldest one is dropped
+    assert_eq!(committed.len(), MAX_COMMITTED_ENTRIES);
+    assert!(committed.get(&txs[0].proposal_short_id()).is_none());
+    assert!(committed.get(&txs[1].proposal_short_id()).is_some());
+
+    committed.prune(2, 1);
+    assert_eq!(committed.len(), 0);
+}
+
+#[test]
+fn test_reorg_clear_depth() {
+    let config = TxPoolConfig {
+        reorg_clear_depth: Some(1),
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) =
+        build_tx_pool_with_chain(config, &[build_block(vec![], 1)], ProposalView::default());
+    let mut callbacks = Callbacks::new();
+    let rejected = Arc::new(Mutex::new(Vec::new()));
+    let rejected_clone = Arc::clone(&rejected);
+    callbacks.register_reject(Box::new(move |_pool, entry, reject| {
+        rejected_clone
+            .lock()
+            .unwrap()
+            .push((entry.transaction().hash(), reject));
+    }));
+    let tx = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+    let local = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let entry = TxEntry::dummy_resolve(local.clone(), MOCK_CYCLES, Capacity::shannons(100), 100)
+        .with_origin(Origin::Local);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+    let snapshot = pool.cloned_snapshot();
+
+    // a shallow reorg is reconciled, the unrelated tx stays
+    let shallow = build_block(vec![], 2);
+    _update_tx_pool_for_reorg(
+        &mut pool,
+        ReorgUpdate {
+            attached_blocks: &[],
+            uncommitted: &[],
+            detached_headers: &HashSet::from_iter([shallow.hash()]),
+            detached_proposal_id: HashSet::new(),
+            snapshot: Arc::clone(&snapshot),
+        },
+        &callbacks,
+        false,
+    );
+    assert!(pool.contains_proposal_id(&tx.proposal_short_id()));
+    assert!(rejected.lock().unwrap().is_empty());
+
+    // a deeper one clears the pool
+    let deep = [build_block(vec![], 2), build_block(vec![], 3)];
+    let outcome = _update_tx_pool_for_reorg(
+        &mut pool,
+        ReorgUpdate {
+            attached_blocks: &[],
+            uncommitted: &[],
+            detached_headers: &HashSet::from_iter(deep.iter().map(|block| block.hash())),
+            detached_proposal_id: HashSet::new(),
+            snapshot,
+        },
+        &callbacks,
+        false,
+    );
+    assert!(!pool.contains_proposal_id(&tx.proposal_short_id()));
+    assert_eq!(pool.pool_map.size(), 0);
+    // the dropped txs are notified, the local one is returned to be submitted again, the
+    // resubmission list is left alone
+    let rejected = rejected.lock().unwrap();
+    assert_eq!(rejected.len(), 2);
+    assert!(rejected
+        .iter()
+        .all(|(_, reject)| matches!(reject, Reject::ReorgCleared(2))));
+    let cleared: Vec<_> = outcome
+        .cleared
+        .iter()
+        .map(|entry| entry.transaction.hash())
+        .collect();
+    assert_eq!(cleared, vec![local.hash()]);
+    assert!(pool.resubmit_list().is_empty());
+    let fee_flow = pool.fee_flow_report().since_start;
+    assert_eq!(fee_flow.cleared, Capacity::shannons(100));
+    assert_eq!(fee_flow.evicted, Capacity::zero());
+}
@@ -0,0 +1,122 @@
+use crate::component::conflict_cache::{ConflictCache, CONFLICT_CACHE_TTL_MS};
+use crate::component::entry::TxEntry;
+use crate::component::tests::util::{build_tx, build_tx_pool, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE};
+use crate::error::Reject;
+use crate::process::{pre_check_tx, pre_check_tx_with};
+use ckb_app_config::TxPoolConfig;
+use ckb_types::{core::FeeRate, packed::Byte32};
+
+#[test]
+fn test_conflict_cache_short_circuits_double_spend() {
+    // RBF disabled, the loser is rejected for double spending the winner
+    let config = TxPoolConfig {
+        min_fee_rate: FeeRate::from_u64(0),
+        min_rbf_rate: FeeRate::from_u64(0),
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+    let winner = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let loser = build_tx(vec![(&Byte32::zero(), 1)], 2);
+    let entry = TxEntry::dummy_resolve(winner.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    pool.add_pending(entry).unwrap();
+
+    let snapshot = pool.cloned_snapshot();
+    for _ in 0..3 {
+        let ret = pre_check_tx(&pool, &snapshot, &loser, 0, 0);
+        assert!(matches!(ret, Err(Reject::Conflict { .. })));
+    }
+    // only the first submission was resolved
+    let stats = pool.conflict_cache_stats();
+    assert_eq!(stats.hits, 2);
+    assert_eq!(stats.invalidations, 0);
+
+    // the winner left the pool, the loser is evaluated again
+    assert!(pool.remove_tx(&winner.proposal_short_id()));
+    let ret = pre_check_tx(&pool, &snapshot, &loser, 0, 0);
+    assert!(!matches!(ret, Err(Reject::Conflict { .. })));
+    let stats = pool.conflict_cache_stats();
+    assert_eq!(stats.hits, 2);
+    assert_eq!(stats.invalidations, 1);
+}
+
+#[test]
+fn test_conflict_cache_expiry() {
+    let winner = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let loser = build_tx(vec![(&Byte32::zero(), 1)], 2);
+    let conflicts = vec![winner.proposal_short_id()].into_iter().collect();
+    let reject = Reject::Duplicated(loser.hash());
+
+    let mut cache = ConflictCache::new(10);
+    cache.insert(loser.hash(), conflicts, reject, 0);
+    assert!(cache
+        .check(&loser.hash(), CONFLICT_CACHE_TTL_MS - 1, |_| true)
+        .is_some());
+    assert!(cache
+        .check(&loser.hash(), CONFLICT_CACHE_TTL_MS, |_| true)
+        .is_none());
+    assert!(cache.check(&loser.hash(), 0, |_| true).is_none());
+    assert_eq!(cache.stats().hits, 1);
+    assert_eq!(cache.stats().invalidations, 1);
+}
+
+#[test]
+fn test_double_spend_attempts() {
+    let config = TxPoolConfig {
+        min_fee_rate: FeeRate::from_u64(0),
+        min_rbf_rate: FeeRate::from_u64(0),
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+    let winner = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let entry = TxEntry::dummy_resolve(winner.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    pool.add_pending(entry).unwrap();
+    let winner_id = winner.proposal_short_id();
+    assert_eq!(pool.double_spend_attempts(&winner_id), None);
+
+    let snapshot = pool.cloned_snapshot();
+    let losers: Vec<_> = (2..4)
+        .map(|outputs| build_tx(vec![(&Byte32::zero(), 1)], outputs))
+        .collect();
+    for loser in losers.iter().chain(losers.iter()) {
+        assert!(pre_check_tx(&pool, &snapshot, loser, 0, 0).is_err());
+    }
+
+    // a loser relayed again is rejected from the conflict cache and counted once
+    let attempts = pool.double_spend_attempts(&winner_id).unwrap();
+    assert_eq!(attempts.count, 2);
+    assert_eq!(
+        attempts.tx_hashes.into_iter().collect::<Vec<_>>(),
+        losers.iter().map(|tx| tx.hash()).collect::<Vec<_>>()
+    );
+    let info = pool.get_all_entry_info(None);
+    let winner_info = &info.pending[&winner.hash()];
+    assert_eq!(winner_info.double_spend_attempts, 2);
+    assert_eq!(winner_info.double_spend_tx_hashes.len(), 2);
+
+    // the record goes away with the entry
+    assert!(pool.remove_tx(&winner_id));
+    assert_eq!(pool.double_spend_attempts(&winner_id), None);
+}
+
+#[test]
+fn test_double_spend_attempts_before_rbf() {
+    // RBF enabled, the loser spends a cell this pool has no chain to resolve from, it fails
+    // before the RBF rules are checked
+    let (_tmp_dir, mut pool) = build_tx_pool(TxPoolConfig::default());
+    assert!(pool.enable_rbf());
+    let winner = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let entry = TxEntry::dummy_resolve(winner.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    pool.add_pending(entry).unwrap();
+    let winner_id = winner.proposal_short_id();
+
+    let snapshot = pool.cloned_snapshot();
+    let loser = build_tx(vec![(&Byte32::zero(), 1)], 2);
+    for count in 1..=2 {
+        let mut lost_double_spend = false;
+        let ret = pre_check_tx_with(&pool, &snapshot, &loser, 0, 0, &mut lost_double_spend);
+        assert!(ret.is_err());
+        assert!(lost_double_spend);
+        // not cached, counted on each attempt
+        assert_eq!(pool.double_spend_attempts(&winner_id).unwrap().count, count);
+    }
+}
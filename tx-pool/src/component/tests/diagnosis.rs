@@ -0,0 +1,94 @@
+use crate::callback::Callbacks;
+use crate::clock::{Clock, ManualClock};
+use crate::component::diagnosis::{Blocker, Diagnosis, PoolStage};
+use crate::component::entry::TxEntry;
+use crate::component::tests::util::{build_block, build_tx, build_tx_pool_with_clock, MOCK_CYCLES};
+use crate::error::Reject;
+use ckb_types::{
+    core::{cell::ResolvedTransaction, Capacity, TransactionView},
+    packed::Byte32,
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+#[test]
+fn test_diagnose() {
+    let start = 1_000_000_000;
+    let clock = Arc::new(ManualClock::new(start));
+    let (_tmp_dir, mut pool) =
+        build_tx_pool_with_clock(Default::default(), Arc::clone(&clock) as Arc<dyn Clock>);
+    let expiry = pool.expiry;
+    let entry = |tx: &TransactionView, fee, timestamp| {
+        let rtx = Arc::new(ResolvedTransaction::dummy_resolve(tx.clone()));
+        TxEntry::new_with_timestamp(rtx, MOCK_CYCLES, Capacity::shannons(fee), 100, timestamp)
+    };
+    let in_pool = |diagnosis| match diagnosis {
+        Diagnosis::InPool(diagnosis) => diagnosis,
+        diagnosis => panic!("unexpected {:?}", diagnosis),
+    };
+
+    let ready = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let pending = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let gap = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    let gap_child = build_tx(vec![(&pending.hash(), 0)], 1);
+    let committed = build_tx(vec![(&Byte32::zero(), 3)], 1);
+    pool.add_proposed(entry(&ready, 10_000, start), false)
+        .unwrap();
+    // zero fee, about to expire
+    pool.add_pending(entry(&pending, 0, start - expiry + 1000))
+        .unwrap();
+    pool.add_gap(entry(&gap, 10_000, start)).unwrap();
+    pool.add_gap(entry(&gap_child, 10_000, start)).unwrap();
+    pool.add_pending(entry(&committed, 10_000, start)).unwrap();
+
+    let diagnosis = in_pool(pool.diagnose(&ready.hash()));
+    assert_eq!(diagnosis.stage, PoolStage::Proposed);
+    assert_eq!(diagnosis.position, Some(0));
+    assert_eq!(diagnosis.blocker, None);
+    assert_eq!(diagnosis.expires_in, expiry);
+    assert!(!diagnosis.expiring_soon);
+    assert!(!diagnosis.eviction_risk);
+    assert_eq!(diagnosis.double_spend_attempts, 0);
+
+    let diagnosis = in_pool(pool.diagnose(&pending.hash()));
+    assert_eq!(diagnosis.stage, PoolStage::Pending);
+    assert_eq!(diagnosis.position, None);
+    assert_eq!(diagnosis.blocker, Some(Blocker::NotProposed));
+    assert_eq!(diagnosis.expires_in, 1000);
+    assert!(diagnosis.expiring_soon);
+    assert!(diagnosis.eviction_risk);
+
+    let diagnosis = in_pool(pool.diagnose(&gap.hash()));
+    assert_eq!(diagnosis.stage, PoolStage::Gap);
+    assert_eq!(diagnosis.blocker, Some(Blocker::AwaitingProposalWindow));
+
+    let diagnosis = in_pool(pool.diagnose(&gap_child.hash()));
+    assert_eq!(
+        diagnosis.blocker,
+        Some(Blocker::AncestorsUnproposed(vec![pending.hash()]))
+    );
+
+    let attempt = build_tx(vec![(&Byte32::zero(), 0)], 2);
+    pool.cache_conflict(
+        attempt.hash(),
+        HashSet::from([ready.proposal_short_id()]),
+        Reject::RBFRejected("attempt".to_owned()),
+        start,
+    );
+    assert_eq!(
+        in_pool(pool.diagnose(&ready.hash())).double_spend_attempts,
+        1
+    );
+
+    let block = build_block(vec![committed.clone()], 1);
+    pool.remove_committed_txs(&[block.clone()], &Callbacks::new(), &HashSet::new());
+    assert_eq!(
+        pool.diagnose(&committed.hash()),
+        Diagnosis::Committed {
+            block_hash: block.hash(),
+            block_number: 1,
+        }
+    );
+
+    assert_eq!(pool.diagnose(&Byte32::zero()), Diagnosis::Unknown);
+}
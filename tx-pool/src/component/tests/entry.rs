@@ -1,11 +1,18 @@
+use crate::component::entry::TxEntry;
 use crate::component::sort_key::EvictKey;
-use ckb_types::core::{Capacity, FeeRate};
+use crate::component::tests::util::{build_tx, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE};
+use ckb_types::{
+    core::{Capacity, FeeRate},
+    packed::{Byte32, OutPoint},
+    prelude::*,
+};
 
 #[test]
 fn test_min_fee_and_weight_evict() {
     let mut result = vec![(500, 10, 30), (10, 10, 31), (100, 10, 32)]
         .into_iter()
         .map(|(fee, weight, timestamp)| EvictKey {
+            zero_fee: fee == 0,
             fee_rate: FeeRate::calculate(Capacity::shannons(fee), weight),
             timestamp,
             descendants_count: 0,
@@ -23,6 +30,7 @@ fn test_min_timestamp_evict() {
     let mut result = vec![(500, 10, 30), (500, 10, 31), (500, 10, 32)]
         .into_iter()
         .map(|(fee, weight, timestamp)| EvictKey {
+            zero_fee: fee == 0,
             fee_rate: FeeRate::calculate(Capacity::shannons(fee), weight),
             timestamp,
             descendants_count: 0,
@@ -40,6 +48,7 @@ fn test_min_weight_evict() {
     let mut result = vec![(500, 10, 30), (500, 12, 31), (500, 13, 32)]
         .into_iter()
         .map(|(fee, weight, timestamp)| EvictKey {
+            zero_fee: fee == 0,
             fee_rate: FeeRate::calculate(Capacity::shannons(fee), weight),
             timestamp,
             descendants_count: 0,
@@ -51,3 +60,57 @@ fn test_min_weight_evict() {
         vec![32, 31, 30]
     );
 }
+
+#[test]
+fn test_zero_fee_evicted_first() {
+    // a tiny fee rounds down to the zero fee rate, it still pays more than nothing
+    let mut result = vec![(0, 10, 30), (1, 10_000, 31), (500, 10, 32)]
+        .into_iter()
+        .map(|(fee, weight, timestamp)| EvictKey {
+            zero_fee: fee == 0,
+            fee_rate: FeeRate::calculate(Capacity::shannons(fee), weight),
+            timestamp,
+            descendants_count: 0,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(result[0].fee_rate, result[1].fee_rate);
+    result.sort();
+    assert_eq!(
+        result.iter().map(|key| key.timestamp).collect::<Vec<_>>(),
+        vec![30, 31, 32]
+    );
+}
+
+#[test]
+fn test_zero_fee_evict_key_of_entry() {
+    let parent = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let mut entry =
+        TxEntry::dummy_resolve(parent.clone(), MOCK_CYCLES, Capacity::zero(), MOCK_SIZE);
+    assert!(entry.as_evict_key().zero_fee);
+
+    // a paying child makes the package worth keeping
+    let child = build_tx(vec![(&parent.hash(), 0)], 1);
+    let child = TxEntry::dummy_resolve(child, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    entry.add_descendant_weight(&child);
+    assert!(!entry.as_evict_key().zero_fee);
+}
+
+#[test]
+fn test_out_points_accessors() {
+    let parent = Byte32::new([1u8; 32]);
+    let tx = build_tx(vec![(&parent, 0), (&parent, 2)], 3);
+    let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+
+    assert_eq!(
+        entry.input_out_points().collect::<Vec<_>>(),
+        vec![OutPoint::new(parent.clone(), 0), OutPoint::new(parent, 2)]
+    );
+    let outputs: Vec<_> = (0..3).map(|i| OutPoint::new(tx.hash(), i)).collect();
+    assert_eq!(entry.output_out_points().collect::<Vec<_>>(), outputs);
+    let created: Vec<_> = entry.created_out_points().collect();
+    assert_eq!(created.len(), 3);
+    for (i, (out_point, output)) in created.into_iter().enumerate() {
+        assert_eq!(out_point, outputs[i]);
+        assert_eq!(output.as_slice(), tx.output(i).unwrap().as_slice());
+    }
+}
@@ -0,0 +1,219 @@
+use crate::callback::Callbacks;
+use crate::clock::{Clock, ManualClock};
+use crate::component::entry::TxEntry;
+use crate::component::tests::util::{
+    build_tx, build_tx_pool_with_clock, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE,
+};
+use crate::error::Reject;
+use crate::process::{TxStatus, _submit_entry};
+use ckb_app_config::TxPoolConfig;
+use ckb_types::{
+    core::{cell::ResolvedTransaction, error::OutPointError, tx_pool::Origin, TransactionView},
+    packed::Byte32,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_expiring_within() {
+    let start = 1_000_000;
+    let clock = Arc::new(ManualClock::new(start));
+    let (_tmp_dir, mut pool) =
+        build_tx_pool_with_clock(Default::default(), Arc::clone(&clock) as Arc<dyn Clock>);
+    let expiry = pool.expiry;
+
+    // entered the pool one minute apart
+    let txs: Vec<_> = (0..4u32)
+        .map(|i| {
+            let tx = build_tx(vec![(&Byte32::zero(), i)], 1);
+            let rtx = Arc::new(ResolvedTransaction::dummy_resolve(tx.clone()));
+            let entry = TxEntry::new_with_timestamp(
+                rtx,
+                MOCK_CYCLES,
+                MOCK_FEE,
+                MOCK_SIZE,
+                start + i as u64 * 60_000,
+            );
+            pool.add_pending(entry).unwrap();
+            tx
+        })
+        .collect();
+
+    clock.set(start + expiry - 30_000);
+    assert_eq!(
+        pool.expiring_within(Duration::from_secs(60), 10),
+        vec![(txs[0].hash(), 30_000)]
+    );
+    assert_eq!(
+        pool.expiring_within(Duration::from_secs(150), 10),
+        vec![(txs[0].hash(), 30_000), (txs[1].hash(), 90_000)]
+    );
+    assert_eq!(
+        pool.expiring_within(Duration::from_secs(3600), 3),
+        vec![
+            (txs[0].hash(), 30_000),
+            (txs[1].hash(), 90_000),
+            (txs[2].hash(), 150_000)
+        ]
+    );
+    clock.set(start);
+    assert!(pool.expiring_within(Duration::from_secs(60), 10).is_empty());
+
+    // already expired but not yet removed
+    clock.set(start + expiry + 70_000);
+    let expired = pool.expiring_within(Duration::from_secs(1), 10);
+    assert_eq!(expired.len(), 2);
+    assert!(expired.iter().all(|(_, remaining)| *remaining == 0));
+}
+
+#[test]
+fn test_proposed_expiring_within() {
+    let start = 1_000_000;
+    let clock = Arc::new(ManualClock::new(start));
+    let (_tmp_dir, mut pool) =
+        build_tx_pool_with_clock(Default::default(), Arc::clone(&clock) as Arc<dyn Clock>);
+    let expiry = pool.expiry;
+
+    // entered the pool one minute apart, the last one stays pending
+    let txs: Vec<_> = (0..4u32)
+        .map(|i| {
+            let tx = build_tx(vec![(&Byte32::zero(), i)], 1);
+            let rtx = Arc::new(ResolvedTransaction::dummy_resolve(tx.clone()));
+            let entry = TxEntry::new_with_timestamp(
+                rtx,
+                MOCK_CYCLES,
+                MOCK_FEE,
+                MOCK_SIZE,
+                start + i as u64 * 60_000,
+            );
+            if i < 3 {
+                pool.add_proposed(entry, false).unwrap();
+            } else {
+                pool.add_pending(entry).unwrap();
+            }
+            tx.proposal_short_id()
+        })
+        .collect();
+
+    clock.set(start + expiry - 30_000);
+    assert_eq!(pool.proposed_expiring_within(60_000), vec![txs[0].clone()]);
+    assert_eq!(
+        pool.proposed_expiring_within(150_000),
+        vec![txs[0].clone(), txs[1].clone()]
+    );
+    // the pending tx is never listed
+    assert_eq!(
+        pool.proposed_expiring_within(3_600_000),
+        vec![txs[0].clone(), txs[1].clone(), txs[2].clone()]
+    );
+    clock.set(start);
+    assert!(pool.proposed_expiring_within(60_000).is_empty());
+}
+
+#[test]
+fn test_remove_expired_boundary() {
+    let start = 1_000_000;
+    let clock = Arc::new(ManualClock::new(start));
+    let (_tmp_dir, mut pool) =
+        build_tx_pool_with_clock(Default::default(), Arc::clone(&clock) as Arc<dyn Clock>);
+    let expiry = pool.expiry;
+
+    let tx = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let rtx = Arc::new(ResolvedTransaction::dummy_resolve(tx.clone()));
+    let entry = TxEntry::new_with_timestamp(rtx, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE, start);
+    pool.add_pending(entry).unwrap();
+    let callbacks = Callbacks::new();
+
+    // one millisecond before the expiry, it has 1ms left
+    clock.set(start + expiry - 1);
+    assert_eq!(
+        pool.expiring_within(Duration::from_millis(2), 10),
+        vec![(tx.hash(), 1)]
+    );
+    assert!(pool
+        .expiring_within(Duration::from_millis(1), 10)
+        .is_empty());
+
+    // at exactly the expiry the entry is still kept, with nothing left
+    clock.advance(1);
+    assert_eq!(
+        pool.expiring_within(Duration::from_millis(1), 10),
+        vec![(tx.hash(), 0)]
+    );
+    pool.remove_expired(&callbacks);
+    assert!(pool.contains_proposal_id(&tx.proposal_short_id()));
+
+    // one millisecond later it's gone
+    clock.advance(1);
+    pool.remove_expired(&callbacks);
+    assert!(!pool.contains_proposal_id(&tx.proposal_short_id()));
+}
+
+#[test]
+fn test_resubmit_expired_local_txs() {
+    let start = 1_000_000_000;
+    let config = TxPoolConfig {
+        resubmit_list_size: 10,
+        resubmit_retention_hours: 1,
+        ..Default::default()
+    };
+    let clock = Arc::new(ManualClock::new(start));
+    let (_tmp_dir, mut pool) =
+        build_tx_pool_with_clock(config, Arc::clone(&clock) as Arc<dyn Clock>);
+    let expired_at = start - pool.expiry - 1;
+    let entry = |tx: &TransactionView, origin, timestamp| {
+        let rtx = Arc::new(ResolvedTransaction::dummy_resolve(tx.clone()));
+        TxEntry::new_with_timestamp(rtx, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE, timestamp)
+            .with_origin(origin)
+    };
+    let callbacks = Callbacks::new();
+
+    let parent = build_tx(vec![(&Byte32::zero(), 0)], 2);
+    let kept = build_tx(vec![(&parent.hash(), 0)], 1);
+    let spent = build_tx(vec![(&parent.hash(), 1)], 1);
+    let remote = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    pool.add_pending(entry(&parent, Origin::Remote, start))
+        .unwrap();
+    pool.add_pending(entry(&kept, Origin::Local, expired_at))
+        .unwrap();
+    pool.add_pending(entry(&spent, Origin::Local, expired_at))
+        .unwrap();
+    pool.add_pending(entry(&remote, Origin::Remote, expired_at))
+        .unwrap();
+
+    // only the local ones are kept
+    pool.remove_expired(&callbacks);
+    let hashes: Vec<_> = pool
+        .resubmit_list()
+        .into_iter()
+        .map(|entry| entry.transaction.hash())
+        .collect();
+    assert_eq!(hashes, vec![kept.hash(), spent.hash()]);
+
+    // the output `spent` used is spent by another tx meanwhile
+    let spender = build_tx(vec![(&parent.hash(), 1)], 2);
+    pool.add_pending(entry(&spender, Origin::Remote, start))
+        .unwrap();
+    let (resubmit, dropped) = pool.take_resubmit_list();
+    assert_eq!(resubmit.len(), 1);
+    assert_eq!(resubmit[0].transaction.hash(), kept.hash());
+    assert_eq!(dropped.len(), 1);
+    assert_eq!(dropped[0].0, spent.hash());
+    assert!(matches!(
+        dropped[0].1,
+        Reject::Resolve(OutPointError::Dead(_))
+    ));
+    assert!(pool.resubmit_list().is_empty());
+
+    let rtx = pool
+        .resolve_tx_from_pool(resubmit[0].transaction.clone(), false)
+        .unwrap();
+    let entry = TxEntry::new(rtx, resubmit[0].cycles, resubmit[0].fee, MOCK_SIZE);
+    _submit_entry(&mut pool, TxStatus::Fresh, entry, &callbacks).unwrap();
+    assert!(pool.contains_proposal_id(&kept.proposal_short_id()));
+
+    // past the retention
+    pool.keep_for_resubmit(resubmit[0].clone());
+    clock.advance(60 * 60 * 1000 + 1);
+    assert!(pool.take_resubmit_list().0.is_empty());
+}
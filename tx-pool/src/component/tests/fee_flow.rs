@@ -0,0 +1,100 @@
+use crate::callback::Callbacks;
+use crate::clock::{Clock, ManualClock};
+use crate::component::entry::TxEntry;
+use crate::component::fee_flow::FeeFlow;
+use crate::component::tests::util::{build_block, build_tx, build_tx_pool_with_clock, MOCK_CYCLES};
+use ckb_app_config::{StatusQuota, TxPoolConfig};
+use ckb_types::{
+    core::{cell::ResolvedTransaction, Capacity, TransactionView},
+    packed::Byte32,
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+#[test]
+fn test_fee_flow_report() {
+    let day_ms = 24 * 60 * 60 * 1000;
+    let start = 10 * day_ms + 1000;
+    let config = TxPoolConfig {
+        pending_quota: StatusQuota {
+            max_size: None,
+            max_count: Some(1),
+        },
+        ..Default::default()
+    };
+    let clock = Arc::new(ManualClock::new(start));
+    let (_tmp_dir, mut pool) =
+        build_tx_pool_with_clock(config, Arc::clone(&clock) as Arc<dyn Clock>);
+    let expiry = pool.expiry;
+    let entry = |tx: &TransactionView, fee, timestamp| {
+        let rtx = Arc::new(ResolvedTransaction::dummy_resolve(tx.clone()));
+        TxEntry::new_with_timestamp(rtx, MOCK_CYCLES, Capacity::shannons(fee), 1000, timestamp)
+    };
+    let callbacks = Callbacks::new();
+
+    let committed = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let expired = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let replaced = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    let replaced_child = build_tx(vec![(&replaced.hash(), 0)], 1);
+    pool.add_pending(entry(&committed, 1000, start)).unwrap();
+    pool.add_pending(entry(&expired, 2000, start - expiry - 1))
+        .unwrap();
+    pool.add_pending(entry(&replaced, 3000, start)).unwrap();
+    pool.add_pending(entry(&replaced_child, 4000, start))
+        .unwrap();
+
+    pool.remove_committed_txs(
+        &[build_block(vec![committed], 1)],
+        &callbacks,
+        &HashSet::new(),
+    );
+    pool.remove_expired(&callbacks);
+    assert_eq!(pool.remove_replaced(&replaced.proposal_short_id()).len(), 2);
+
+    // over the pending quota, the cheaper one is evicted
+    let kept = build_tx(vec![(&Byte32::zero(), 3)], 1);
+    let evicted = build_tx(vec![(&Byte32::zero(), 4)], 1);
+    pool.add_pending(entry(&kept, 6000, start)).unwrap();
+    pool.add_pending(entry(&evicted, 500, start)).unwrap();
+    pool.limit_size(&callbacks);
+    assert!(pool.contains_proposal_id(&kept.proposal_short_id()));
+    assert!(!pool.contains_proposal_id(&evicted.proposal_short_id()));
+
+    // spent by a committed tx, with its child
+    let conflicted = build_tx(vec![(&Byte32::zero(), 5)], 1);
+    let conflicted_child = build_tx(vec![(&conflicted.hash(), 0)], 1);
+    let spender = build_tx(vec![(&Byte32::zero(), 5)], 2);
+    pool.add_pending(entry(&conflicted, 100, start)).unwrap();
+    pool.add_pending(entry(&conflicted_child, 200, start))
+        .unwrap();
+    pool.remove_committed_txs(
+        &[build_block(vec![spender], 2)],
+        &callbacks,
+        &HashSet::new(),
+    );
+
+    let report = pool.fee_flow_report();
+    assert_eq!(report.day, 10);
+    assert_eq!(report.today.committed, Capacity::shannons(1000));
+    assert_eq!(report.today.expired, Capacity::shannons(2000));
+    assert_eq!(report.today.replaced, Capacity::shannons(7000));
+    assert_eq!(report.today.evicted, Capacity::shannons(500));
+    assert_eq!(report.today.conflicted, Capacity::shannons(300));
+    // every removed entry lands in exactly one bucket
+    assert_eq!(report.today.total(), Capacity::shannons(10_800));
+    assert_eq!(report.since_start, report.today);
+    assert_eq!(report.previous_day, FeeFlow::default());
+
+    // the day rolls over at midnight
+    clock.advance(day_ms);
+    let next = pool.fee_flow_report();
+    assert_eq!(next.day, 11);
+    assert_eq!(next.today, FeeFlow::default());
+    assert_eq!(next.previous_day, report.today);
+    assert_eq!(next.since_start, report.since_start);
+
+    // a skipped day leaves nothing for the previous one
+    clock.advance(2 * day_ms);
+    assert_eq!(pool.fee_flow_report().previous_day, FeeFlow::default());
+    assert_eq!(pool.fee_flow_report().since_start, report.since_start);
+}
@@ -0,0 +1,500 @@
+use crate::callback::Callbacks;
+use crate::clock::{Clock, ManualClock};
+use crate::component::entry::TxEntry;
+use crate::component::pool_map::Status;
+use crate::component::tests::util::{
+    build_tx, build_tx_pool, build_tx_pool_with_clock, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE,
+};
+use crate::error::{Reject, ThresholdsError};
+use crate::pool::{TxPool, TxPoolThresholds};
+use crate::util::check_entry_min_fee;
+use ckb_app_config::{EvictionStrategy, StatusQuota, TxPoolConfig};
+use ckb_types::{
+    core::{Capacity, FeeRate},
+    packed::Byte32,
+};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_update_thresholds_evicts() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let evicted = Arc::new(Mutex::new(Vec::new()));
+    let mut callbacks = Callbacks::new();
+    let evicted_clone = Arc::clone(&evicted);
+    callbacks.register_reject(Box::new(move |pool, entry, _reject| {
+        pool.update_statics_for_remove_tx(entry.size, entry.cycles);
+        evicted_clone
+            .lock()
+            .unwrap()
+            .push(entry.proposal_short_id());
+    }));
+
+    let txs: Vec<_> = (0..3u32)
+        .map(|i| build_tx(vec![(&Byte32::zero(), i)], 1))
+        .collect();
+    for (i, tx) in txs.iter().enumerate() {
+        let fee = Capacity::shannons(100 * (i as u64 + 1));
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, fee, 100);
+        pool.update_statics_for_add_tx(entry.size, entry.cycles);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+    assert_eq!(pool.total_tx_size, 300);
+
+    let thresholds = TxPoolThresholds {
+        max_tx_pool_size: Some(150),
+        min_fee_rate: Some(FeeRate::from_u64(2000)),
+        ..Default::default()
+    };
+    pool.update_thresholds(thresholds, &callbacks).unwrap();
+
+    assert_eq!(pool.config().max_tx_pool_size, 150);
+    assert_eq!(pool.config().min_fee_rate, FeeRate::from_u64(2000));
+    assert_eq!(pool.total_tx_size, 100);
+    // the lowest fee rate txs are evicted first
+    assert_eq!(
+        *evicted.lock().unwrap(),
+        vec![txs[0].proposal_short_id(), txs[1].proposal_short_id()]
+    );
+    assert!(pool.get_pool_entry(&txs[2].proposal_short_id()).is_some());
+}
+
+#[test]
+fn test_update_thresholds_rejects_structural_change() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let callbacks = Callbacks::new();
+    let max_ancestors_count = pool.config().max_ancestors_count;
+    let min_fee_rate = pool.config().min_fee_rate;
+
+    let thresholds = TxPoolThresholds {
+        max_ancestors_count: Some(max_ancestors_count + 1),
+        min_fee_rate: Some(FeeRate::from_u64(2000)),
+        ..Default::default()
+    };
+    assert_eq!(
+        pool.update_thresholds(thresholds, &callbacks),
+        Err(ThresholdsError::Structural(
+            "max_ancestors_count".to_string()
+        ))
+    );
+    // nothing applied
+    assert_eq!(pool.config().max_ancestors_count, max_ancestors_count);
+    assert_eq!(pool.config().min_fee_rate, min_fee_rate);
+
+    // restating the current value is fine
+    let thresholds = TxPoolThresholds {
+        max_ancestors_count: Some(max_ancestors_count),
+        ..Default::default()
+    };
+    assert!(pool.update_thresholds(thresholds, &callbacks).is_ok());
+}
+
+#[test]
+fn test_pending_quota_keeps_proposed() {
+    let config = TxPoolConfig {
+        pending_quota: StatusQuota {
+            max_size: None,
+            max_count: Some(3),
+        },
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+    let callbacks = Callbacks::new();
+
+    // proposed txs pay less than the pending flood
+    let proposed: Vec<_> = (0..2u32)
+        .map(|i| build_tx(vec![(&Byte32::zero(), i)], 1))
+        .collect();
+    for tx in proposed.iter() {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, Capacity::shannons(1), 100);
+        assert!(pool.add_proposed(entry, false).unwrap().is_some());
+    }
+    let pending: Vec<_> = (2..12u32)
+        .map(|i| build_tx(vec![(&Byte32::zero(), i)], 1))
+        .collect();
+    for (i, tx) in pending.iter().enumerate() {
+        let fee = Capacity::shannons(100 * (i as u64 + 1));
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, fee, 100);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+        pool.limit_size(&callbacks);
+    }
+
+    assert_eq!(pool.pool_map.proposed_size(), 2);
+    for tx in proposed.iter() {
+        assert!(pool.get_pool_entry(&tx.proposal_short_id()).is_some());
+    }
+    // the highest fee rate pending txs are kept
+    assert_eq!(pool.pool_map.get_by_status(Status::Pending).len(), 3);
+    for tx in pending.iter().skip(7) {
+        assert!(pool.get_pool_entry(&tx.proposal_short_id()).is_some());
+    }
+}
+
+#[test]
+fn test_package_limits() {
+    let config = TxPoolConfig {
+        max_package_size: Some(300),
+        max_package_cycles: Some(3_000),
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+
+    // a chain of 100 bytes txs, the third one fills the size limit exactly
+    let mut parent = Byte32::zero();
+    for _ in 0..3 {
+        let tx = build_tx(vec![(&parent, 0)], 1);
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, 100);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+        parent = tx.hash();
+    }
+    let tx = build_tx(vec![(&parent, 0)], 1);
+    let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, 100);
+    assert!(matches!(
+        pool.add_pending(entry),
+        Err(Reject::ExceededPackageLimit(kind, 400, 300)) if kind == "size"
+    ));
+    assert!(!pool.contains_proposal_id(&tx.proposal_short_id()));
+
+    // a chain of 1000 cycles txs
+    let mut parent = Byte32::zero();
+    for index in [1, 0, 0] {
+        let tx = build_tx(vec![(&parent, index)], 1);
+        let entry = TxEntry::dummy_resolve(tx.clone(), 1_000, MOCK_FEE, MOCK_SIZE);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+        parent = tx.hash();
+    }
+    let tx = build_tx(vec![(&parent, 0)], 1);
+    let entry = TxEntry::dummy_resolve(tx, 1_000, MOCK_FEE, MOCK_SIZE);
+    assert!(matches!(
+        pool.add_pending(entry),
+        Err(Reject::ExceededPackageLimit(kind, 4_000, 3_000)) if kind == "cycles"
+    ));
+}
+
+#[test]
+fn test_saturation() {
+    let config = TxPoolConfig {
+        max_tx_pool_size: 2_000,
+        min_fee_rate: FeeRate::from_u64(1000),
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+    let add = |pool: &mut TxPool, index: u32, fee: u64| {
+        let tx = build_tx(vec![(&Byte32::zero(), index)], 1);
+        let entry = TxEntry::dummy_resolve(tx, 0, Capacity::shannons(fee), 500);
+        let fee_rate = entry.fee_rate();
+        pool.add_pending(entry).unwrap();
+        pool.update_statics_for_add_tx(500, 0);
+        fee_rate
+    };
+
+    let empty = pool.saturation();
+    assert_eq!(empty.ratio, 0.0);
+    assert_eq!(empty.min_fee_rate, FeeRate::from_u64(1000));
+
+    // below the limit the floor is the configured min fee rate
+    add(&mut pool, 0, 100_000);
+    add(&mut pool, 1, 200_000);
+    let half_full = pool.saturation();
+    assert_eq!(half_full.ratio, 0.5);
+    assert_eq!(half_full.min_fee_rate, FeeRate::from_u64(1000));
+
+    // at or over the limit a new tx has to beat the next entry to evict
+    let cheapest = add(&mut pool, 2, 50_000);
+    add(&mut pool, 3, 300_000);
+    add(&mut pool, 4, 400_000);
+    let over_limit = pool.saturation();
+    assert_eq!(over_limit.ratio, 1.0);
+    assert_eq!(over_limit.min_fee_rate, cheapest);
+}
+
+#[test]
+fn test_dynamic_min_fee_rate_decay() {
+    let config = TxPoolConfig {
+        min_fee_rate: FeeRate::from_u64(1000),
+        min_fee_decay_half_life_s: 60,
+        pending_quota: StatusQuota {
+            max_size: None,
+            max_count: Some(2),
+        },
+        ..Default::default()
+    };
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let (_tmp_dir, mut pool) =
+        build_tx_pool_with_clock(config, Arc::clone(&clock) as Arc<dyn Clock>);
+    assert_eq!(pool.dynamic_min_fee_rate(), FeeRate::from_u64(1000));
+
+    // with a weight of 1000 the fee rate equals the fee, the cheapest tx gets evicted
+    for (i, fee) in [8000, 20_000, 30_000].into_iter().enumerate() {
+        let tx = build_tx(vec![(&Byte32::zero(), i as u32)], 1);
+        let entry = TxEntry::dummy_resolve(tx, 0, Capacity::shannons(fee), 1000);
+        pool.add_pending(entry).unwrap();
+    }
+    pool.limit_size(&Callbacks::new());
+    assert_eq!(pool.pool_map.size(), 2);
+    assert_eq!(pool.dynamic_min_fee_rate(), FeeRate::from_u64(8000));
+
+    // admission checks against the raised floor, the configured min fee rate is not enough
+    let tx = build_tx(vec![(&Byte32::zero(), 3)], 1);
+    let entry = TxEntry::dummy_resolve(tx, 0, Capacity::shannons(5000), 1000);
+    assert!(matches!(
+        check_entry_min_fee(&pool, &entry),
+        Err(Reject::LowFeeRate(fee_rate, 8000, 5000)) if fee_rate == FeeRate::from_u64(8000)
+    ));
+
+    clock.advance(30_000);
+    assert_eq!(pool.dynamic_min_fee_rate(), FeeRate::from_u64(5656));
+    clock.advance(30_000);
+    assert_eq!(pool.dynamic_min_fee_rate(), FeeRate::from_u64(4000));
+    assert!(check_entry_min_fee(&pool, &entry).is_ok());
+    clock.advance(60_000);
+    assert_eq!(pool.dynamic_min_fee_rate(), FeeRate::from_u64(2000));
+    // never below the configured min fee rate
+    clock.advance(120_000);
+    assert_eq!(pool.dynamic_min_fee_rate(), FeeRate::from_u64(1000));
+}
+
+#[test]
+fn test_entry_limit() {
+    let config = TxPoolConfig {
+        max_tx_pool_entries: 3,
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+    let evicted = Arc::new(Mutex::new(Vec::new()));
+    let mut callbacks = Callbacks::new();
+    let evicted_clone = Arc::clone(&evicted);
+    callbacks.register_reject(Box::new(move |pool, entry, reject| {
+        assert!(matches!(reject, Reject::Full(_)));
+        pool.update_statics_for_remove_tx(entry.size, entry.cycles);
+        evicted_clone
+            .lock()
+            .unwrap()
+            .push(entry.proposal_short_id());
+    }));
+
+    // minimal entries, far below max_tx_pool_size
+    let txs: Vec<_> = (0..4u32)
+        .map(|i| build_tx(vec![(&Byte32::zero(), i)], 1))
+        .collect();
+    let fees = [300, 100, 400, 200];
+    for (tx, fee) in txs.iter().zip(fees) {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, Capacity::shannons(fee), 1);
+        pool.update_statics_for_add_tx(entry.size, entry.cycles);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+    assert!(pool.total_tx_size < pool.config().max_tx_pool_size);
+    assert_eq!(pool.saturation().ratio, 1.0);
+
+    pool.limit_size(&callbacks);
+    assert_eq!(*evicted.lock().unwrap(), vec![txs[1].proposal_short_id()]);
+    assert_eq!(pool.pool_map.size(), 3);
+
+    // at the limit a new tx has to pay more than the next entry to evict
+    let floor = pool.saturation().min_fee_rate;
+    assert_eq!(floor, FeeRate::calculate(Capacity::shannons(200), 1));
+    assert!(matches!(
+        pool.check_entry_limit(Capacity::shannons(200), 1),
+        Err(Reject::Full(_))
+    ));
+    assert!(pool.check_entry_limit(Capacity::shannons(201), 1).is_ok());
+    // the cycles weigh the tx down below the floor
+    let tx = build_tx(vec![(&Byte32::zero(), 4)], 1);
+    let heavy = TxEntry::dummy_resolve(tx, 1_000_000, Capacity::shannons(201), 1);
+    assert!(heavy.weight() > 1);
+    assert!(matches!(
+        pool.check_entry_limit(heavy.fee, heavy.weight()),
+        Err(Reject::Full(_))
+    ));
+
+    pool.remove_tx(&txs[3].proposal_short_id());
+    assert!(pool.check_entry_limit(Capacity::shannons(0), 1).is_ok());
+}
+
+#[test]
+fn test_eviction_strategies() {
+    // a cheap child of a well paying parent and a large unrelated tx, 1000 bytes over the limit
+    let evict = |eviction_strategy| {
+        let config = TxPoolConfig {
+            max_tx_pool_size: 1200,
+            eviction_strategy,
+            ..Default::default()
+        };
+        let (_tmp_dir, mut pool) = build_tx_pool(config);
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let mut callbacks = Callbacks::new();
+        let evicted_clone = Arc::clone(&evicted);
+        callbacks.register_reject(Box::new(move |pool, entry, _| {
+            pool.update_statics_for_remove_tx(entry.size, entry.cycles);
+            evicted_clone
+                .lock()
+                .unwrap()
+                .push(entry.transaction().hash());
+        }));
+
+        let parent = build_tx(vec![(&Byte32::zero(), 0)], 1);
+        let child = build_tx(vec![(&parent.hash(), 0)], 1);
+        let unrelated = build_tx(vec![(&Byte32::zero(), 1)], 1);
+        for (tx, fee, size) in [
+            (&parent, 10_000, 100),
+            (&child, 900, 900),
+            (&unrelated, 3_000, 1200),
+        ] {
+            let entry =
+                TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, Capacity::shannons(fee), size);
+            pool.update_statics_for_add_tx(entry.size, entry.cycles);
+            assert!(pool.add_pending(entry).unwrap().is_some());
+        }
+        pool.limit_size(&callbacks);
+        assert!(pool.total_tx_size <= 1200);
+        let evicted = evicted.lock().unwrap().clone();
+        (evicted, [child.hash(), unrelated.hash()])
+    };
+
+    // the lowest package fee rate first, the child, then the unrelated tx to free the rest
+    let (evicted, [child, unrelated]) = evict(EvictionStrategy::PackageFeeRate);
+    assert_eq!(evicted, vec![child, unrelated]);
+
+    // the unrelated tx alone frees enough room at a lower package fee rate than the parent package
+    let (evicted, [_, unrelated]) = evict(EvictionStrategy::DescendantScore);
+    assert_eq!(evicted, vec![unrelated]);
+}
+
+#[test]
+fn test_descendant_score_eviction_by_count() {
+    let config = TxPoolConfig {
+        max_tx_pool_entries: 2,
+        eviction_strategy: EvictionStrategy::DescendantScore,
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+    let evicted = Arc::new(Mutex::new(Vec::new()));
+    let mut callbacks = Callbacks::new();
+    let evicted_clone = Arc::clone(&evicted);
+    callbacks.register_reject(Box::new(move |pool, entry, _| {
+        pool.update_statics_for_remove_tx(entry.size, entry.cycles);
+        evicted_clone
+            .lock()
+            .unwrap()
+            .push(entry.transaction().hash());
+    }));
+
+    // the small tx pays the least fee, the large one the lowest fee rate
+    let small = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let large = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let paying = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    for (tx, fee, size) in [
+        (&small, 500, 100),
+        (&large, 1000, 1000),
+        (&paying, 10_000, 100),
+    ] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, Capacity::shannons(fee), size);
+        pool.update_statics_for_add_tx(entry.size, entry.cycles);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+    // only the count is over, the tx with the lowest fee rate goes
+    pool.limit_size(&callbacks);
+    assert_eq!(*evicted.lock().unwrap(), vec![large.hash()]);
+}
+
+#[test]
+fn test_package_size_eviction() {
+    // a cheap root with 4 children, 1400 bytes, a small cheap tx of 600 bytes and a well paying
+    // tx of 1000 bytes, 500 bytes over the limit
+    let evict = |eviction_strategy| {
+        let config = TxPoolConfig {
+            max_tx_pool_size: 2500,
+            eviction_strategy,
+            ..Default::default()
+        };
+        let (_tmp_dir, mut pool) = build_tx_pool(config);
+        let evicted = Arc::new(Mutex::new(HashSet::new()));
+        let mut callbacks = Callbacks::new();
+        let evicted_clone = Arc::clone(&evicted);
+        callbacks.register_reject(Box::new(move |pool, entry, _| {
+            pool.update_statics_for_remove_tx(entry.size, entry.cycles);
+            evicted_clone
+                .lock()
+                .unwrap()
+                .insert(entry.transaction().hash());
+        }));
+
+        let root = build_tx(vec![(&Byte32::zero(), 0)], 4);
+        let children: Vec<_> = (0..4)
+            .map(|i| build_tx(vec![(&root.hash(), i)], 1))
+            .collect();
+        let small = build_tx(vec![(&Byte32::zero(), 1)], 1);
+        let paying = build_tx(vec![(&Byte32::zero(), 2)], 1);
+        let mut txs = vec![(&root, 100, 200)];
+        txs.extend(children.iter().map(|child| (child, 300, 300)));
+        txs.extend([(&small, 570, 600), (&paying, 100_000, 1000)]);
+        for (tx, fee, size) in txs {
+            let entry =
+                TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, Capacity::shannons(fee), size);
+            pool.update_statics_for_add_tx(entry.size, entry.cycles);
+            assert!(pool.add_pending(entry).unwrap().is_some());
+        }
+        pool.limit_size(&callbacks);
+        assert!(pool.total_tx_size <= 2500);
+        let evicted = evicted.lock().unwrap().clone();
+        let mut giant: HashSet<_> = children.iter().map(|child| child.hash()).collect();
+        giant.insert(root.hash());
+        (evicted, giant, small.hash())
+    };
+
+    // the root has the lowest package fee rate, the whole tree goes
+    let (evicted, giant, _) = evict(EvictionStrategy::PackageFeeRate);
+    assert_eq!(evicted, giant);
+
+    // the small tx is almost as cheap and frees enough room alone
+    let (evicted, _, small) = evict(EvictionStrategy::PackageSize);
+    assert_eq!(evicted, HashSet::from([small]));
+}
+
+#[test]
+fn test_eviction_summary() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let mut callbacks = Callbacks::new();
+    callbacks.register_reject(Box::new(|pool, entry, _reject| {
+        pool.update_statics_for_remove_tx(entry.size, entry.cycles);
+    }));
+
+    // a pays 1000 on its own but 2000 with its child b, c pays 1500, d pays 5000
+    let a = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let b = build_tx(vec![(&a.hash(), 0)], 1);
+    let c = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let d = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    for (tx, fee) in [(&a, 100), (&b, 300), (&c, 150), (&d, 500)] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), 10, Capacity::shannons(fee), 100);
+        pool.update_statics_for_add_tx(entry.size, entry.cycles);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+
+    let thresholds = TxPoolThresholds {
+        min_fee_rate: Some(FeeRate::from_u64(0)),
+        ..Default::default()
+    };
+    // no size limit change, nothing evicted
+    assert!(pool
+        .update_thresholds(thresholds, &callbacks)
+        .unwrap()
+        .is_empty());
+
+    let thresholds = TxPoolThresholds {
+        max_tx_pool_size: Some(150),
+        ..Default::default()
+    };
+    let summary = pool.update_thresholds(thresholds, &callbacks).unwrap();
+    // c goes first, then a with its child b
+    assert_eq!(summary.removed_count, 3);
+    assert_eq!(summary.removed_bytes, 300);
+    assert_eq!(summary.removed_cycles, 30);
+    assert_eq!(summary.min_fee_rate_removed, Some(FeeRate::from_u64(1500)));
+    // b is counted at the package fee rate of a, not at its own
+    assert_eq!(summary.max_fee_rate_removed, Some(FeeRate::from_u64(2000)));
+    assert_eq!(pool.total_tx_size, 100);
+    assert!(pool.get_pool_entry(&d.proposal_short_id()).is_some());
+
+    // within the limits, nothing more to evict
+    assert!(pool.limit_size(&callbacks).is_empty());
+}
@@ -1,8 +1,24 @@
+mod archive;
+mod changelog;
 mod chunk;
+mod committed;
+mod conflict_cache;
+mod diagnosis;
 mod entry;
+mod expiry;
+mod fee_flow;
+mod limits;
 mod orphan;
 mod pending;
 mod proposed;
+mod rate_limit;
+mod rbf;
+mod rebroadcast;
 mod recent_reject;
+mod replay;
 mod score_key;
+mod template;
+mod trace;
+mod tx_pool;
 mod util;
+mod view;
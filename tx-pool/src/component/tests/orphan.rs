@@ -56,3 +56,26 @@ fn test_orphan_duplicated() {
     assert!(txs.contains(&&tx2.proposal_short_id()));
     assert!(txs.contains(&&tx5.proposal_short_id()));
 }
+
+#[test]
+fn test_remove_orphans_by_replaced() {
+    // tx1 is in the pool and gets replaced, tx2 spends its output, tx3 spends tx2's
+    let tx1 = build_tx(vec![(&Byte32::zero(), 1)], 2);
+    let tx2 = build_tx(vec![(&tx1.hash(), 0)], 1);
+    let tx3 = build_tx(vec![(&tx2.hash(), 0)], 1);
+    let unrelated = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    let orphan_of_unrelated = build_tx(vec![(&unrelated.hash(), 0)], 1);
+
+    let mut orphan = OrphanPool::new();
+    orphan.add_orphan_tx(tx2.clone(), 0.into(), 0);
+    orphan.add_orphan_tx(tx3.clone(), 0.into(), 0);
+    orphan.add_orphan_tx(orphan_of_unrelated.clone(), 0.into(), 0);
+    assert_eq!(orphan.len(), 3);
+
+    let removed = orphan.remove_orphans_by_previous(vec![tx1].iter());
+    let removed: Vec<_> = removed.into_iter().map(|entry| entry.tx.hash()).collect();
+    assert_eq!(removed, vec![tx2.hash(), tx3.hash()]);
+    assert_eq!(orphan.len(), 1);
+    assert!(orphan.contains_key(&orphan_of_unrelated.proposal_short_id()));
+    assert!(orphan.find_by_previous(&tx2).is_empty());
+}
@@ -1,3 +1,4 @@
+use crate::component::changelog::RemoveReason;
 use crate::component::edges::Edges;
 use crate::component::tests::util::{
     build_tx, build_tx_with_dep, build_tx_with_header_dep, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE,
@@ -6,7 +7,8 @@ use crate::component::{
     entry::TxEntry,
     pool_map::{PoolMap, Status},
 };
-use ckb_types::core::Capacity;
+use crate::error::Reject;
+use ckb_types::core::{Capacity, FeeRate};
 use ckb_types::packed::OutPoint;
 use ckb_types::{h256, packed::Byte32, prelude::*};
 use std::collections::HashSet;
@@ -152,9 +154,9 @@ fn test_remove_entry() {
     assert!(pool.add_entry(entry1.clone(), Status::Pending).unwrap());
     assert!(pool.add_entry(entry2.clone(), Status::Pending).unwrap());
 
-    let removed = pool.remove_entry(&tx1.proposal_short_id());
+    let removed = pool.remove_entry(&tx1.proposal_short_id(), RemoveReason::Removed);
     assert_eq!(removed, Some(entry1));
-    let removed = pool.remove_entry(&tx2.proposal_short_id());
+    let removed = pool.remove_entry(&tx2.proposal_short_id(), RemoveReason::Removed);
     assert_eq!(removed, Some(entry2));
     assert!(pool.entries.is_empty());
     assert!(pool.edges.deps.is_empty());
@@ -307,19 +309,53 @@ fn test_pool_evict() {
 
     let e1 = pool.next_evict_entry(Status::Pending).unwrap();
     assert_eq!(e1, tx1.proposal_short_id());
-    pool.remove_entry(&e1);
+    pool.remove_entry(&e1, RemoveReason::Removed);
 
     let e2 = pool.next_evict_entry(Status::Pending).unwrap();
     assert_eq!(e2, tx2.proposal_short_id());
-    pool.remove_entry(&e2);
+    pool.remove_entry(&e2, RemoveReason::Removed);
 
     let e3 = pool.next_evict_entry(Status::Pending).unwrap();
     assert_eq!(e3, tx3.proposal_short_id());
-    pool.remove_entry(&e3);
+    pool.remove_entry(&e3, RemoveReason::Removed);
 
     assert!(pool.next_evict_entry(Status::Pending).is_none());
 }
 
+#[test]
+fn test_pool_evict_by_package_fee_rate() {
+    let mut pool = PoolMap::new(1000);
+    // a low-fee parent bumped by a high-fee child, and an unrelated tx in between
+    let parent = build_tx(vec![(&h256!("0x1").pack(), 1)], 1);
+    let child = build_tx(vec![(&parent.hash(), 0)], 1);
+    let other = build_tx(vec![(&h256!("0x2").pack(), 1)], 1);
+    let parent_entry = TxEntry::dummy_resolve(parent.clone(), 0, Capacity::shannons(100), 100);
+    let child_entry = TxEntry::dummy_resolve(child.clone(), 0, Capacity::shannons(900), 100);
+    let other_entry = TxEntry::dummy_resolve(other.clone(), 0, Capacity::shannons(300), 100);
+
+    assert!(pool
+        .add_entry(parent_entry.clone(), Status::Pending)
+        .unwrap());
+    assert!(pool.add_entry(child_entry, Status::Pending).unwrap());
+    assert!(pool
+        .add_entry(other_entry.clone(), Status::Pending)
+        .unwrap());
+
+    let parent_id = parent.proposal_short_id();
+    let parent_in_pool = pool.get(&parent_id).unwrap();
+    assert_eq!(parent_in_pool.fee_rate(), parent_entry.fee_rate());
+    assert_eq!(
+        parent_in_pool.package_fee_rate(),
+        FeeRate::calculate(Capacity::shannons(1000), 200)
+    );
+    assert!(parent_entry.fee_rate() < other_entry.fee_rate());
+    assert!(parent_in_pool.package_fee_rate() > other_entry.package_fee_rate());
+
+    // individually the parent pays the least, but its package outbids the unrelated tx
+    let e1 = pool.next_evict_entry(Status::Pending).unwrap();
+    assert_eq!(e1, other.proposal_short_id());
+}
+
 #[test]
 fn test_pool_min_weight_evict() {
     let mut pool = PoolMap::new(1000);
@@ -345,15 +381,15 @@ fn test_pool_min_weight_evict() {
 
     let e1 = pool.next_evict_entry(Status::Pending).unwrap();
     assert_eq!(e1, tx3.proposal_short_id());
-    pool.remove_entry(&e1);
+    pool.remove_entry(&e1, RemoveReason::Removed);
 
     let e2 = pool.next_evict_entry(Status::Pending).unwrap();
     assert_eq!(e2, tx2.proposal_short_id());
-    pool.remove_entry(&e2);
+    pool.remove_entry(&e2, RemoveReason::Removed);
 
     let e3 = pool.next_evict_entry(Status::Pending).unwrap();
     assert_eq!(e3, tx1.proposal_short_id());
-    pool.remove_entry(&e3);
+    pool.remove_entry(&e3, RemoveReason::Removed);
 
     assert!(pool.next_evict_entry(Status::Pending).is_none());
 }
@@ -383,15 +419,15 @@ fn test_pool_max_size_evict() {
 
     let e1 = pool.next_evict_entry(Status::Pending).unwrap();
     assert_eq!(e1, tx1.proposal_short_id());
-    pool.remove_entry(&e1);
+    pool.remove_entry(&e1, RemoveReason::Removed);
 
     let e2 = pool.next_evict_entry(Status::Pending).unwrap();
     assert_eq!(e2, tx2.proposal_short_id());
-    pool.remove_entry(&e2);
+    pool.remove_entry(&e2, RemoveReason::Removed);
 
     let e3 = pool.next_evict_entry(Status::Pending).unwrap();
     assert_eq!(e3, tx3.proposal_short_id());
-    pool.remove_entry(&e3);
+    pool.remove_entry(&e3, RemoveReason::Removed);
 
     assert!(pool.next_evict_entry(Status::Pending).is_none());
 }
@@ -414,15 +450,51 @@ fn test_pool_min_descendants_evict() {
 
     let e1 = pool.next_evict_entry(Status::Pending).unwrap();
     assert_eq!(e1, tx3.proposal_short_id());
-    pool.remove_entry(&e1);
+    pool.remove_entry(&e1, RemoveReason::Removed);
 
     let e2 = pool.next_evict_entry(Status::Pending).unwrap();
     assert_eq!(e2, tx2.proposal_short_id());
-    pool.remove_entry(&e2);
+    pool.remove_entry(&e2, RemoveReason::Removed);
 
     let e3 = pool.next_evict_entry(Status::Pending).unwrap();
     assert_eq!(e3, tx1.proposal_short_id());
-    pool.remove_entry(&e3);
+    pool.remove_entry(&e3, RemoveReason::Removed);
 
     assert!(pool.next_evict_entry(Status::Pending).is_none());
 }
+
+#[test]
+fn test_max_header_deps() {
+    let mut pool = PoolMap::new(1000).with_max_header_deps(Some(3));
+    let headers: Vec<Byte32> = vec![
+        h256!("0x1").pack(),
+        h256!("0x2").pack(),
+        h256!("0x3").pack(),
+        h256!("0x4").pack(),
+    ];
+
+    let over_limit = build_tx_with_header_dep(vec![(&Byte32::zero(), 1)], headers.clone(), 1);
+    let entry = TxEntry::dummy_resolve(over_limit, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(matches!(
+        pool.add_entry(entry, Status::Pending),
+        Err(Reject::ExceededMaximumHeaderDeps(4, 3))
+    ));
+    assert_eq!(pool.header_deps_len(), 0);
+    assert_eq!(pool.inputs_len(), 0);
+
+    let at_limit = build_tx_with_header_dep(vec![(&Byte32::zero(), 2)], headers[..3].to_vec(), 1);
+    let entry = TxEntry::dummy_resolve(at_limit, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(pool.add_entry(entry.clone(), Status::Pending).unwrap());
+    assert_eq!(pool.header_deps_len(), 1);
+    assert_eq!(pool.edges.header_dep_txs.len(), 3);
+
+    // detaching all its headers removes the tx once and clears the reverse index
+    let detached: HashSet<_> = headers[..3].iter().cloned().collect();
+    let conflicts = pool.resolve_conflict_header_dep(&detached);
+    assert_eq!(
+        conflicts.into_iter().map(|i| i.0).collect::<Vec<_>>(),
+        vec![entry]
+    );
+    assert_eq!(pool.header_deps_len(), 0);
+    assert!(pool.edges.header_dep_txs.is_empty());
+}
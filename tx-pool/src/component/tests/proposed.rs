@@ -1,22 +1,24 @@
+use crate::component::changelog::RemoveReason;
 use crate::component::pool_map::Status;
 use crate::component::tests::util::{
     build_tx, build_tx_with_dep, build_tx_with_header_dep, DEFAULT_MAX_ANCESTORS_COUNT,
     MOCK_CYCLES, MOCK_FEE, MOCK_SIZE,
 };
 
-use crate::component::{entry::TxEntry, pool_map::PoolMap};
+use crate::component::{commit_txs_scanner::CommitTxsScanner, entry::TxEntry, pool_map::PoolMap};
 use ckb_types::{
     bytes::Bytes,
     core::{
         cell::{get_related_dep_out_points, CellMeta, ResolvedTransaction},
-        Capacity, DepType, TransactionBuilder, TransactionView,
+        Capacity, Cycle, DepType, TransactionBuilder, TransactionView,
     },
     h256,
-    packed::{Byte32, CellDep, CellInput, CellOutput, OutPoint},
+    packed::{Byte32, CellDep, CellInput, CellOutput, OutPoint, ProposalShortId},
     prelude::*,
 };
 use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 fn dummy_resolve<F: Fn(&OutPoint) -> Option<Bytes>>(
     tx: TransactionView,
@@ -71,7 +73,7 @@ fn test_add_entry() {
     assert_eq!(pool.size(), 2);
     assert_eq!(pool.edges.inputs_len(), 3);
 
-    pool.remove_entry(&tx1.proposal_short_id());
+    pool.remove_entry(&tx1.proposal_short_id(), RemoveReason::Removed);
     assert_eq!(pool.edges.inputs_len(), 1);
 }
 
@@ -136,7 +138,7 @@ fn test_add_entry_from_detached() {
         assert!(pool.links.get_children(&id3).unwrap().is_empty());
     }
 
-    pool.remove_entry(&tx1.proposal_short_id());
+    pool.remove_entry(&tx1.proposal_short_id(), RemoveReason::Removed);
     assert_eq!(pool.edges.inputs_len(), 2);
     assert_eq!(pool.entries.len(), 2);
 
@@ -216,7 +218,7 @@ fn test_add_roots() {
 
     assert_eq!(pool.edges.inputs_len(), 4);
 
-    pool.remove_entry(&tx1.proposal_short_id());
+    pool.remove_entry(&tx1.proposal_short_id(), RemoveReason::Removed);
 
     assert_eq!(pool.edges.inputs_len(), 2);
 }
@@ -275,7 +277,7 @@ fn test_add_no_roots() {
 
     assert_eq!(pool.edges.inputs_len(), 7);
 
-    pool.remove_entry(&tx1.proposal_short_id());
+    pool.remove_entry(&tx1.proposal_short_id(), RemoveReason::Removed);
 
     assert_eq!(pool.edges.inputs_len(), 6);
 }
@@ -576,7 +578,7 @@ fn test_dep_group() {
     assert_eq!(get_deps_len(&pool, &tx2_out_point), 1);
     assert_eq!(get_deps_len(&pool, &tx3_out_point), 0);
 
-    pool.remove_entry(&tx3.proposal_short_id());
+    pool.remove_entry(&tx3.proposal_short_id(), RemoveReason::Removed);
     assert_eq!(get_deps_len(&pool, &tx1_out_point), 0);
     assert_eq!(get_deps_len(&pool, &tx2_out_point), 0);
     assert_eq!(get_deps_len(&pool, &tx3_out_point), 0);
@@ -607,6 +609,52 @@ fn test_resolve_conflict_header_dep() {
     );
 }
 
+#[test]
+fn test_entries_with_header_dep() {
+    let mut pool = PoolMap::new(DEFAULT_MAX_ANCESTORS_COUNT);
+
+    let header1: Byte32 = h256!("0x1").pack();
+    let header2: Byte32 = h256!("0x2").pack();
+    let header3: Byte32 = h256!("0x3").pack();
+    let tx1 = build_tx_with_header_dep(vec![(&Byte32::zero(), 1)], vec![header1.clone()], 1);
+    let tx2 = build_tx_with_header_dep(
+        vec![(&Byte32::zero(), 2)],
+        vec![header1.clone(), header2.clone()],
+        1,
+    );
+    let tx3 = build_tx(vec![(&Byte32::zero(), 3)], 1);
+    for tx in [&tx1, &tx2, &tx3] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        pool.add_entry(entry, Status::Pending).unwrap();
+    }
+
+    let ids = |header: &Byte32| {
+        pool.entries_with_header_dep(header)
+            .into_iter()
+            .map(|entry| entry.id.clone())
+            .collect::<HashSet<_>>()
+    };
+    assert_eq!(
+        ids(&header1),
+        HashSet::from_iter(vec![tx1.proposal_short_id(), tx2.proposal_short_id()])
+    );
+    assert_eq!(
+        ids(&header2),
+        HashSet::from_iter(vec![tx2.proposal_short_id()])
+    );
+    assert!(ids(&header3).is_empty());
+
+    pool.remove_entry(&tx2.proposal_short_id(), RemoveReason::Removed);
+    assert_eq!(
+        pool.entries_with_header_dep(&header1)
+            .into_iter()
+            .map(|entry| entry.id.clone())
+            .collect::<Vec<_>>(),
+        vec![tx1.proposal_short_id()]
+    );
+    assert!(pool.entries_with_header_dep(&header2).is_empty());
+}
+
 #[test]
 fn test_disordered_remove_committed_tx() {
     let tx1 = build_tx(vec![(&Byte32::zero(), 1)], 1);
@@ -633,8 +681,8 @@ fn test_disordered_remove_committed_tx() {
 
     assert_eq!(pool.edges.inputs_len(), 2);
 
-    pool.remove_entry(&tx2.proposal_short_id());
-    pool.remove_entry(&tx1.proposal_short_id());
+    pool.remove_entry(&tx2.proposal_short_id(), RemoveReason::Removed);
+    pool.remove_entry(&tx1.proposal_short_id(), RemoveReason::Removed);
 
     assert_eq!(pool.edges.inputs_len(), 0);
 }
@@ -732,3 +780,93 @@ fn test_container_bench_add_limits() {
     pool.clear();
     assert_eq!(pool.size(), 0);
 }
+
+#[test]
+fn test_txs_to_commit_soft_cycles_target() {
+    let mut pool = PoolMap::new(DEFAULT_MAX_ANCESTORS_COUNT);
+    for i in 0..10u32 {
+        let tx = build_tx(vec![(&Byte32::zero(), i)], 1);
+        pool.add_proposed(TxEntry::dummy_resolve(
+            tx,
+            100,
+            Capacity::shannons(100 + i as u64),
+            MOCK_SIZE,
+        ))
+        .unwrap();
+    }
+
+    // selecting stops at the first tx reaching the soft target
+    let (entries, _size, cycles, _timed_out) = CommitTxsScanner::new(&pool)
+        .with_soft_cycles_target(Some(350))
+        .txs_to_commit(usize::MAX, 1_000, None);
+    assert_eq!(entries.len(), 4);
+    assert_eq!(cycles, 400);
+
+    // the hard limit still applies below the soft target
+    let (entries, _size, cycles, _timed_out) = CommitTxsScanner::new(&pool)
+        .with_soft_cycles_target(Some(350))
+        .txs_to_commit(usize::MAX, 250, None);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(cycles, 200);
+
+    let (entries, _size, _cycles, _timed_out) = CommitTxsScanner::new(&pool)
+        .with_soft_cycles_target(None)
+        .txs_to_commit(usize::MAX, 1_000, None);
+    assert_eq!(entries.len(), 10);
+}
+
+#[test]
+fn test_txs_to_commit_timeout() {
+    let mut pool = PoolMap::new(DEFAULT_MAX_ANCESTORS_COUNT);
+    let mut parent = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    pool.add_proposed(TxEntry::dummy_resolve(
+        parent.clone(),
+        MOCK_CYCLES,
+        MOCK_FEE,
+        MOCK_SIZE,
+    ))
+    .unwrap();
+    for i in 1..100u32 {
+        let tx = if i % 2 == 0 {
+            build_tx(vec![(&parent.hash(), 0)], 1)
+        } else {
+            build_tx(vec![(&Byte32::zero(), i)], 1)
+        };
+        pool.add_proposed(TxEntry::dummy_resolve(
+            tx.clone(),
+            MOCK_CYCLES,
+            Capacity::shannons(100 + i as u64),
+            MOCK_SIZE,
+        ))
+        .unwrap();
+        if i % 2 == 0 {
+            parent = tx;
+        }
+    }
+
+    let (entries, _size, _cycles, timed_out) =
+        CommitTxsScanner::new(&pool).txs_to_commit(usize::MAX, Cycle::MAX, None);
+    assert!(!timed_out);
+    assert_eq!(entries.len(), 100);
+
+    let now = Instant::now();
+    let (entries, size, cycles, timed_out) =
+        CommitTxsScanner::new(&pool).txs_to_commit(usize::MAX, Cycle::MAX, Some(Duration::ZERO));
+    assert!(now.elapsed() < Duration::from_secs(1));
+    assert!(timed_out);
+    assert!(!entries.is_empty() && entries.len() < 100);
+    assert_eq!(size, entries.iter().map(|e| e.size).sum::<usize>());
+    assert_eq!(cycles, entries.iter().map(|e| e.cycles).sum::<Cycle>());
+
+    // every selected tx has its in-pool parent selected before it
+    let mut selected = HashSet::new();
+    for entry in &entries {
+        for input in entry.transaction().input_pts_iter() {
+            let parent_id = ProposalShortId::from_tx_hash(&input.tx_hash());
+            if pool.get_proposed(&parent_id).is_some() {
+                assert!(selected.contains(&parent_id));
+            }
+        }
+        selected.insert(entry.proposal_short_id());
+    }
+}
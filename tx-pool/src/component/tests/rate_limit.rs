@@ -0,0 +1,62 @@
+use crate::component::rate_limit::{PeerAdmissionStats, PeerRateLimiter};
+use ckb_network::PeerIndex;
+
+#[test]
+fn test_rate_limit_throttles_offender() {
+    let honest: PeerIndex = 1.into();
+    let flooder: PeerIndex = 2.into();
+    // 10 txs per second, bursts of 20
+    let mut limiter = PeerRateLimiter::new(10, 20);
+
+    // over 10 seconds the honest peer sends 5 txs per second, the flooder 100 txs per second
+    let mut honest_throttled = 0;
+    let mut flooder_admitted = 0;
+    for now in (0..10_000).step_by(10) {
+        if now % 200 == 0 && !limiter.try_admit(honest, now) {
+            honest_throttled += 1;
+        }
+        if limiter.try_admit(flooder, now) {
+            flooder_admitted += 1;
+        }
+    }
+
+    assert_eq!(honest_throttled, 0);
+    // the burst plus the whole tokens refilled until the last attempt at 9990ms
+    assert_eq!(flooder_admitted, 20 + 99);
+
+    let stats = limiter.stats();
+    assert_eq!(
+        stats[&honest],
+        PeerAdmissionStats {
+            admitted: 50,
+            throttled: 0,
+        }
+    );
+    assert_eq!(stats[&flooder].admitted, 119);
+    assert_eq!(stats[&flooder].throttled, 1000 - 119);
+}
+
+#[test]
+fn test_rate_limit_refill() {
+    let peer: PeerIndex = 1.into();
+    let mut limiter = PeerRateLimiter::new(1, 2);
+
+    assert!(limiter.try_admit(peer, 0));
+    assert!(limiter.try_admit(peer, 0));
+    assert!(!limiter.try_admit(peer, 0));
+    assert!(!limiter.try_admit(peer, 999));
+    assert!(limiter.try_admit(peer, 1000));
+    // the bucket never holds more than the burst
+    assert!(limiter.try_admit(peer, 60_000));
+    assert!(limiter.try_admit(peer, 60_000));
+    assert!(!limiter.try_admit(peer, 60_000));
+}
+
+#[test]
+fn test_rate_limit_disabled() {
+    let peer: PeerIndex = 1.into();
+    let mut limiter = PeerRateLimiter::new(0, 20);
+    assert!(!limiter.is_enabled());
+    assert!((0..100).all(|_| limiter.try_admit(peer, 0)));
+    assert!(limiter.stats().is_empty());
+}
@@ -0,0 +1,359 @@
+use crate::callback::Callbacks;
+use crate::component::changelog::RemoveReason;
+use crate::component::entry::TxEntry;
+use crate::component::tests::util::{
+    build_block, build_tx, build_tx_pool, build_tx_with_dep, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE,
+};
+use crate::error::Reject;
+use crate::process::{pre_check_tx, TxStatus, _submit_entry};
+use ckb_app_config::{ConsumedDepPolicy, TxPoolConfig};
+use ckb_types::{
+    core::{
+        cell::{CellMetaBuilder, ResolvedTransaction},
+        error::OutPointError,
+        tx_pool::RBFRule,
+        Capacity, FeeRate,
+    },
+    packed::{Byte32, OutPoint},
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+#[test]
+fn test_min_replace_fee_missing_entry() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    assert!(pool.enable_rbf());
+
+    let tx1 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let entry1 = TxEntry::dummy_resolve(tx1, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    pool.add_pending(entry1.clone()).unwrap();
+    assert!(pool.min_replace_fee(&entry1).is_some());
+
+    let tx2 = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    let entry2 = TxEntry::dummy_resolve(tx2, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert_eq!(pool.min_replace_fee(&entry2), None);
+}
+
+#[test]
+fn test_replace_summary_log() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let tx1 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let tx2 = build_tx(vec![(&tx1.hash(), 0)], 1);
+    let tx3 = build_tx(vec![(&Byte32::zero(), 1)], 2);
+    let tx4 = build_tx(vec![(&Byte32::zero(), 1)], 3);
+    let fee = |shannons| Capacity::shannons(shannons);
+    for (tx, shannons) in [(&tx1, 100), (&tx2, 50)] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, fee(shannons), MOCK_SIZE);
+        pool.add_pending(entry).unwrap();
+    }
+
+    // tx3 replaces tx1 and its child tx2
+    let removed = pool
+        .pool_map
+        .remove_entry_and_descendants(&tx1.proposal_short_id(), RemoveReason::Replaced);
+    pool.record_replacement(tx3.hash(), &removed);
+    let entry = TxEntry::dummy_resolve(tx3.clone(), MOCK_CYCLES, fee(200), MOCK_SIZE);
+    pool.add_pending(entry).unwrap();
+
+    // tx4 replaces tx3, taking over its ancestry
+    let removed = pool
+        .pool_map
+        .remove_entry_and_descendants(&tx3.proposal_short_id(), RemoveReason::Replaced);
+    pool.record_replacement(tx4.hash(), &removed);
+
+    assert!(pool.replace_summary_log(&tx3).is_none());
+    let summary = pool.replace_summary_log(&tx4).unwrap();
+    assert_eq!(summary.tx_hash, tx4.hash());
+    let replaced: HashSet<_> = summary.replaced.into_iter().collect();
+    let expected: HashSet<_> = vec![tx1.hash(), tx2.hash(), tx3.hash()]
+        .into_iter()
+        .collect();
+    assert_eq!(replaced, expected);
+    assert_eq!(summary.displaced_fee, fee(350));
+
+    // committing the replacement consumes its record
+    pool.record_replacement(tx4.hash(), &[]);
+    pool.remove_committed_txs(
+        &[build_block(vec![tx4.clone()], 1)],
+        &Callbacks::new(),
+        &HashSet::new(),
+    );
+    assert!(pool.replace_summary_log(&tx4).is_none());
+}
+
+#[test]
+fn test_check_rbf_detailed() {
+    let config = TxPoolConfig {
+        min_fee_rate: FeeRate::zero(),
+        min_rbf_rate: FeeRate::from_u64(1000),
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+    let old = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let entry = TxEntry::dummy_resolve(old.clone(), MOCK_CYCLES, Capacity::shannons(100), 100);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+
+    let new = build_tx(vec![(&Byte32::zero(), 0)], 2);
+    let rtx = ResolvedTransaction::dummy_resolve(new);
+    let conflicts: HashSet<_> = vec![old.proposal_short_id()].into_iter().collect();
+    let snapshot = pool.cloned_snapshot();
+
+    // pays more than the replaced tx, but less than its fee plus 1000 bytes at min_rbf_rate
+    let (ret, rules) = pool.check_rbf(
+        &snapshot,
+        &rtx,
+        &conflicts,
+        Capacity::shannons(150),
+        1000,
+        true,
+    );
+    assert!(matches!(ret, Err(Reject::RBFRejected(_))));
+    assert_eq!(
+        rules,
+        vec![
+            (RBFRule::NoNewUnconfirmedInputs, true),
+            (RBFRule::PaysReplacedFees, true),
+            (RBFRule::PaysMinRbfFee, false),
+        ]
+    );
+
+    // a new unconfirmed input is reported before the fees
+    let unconfirmed = build_tx(vec![(&Byte32::zero(), 0), (&old.hash(), 1)], 2);
+    let rtx = ResolvedTransaction::dummy_resolve(unconfirmed);
+    let (ret, rules) = pool.check_rbf(
+        &snapshot,
+        &rtx,
+        &conflicts,
+        Capacity::shannons(50),
+        1000,
+        true,
+    );
+    assert!(matches!(ret, Err(Reject::RBFRejected(_))));
+    assert_eq!(rules, vec![(RBFRule::NoNewUnconfirmedInputs, false)]);
+
+    let rtx = ResolvedTransaction::dummy_resolve(build_tx(vec![(&Byte32::zero(), 0)], 2));
+
+    let (ret, rules) = pool.check_rbf(
+        &snapshot,
+        &rtx,
+        &conflicts,
+        Capacity::shannons(150),
+        1000,
+        false,
+    );
+    assert!(ret.is_err());
+    assert!(rules.is_empty());
+}
+
+#[test]
+fn test_fee_delta_to_replace() {
+    let config = TxPoolConfig {
+        min_fee_rate: FeeRate::zero(),
+        min_rbf_rate: FeeRate::from_u64(1000),
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+    let old = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let child = build_tx(vec![(&old.hash(), 0)], 1);
+    for tx in [&old, &child] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, Capacity::shannons(100), 100);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+    assert!(pool
+        .fee_delta_to_replace(&build_tx(vec![], 1).proposal_short_id(), 1000)
+        .is_none());
+
+    let new = build_tx(vec![(&Byte32::zero(), 0)], 2);
+    let rtx = ResolvedTransaction::dummy_resolve(new);
+    let conflicts: HashSet<_> = vec![old.proposal_short_id()].into_iter().collect();
+    let snapshot = pool.cloned_snapshot();
+    let old_fee = Capacity::shannons(100);
+
+    // matching the fee of the replaced tx is not enough
+    let (ret, _) = pool.check_rbf(&snapshot, &rtx, &conflicts, old_fee, 1000, false);
+    assert!(matches!(ret, Err(Reject::RBFRejected(_))));
+
+    let delta = pool
+        .fee_delta_to_replace(&old.proposal_short_id(), 1000)
+        .unwrap();
+    assert_eq!(delta, Capacity::shannons(1000));
+    let fee = old_fee.safe_add(delta).unwrap();
+    let (ret, _) = pool.check_rbf(&snapshot, &rtx, &conflicts, fee, 1000, false);
+    assert!(ret.is_ok());
+    let fee = Capacity::shannons(fee.as_u64() - 1);
+    let (ret, _) = pool.check_rbf(&snapshot, &rtx, &conflicts, fee, 1000, false);
+    assert!(ret.is_err());
+
+    // proposed txs can't be replaced, nor the ones with a proposed descendant
+    pool.set_entry_proposed(&child.proposal_short_id());
+    assert!(pool
+        .fee_delta_to_replace(&old.proposal_short_id(), 1000)
+        .is_none());
+    pool.set_entry_proposed(&old.proposal_short_id());
+    assert!(pool
+        .fee_delta_to_replace(&old.proposal_short_id(), 1000)
+        .is_none());
+}
+
+#[test]
+fn test_consumed_dep_policy() {
+    let code_cell = Byte32::zero();
+    let group_cell = build_tx(vec![(&Byte32::zero(), 1)], 1).hash();
+    let member_cell = build_tx(vec![(&Byte32::zero(), 2)], 1).hash();
+
+    // `plain` uses `code_cell` as cell dep, `grouped` uses `member_cell` through a dep group,
+    // `child` spends an output of `grouped`
+    let plain = build_tx_with_dep(vec![(&Byte32::zero(), 3)], vec![(&code_cell, 0)], 1);
+    let grouped = build_tx(vec![(&Byte32::zero(), 4)], 1);
+    let grouped_rtx = ResolvedTransaction {
+        resolved_inputs: vec![CellMetaBuilder::default()
+            .out_point(OutPoint::new(Byte32::zero(), 4))
+            .build()],
+        resolved_cell_deps: vec![CellMetaBuilder::default()
+            .out_point(OutPoint::new(member_cell.clone(), 0))
+            .build()],
+        resolved_dep_groups: vec![CellMetaBuilder::default()
+            .out_point(OutPoint::new(group_cell.clone(), 0))
+            .build()],
+        transaction: grouped.clone(),
+    };
+    let child = build_tx(vec![(&grouped.hash(), 0)], 1);
+    let spend_code = build_tx(vec![(&code_cell, 0)], 1);
+    let spend_member = build_tx(vec![(&member_cell, 0)], 1);
+
+    let build_pool = |policy| {
+        let config = TxPoolConfig {
+            consumed_dep_policy: policy,
+            ..Default::default()
+        };
+        let (tmp_dir, mut pool) = build_tx_pool(config);
+        let entries = vec![
+            TxEntry::dummy_resolve(plain.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE),
+            TxEntry::new(
+                Arc::new(grouped_rtx.clone()),
+                MOCK_CYCLES,
+                MOCK_FEE,
+                MOCK_SIZE,
+            ),
+            TxEntry::dummy_resolve(child.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE),
+        ];
+        for entry in entries {
+            _submit_entry(&mut pool, TxStatus::Fresh, entry, &Callbacks::new()).unwrap();
+        }
+        (tmp_dir, pool)
+    };
+
+    // by default the spenders are admitted and the dep users kept
+    let (_tmp_dir, mut pool) = build_pool(ConsumedDepPolicy::default());
+    for spender in [&spend_code, &spend_member] {
+        assert!(pool.check_dep_consumers(spender, MOCK_FEE, 1000).is_ok());
+        let entry = TxEntry::dummy_resolve(spender.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        _submit_entry(&mut pool, TxStatus::Fresh, entry, &Callbacks::new()).unwrap();
+    }
+    assert_eq!(pool.pool_map.size(), 5);
+
+    let (_tmp_dir, pool) = build_pool(ConsumedDepPolicy::Reject);
+    for spender in [&spend_code, &spend_member] {
+        let ret = pool.check_dep_consumers(spender, Capacity::shannons(u64::MAX), 1000);
+        assert!(matches!(ret, Err(Reject::DepConsumed(_))));
+    }
+
+    // the eviction runs the reject callbacks, see `test_evict_dep_consumers_through_service`
+    let (_tmp_dir, pool) = build_pool(ConsumedDepPolicy::Evict);
+    let min_fee = pool.config.min_rbf_rate.fee(1000);
+    let ret = pool.check_dep_consumers(
+        &spend_member,
+        Capacity::shannons(min_fee.as_u64() - 1),
+        1000,
+    );
+    assert!(matches!(ret, Err(Reject::DepConsumed(_))));
+    assert!(pool
+        .check_dep_consumers(&spend_member, min_fee, 1000)
+        .is_ok());
+}
+
+#[test]
+fn test_conflict_reject() {
+    // RBF disabled
+    let config = TxPoolConfig {
+        min_fee_rate: FeeRate::from_u64(0),
+        min_rbf_rate: FeeRate::from_u64(0),
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+    let tx1 = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let tx2 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    for tx in [&tx1, &tx2] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+    let snapshot = pool.cloned_snapshot();
+
+    // double spends both, the ids follow the inputs order
+    let double_spend = build_tx(
+        vec![(&Byte32::zero(), 1), (&Byte32::zero(), 0), (&tx1.hash(), 0)],
+        1,
+    );
+    let ret = pre_check_tx(&pool, &snapshot, &double_spend, 100, pool.now_ms());
+    assert!(matches!(
+        ret,
+        Err(Reject::Conflict { conflicting_ids })
+            if conflicting_ids == vec![tx2.proposal_short_id(), tx1.proposal_short_id()]
+    ));
+
+    // an input nobody knows is still a resolve failure
+    let missing = build_tx(vec![(&Byte32::new([1; 32]), 0)], 1);
+    let ret = pre_check_tx(&pool, &snapshot, &missing, 100, pool.now_ms());
+    assert!(matches!(
+        ret,
+        Err(Reject::Resolve(OutPointError::Unknown(_)))
+    ));
+}
+
+#[test]
+fn test_check_rbf_ancestors_limit() {
+    let config = TxPoolConfig {
+        min_fee_rate: FeeRate::zero(),
+        min_rbf_rate: FeeRate::from_u64(1000),
+        max_ancestors_count: 3,
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+    let a1 = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let a2 = build_tx(vec![(&a1.hash(), 0)], 1);
+    let old1 = build_tx(vec![(&a2.hash(), 0)], 1);
+    let b1 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let old2 = build_tx(vec![(&b1.hash(), 0)], 1);
+    for tx in [&a1, &a2, &old1, &b1, &old2] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, Capacity::shannons(100), 100);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+    let snapshot = pool.cloned_snapshot();
+    let fee = Capacity::shannons(100_000);
+
+    // merging both conflicts gives the replacement the ancestors of both, a1, a2 and b1
+    let new = build_tx(vec![(&a2.hash(), 0), (&b1.hash(), 0)], 1);
+    let rtx = ResolvedTransaction::dummy_resolve(new);
+    let conflicts = HashSet::from_iter([old1.proposal_short_id(), old2.proposal_short_id()]);
+    let (ret, rules) = pool.check_rbf(&snapshot, &rtx, &conflicts, fee, 100, true);
+    assert!(matches!(ret, Err(Reject::RBFRejected(_))));
+    assert_eq!(
+        rules,
+        vec![
+            (RBFRule::NoNewUnconfirmedInputs, true),
+            (RBFRule::PaysReplacedFees, true),
+            (RBFRule::PaysMinRbfFee, true),
+            (RBFRule::LimitedReplacement, true),
+            (RBFRule::ReplacesPendingOnly, true),
+            (RBFRule::WithinAncestorLimits, false),
+        ]
+    );
+
+    // replacing old1 alone keeps the same ancestors
+    let new = build_tx(vec![(&a2.hash(), 0)], 2);
+    let rtx = ResolvedTransaction::dummy_resolve(new);
+    let conflicts = HashSet::from_iter([old1.proposal_short_id()]);
+    let (ret, rules) = pool.check_rbf(&snapshot, &rtx, &conflicts, fee, 100, true);
+    assert!(ret.is_ok());
+    assert_eq!(rules.last(), Some(&(RBFRule::WithinAncestorLimits, true)));
+}
@@ -0,0 +1,113 @@
+use crate::component::rebroadcast::{RebroadcastSchedule, MAX_REBROADCAST_BACKOFF};
+use crate::component::tests::util::build_tx;
+use ckb_types::packed::{Byte32, ProposalShortId};
+use std::collections::HashSet;
+
+const MIN_AGE: u64 = 1_000;
+
+// drives the schedule at `now` and hands the due hashes to the mock relayer
+fn tick(
+    schedule: &mut RebroadcastSchedule,
+    now: u64,
+    pending: &HashSet<ProposalShortId>,
+    relayer: &mut Vec<(u64, Byte32)>,
+) {
+    for tx_hash in schedule.due(now, |id| pending.contains(id)) {
+        relayer.push((now, tx_hash));
+    }
+}
+
+#[test]
+fn test_rebroadcast_backoff() {
+    let tx = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let pending: HashSet<_> = vec![tx.proposal_short_id()].into_iter().collect();
+    let mut relayer = Vec::new();
+
+    let mut schedule = RebroadcastSchedule::new(MIN_AGE, 10);
+    schedule.track(tx.proposal_short_id(), tx.hash(), 0);
+
+    // step the injected clock by 100ms, recording when the tx gets re-announced
+    let mut now = 0;
+    while now <= 15 * MIN_AGE {
+        tick(&mut schedule, now, &pending, &mut relayer);
+        now += 100;
+    }
+
+    let attempts: Vec<_> = relayer.iter().map(|(ts, _)| *ts).collect();
+    assert_eq!(
+        attempts,
+        vec![MIN_AGE, 3 * MIN_AGE, 7 * MIN_AGE, 15 * MIN_AGE]
+    );
+    assert!(relayer.iter().all(|(_, hash)| hash == &tx.hash()));
+}
+
+#[test]
+fn test_rebroadcast_backoff_capped() {
+    let tx = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let pending: HashSet<_> = vec![tx.proposal_short_id()].into_iter().collect();
+    let mut relayer = Vec::new();
+
+    let min_age = MAX_REBROADCAST_BACKOFF / 2;
+    let mut schedule = RebroadcastSchedule::new(min_age, 10);
+    schedule.track(tx.proposal_short_id(), tx.hash(), 0);
+
+    let mut now = min_age;
+    for _ in 0..4 {
+        tick(&mut schedule, now, &pending, &mut relayer);
+        now += MAX_REBROADCAST_BACKOFF;
+    }
+    assert_eq!(relayer.len(), 4);
+}
+
+#[test]
+fn test_rebroadcast_batch_size() {
+    let txs: Vec<_> = (0..5u32)
+        .map(|i| build_tx(vec![(&Byte32::zero(), i)], 1))
+        .collect();
+    let pending: HashSet<_> = txs.iter().map(|tx| tx.proposal_short_id()).collect();
+    let mut relayer = Vec::new();
+
+    let mut schedule = RebroadcastSchedule::new(MIN_AGE, 2);
+    for (i, tx) in txs.iter().enumerate() {
+        schedule.track(tx.proposal_short_id(), tx.hash(), i as u64);
+    }
+
+    tick(&mut schedule, 2 * MIN_AGE, &pending, &mut relayer);
+    // the oldest txs go first
+    let announced: Vec<_> = relayer.iter().map(|(_, h)| h.clone()).collect();
+    assert_eq!(announced, vec![txs[0].hash(), txs[1].hash()]);
+
+    tick(&mut schedule, 2 * MIN_AGE, &pending, &mut relayer);
+    tick(&mut schedule, 2 * MIN_AGE, &pending, &mut relayer);
+    let announced: HashSet<_> = relayer.iter().map(|(_, h)| h.clone()).collect();
+    assert_eq!(announced.len(), 5);
+}
+
+#[test]
+fn test_rebroadcast_drop_not_pending() {
+    let tx1 = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let tx2 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let mut relayer = Vec::new();
+
+    let mut schedule = RebroadcastSchedule::new(MIN_AGE, 10);
+    schedule.track(tx1.proposal_short_id(), tx1.hash(), 0);
+    schedule.track(tx2.proposal_short_id(), tx2.hash(), 0);
+    assert_eq!(schedule.len(), 2);
+
+    // tx1 got proposed or committed, tx2 is still pending
+    let pending: HashSet<_> = vec![tx2.proposal_short_id()].into_iter().collect();
+    tick(&mut schedule, MIN_AGE, &pending, &mut relayer);
+
+    assert_eq!(schedule.len(), 1);
+    assert!(!schedule.contains_key(&tx1.proposal_short_id()));
+    assert_eq!(relayer, vec![(MIN_AGE, tx2.hash())]);
+}
+
+#[test]
+fn test_rebroadcast_disabled() {
+    let tx = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let mut schedule = RebroadcastSchedule::new(0, 10);
+    assert!(!schedule.is_enabled());
+    schedule.track(tx.proposal_short_id(), tx.hash(), 0);
+    assert_eq!(schedule.len(), 0);
+}
@@ -1,7 +1,9 @@
 use ckb_hash::blake2b_256;
 use ckb_types::{core::tx_pool::Reject, packed::Byte32};
 
-use crate::component::recent_reject::RecentReject;
+use crate::component::entry::TxEntry;
+use crate::component::recent_reject::{RecentReject, TUNING_WINDOW_MS};
+use crate::component::tests::util::{build_tx, build_tx_pool, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE};
 
 #[test]
 fn test_basic() {
@@ -15,7 +17,11 @@ fn test_basic() {
     for i in 0..80u64 {
         let key = Byte32::new(blake2b_256(i.to_le_bytes()));
         recent_reject
-            .put(&key, Reject::Malformed(i.to_string(), Default::default()))
+            .put(
+                &key,
+                Reject::Malformed(i.to_string(), Default::default()),
+                0,
+            )
             .unwrap();
     }
 
@@ -32,9 +38,135 @@ fn test_basic() {
     for i in 0..80u64 {
         let key = Byte32::new(blake2b_256(i.to_le_bytes()));
         recent_reject
-            .put(&key, Reject::Malformed(i.to_string(), Default::default()))
+            .put(
+                &key,
+                Reject::Malformed(i.to_string(), Default::default()),
+                0,
+            )
             .unwrap();
     }
 
     assert!(recent_reject.total_keys_num < 100);
 }
+
+#[test]
+fn test_count_limit_tuning() {
+    let tmp_dir = tempfile::Builder::new().tempdir().unwrap();
+    let min = 100;
+    let max = 400;
+    let mut recent_reject = RecentReject::build(tmp_dir.path(), 2, min, -1)
+        .unwrap()
+        .with_max_count_limit(max);
+    assert_eq!(recent_reject.effective_count_limit(), min);
+
+    let mut i = 0u64;
+    let mut put_burst = |recent_reject: &mut RecentReject, now: u64, count: u64| {
+        for _ in 0..count {
+            let key = Byte32::new(blake2b_256(i.to_le_bytes()));
+            recent_reject
+                .put(
+                    &key,
+                    Reject::Malformed(i.to_string(), Default::default()),
+                    now,
+                )
+                .unwrap();
+            i += 1;
+        }
+    };
+
+    // a burst lasting several windows grows the limit up to the max
+    let mut now = 1_000_000;
+    let mut limits = Vec::new();
+    for _ in 0..4 {
+        put_burst(&mut recent_reject, now, 200);
+        limits.push(recent_reject.effective_count_limit());
+        now += TUNING_WINDOW_MS;
+    }
+    assert_eq!(limits, vec![min, 2 * min, 4 * min, max]);
+
+    // calm windows shrink it back down to the min
+    let mut limits = Vec::new();
+    for _ in 0..4 {
+        put_burst(&mut recent_reject, now, 1);
+        limits.push(recent_reject.effective_count_limit());
+        now += TUNING_WINDOW_MS;
+    }
+    assert_eq!(limits, vec![max, max / 2, min, min]);
+}
+
+#[test]
+fn test_count_limit_fixed_without_max() {
+    let tmp_dir = tempfile::Builder::new().tempdir().unwrap();
+    let mut recent_reject = RecentReject::build(tmp_dir.path(), 2, 100, -1).unwrap();
+
+    let mut now = 1_000_000;
+    for i in 0..400u64 {
+        let key = Byte32::new(blake2b_256(i.to_le_bytes()));
+        recent_reject
+            .put(
+                &key,
+                Reject::Malformed(i.to_string(), Default::default()),
+                now,
+            )
+            .unwrap();
+        now += TUNING_WINDOW_MS / 100;
+    }
+    assert_eq!(recent_reject.effective_count_limit(), 100);
+}
+
+#[test]
+fn test_write_failure_disables_recent_reject() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    assert!(pool.recent_reject_healthy());
+
+    // a store whose writes fail: every shard column family is gone
+    let tmp_dir = tempfile::Builder::new().tempdir().unwrap();
+    let shard_num = 2;
+    let mut recent_reject = RecentReject::build(tmp_dir.path(), shard_num, 100, -1).unwrap();
+    for shard in 0..shard_num {
+        recent_reject.db.drop_cf(&shard.to_string()).unwrap();
+    }
+    pool.recent_reject = Some(recent_reject);
+
+    let key = Byte32::new(blake2b_256(0u64.to_le_bytes()));
+    pool.put_recent_reject(
+        &key,
+        &Reject::Malformed("0".to_string(), Default::default()),
+    );
+    assert!(pool.recent_reject.is_none());
+    assert!(!pool.recent_reject_healthy());
+
+    // later rejects are skipped and the pool keeps accepting txs
+    pool.put_recent_reject(
+        &key,
+        &Reject::Malformed("0".to_string(), Default::default()),
+    );
+    let tx = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+    assert!(pool.contains_proposal_id(&tx.proposal_short_id()));
+}
+
+#[test]
+fn test_repeated_rejects_update_record() {
+    let tmp_dir = tempfile::Builder::new().tempdir().unwrap();
+    let mut recent_reject = RecentReject::build(tmp_dir.path(), 2, 100, -1).unwrap();
+    let key = Byte32::new(blake2b_256(0u64.to_le_bytes()));
+
+    for i in 0..5u64 {
+        let reject = if i % 2 == 0 {
+            Reject::Malformed("even".to_string(), Default::default())
+        } else {
+            Reject::Duplicated(key.clone())
+        };
+        recent_reject.put(&key, reject, 1000 + i).unwrap();
+    }
+
+    let latest: ckb_jsonrpc_types::PoolTransactionReject =
+        Reject::Malformed("even".to_string(), Default::default()).into();
+    let record = recent_reject.get_record(&key).unwrap().unwrap();
+    assert_eq!(record.count, 5);
+    assert_eq!(record.timestamp, 1004);
+    assert_eq!(record.reason, serde_json::to_string(&latest).unwrap());
+    assert_eq!(recent_reject.get(&key).unwrap(), Some(record.reason));
+}
@@ -0,0 +1,441 @@
+use crate::callback::Callbacks;
+use crate::clock::{Clock, ManualClock};
+use crate::component::entry::TxEntry;
+use crate::component::replay::ReplayEvent;
+use crate::component::tests::util::{build_block, build_snapshot, build_store, MOCK_CYCLES};
+use crate::pool::{TxPool, TxPoolBuilder, TxPoolThresholds};
+use crate::process::{
+    pre_check_attempt, ReorgUpdate, TxStatus, _submit_entry, _update_tx_pool_for_reorg,
+};
+use crate::util::check_tx_fee;
+use ckb_app_config::TxPoolConfig;
+use ckb_jsonrpc_types::TxPoolEntries;
+use ckb_proposal_table::ProposalView;
+use ckb_store::ChainDB;
+use ckb_types::{
+    bytes::Bytes,
+    core::{
+        cell::ResolvedTransaction,
+        tx_pool::{Origin, Reject},
+        Capacity, HeaderBuilder, TransactionBuilder, TransactionView,
+    },
+    packed::{Byte32, CellDataEntry, CellInput, CellOutput, OutPoint, ProposalShortId},
+    prelude::*,
+};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+/// Feeds the replay log at `path` to a fresh `TxPool` running on a manual clock set to the time
+/// of each record, and checks the pool against each expectation the log holds.
+///
+/// Each attempt runs the pre-check of the node against the chain cells it recorded and must end
+/// as it did on the node, a submission then goes on with the cycles the node verified. The
+/// committed blocks only carry their txs, the cells they create are recorded by the attempts
+/// spending them.
+pub(crate) fn replay(path: &Path) -> (TempDir, TxPool) {
+    let (tmp_dir, store) = build_store();
+    let clock = Arc::new(ManualClock::default());
+    let callbacks = Callbacks::new();
+    let mut tip = HeaderBuilder::default().build();
+    let mut proposals = ProposalView::default();
+    let mut replayed: Option<TxPool> = None;
+    // the txs which passed the pre-check, by hash
+    let mut checked: HashMap<Byte32, (Arc<ResolvedTransaction>, Capacity)> = HashMap::new();
+    let log = fs::read_to_string(path).unwrap();
+    for (index, line) in log.lines().enumerate() {
+        let (timestamp, event) = ReplayEvent::decode(line)
+            .unwrap_or_else(|err| panic!("line {} of the replay log: {}", index + 1, err));
+        clock.set(timestamp);
+        if let ReplayEvent::Start(config) = event {
+            let mut config = *config;
+            // the replayed pool records nothing and keeps no reject store
+            config.replay_log = None;
+            config.recent_reject = PathBuf::new();
+            let snapshot = build_snapshot(&store, tip.clone(), proposals.clone());
+            let pool = TxPoolBuilder::new(config, snapshot)
+                .clock(Arc::clone(&clock) as Arc<dyn Clock>)
+                .build()
+                .unwrap();
+            replayed = Some(pool);
+            checked.clear();
+            continue;
+        }
+
+        let pool = replayed
+            .as_mut()
+            .expect("the replay log starts with a start record");
+        match event {
+            ReplayEvent::Start(_) => unreachable!(),
+            ReplayEvent::Attempt {
+                tx,
+                origin,
+                cells,
+                reject,
+            } => {
+                if !cells.is_empty() {
+                    let txn = store.begin_transaction();
+                    txn.insert_cells(cells.into_iter().map(|cell| {
+                        let data = cell.data.map(|data| {
+                            CellDataEntry::new_builder()
+                                .output_data_hash(CellOutput::calc_data_hash(&data))
+                                .output_data(data.pack())
+                                .build()
+                        });
+                        (cell.out_point, cell.entry, data)
+                    }))
+                    .unwrap();
+                    txn.commit().unwrap();
+                    pool.set_snapshot(build_snapshot(&store, tip.clone(), proposals.clone()));
+                }
+                let snapshot = pool.cloned_snapshot();
+                let ret = pre_check_attempt(pool, &snapshot, &tx, origin, timestamp, &mut false);
+                match ret {
+                    Ok((_, rtx, _, fee, _, _)) if reject.is_none() => {
+                        checked.insert(tx.hash(), (rtx, fee));
+                    }
+                    Err(_) if reject.is_some() => {}
+                    ret => panic!(
+                        "line {}: the pre-check of {} diverged, {:?} on the node, {:?} on replay",
+                        index + 1,
+                        tx.hash(),
+                        reject,
+                        ret.err().map(|reject| reject.to_string())
+                    ),
+                }
+            }
+            ReplayEvent::Rejected { tx_hash, .. } => {
+                checked.remove(&tx_hash);
+            }
+            ReplayEvent::Submit {
+                tx,
+                origin,
+                status,
+                cycles,
+                fee,
+                size,
+                entered_at,
+            } => {
+                // the detached txs are added back without an attempt, see `readd_detached_tx`
+                let (rtx, checked_fee) = checked.remove(&tx.hash()).unwrap_or_else(|| {
+                    let resolved = pool
+                        .resolve_tx_from_pool(tx.clone(), false)
+                        .and_then(|rtx| {
+                            check_tx_fee(pool, pool.snapshot(), &rtx, size).map(|fee| (rtx, fee))
+                        });
+                    resolved.unwrap_or_else(|reject| {
+                        panic!(
+                            "line {}: {} was rejected on replay: {}",
+                            index + 1,
+                            tx.hash(),
+                            reject
+                        )
+                    })
+                });
+                assert_eq!(checked_fee, fee, "line {}: the fee diverged", index + 1);
+                let entry = TxEntry::new_with_timestamp(rtx, cycles, fee, size, entered_at)
+                    .with_origin(origin);
+                // a rejected submission leaves the pool as it was, as it did on the node
+                let _ = _submit_entry(pool, status, entry, &callbacks);
+            }
+            ReplayEvent::Replaced(id) => {
+                pool.remove_replaced(&id);
+            }
+            ReplayEvent::Commit {
+                blocks,
+                foreign,
+                detached,
+                proposed,
+                gap,
+            } => {
+                let foreign: HashMap<_, _> =
+                    foreign.into_iter().map(|tx| (tx.hash(), tx)).collect();
+                let blocks: Vec<_> = blocks
+                    .into_iter()
+                    .map(|(number, hashes)| {
+                        let txs = hashes
+                            .iter()
+                            .filter_map(|hash| {
+                                pool.get_tx_from_pool(&ProposalShortId::from_tx_hash(hash))
+                                    .filter(|tx| &tx.hash() == hash)
+                                    .or_else(|| foreign.get(hash))
+                                    .cloned()
+                            })
+                            .collect();
+                        build_block(txs, number)
+                    })
+                    .collect();
+                // the cells the blocks spend are gone from the chain
+                let txn = store.begin_transaction();
+                txn.delete_cells(
+                    blocks
+                        .iter()
+                        .flat_map(|block| block.transactions().into_iter().skip(1))
+                        .flat_map(|tx| tx.input_pts_iter()),
+                )
+                .unwrap();
+                txn.commit().unwrap();
+                if let Some(block) = blocks.last() {
+                    tip = block.header();
+                }
+                proposals =
+                    ProposalView::new(gap.into_iter().collect(), proposed.into_iter().collect());
+                _update_tx_pool_for_reorg(
+                    pool,
+                    ReorgUpdate {
+                        attached_blocks: &blocks,
+                        uncommitted: &[],
+                        detached_headers: &detached.into_iter().collect(),
+                        detached_proposal_id: HashSet::new(),
+                        snapshot: build_snapshot(&store, tip.clone(), proposals.clone()),
+                    },
+                    &callbacks,
+                    true,
+                );
+            }
+            ReplayEvent::Thresholds(thresholds) => {
+                pool.update_thresholds(thresholds, &callbacks)
+                    .expect("the recorded thresholds were accepted");
+            }
+            ReplayEvent::Clear => {
+                let snapshot = pool.cloned_snapshot();
+                pool.clear(snapshot);
+            }
+            ReplayEvent::Expect(expected) => {
+                let actual = TxPoolEntries::from(pool.get_all_entry_info(None));
+                assert_eq!(
+                    comparable(actual),
+                    comparable(*expected),
+                    "the replay diverged at line {}",
+                    index + 1
+                );
+            }
+        }
+    }
+    let pool = replayed.expect("the replay log starts with a start record");
+    (tmp_dir, pool)
+}
+
+// drops what a replay can't reproduce, the attempts pre-checked once the log was full still
+// count double spends until it's closed
+fn comparable(mut entries: TxPoolEntries) -> TxPoolEntries {
+    entries.tip_hash = None;
+    entries.seq = Default::default();
+    for entry in entries
+        .pending
+        .values_mut()
+        .chain(entries.proposed.values_mut())
+    {
+        entry.double_spend_attempts = 0.into();
+        entry.double_spend_tx_hashes.clear();
+    }
+    entries
+}
+
+// the capacity of each output of the funding tx
+const FUNDS: u64 = 100_000_000;
+
+// a pool on `clock` whose chain tip commits a tx funding `count` outputs, returned with the store
+// and the funded out points
+fn build_funded_pool(
+    config: TxPoolConfig,
+    clock: Arc<dyn Clock>,
+    count: usize,
+) -> (TempDir, ChainDB, TxPool, Vec<OutPoint>) {
+    let funding = TransactionBuilder::default()
+        .outputs((0..count).map(|_| CellOutput::new_builder().capacity(FUNDS.pack()).build()))
+        .outputs_data((0..count).map(|_| Bytes::new().pack()))
+        .build();
+    let block = build_block(vec![funding.clone()], 1);
+    let (tmp_dir, store) = build_store();
+    let txn = store.begin_transaction();
+    txn.insert_block(&block).unwrap();
+    txn.attach_block(&block).unwrap();
+    txn.commit().unwrap();
+    let snapshot = build_snapshot(&store, block.header(), ProposalView::default());
+    let pool = TxPoolBuilder::new(config, snapshot)
+        .clock(clock)
+        .build()
+        .unwrap();
+    let funds = (0..count)
+        .map(|index| OutPoint::new(funding.hash(), index as u32))
+        .collect();
+    (tmp_dir, store, pool, funds)
+}
+
+// a tx spending `inputs` to one output of `capacity` shannons
+fn build_spend(inputs: &[&OutPoint], capacity: u64) -> TransactionView {
+    TransactionBuilder::default()
+        .inputs(
+            inputs
+                .iter()
+                .map(|out_point| CellInput::new((*out_point).clone(), 0)),
+        )
+        .output(CellOutput::new_builder().capacity(capacity.pack()).build())
+        .output_data(Bytes::new().pack())
+        .build()
+}
+
+// admits `tx` the way the service does, without running its scripts
+fn admit(
+    pool: &mut TxPool,
+    tx: &TransactionView,
+    callbacks: &Callbacks,
+) -> Result<TxStatus, Reject> {
+    let snapshot = pool.cloned_snapshot();
+    let now = pool.now_ms();
+    let (_, rtx, status, fee, size, conflicts) =
+        pre_check_attempt(pool, &snapshot, tx, Origin::Remote, now, &mut false)?;
+    for id in conflicts.iter() {
+        pool.remove_replaced(id);
+    }
+    let entry =
+        TxEntry::new_with_timestamp(rtx, MOCK_CYCLES, fee, size, now).with_origin(Origin::Remote);
+    _submit_entry(pool, status, entry, callbacks)
+}
+
+#[test]
+fn test_replay_log() {
+    let log_dir = TempDir::new().unwrap();
+    let path = log_dir.path().join("replay.log");
+    let config = TxPoolConfig {
+        replay_log: Some(path.clone()),
+        ..Default::default()
+    };
+    let clock = Arc::new(ManualClock::new(1_000));
+    let (_tmp_dir, store, mut pool, funds) =
+        build_funded_pool(config, Arc::clone(&clock) as Arc<dyn Clock>, 5);
+    let callbacks = Callbacks::new();
+
+    let tx1 = build_spend(&[&funds[0]], FUNDS - 10_000);
+    let tx2 = build_spend(&[&OutPoint::new(tx1.hash(), 0)], FUNDS - 20_000);
+    let tx3 = build_spend(&[&funds[1]], FUNDS - 10_000);
+    let tx3_replacement = build_spend(&[&funds[1]], FUNDS - 30_000);
+    let tx4 = build_spend(&[&funds[2]], FUNDS - 10_000);
+    // pays less than tx4, the RBF rules reject it
+    let tx4_double_spend = build_spend(&[&funds[2]], FUNDS - 5_000);
+    let tx5 = build_spend(&[&funds[3]], FUNDS - 10_000);
+    // committed by another node, double spending tx5
+    let foreign = build_spend(&[&funds[3]], FUNDS - 20_000);
+    for tx in [&tx1, &tx2, &tx3, &tx4, &tx5] {
+        clock.advance(100);
+        admit(&mut pool, tx, &callbacks).unwrap();
+    }
+    clock.advance(100);
+    admit(&mut pool, &tx3_replacement, &callbacks).unwrap();
+    clock.advance(100);
+    assert!(admit(&mut pool, &tx4_double_spend, &callbacks).is_err());
+    // spends nothing the chain or the pool knows of
+    let unknown = build_spend(&[&OutPoint::new(Byte32::zero(), 0)], FUNDS);
+    assert!(admit(&mut pool, &unknown, &callbacks).is_err());
+    clock.advance(100);
+    let thresholds = TxPoolThresholds {
+        expiry_hours: Some(1),
+        ..Default::default()
+    };
+    pool.update_thresholds(thresholds, &callbacks).unwrap();
+
+    clock.advance(100);
+    let block = build_block(vec![tx1.clone(), foreign], 2);
+    let proposals = ProposalView::new(
+        HashSet::new(),
+        HashSet::from_iter([tx4.proposal_short_id()]),
+    );
+    _update_tx_pool_for_reorg(
+        &mut pool,
+        ReorgUpdate {
+            attached_blocks: &[block.clone()],
+            uncommitted: &[],
+            detached_headers: &HashSet::new(),
+            detached_proposal_id: HashSet::new(),
+            snapshot: build_snapshot(&store, block.header(), proposals),
+        },
+        &callbacks,
+        true,
+    );
+    let expected = pool.get_all_entry_info(None);
+    let pending: HashSet<_> = expected.pending.keys().cloned().collect();
+    assert_eq!(
+        pending,
+        HashSet::from_iter([tx2.hash(), tx3_replacement.hash()])
+    );
+    let proposed: HashSet<_> = expected.proposed.keys().cloned().collect();
+    assert_eq!(proposed, HashSet::from_iter([tx4.hash()]));
+    assert_eq!(expected.proposed[&tx4.hash()].double_spend_attempts, 1);
+
+    pool.close_replay_log();
+    let log = fs::read_to_string(&path).unwrap();
+    let attempts = log
+        .lines()
+        .filter_map(|line| match ReplayEvent::decode(line) {
+            Ok((_, ReplayEvent::Attempt { reject, .. })) => Some(reject.is_some()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        attempts,
+        [false, false, false, false, false, false, true, true]
+    );
+
+    // the rejected attempts are replayed too, down to the double spend they count
+    let (_replay_dir, replayed) = replay(&path);
+    let mut actual = replayed.get_all_entry_info(None);
+    actual.tip_hash = expected.tip_hash.clone();
+    actual.seq = expected.seq;
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_replay_log_max_size() {
+    let log_dir = TempDir::new().unwrap();
+    let path = log_dir.path().join("replay.log");
+    let max_size = 20_000;
+    let config = TxPoolConfig {
+        replay_log: Some(path.clone()),
+        replay_log_max_size: max_size,
+        ..Default::default()
+    };
+    let clock = Arc::new(ManualClock::new(1_000));
+    let (_tmp_dir, _store, mut pool, funds) =
+        build_funded_pool(config.clone(), Arc::clone(&clock) as Arc<dyn Clock>, 50);
+    let callbacks = Callbacks::new();
+    for out_point in funds.iter() {
+        clock.advance(100);
+        let tx = build_spend(&[out_point], FUNDS - 10_000);
+        admit(&mut pool, &tx, &callbacks).unwrap();
+    }
+    // full, the log was closed with the entries the pool held then, within the size bound
+    assert!(pool.replay_log.is_none());
+    let log = fs::read_to_string(&path).unwrap();
+    assert!(log.len() as u64 <= max_size);
+    let lines: Vec<_> = log.lines().collect();
+    let (last, records) = lines.split_last().unwrap();
+    assert!(matches!(
+        ReplayEvent::decode(last),
+        Ok((_, ReplayEvent::Expect(_)))
+    ));
+    let submitted = records
+        .iter()
+        .filter(|line| {
+            matches!(
+                ReplayEvent::decode(line),
+                Ok((_, ReplayEvent::Submit { .. }))
+            )
+        })
+        .count();
+    assert!(submitted > 0 && submitted < 50);
+
+    let (_replay_dir, replayed) = replay(&path);
+    assert_eq!(replayed.get_all_entry_info(None).pending.len(), submitted);
+
+    // a restarted node appends to the room left, still within the bound
+    let (_tmp_dir, _store, mut pool, _) =
+        build_funded_pool(config, Arc::clone(&clock) as Arc<dyn Clock>, 1);
+    pool.close_replay_log();
+    let appended = fs::read_to_string(&path).unwrap();
+    assert!(appended.starts_with(&log));
+    assert!(appended.len() as u64 <= max_size);
+    replay(&path);
+}
@@ -5,7 +5,9 @@ use ckb_types::{
     prelude::*,
 };
 
-use crate::component::{entry::TxEntry, pool_map::PoolMap, sort_key::AncestorsScoreSortKey};
+use crate::component::{
+    changelog::RemoveReason, entry::TxEntry, pool_map::PoolMap, sort_key::AncestorsScoreSortKey,
+};
 
 const DEFAULT_MAX_ANCESTORS_COUNT: usize = 125;
 
@@ -95,6 +97,20 @@ fn test_ancestors_sorted_key_order() {
     assert_eq!(now, expect);
 }
 
+#[test]
+fn test_zero_fee_sorted_last() {
+    let key = |fee, weight| AncestorsScoreSortKey {
+        fee: Capacity::shannons(fee),
+        weight,
+        ancestors_fee: Capacity::shannons(fee),
+        ancestors_weight: weight,
+    };
+    // the cross-multiplied fee rates tie when the weights are zero
+    assert!(key(0, 0) < key(1, 0));
+    assert!(key(0, 1) < key(1, std::u64::MAX));
+    assert_eq!(key(0, 10).cmp(&key(0, 10)), std::cmp::Ordering::Equal);
+}
+
 #[test]
 fn test_remove_entry() {
     let mut map = PoolMap::new(DEFAULT_MAX_ANCESTORS_COUNT);
@@ -155,7 +171,7 @@ fn test_remove_entry() {
     let tx3_entry = tx3_entry.unwrap();
     assert_eq!(tx3_entry.ancestors_count, 3);
 
-    map.remove_entry(&tx1_id);
+    map.remove_entry(&tx1_id, RemoveReason::Removed);
     assert!(!map.contains_key(&tx1_id));
     assert!(map.contains_key(&tx2_id));
     assert!(map.contains_key(&tx3_id));
@@ -222,7 +238,7 @@ fn test_remove_entry_and_descendants() {
     let descendants_set = map.calc_descendants(&tx1_id);
     assert!(descendants_set.contains(&tx2_id));
     assert!(descendants_set.contains(&tx3_id));
-    map.remove_entry_and_descendants(&tx2_id);
+    map.remove_entry_and_descendants(&tx2_id, RemoveReason::Removed);
     assert!(!map.contains_key(&tx2_id));
     assert!(!map.contains_key(&tx3_id));
     let descendants_set = map.calc_descendants(&tx1_id);
@@ -0,0 +1,601 @@
+use crate::callback::Callbacks;
+use crate::clock::{Clock, ManualClock};
+use crate::component::changelog::RemoveReason;
+use crate::component::commit_txs_scanner::CommitTxsScanner;
+use crate::component::entry::TxEntry;
+use crate::component::pool_map::Status;
+use crate::component::tests::util::{
+    build_block, build_tx, build_tx_pool, build_tx_pool_with_chain, build_tx_pool_with_clock,
+    build_tx_pool_with_proposals, build_tx_with_dep, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE,
+};
+use crate::error::Reject;
+use crate::pool::TxPool;
+use crate::process::{TxStatus, _submit_entry};
+use ckb_app_config::TxPoolConfig;
+use ckb_proposal_table::ProposalView;
+use ckb_types::{
+    bytes::Bytes,
+    core::{
+        error::OutPointError, tx_pool::DEFAULT_BYTES_PER_CYCLES, Capacity, Cycle, FeeRate,
+        TransactionBuilder, TransactionView,
+    },
+    packed::{Byte32, CellInput, CellOutput, OutPoint},
+    prelude::*,
+};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_compare_for_mining() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let low = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let high = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    let low_entry = TxEntry::dummy_resolve(low.clone(), MOCK_CYCLES, Capacity::shannons(100), 200);
+    let high_entry =
+        TxEntry::dummy_resolve(high.clone(), MOCK_CYCLES, Capacity::shannons(1000), 200);
+    pool.add_proposed(low_entry, false).unwrap();
+    pool.add_proposed(high_entry, false).unwrap();
+
+    let low_id = low.proposal_short_id();
+    let high_id = high.proposal_short_id();
+    assert_eq!(
+        pool.compare_for_mining(&high_id, &low_id),
+        Some(Ordering::Less)
+    );
+    assert_eq!(
+        pool.compare_for_mining(&low_id, &high_id),
+        Some(Ordering::Greater)
+    );
+    assert_eq!(
+        pool.compare_for_mining(&low_id, &low_id),
+        Some(Ordering::Equal)
+    );
+
+    // the comparator agrees with the block assembler selection
+    let (selected, _size, _cycles, _timed_out) =
+        CommitTxsScanner::new(&pool.pool_map).txs_to_commit(usize::MAX, u64::MAX, None);
+    let selected: Vec<_> = selected.iter().map(|e| e.proposal_short_id()).collect();
+    let mut sorted = vec![low_id.clone(), high_id.clone()];
+    sorted.sort_by(|a, b| pool.compare_for_mining(a, b).unwrap());
+    assert_eq!(selected, sorted);
+
+    // ties on fee rate are broken by age
+    let older = build_tx(vec![(&Byte32::zero(), 3)], 1);
+    let newer = build_tx(vec![(&Byte32::zero(), 4)], 1);
+    let mut older_entry = TxEntry::dummy_resolve(older.clone(), 0, Capacity::shannons(500), 200);
+    let mut newer_entry = TxEntry::dummy_resolve(newer.clone(), 0, Capacity::shannons(500), 200);
+    older_entry.timestamp = 1;
+    newer_entry.timestamp = 2;
+    pool.add_pending(newer_entry).unwrap();
+    pool.add_pending(older_entry).unwrap();
+    assert_eq!(
+        pool.compare_for_mining(&older.proposal_short_id(), &newer.proposal_short_id()),
+        Some(Ordering::Less)
+    );
+
+    let unknown = build_tx(vec![(&Byte32::zero(), 5)], 1);
+    assert_eq!(
+        pool.compare_for_mining(&unknown.proposal_short_id(), &low_id),
+        None
+    );
+}
+
+#[test]
+fn test_add_proposed_checks_proposal() {
+    let proposed_tx = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let unproposed_tx = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    let proposals = ProposalView::new(
+        HashSet::new(),
+        vec![proposed_tx.proposal_short_id()].into_iter().collect(),
+    );
+    let (_tmp_dir, mut pool) = build_tx_pool_with_proposals(Default::default(), proposals);
+
+    let entry = TxEntry::dummy_resolve(unproposed_tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(matches!(
+        pool.add_proposed(entry.clone(), true),
+        Err(Reject::NotProposed(hash)) if hash == unproposed_tx.hash()
+    ));
+    assert!(pool
+        .get_pool_entry(&unproposed_tx.proposal_short_id())
+        .is_none());
+
+    let proposed = TxEntry::dummy_resolve(proposed_tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(pool.add_proposed(proposed, true).unwrap().is_some());
+    let in_pool = pool
+        .get_pool_entry(&proposed_tx.proposal_short_id())
+        .unwrap();
+    assert_eq!(in_pool.status, Status::Proposed);
+
+    // without the check the caller is trusted
+    assert!(pool.add_proposed(entry, false).unwrap().is_some());
+}
+
+#[test]
+fn test_sorted_ready_proposed_iter() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let ready = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    // absolute since, not committable before block 1000
+    let locked = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(Byte32::zero(), 2), 1000))
+        .output(Default::default())
+        .output_data(Default::default())
+        .build();
+    for tx in [&ready, &locked] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        assert!(pool.add_proposed(entry, false).unwrap().is_some());
+    }
+
+    let all: HashSet<_> = pool
+        .pool_map
+        .sorted_proposed_iter()
+        .map(|entry| entry.transaction().hash())
+        .collect();
+    assert_eq!(all, vec![ready.hash(), locked.hash()].into_iter().collect());
+
+    let ready_hashes: Vec<_> = pool
+        .sorted_ready_proposed_iter()
+        .map(|entry| entry.transaction().hash())
+        .collect();
+    assert_eq!(ready_hashes, vec![ready.hash()]);
+}
+
+#[test]
+fn test_weight_blends_cycles() {
+    let cheap = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let heavy = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    let fee = Capacity::shannons(10_000);
+    let size = 500;
+    // about 1_700 bytes with the default ratio
+    let heavy_cycles = 10_000_000;
+
+    let weights_with = |bytes_per_cycles: f64| {
+        let config = TxPoolConfig {
+            bytes_per_cycles,
+            ..Default::default()
+        };
+        let (_tmp_dir, mut pool) = build_tx_pool(config);
+        let entry = TxEntry::dummy_resolve(cheap.clone(), 1_000, fee, size);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+        let entry = TxEntry::dummy_resolve(heavy.clone(), heavy_cycles, fee, size);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+
+        let cheap_rate = pool.fee_rate_of(&cheap.proposal_short_id(), false).unwrap();
+        let heavy_rate = pool.fee_rate_of(&heavy.proposal_short_id(), false).unwrap();
+        let info = pool.get_all_entry_info(None);
+        let heavy_weight = info.pending[&heavy.hash()].weight;
+        let first = pool
+            .pool_map
+            .score_sorted_iter_by(vec![Status::Pending])
+            .next()
+            .map(|entry| entry.transaction().hash());
+        (cheap_rate, heavy_rate, heavy_weight, first)
+    };
+
+    // fee per byte only, both txs are scored the same
+    let (cheap_rate, heavy_rate, heavy_weight, _) = weights_with(0.0);
+    assert_eq!(cheap_rate, heavy_rate);
+    assert_eq!(heavy_weight, size as u64);
+
+    let (cheap_rate, heavy_rate, heavy_weight, first) = weights_with(DEFAULT_BYTES_PER_CYCLES);
+    assert!(heavy_rate < cheap_rate);
+    assert_eq!(
+        heavy_weight,
+        (heavy_cycles as f64 * DEFAULT_BYTES_PER_CYCLES) as u64
+    );
+    assert_eq!(first, Some(cheap.hash()));
+}
+
+fn build_tx_with_capacities(inputs: Vec<(&Byte32, u32)>, capacities: &[u64]) -> TransactionView {
+    TransactionBuilder::default()
+        .inputs(
+            inputs
+                .into_iter()
+                .map(|(txid, index)| CellInput::new(OutPoint::new(txid.to_owned(), index), 0)),
+        )
+        .outputs(capacities.iter().map(|capacity| {
+            CellOutput::new_builder()
+                .capacity(Capacity::shannons(*capacity).pack())
+                .build()
+        }))
+        .outputs_data(capacities.iter().map(|_| Bytes::new().pack()))
+        .build()
+}
+
+#[test]
+fn test_check_package() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    // outputs of 1 and 2 CKBytes
+    let funding = build_tx(vec![(&Byte32::zero(), 0)], 2);
+    let entry = TxEntry::dummy_resolve(funding.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+
+    let parent = build_tx_with_capacities(vec![(&funding.hash(), 1)], &[199_999_000]);
+    let child = build_tx_with_capacities(vec![(&parent.hash(), 0)], &[199_990_000]);
+    let stats = pool
+        .check_package(&[parent.clone(), child.clone()])
+        .unwrap();
+    let size = parent.data().serialized_size_in_block() + child.data().serialized_size_in_block();
+    assert_eq!(stats.count, 2);
+    assert_eq!(stats.fee, Capacity::shannons(10_000));
+    assert_eq!(stats.size, size);
+    assert_eq!(
+        stats.fee_rate,
+        FeeRate::calculate(Capacity::shannons(10_000), size as u64)
+    );
+    // nothing is inserted
+    assert_eq!(pool.pool_map.size(), 1);
+
+    // the child can't come before its parent
+    let ret = pool.check_package(&[child.clone(), parent.clone()]);
+    assert!(matches!(
+        ret,
+        Err(Reject::Resolve(OutPointError::Unknown(_)))
+    ));
+
+    // two children spending the same parent output
+    let sibling = build_tx_with_capacities(vec![(&parent.hash(), 0)], &[199_980_000]);
+    let ret = pool.check_package(&[parent.clone(), child, sibling]);
+    assert!(matches!(ret, Err(Reject::Resolve(OutPointError::Dead(_)))));
+
+    // an input neither in the pool nor in the chain
+    let orphan = build_tx_with_capacities(vec![(&Byte32::zero(), 5)], &[100]);
+    let ret = pool.check_package(&[parent.clone(), orphan]);
+    assert!(matches!(
+        ret,
+        Err(Reject::Resolve(OutPointError::Unknown(_)))
+    ));
+
+    // the package may not repeat a pool tx
+    let ret = pool.check_package(&[funding.clone(), parent]);
+    assert!(matches!(ret, Err(Reject::Duplicated(hash)) if hash == funding.hash()));
+
+    assert!(matches!(
+        pool.check_package(&[]),
+        Err(Reject::Malformed(_, _))
+    ));
+    assert_eq!(pool.pool_map.size(), 1);
+}
+
+#[test]
+fn test_simulate_commit() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let tx1 = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let tx2 = build_tx(vec![(&tx1.hash(), 0)], 1);
+    let tx3 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let tx4 = build_tx(vec![(&tx3.hash(), 0)], 1);
+    let tx5 = build_tx_with_dep(vec![(&Byte32::zero(), 4)], vec![(&Byte32::zero(), 2)], 1);
+    let tx6 = build_tx(vec![(&Byte32::zero(), 3)], 1);
+    for tx in [&tx1, &tx2, &tx3, &tx4, &tx5, &tx6] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), 10, MOCK_FEE, 100);
+        pool.update_statics_for_add_tx(entry.size, entry.cycles);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+
+    // commits tx1, double spends tx3 and consumes the dep of tx5
+    let spend_tx3_input = build_tx(vec![(&Byte32::zero(), 1)], 2);
+    let spend_tx5_dep = build_tx(vec![(&Byte32::zero(), 2)], 2);
+    let block_txs = vec![tx1.clone(), spend_tx3_input, spend_tx5_dep];
+
+    let simulation = pool.simulate_commit(&block_txs);
+    assert_eq!(simulation.committed, vec![tx1.hash()]);
+    let conflicted: HashSet<_> = simulation
+        .conflicted
+        .iter()
+        .map(|(hash, _)| hash.clone())
+        .collect();
+    assert_eq!(
+        conflicted,
+        HashSet::from_iter([tx3.hash(), tx4.hash(), tx5.hash()])
+    );
+    assert!(simulation
+        .conflicted
+        .iter()
+        .all(|(_, reject)| matches!(reject, Reject::Resolve(OutPointError::Dead(_)))));
+    assert_eq!(simulation.total_tx_size, 200);
+    assert_eq!(simulation.total_tx_cycles, 20);
+    // nothing changed yet
+    assert_eq!(pool.pool_map.size(), 6);
+    assert_eq!(pool.total_tx_size, 600);
+
+    let committed = Arc::new(Mutex::new(Vec::new()));
+    let rejected = Arc::new(Mutex::new(HashSet::new()));
+    let mut callbacks = Callbacks::new();
+    let committed_clone = Arc::clone(&committed);
+    callbacks.register_committed(Box::new(move |pool, entry| {
+        pool.update_statics_for_remove_tx(entry.size, entry.cycles);
+        committed_clone
+            .lock()
+            .unwrap()
+            .push(entry.transaction().hash());
+    }));
+    let rejected_clone = Arc::clone(&rejected);
+    callbacks.register_reject(Box::new(move |pool, entry, _reject| {
+        pool.update_statics_for_remove_tx(entry.size, entry.cycles);
+        rejected_clone
+            .lock()
+            .unwrap()
+            .insert(entry.transaction().hash());
+    }));
+    pool.remove_committed_txs(&[build_block(block_txs, 1)], &callbacks, &HashSet::new());
+
+    assert_eq!(*committed.lock().unwrap(), simulation.committed);
+    assert_eq!(*rejected.lock().unwrap(), conflicted);
+    assert_eq!(pool.total_tx_size, simulation.total_tx_size);
+    assert_eq!(pool.total_tx_cycles, simulation.total_tx_cycles);
+    assert_eq!(pool.pool_map.size(), 2);
+}
+
+#[test]
+fn test_time_in_proposed() {
+    let clock = Arc::new(ManualClock::new(1_000));
+    let (_tmp_dir, mut pool) =
+        build_tx_pool_with_clock(Default::default(), Arc::clone(&clock) as Arc<dyn Clock>);
+    let tx = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let id = tx.proposal_short_id();
+    let entry = TxEntry::dummy_resolve(tx, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+    assert_eq!(pool.time_in_proposed(&id), None);
+
+    clock.advance(500);
+    pool.set_entry_proposed(&id);
+    assert_eq!(pool.time_in_proposed(&id), Some(0));
+    clock.advance(300);
+    assert_eq!(pool.time_in_proposed(&id), Some(300));
+    // setting the same status again keeps the time it was entered
+    pool.set_entry_proposed(&id);
+    clock.advance(200);
+    assert_eq!(pool.time_in_proposed(&id), Some(500));
+
+    pool.set_entry_gap(&id);
+    assert_eq!(pool.time_in_proposed(&id), None);
+}
+
+#[test]
+fn test_template_refresh_threshold() {
+    let config = TxPoolConfig {
+        template_refresh_fee: Some(1000),
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+    let callbacks = Callbacks::new();
+    let entry = |i: u32, fee| {
+        let tx = build_tx(vec![(&Byte32::zero(), i)], 1);
+        TxEntry::dummy_resolve(tx, MOCK_CYCLES, Capacity::shannons(fee), 1000)
+    };
+    // the last template's lowest fee rate is 1000 shannons/KW
+    pool.template_built(&[entry(0, 1000)], 1000);
+
+    // pending txs and txs paying no more than the template don't count
+    _submit_entry(&mut pool, TxStatus::Fresh, entry(1, 5000), &callbacks).unwrap();
+    _submit_entry(&mut pool, TxStatus::Proposed, entry(2, 1000), &callbacks).unwrap();
+    assert!(!pool.is_template_dirty());
+
+    // each proposed tx improves the template by 400 shannons
+    for i in 3..5 {
+        _submit_entry(&mut pool, TxStatus::Proposed, entry(i, 1400), &callbacks).unwrap();
+        assert!(!pool.is_template_dirty());
+    }
+    _submit_entry(&mut pool, TxStatus::Proposed, entry(5, 1400), &callbacks).unwrap();
+    assert!(pool.is_template_dirty());
+
+    // rebuilding the template starts over
+    pool.template_built(&[entry(0, 1000)], 1000);
+    assert!(!pool.is_template_dirty());
+    assert!(!pool.record_template_candidate(&entry(6, 1400)));
+
+    // pending txs proposed by a block count too, once
+    pool.template_built(&[entry(0, 1000)], 1000);
+    for i in 7..10 {
+        _submit_entry(&mut pool, TxStatus::Fresh, entry(i, 1400), &callbacks).unwrap();
+    }
+    for i in 7..9 {
+        pool.proposed_rtx(&entry(i, 1400).proposal_short_id())
+            .unwrap();
+    }
+    assert!(pool
+        .proposed_rtx(&entry(8, 1400).proposal_short_id())
+        .is_err());
+    assert!(!pool.is_template_dirty());
+    pool.set_entry_proposed(&entry(9, 1400).proposal_short_id());
+    assert!(pool.is_template_dirty());
+}
+
+#[test]
+fn test_preview_blocks() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let parent = build_tx(vec![(&Byte32::zero(), 0)], 2);
+    let child = build_tx(vec![(&parent.hash(), 0)], 1);
+    let other = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let pending = build_tx(vec![(&parent.hash(), 1)], 1);
+    let gap = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    for (tx, fee) in [(&parent, 100), (&child, 5000), (&other, 300)] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), 10, Capacity::shannons(fee), 100);
+        assert!(pool.add_proposed(entry, false).unwrap().is_some());
+    }
+    let entry = TxEntry::dummy_resolve(pending.clone(), 10, Capacity::shannons(700), 100);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+    let entry = TxEntry::dummy_resolve(gap.clone(), 10, Capacity::shannons(50), 100);
+    assert!(pool.add_gap(entry).unwrap().is_some());
+
+    let consensus = pool.snapshot().consensus();
+    let (packaged, size, cycles) = pool.package_txs(
+        consensus.max_block_cycles(),
+        consensus.max_block_bytes() as usize,
+    );
+    let packaged: Vec<_> = packaged
+        .iter()
+        .map(|entry| entry.transaction().hash())
+        .collect();
+
+    let preview = pool.preview_blocks(3);
+    assert_eq!(preview.tip_hash, pool.snapshot().tip_hash());
+    let preview = preview.blocks;
+    assert_eq!(preview.len(), 2);
+    assert_eq!(preview[0].proposed, packaged);
+    assert!(preview[0].unproposed.is_empty());
+    assert_eq!(preview[0].size, size);
+    assert_eq!(preview[0].cycles, cycles);
+    assert_eq!(preview[0].fee, Capacity::shannons(5400));
+    // the unproposed txs come in the second block at the earliest
+    assert!(preview[1].proposed.is_empty());
+    assert_eq!(preview[1].unproposed, vec![pending.hash(), gap.hash()]);
+    assert_eq!(preview[1].size, 200);
+    assert_eq!(preview[1].cycles, 20);
+    assert_eq!(preview[1].fee, Capacity::shannons(750));
+    assert_eq!(pool.pool_map.size(), 5);
+    assert!(pool.preview_blocks(0).blocks.is_empty());
+
+    // bounded by the size left for txs in the last template
+    pool.template_built(&[], 250);
+    let preview = pool.preview_blocks(3).blocks;
+    assert_eq!(preview.len(), 3);
+    assert!(preview.iter().all(|block| block.size <= 250));
+}
+
+#[test]
+fn test_queries_report_tip_hash() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let tx = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let entry = TxEntry::dummy_resolve(tx.clone(), 10, Capacity::shannons(100), 100);
+    assert!(pool.add_proposed(entry, false).unwrap().is_some());
+    let (_other_dir, other) = build_tx_pool_with_chain(
+        Default::default(),
+        &[build_block(vec![], 1)],
+        ProposalView::default(),
+    );
+    let new_tip = other.snapshot().tip_hash();
+
+    let old_tip = pool.snapshot().tip_hash();
+    assert_ne!(old_tip, new_tip);
+    let info = pool.get_all_entry_info(None);
+    assert_eq!(info.tip_hash, old_tip);
+    assert!(info.proposed.contains_key(&tx.hash()));
+    let preview = pool.preview_blocks(1);
+    assert_eq!(preview.tip_hash, old_tip);
+    assert_eq!(preview.blocks[0].proposed, vec![tx.hash()]);
+
+    // a block arrives, the entries left in the pool are reported against the new tip
+    pool.set_snapshot(other.cloned_snapshot());
+    let info = pool.get_all_entry_info(None);
+    assert_eq!(info.tip_hash, new_tip);
+    assert!(info.proposed.contains_key(&tx.hash()));
+    assert_eq!(pool.preview_blocks(1).tip_hash, new_tip);
+}
+
+#[test]
+fn test_package_txs_deterministic() {
+    // equal fee rates everywhere, with packages sharing ancestors and descendants, so the
+    // selection relies on the tie breaks
+    let build = || {
+        let (tmp_dir, mut pool) = build_tx_pool(Default::default());
+        let mut txs = Vec::new();
+        for i in 0..8 {
+            let parent = build_tx(vec![(&Byte32::zero(), i)], 2);
+            let left = build_tx(vec![(&parent.hash(), 0)], 1);
+            let right = build_tx(vec![(&parent.hash(), 1)], 1);
+            let merge = build_tx(vec![(&left.hash(), 0), (&right.hash(), 0)], 1);
+            txs.extend([parent, left, right, merge]);
+        }
+        for tx in txs.iter() {
+            let entry = TxEntry::dummy_resolve(tx.clone(), 100, Capacity::shannons(1000), 100);
+            assert!(pool.add_proposed(entry, false).unwrap().is_some());
+        }
+        (tmp_dir, pool)
+    };
+    let package = |pool: &TxPool| {
+        pool.package_txs(Cycle::MAX, usize::MAX)
+            .0
+            .iter()
+            .map(|entry| entry.transaction().hash())
+            .collect::<Vec<_>>()
+    };
+
+    let (_tmp_dir, pool) = build();
+    let first = package(&pool);
+    assert_eq!(first.len(), 32);
+    for _ in 0..5 {
+        assert_eq!(package(&pool), first);
+    }
+    let (_other_dir, other) = build();
+    assert_eq!(package(&other), first);
+}
+
+#[test]
+fn test_fee_depth() {
+    let (_tmp_dir, mut pool) = build_tx_pool(TxPoolConfig::default());
+    assert!(pool.fee_depth().is_empty());
+
+    let fees = [500, 2000, 1000, 2000, 100];
+    for (i, fee) in fees.iter().enumerate() {
+        let tx = build_tx(vec![(&Byte32::zero(), i as u32)], 1);
+        let entry = TxEntry::dummy_resolve(tx, MOCK_CYCLES, Capacity::shannons(*fee), 100);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+
+    let depth = pool.fee_depth();
+    // the two entries paying 2000 share a bucket
+    assert_eq!(depth.len(), 4);
+    for pair in depth.windows(2) {
+        assert!(pair[0].0 > pair[1].0);
+        assert!(pair[0].1 < pair[1].1);
+    }
+    assert_eq!(depth[0].1, 200);
+    assert_eq!(depth.last().unwrap().1, 500);
+}
+
+#[test]
+fn test_do_not_mine() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let tx1 = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let tx2 = build_tx(vec![(&tx1.hash(), 0)], 1);
+    let tx3 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    for tx in [&tx1, &tx2, &tx3] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        assert!(pool.add_proposed(entry, false).unwrap().is_some());
+    }
+    let pending1 = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    let pending2 = build_tx(vec![(&Byte32::zero(), 3)], 1);
+    let pending_child = build_tx(vec![(&pending1.hash(), 0)], 1);
+    for tx in [&pending1, &pending2, &pending_child] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+
+    assert!(pool.set_do_not_mine(&tx1.proposal_short_id(), true));
+    assert!(pool.set_do_not_mine(&pending1.proposal_short_id(), true));
+    let absent = build_tx(vec![(&Byte32::zero(), 4)], 1);
+    assert!(!pool.set_do_not_mine(&absent.proposal_short_id(), true));
+
+    // the flagged txs and their descendants are left out of the block and the proposals
+    let (packaged, _, _) = pool.package_txs(Cycle::MAX, usize::MAX);
+    let packaged: Vec<_> = packaged
+        .iter()
+        .map(|entry| entry.transaction().hash())
+        .collect();
+    assert_eq!(packaged, vec![tx3.hash()]);
+    let proposals = pool.get_proposals(usize::MAX, &HashSet::new());
+    assert_eq!(
+        proposals,
+        HashSet::from_iter([pending2.proposal_short_id()])
+    );
+
+    // but stay in the pool and can still be relayed
+    for tx in [&tx1, &tx2, &pending1] {
+        assert!(pool.contains_proposal_id(&tx.proposal_short_id()));
+        assert!(pool.get_tx_with_cycles(&tx.proposal_short_id()).is_some());
+    }
+
+    assert!(pool.set_do_not_mine(&tx1.proposal_short_id(), false));
+    assert!(pool.set_do_not_mine(&pending1.proposal_short_id(), false));
+    let (packaged, _, _) = pool.package_txs(Cycle::MAX, usize::MAX);
+    assert_eq!(packaged.len(), 3);
+    assert_eq!(pool.get_proposals(usize::MAX, &HashSet::new()).len(), 3);
+
+    // the flag leaves the pool with the entry
+    assert!(pool.set_do_not_mine(&tx3.proposal_short_id(), true));
+    assert!(pool.pool_map.is_do_not_mine(&tx3.proposal_short_id()));
+    pool.pool_map
+        .remove_entry(&tx3.proposal_short_id(), RemoveReason::Removed);
+    assert!(!pool.pool_map.is_do_not_mine(&tx3.proposal_short_id()));
+}
@@ -0,0 +1,144 @@
+use crate::callback::Callbacks;
+use crate::clock::{Clock, ManualClock};
+use crate::component::changelog::RemoveReason;
+use crate::component::entry::TxEntry;
+use crate::component::tests::util::{
+    build_block, build_tx, build_tx_pool_with_clock, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE,
+};
+use crate::component::trace::{TraceEvent, TxEvent, TxTracer, MAX_TRACED_TXS, MAX_TRACE_EVENTS};
+use crate::process::{_submit_entry, pre_check_tx};
+use ckb_app_config::TxPoolConfig;
+use ckb_types::{core::FeeRate, packed::Byte32, prelude::*};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+fn hash(n: u32) -> Byte32 {
+    let mut bytes = [0u8; 32];
+    bytes[..4].copy_from_slice(&n.to_le_bytes());
+    bytes.pack()
+}
+
+#[test]
+fn test_tracer_bounds() {
+    let clock = Arc::new(ManualClock::new(0));
+    let tracer = TxTracer::new(Arc::clone(&clock) as Arc<dyn Clock>, 0.0);
+    tracer.watch(hash(0));
+    for cycles in 0..(MAX_TRACE_EVENTS as u64 + 2) {
+        clock.advance(1);
+        tracer.record(&hash(0), TxEvent::Verified { cycles });
+    }
+    // the oldest events are dropped
+    let events = tracer.get(&hash(0)).unwrap();
+    assert_eq!(events.len(), MAX_TRACE_EVENTS);
+    assert_eq!(events[0].event, TxEvent::Verified { cycles: 2 });
+    assert_eq!(events[0].timestamp, 3);
+
+    // the oldest traces are dropped
+    for n in 1..=MAX_TRACED_TXS as u32 {
+        tracer.watch(hash(n));
+    }
+    assert!(tracer.get(&hash(0)).is_none());
+    assert!(tracer.get(&hash(1)).is_some());
+    assert!(tracer.get(&hash(MAX_TRACED_TXS as u32)).is_some());
+}
+
+#[test]
+fn test_tracer_sampling() {
+    let clock = Arc::new(ManualClock::new(0));
+    let tracer = TxTracer::new(clock as Arc<dyn Clock>, 0.5);
+    // picked by the first 4 bytes of the hash, read as a little endian integer
+    tracer.record(&hash(1), TxEvent::Received);
+    tracer.record(&hash(u32::MAX), TxEvent::Received);
+    assert_eq!(tracer.get(&hash(1)).map(|events| events.len()), Some(1));
+    assert!(tracer.get(&hash(u32::MAX)).is_none());
+
+    // not sampled at all by default
+    let tracer = TxTracer::new(Arc::new(ManualClock::new(0)), 0.0);
+    tracer.record(&hash(1), TxEvent::Received);
+    assert!(tracer.get(&hash(1)).is_none());
+}
+
+#[test]
+fn test_tracer_keeps_watched_apart() {
+    let tracer = TxTracer::new(Arc::new(ManualClock::new(0)), 1.0);
+    tracer.watch(hash(0));
+    tracer.record(&hash(0), TxEvent::Received);
+    // a burst of sampled txs only pushes out the oldest sampled traces
+    for n in 1..=(MAX_TRACED_TXS as u32 + 1) {
+        tracer.record(&hash(n), TxEvent::Received);
+    }
+    assert_eq!(tracer.get(&hash(0)).map(|events| events.len()), Some(1));
+    assert!(tracer.get(&hash(1)).is_none());
+    assert!(tracer.get(&hash(2)).is_some());
+
+    // watching a sampled tx keeps its events
+    tracer.watch(hash(2));
+    tracer.record(&hash(2), TxEvent::Resolved);
+    assert_eq!(tracer.get(&hash(2)).map(|events| events.len()), Some(2));
+}
+
+#[test]
+fn test_tracer_is_traced() {
+    let tracer = TxTracer::new(Arc::new(ManualClock::new(0)), 0.5);
+    assert!(tracer.is_traced(&hash(1)));
+    assert!(!tracer.is_traced(&hash(u32::MAX)));
+    // once a tx is watched every record goes through the lock
+    tracer.watch(hash(u32::MAX - 1));
+    assert!(tracer.is_traced(&hash(u32::MAX)));
+    tracer.record(&hash(u32::MAX), TxEvent::Received);
+    assert!(tracer.get(&hash(u32::MAX)).is_none());
+}
+
+#[test]
+fn test_trace_lifecycle() {
+    let config = TxPoolConfig {
+        min_fee_rate: FeeRate::from_u64(0),
+        ..Default::default()
+    };
+    let clock = Arc::new(ManualClock::new(1_000));
+    let (_tmp_dir, mut pool) =
+        build_tx_pool_with_clock(config, Arc::clone(&clock) as Arc<dyn Clock>);
+    let parent = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let entry = TxEntry::dummy_resolve(parent.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+
+    let tx = build_tx(vec![(&parent.hash(), 0)], 1);
+    let tx_hash = tx.hash();
+    pool.trace_tx(tx_hash.clone());
+    assert_eq!(pool.trace(&tx_hash), Some(vec![]));
+
+    let snapshot = pool.cloned_snapshot();
+    let (_, rtx, status, fee, tx_size, _) =
+        pre_check_tx(&pool, &snapshot, &tx, 100, pool.now_ms()).unwrap();
+    clock.advance(10);
+    // recorded by the service once the scripts ran, see `_process_tx`
+    pool.record_trace(&tx_hash, TxEvent::Verified { cycles: 1000 });
+    let entry = TxEntry::new(rtx, 1000, fee, tx_size);
+    _submit_entry(&mut pool, status, entry, &Callbacks::new()).unwrap();
+    clock.advance(10);
+    pool.gap_rtx(&tx.proposal_short_id()).unwrap();
+    clock.advance(10);
+    pool.proposed_rtx(&tx.proposal_short_id()).unwrap();
+    clock.advance(10);
+    pool.remove_committed_txs(
+        &[build_block(vec![tx], 1)],
+        &Callbacks::new(),
+        &HashSet::new(),
+    );
+
+    let events: Vec<_> = [
+        (1_000, TxEvent::Received),
+        (1_000, TxEvent::Resolved),
+        (1_010, TxEvent::Verified { cycles: 1000 }),
+        (1_010, TxEvent::Admitted),
+        (1_020, TxEvent::Gap),
+        (1_030, TxEvent::Proposed),
+        (1_040, TxEvent::Removed(RemoveReason::Committed)),
+    ]
+    .into_iter()
+    .map(|(timestamp, event)| TraceEvent { timestamp, event })
+    .collect();
+    assert_eq!(pool.trace(&tx_hash), Some(events));
+    // only the registered tx is traced
+    assert_eq!(pool.trace(&parent.hash()), None);
+}
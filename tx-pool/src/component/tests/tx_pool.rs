@@ -0,0 +1,1211 @@
+use crate::callback::Callbacks;
+use crate::clock::{Clock, ManualClock};
+use crate::component::changelog::RemoveReason;
+use crate::component::entry::TxEntry;
+use crate::component::pool_map::Status;
+use crate::component::recent_reject::RecentReject;
+use crate::component::tests::util::{
+    build_block, build_tx, build_tx_pool, build_tx_pool_with_chain, build_tx_pool_with_clock,
+    build_tx_with_dep, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE,
+};
+use crate::error::{BuildError, IntegrityError, Reject};
+use crate::pool::TxPoolBuilder;
+use crate::process::{
+    check_cellbase_maturity, pre_check_tx, ReorgUpdate, TxStatus, _submit_entry,
+    _update_tx_pool_for_reorg,
+};
+use crate::util::{
+    check_entry_min_fee, check_hash_types, check_min_fee, check_output_policy, is_missing_input,
+    is_recorded_reject,
+};
+use ckb_app_config::TxPoolConfig;
+use ckb_proposal_table::ProposalView;
+use ckb_traits::HeaderFieldsProvider;
+use ckb_types::{
+    bytes::Bytes,
+    core::{
+        cell::{CellMetaBuilder, ResolvedTransaction},
+        error::OutPointError,
+        tx_pool::{AnnotatedTxId, Origin, OutputPolicyViolation, ResolveClass},
+        BlockBuilder, BlockView, Capacity, Cycle, EpochNumberWithFraction, FeeRate, ScriptHashType,
+        TransactionBuilder, TransactionInfo, TransactionView,
+    },
+    packed::{Byte32, CellInput, CellOutput, OutPoint, ProposalShortId, Script},
+    prelude::*,
+};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+fn test_origin_round_trip() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let tx1 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let tx2 = build_tx(vec![(&tx1.hash(), 0)], 1);
+    let entry1 = TxEntry::dummy_resolve(tx1.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE)
+        .with_origin(Origin::Local);
+    // an entry is remote unless told otherwise
+    let entry2 = TxEntry::dummy_resolve(tx2.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert_eq!(entry2.origin, Origin::Remote);
+    let id1 = tx1.proposal_short_id();
+    let id2 = tx2.proposal_short_id();
+
+    assert!(pool.add_pending(entry1).unwrap().is_some());
+    assert!(pool.add_pending(entry2).unwrap().is_some());
+
+    pool.set_entry_proposed(&id1);
+    pool.set_entry_proposed(&id2);
+    let entry = pool.get_pool_entry(&id1).unwrap();
+    assert_eq!(entry.status, Status::Proposed);
+    assert_eq!(entry.inner.origin, Origin::Local);
+
+    pool.remove_by_detached_proposal(vec![id1.clone()].iter());
+    for (id, origin) in [(&id1, Origin::Local), (&id2, Origin::Remote)] {
+        let entry = pool.get_pool_entry(id).unwrap();
+        assert_eq!(entry.status, Status::Pending);
+        assert_eq!(entry.inner.origin, origin);
+    }
+
+    let local = pool.get_all_entry_info(Some(Origin::Local));
+    assert_eq!(local.pending.len(), 1);
+    assert_eq!(local.pending[&tx1.hash()].origin, Origin::Local);
+    assert!(pool
+        .get_all_entry_info(Some(Origin::Reorg))
+        .pending
+        .is_empty());
+    assert_eq!(pool.get_all_entry_info(None).pending.len(), 2);
+}
+
+#[test]
+fn test_submit_keeps_entry_timestamp() {
+    let clock = Arc::new(ManualClock::new(42));
+    let (_tmp_dir, mut pool) =
+        build_tx_pool_with_clock(Default::default(), Arc::clone(&clock) as Arc<dyn Clock>);
+
+    // stamped by the pool clock when verified, the entry keeps that time once submitted
+    let tx = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let rtx = Arc::new(ResolvedTransaction::dummy_resolve(tx.clone()));
+    let entry = TxEntry::new_with_timestamp(rtx, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE, pool.now_ms());
+    clock.advance(1_000);
+    _submit_entry(&mut pool, TxStatus::Fresh, entry, &Callbacks::new()).unwrap();
+    let entry = pool.pool_map.get(&tx.proposal_short_id()).unwrap();
+    assert_eq!(entry.timestamp, 42);
+}
+
+#[test]
+fn test_fee_rate_of() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let parent = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let child = build_tx(vec![(&parent.hash(), 0)], 1);
+    let parent_entry = TxEntry::dummy_resolve(parent.clone(), 0, Capacity::shannons(100), 200);
+    let child_entry = TxEntry::dummy_resolve(child.clone(), 0, Capacity::shannons(1000), 200);
+    pool.add_pending(parent_entry.clone()).unwrap();
+    pool.add_pending(child_entry.clone()).unwrap();
+
+    let parent_id = parent.proposal_short_id();
+    let child_id = child.proposal_short_id();
+    assert_eq!(
+        pool.fee_rate_of(&parent_id, false),
+        Some(parent_entry.fee_rate())
+    );
+    assert_eq!(
+        pool.fee_rate_of(&child_id, false),
+        Some(child_entry.fee_rate())
+    );
+
+    // the parent has no in-pool ancestors, its package is itself
+    assert_eq!(
+        pool.fee_rate_of(&parent_id, true),
+        pool.fee_rate_of(&parent_id, false)
+    );
+    // the high-fee child pays for its low-fee parent
+    let package_rate = pool.fee_rate_of(&child_id, true).unwrap();
+    assert_eq!(
+        package_rate,
+        FeeRate::calculate(Capacity::shannons(1100), 400)
+    );
+    assert!(package_rate < child_entry.fee_rate());
+    assert!(package_rate > parent_entry.fee_rate());
+
+    let unknown = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    assert_eq!(pool.fee_rate_of(&unknown.proposal_short_id(), true), None);
+}
+
+#[test]
+fn test_get_annotated_ids() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let parent = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let child = build_tx(vec![(&parent.hash(), 0)], 1);
+    let proposed = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    pool.add_pending(TxEntry::dummy_resolve(
+        parent,
+        MOCK_CYCLES,
+        Capacity::shannons(100),
+        200,
+    ))
+    .unwrap();
+    pool.add_pending(TxEntry::dummy_resolve(
+        child,
+        MOCK_CYCLES,
+        Capacity::shannons(1000),
+        300,
+    ))
+    .unwrap();
+    pool.add_proposed(
+        TxEntry::dummy_resolve(proposed, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE),
+        false,
+    )
+    .unwrap();
+
+    let ids = pool.get_ids();
+    let annotated = pool.get_annotated_ids();
+    let info = pool.get_all_entry_info(None);
+
+    // same txs in the same order as the compact form
+    let hashes = |ids: &[AnnotatedTxId]| ids.iter().map(|id| id.hash.clone()).collect::<Vec<_>>();
+    assert_eq!(hashes(&annotated.pending), ids.pending);
+    assert_eq!(hashes(&annotated.proposed), ids.proposed);
+
+    for (annotated, info) in annotated
+        .pending
+        .iter()
+        .map(|id| (id, &info.pending))
+        .chain(annotated.proposed.iter().map(|id| (id, &info.proposed)))
+    {
+        let entry = &info[&annotated.hash];
+        assert_eq!(annotated.fee, entry.fee);
+        assert_eq!(annotated.size, entry.size);
+        assert_eq!(
+            annotated.fee_rate,
+            FeeRate::calculate(entry.fee, entry.weight)
+        );
+    }
+}
+
+#[test]
+fn test_median_time_past() {
+    // timestamps deliberately out of order, the median is not simply the middle block
+    let timestamps = [
+        1_000u64, 1_900, 1_100, 1_800, 1_200, 1_700, 1_300, 1_600, 1_400,
+    ];
+    let mut blocks: Vec<BlockView> = Vec::with_capacity(timestamps.len());
+    for (number, timestamp) in timestamps.iter().enumerate() {
+        let parent_hash = blocks
+            .last()
+            .map(|block| block.hash())
+            .unwrap_or_else(Byte32::zero);
+        let block = BlockBuilder::default()
+            .number((number as u64).pack())
+            .timestamp(timestamp.pack())
+            .parent_hash(parent_hash)
+            .build();
+        blocks.push(block);
+    }
+    let (_tmp_dir, pool) =
+        build_tx_pool_with_chain(Default::default(), &blocks, ProposalView::default());
+
+    let snapshot = pool.snapshot();
+    assert_eq!(snapshot.tip_hash(), blocks.last().unwrap().hash());
+    assert_eq!(
+        pool.median_time_past(),
+        snapshot.block_median_time(
+            &snapshot.tip_hash(),
+            snapshot.consensus().median_time_block_count()
+        )
+    );
+    // the chain is shorter than the median window, all of it is taken into account
+    assert_eq!(pool.median_time_past(), 1_400);
+}
+
+#[test]
+fn test_dependents_of_outpoint() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let out_point = OutPoint::new(Byte32::zero(), 1);
+
+    // tx1 <- tx2 <- tx3 spend the out point, tx4 uses it as cell dep, tx5 is unrelated
+    let tx1 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let tx2 = build_tx(vec![(&tx1.hash(), 0)], 1);
+    let tx3 = build_tx(vec![(&tx2.hash(), 0)], 1);
+    let tx4 = build_tx_with_dep(vec![(&Byte32::zero(), 2)], vec![(&Byte32::zero(), 1)], 1);
+    let tx5 = build_tx(vec![(&Byte32::zero(), 3)], 1);
+    for tx in [&tx1, &tx2, &tx3, &tx4, &tx5] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+
+    let dependents: HashSet<_> = pool
+        .dependents_of_outpoint(&out_point)
+        .into_iter()
+        .collect();
+    let expected: HashSet<_> = [&tx1, &tx2, &tx3, &tx4]
+        .iter()
+        .map(|tx| tx.proposal_short_id())
+        .collect();
+    assert_eq!(dependents, expected);
+
+    // only the tail of the chain depends on the output of tx2
+    assert_eq!(
+        pool.dependents_of_outpoint(&OutPoint::new(tx2.hash(), 0)),
+        vec![tx3.proposal_short_id()]
+    );
+    assert!(pool
+        .dependents_of_outpoint(&OutPoint::new(tx5.hash(), 0))
+        .is_empty());
+}
+
+#[test]
+fn test_get_tx_from_pool_or_store_miss_callback() {
+    let committed_tx = build_tx(vec![(&Byte32::zero(), 3)], 1);
+    let block = build_block(vec![committed_tx.clone()], 1);
+    let (_tmp_dir, mut pool) = build_tx_pool_with_chain(
+        Default::default(),
+        &[block.clone()],
+        ProposalView::default(),
+    );
+    let misses = Arc::new(Mutex::new(Vec::new()));
+    let mut callbacks = Callbacks::new();
+    let misses_clone = Arc::clone(&misses);
+    callbacks.register_pool_miss(Box::new(move |id| {
+        misses_clone.lock().unwrap().push(id.clone());
+    }));
+
+    let tx = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    pool.add_pending(entry).unwrap();
+
+    let hit = pool.get_tx_from_pool_or_store(&tx.proposal_short_id(), &callbacks);
+    assert_eq!(hit.map(|tx| tx.hash()), Some(tx.hash()));
+    assert!(misses.lock().unwrap().is_empty());
+
+    // found in the store, not a miss
+    pool.remove_committed_txs(&[block], &callbacks, &HashSet::new());
+    let committed = pool.get_tx_from_pool_or_store(&committed_tx.proposal_short_id(), &callbacks);
+    assert_eq!(committed.map(|tx| tx.hash()), Some(committed_tx.hash()));
+    assert!(misses.lock().unwrap().is_empty());
+
+    let cold = build_tx(vec![(&Byte32::zero(), 2)], 1).proposal_short_id();
+    assert!(pool.get_tx_from_pool_or_store(&cold, &callbacks).is_none());
+    assert_eq!(*misses.lock().unwrap(), vec![cold]);
+}
+
+#[test]
+fn test_zero_fee_rejected() {
+    let config = TxPoolConfig {
+        min_fee_rate: FeeRate::zero(),
+        allow_zero_fee: false,
+        ..Default::default()
+    };
+    assert!(matches!(
+        check_min_fee(&config, config.min_fee_rate, Capacity::zero(), 1000),
+        Err(Reject::LowFeeRate(_, 1, 0))
+    ));
+    assert!(check_min_fee(&config, config.min_fee_rate, Capacity::one(), 1000).is_ok());
+}
+
+#[test]
+fn test_entry_min_fee_by_weight() {
+    let (_tmp_dir, pool) = build_tx_pool(TxPoolConfig::default());
+    let tx = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let fee = Capacity::shannons(1000);
+    // enough for the size alone
+    assert!(check_min_fee(&pool.config, pool.config.min_fee_rate, fee, 100).is_ok());
+    let light = TxEntry::dummy_resolve(tx.clone(), 1000, fee, 100);
+    assert!(check_entry_min_fee(&pool, &light).is_ok());
+    // the cycles weigh more than the size
+    let heavy = TxEntry::dummy_resolve(tx, 1_000_000_000, fee, 100);
+    assert!(matches!(
+        check_entry_min_fee(&pool, &heavy),
+        Err(Reject::LowFeeRate(..))
+    ));
+}
+
+#[test]
+fn test_zero_fee_allowed() {
+    let config = TxPoolConfig {
+        min_fee_rate: FeeRate::zero(),
+        allow_zero_fee: true,
+        ..Default::default()
+    };
+    assert!(check_min_fee(&config, config.min_fee_rate, Capacity::zero(), 1000).is_ok());
+    // the min fee rate still applies
+    let config = TxPoolConfig {
+        allow_zero_fee: true,
+        ..Default::default()
+    };
+    assert!(check_min_fee(&config, config.min_fee_rate, Capacity::zero(), 1000).is_err());
+
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+    let txs: Vec<_> = (0..3u32)
+        .map(|i| build_tx(vec![(&Byte32::zero(), i)], 1))
+        .collect();
+    for (i, tx) in txs.iter().enumerate() {
+        let fee = Capacity::shannons(1000 * i as u64);
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, fee, 100);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+
+    let zero_fee_id = txs[0].proposal_short_id();
+    let packaged: Vec<_> = pool
+        .pool_map
+        .score_sorted_iter_by(vec![Status::Pending])
+        .map(|entry| entry.proposal_short_id())
+        .collect();
+    assert_eq!(packaged.last(), Some(&zero_fee_id));
+    assert_eq!(
+        pool.pool_map.next_evict_entry(Status::Pending),
+        Some(zero_fee_id)
+    );
+}
+
+#[test]
+fn test_score_sorted_iter_with() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let txs: Vec<_> = (0..4u32)
+        .map(|i| build_tx(vec![(&Byte32::zero(), i)], 1))
+        .collect();
+    let sizes = [300, 100, 400, 200];
+    for (tx, size) in txs.iter().zip(sizes) {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, size);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+    pool.pool_map
+        .set_entry(&txs[3].proposal_short_id(), Status::Gap);
+
+    // smallest first, across pending and gap
+    let ordered: Vec<_> = pool
+        .pool_map
+        .score_sorted_iter_with(vec![Status::Pending, Status::Gap], |entry| {
+            -(entry.inner.size as i128)
+        })
+        .map(|entry| entry.size)
+        .collect();
+    assert_eq!(ordered, vec![100, 200, 300, 400]);
+
+    let ordered: Vec<_> = pool
+        .pool_map
+        .score_sorted_iter_with(vec![Status::Pending], |entry| entry.inner.size as i128)
+        .map(|entry| entry.size)
+        .collect();
+    assert_eq!(ordered, vec![400, 300, 100]);
+}
+
+#[test]
+fn test_status_usage_counters() {
+    let (_tmp_dir, mut pool) = build_tx_pool(TxPoolConfig::default());
+    let tx1 = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let tx2 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    for tx in [&tx1, &tx2] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, 100);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+    assert_eq!(pool.pool_map.status_usage(Status::Pending), (200, 2));
+
+    pool.pool_map
+        .set_entry(&tx1.proposal_short_id(), Status::Proposed);
+    assert_eq!(pool.pool_map.status_usage(Status::Pending), (100, 1));
+    assert_eq!(pool.pool_map.status_usage(Status::Proposed), (100, 1));
+
+    pool.pool_map
+        .remove_entry(&tx1.proposal_short_id(), RemoveReason::Committed);
+    assert_eq!(pool.pool_map.status_usage(Status::Proposed), (0, 0));
+    assert_eq!(pool.quota_usage(Status::Pending).count, 1);
+    assert_eq!(pool.quota_usage(Status::Pending).max_count, None);
+}
+
+#[test]
+fn test_verify_integrity_inputs_index() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let tx1 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let tx2 = build_tx(vec![(&tx1.hash(), 0)], 1);
+    let tx3 = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    for tx in [&tx1, &tx2, &tx3] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        pool.add_pending(entry).unwrap();
+    }
+    assert_eq!(pool.verify_integrity(), Ok(()));
+
+    assert!(pool.remove_tx(&tx2.proposal_short_id()));
+    assert_eq!(pool.verify_integrity(), Ok(()));
+
+    // the index still maps tx1's input to the removed entry
+    pool.pool_map
+        .remove_entry_skip_index(&tx1.proposal_short_id())
+        .unwrap();
+    let out_point = OutPoint::new(Byte32::zero(), 1);
+    assert_eq!(
+        pool.verify_integrity(),
+        Err(IntegrityError::StaleInput(
+            out_point.to_string(),
+            tx1.proposal_short_id().to_string()
+        ))
+    );
+    pool.pool_map.edges.remove_input(&out_point);
+    assert_eq!(pool.verify_integrity(), Ok(()));
+
+    // a live entry whose input is not indexed
+    let out_point = OutPoint::new(Byte32::zero(), 2);
+    pool.pool_map.edges.remove_input(&out_point);
+    assert_eq!(
+        pool.verify_integrity(),
+        Err(IntegrityError::MissingInput(
+            out_point.to_string(),
+            tx3.proposal_short_id().to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_resolve_reject_classification() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let parent = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let entry = TxEntry::dummy_resolve(parent.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    pool.add_pending(entry).unwrap();
+    let unknown_hash = build_tx(vec![(&Byte32::zero(), 9)], 1).hash();
+
+    // double spends the in-pool parent's input
+    let dead = build_tx(vec![(&Byte32::zero(), 1)], 2);
+    // spends the output of a tx which was never seen
+    let orphan = build_tx(vec![(&unknown_hash, 0)], 1);
+    // spends the in-pool parent, with a cell dep which was never seen
+    let missing_dep = build_tx_with_dep(vec![(&parent.hash(), 0)], vec![(&unknown_hash, 0)], 1);
+
+    for (tx, class, missing_input, recorded) in [
+        (&dead, ResolveClass::Dead, false, true),
+        (&orphan, ResolveClass::UnknownInput, true, false),
+        (&missing_dep, ResolveClass::MissingDep, true, false),
+    ] {
+        let reject = pool.resolve_tx_from_pool(tx.clone(), false).unwrap_err();
+        assert_eq!(reject.resolve_class(tx), Some(class));
+        assert_eq!(reject.is_retryable(), class != ResolveClass::Dead);
+        assert_eq!(is_missing_input(&reject, tx), missing_input);
+        assert_eq!(is_recorded_reject(&reject), recorded);
+    }
+}
+
+#[test]
+fn test_entries_in_size_range() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let sizes = [100, 250, 500, 1_000, 5_000];
+    let txs: Vec<_> = (0..sizes.len() as u32)
+        .map(|i| build_tx(vec![(&Byte32::zero(), i)], 1))
+        .collect();
+    for (tx, size) in txs.iter().zip(sizes) {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, size);
+        pool.add_pending(entry).unwrap();
+    }
+
+    let in_range = |min, max| -> HashSet<Byte32> {
+        pool.entries_in_size_range(min, max)
+            .into_iter()
+            .map(|entry| entry.inner.transaction().hash())
+            .collect()
+    };
+    // both bounds are inclusive
+    assert_eq!(
+        in_range(250, 1_000),
+        vec![txs[1].hash(), txs[2].hash(), txs[3].hash()]
+            .into_iter()
+            .collect()
+    );
+    assert_eq!(in_range(0, usize::MAX).len(), 5);
+    assert!(in_range(1_001, 4_999).is_empty());
+    assert!(in_range(1_000, 100).is_empty());
+}
+
+#[test]
+fn test_clear_returning() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let parent = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let child = build_tx(vec![(&parent.hash(), 0)], 1);
+    let grandchild = build_tx(vec![(&child.hash(), 0)], 1);
+    let other = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    // timestamps decrease down the chain, the order must still put parents first
+    for (i, tx) in [&parent, &child, &grandchild, &other].iter().enumerate() {
+        let mut entry = TxEntry::dummy_resolve((*tx).clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        entry.timestamp = 10 - i as u64;
+        if tx.hash() == child.hash() {
+            pool.add_proposed(entry, false).unwrap();
+        } else {
+            pool.add_pending(entry).unwrap();
+        }
+    }
+    assert_eq!(pool.pool_map.size(), 4);
+
+    let (_new_tmp_dir, new_pool) = build_tx_pool(Default::default());
+    let new_snapshot = new_pool.cloned_snapshot();
+    let entries = pool.clear_returning(Arc::clone(&new_snapshot));
+    let txs: Vec<_> = entries
+        .iter()
+        .map(|entry| entry.transaction().clone())
+        .collect();
+
+    let hashes: HashSet<_> = txs.iter().map(|tx| tx.hash()).collect();
+    let expected: HashSet<_> = [&parent, &child, &grandchild, &other]
+        .iter()
+        .map(|tx| tx.hash())
+        .collect();
+    assert_eq!(hashes, expected);
+    let position = |tx: &TransactionView| txs.iter().position(|t| t.hash() == tx.hash());
+    assert!(position(&parent) < position(&child));
+    assert!(position(&child) < position(&grandchild));
+
+    assert_eq!(pool.pool_map.size(), 0);
+    assert!(Arc::ptr_eq(&pool.snapshot, &new_snapshot));
+}
+
+#[test]
+fn test_builder_components() {
+    let (_tmp_dir, pool) = build_tx_pool(Default::default());
+    let snapshot = pool.cloned_snapshot();
+
+    let reject_dir = tempfile::Builder::new().tempdir().unwrap();
+    let recent_reject = RecentReject::new(reject_dir.path(), 100, -1).unwrap();
+    let clock = Arc::new(ManualClock::new(42));
+    let pool = TxPoolBuilder::new(TxPoolConfig::default(), Arc::clone(&snapshot))
+        .recent_reject(Some(recent_reject))
+        .clock(Arc::clone(&clock) as Arc<dyn Clock>)
+        .expiry(1000)
+        .committed_hash_cache_size(2)
+        .replacement_ledger_size(3)
+        .conflict_cache_size(4)
+        .build()
+        .unwrap();
+    assert!(pool.recent_reject.is_some());
+    assert_eq!(pool.now_ms(), 42);
+    assert_eq!(pool.expiry, 1000);
+    assert_eq!(pool.committed_txs_hash_cache.cap(), 2);
+
+    let pool = TxPoolBuilder::new(TxPoolConfig::default(), snapshot)
+        .recent_reject(None)
+        .build()
+        .unwrap();
+    assert!(pool.recent_reject.is_none());
+    assert_eq!(pool.expiry, 12 * 60 * 60 * 1000);
+}
+
+#[test]
+fn test_builder_initial_entries() {
+    let (_tmp_dir, pool) = build_tx_pool(Default::default());
+    let snapshot = pool.cloned_snapshot();
+
+    let parent = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let child = build_tx(vec![(&parent.hash(), 0)], 1);
+    let other = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    let entry = |tx: &TransactionView, size| {
+        TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, size)
+    };
+    let entries = vec![
+        (entry(&parent, 100), Status::Pending),
+        (entry(&child, 200), Status::Pending),
+        (entry(&other, 300), Status::Proposed),
+    ];
+    let pool = TxPoolBuilder::new(TxPoolConfig::default(), Arc::clone(&snapshot))
+        .entries(entries)
+        .build()
+        .unwrap();
+    assert_eq!(pool.pool_map.size(), 3);
+    assert_eq!(pool.pool_map.proposed_size(), 1);
+    assert_eq!(pool.total_tx_size, 600);
+    assert_eq!(pool.total_tx_cycles, 3 * MOCK_CYCLES);
+    let child_entry = pool.pool_map.get(&child.proposal_short_id()).unwrap();
+    assert_eq!(child_entry.ancestors_count, 2);
+
+    let duplicated = vec![
+        (entry(&parent, 100), Status::Pending),
+        (entry(&parent, 100), Status::Pending),
+    ];
+    let ret = TxPoolBuilder::new(TxPoolConfig::default(), Arc::clone(&snapshot))
+        .entries(duplicated)
+        .build();
+    assert!(matches!(ret, Err(BuildError::DuplicatedEntry(_))));
+
+    let config = TxPoolConfig {
+        max_ancestors_count: 1,
+        ..Default::default()
+    };
+    let too_deep = vec![
+        (entry(&parent, 100), Status::Pending),
+        (entry(&child, 200), Status::Pending),
+    ];
+    let ret = TxPoolBuilder::new(config, snapshot)
+        .entries(too_deep)
+        .build();
+    assert!(matches!(ret, Err(BuildError::RejectedEntry(_, _))));
+}
+
+#[test]
+fn test_acceptance_latency_histogram() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let callbacks = Callbacks::new();
+    let latencies = [
+        Some(Duration::from_micros(500)),
+        Some(Duration::from_millis(1)),
+        Some(Duration::from_millis(8)),
+        Some(Duration::from_millis(250)),
+        Some(Duration::from_secs(30)),
+        None,
+    ];
+    for (i, latency) in latencies.iter().enumerate() {
+        let tx = build_tx(vec![(&Byte32::zero(), i as u32)], 1);
+        let mut entry = TxEntry::dummy_resolve(tx, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        if let Some(latency) = latency {
+            entry = entry.with_verify_latency(*latency);
+        }
+        _submit_entry(&mut pool, TxStatus::Fresh, entry, &callbacks).unwrap();
+    }
+
+    let histogram = pool.acceptance_latency_histogram();
+    assert_eq!(histogram.counts, [2, 1, 0, 1, 0, 1]);
+    assert_eq!(histogram.measured(), 5);
+    assert_eq!(histogram.unmeasured, 1);
+    assert_eq!(histogram.max, Duration::from_secs(30));
+    assert_eq!(
+        histogram.total,
+        Duration::from_micros(500) + Duration::from_millis(1 + 8 + 250) + Duration::from_secs(30)
+    );
+
+    let tx = build_tx(vec![(&Byte32::zero(), 4)], 1);
+    assert!(pool.remove_tx(&tx.proposal_short_id()));
+    assert_eq!(
+        pool.acceptance_latency_histogram().counts,
+        [2, 1, 0, 1, 0, 0]
+    );
+}
+
+#[test]
+fn test_swap_witnesses() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let parent = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let child = build_tx(vec![(&parent.hash(), 0)], 1);
+    for tx in [&parent, &child] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), 10, Capacity::shannons(1000), 100);
+        pool.update_statics_for_add_tx(entry.size, entry.cycles);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+    pool.set_entry_proposed(&parent.proposal_short_id());
+    let timestamp = pool
+        .pool_map
+        .get(&parent.proposal_short_id())
+        .unwrap()
+        .timestamp;
+
+    let variant = parent
+        .as_advanced_builder()
+        .witness(Bytes::from(vec![1, 2, 3]).pack())
+        .build();
+    assert_eq!(variant.hash(), parent.hash());
+    assert_ne!(variant.witness_hash(), parent.witness_hash());
+
+    // the first seen variant is kept on the regular path
+    let snapshot = pool.cloned_snapshot();
+    let ret = pre_check_tx(&pool, &snapshot, &variant, 150, pool.now_ms());
+    assert!(matches!(ret, Err(Reject::Duplicated(hash)) if hash == parent.hash()));
+
+    // the swap is checked like a new tx
+    let rtx = Arc::new(ResolvedTransaction::dummy_resolve(variant.clone()));
+    pool.set_read_only(true);
+    assert!(matches!(
+        pool.swap_witnesses(Arc::clone(&rtx), 20, 150),
+        Err(Reject::Maintenance)
+    ));
+    pool.set_read_only(false);
+    assert!(matches!(
+        pool.swap_witnesses(Arc::clone(&rtx), 10_000_000_000, 150),
+        Err(Reject::LowFeeRate(..))
+    ));
+    // the package of the child grows over the limit
+    pool.pool_map.max_package_size = 249;
+    assert!(matches!(
+        pool.swap_witnesses(Arc::clone(&rtx), 20, 150),
+        Err(Reject::ExceededPackageLimit(kind, 250, 249)) if kind == "size"
+    ));
+    pool.pool_map.max_package_size = usize::MAX;
+
+    let old = pool.swap_witnesses(rtx, 20, 150).unwrap();
+    assert_eq!(old.transaction().witness_hash(), parent.witness_hash());
+
+    let id = parent.proposal_short_id();
+    let swapped = pool.get_pool_entry(&id).unwrap();
+    assert_eq!(
+        swapped.inner.transaction().witness_hash(),
+        variant.witness_hash()
+    );
+    assert_eq!(swapped.status, Status::Proposed);
+    assert_eq!(swapped.inner.timestamp, timestamp);
+    assert_eq!(swapped.inner.fee, Capacity::shannons(1000));
+    assert_eq!(
+        (
+            swapped.inner.descendants_size,
+            swapped.inner.descendants_cycles
+        ),
+        (250, 30)
+    );
+    let child_entry = pool.pool_map.get(&child.proposal_short_id()).unwrap();
+    assert_eq!(child_entry.ancestors_count, 2);
+    assert_eq!(
+        (child_entry.ancestors_size, child_entry.ancestors_cycles),
+        (250, 30)
+    );
+    assert_eq!((pool.total_tx_size, pool.total_tx_cycles), (250, 30));
+    assert!(pool.verify_integrity().is_ok());
+
+    // nothing left to swap once the witnesses match
+    let rtx = Arc::new(ResolvedTransaction::dummy_resolve(variant));
+    assert!(matches!(
+        pool.swap_witnesses(rtx, 20, 150),
+        Err(Reject::Duplicated(_))
+    ));
+}
+
+#[test]
+fn test_check_cellbase_maturity() {
+    // a cellbase output created in epoch 1, the default maturity is 4 epochs
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(1))
+        .output(
+            CellOutput::new_builder()
+                .capacity(Capacity::bytes(100).unwrap().pack())
+                .build(),
+        )
+        .output_data(Bytes::new().pack())
+        .build();
+    let cell = CellMetaBuilder::from_cell_output(
+        cellbase.output(0).unwrap(),
+        cellbase.outputs_data().get(0).unwrap().raw_data(),
+    )
+    .out_point(OutPoint::new(cellbase.hash(), 0))
+    .transaction_info(TransactionInfo::new(
+        1,
+        EpochNumberWithFraction::new(1, 0, 1000),
+        Byte32::zero(),
+        0,
+    ))
+    .build();
+    let tx = build_tx(vec![(&cellbase.hash(), 0)], 1);
+    let rtx = Arc::new(ResolvedTransaction {
+        transaction: tx,
+        resolved_cell_deps: vec![],
+        resolved_inputs: vec![cell],
+        resolved_dep_groups: vec![],
+    });
+
+    let tip_in_epoch = |epoch: u64| {
+        let tip = BlockBuilder::default()
+            .number(1000u64.pack())
+            .epoch(EpochNumberWithFraction::new(epoch, 0, 1000).pack())
+            .build();
+        build_tx_pool_with_chain(Default::default(), &[tip], ProposalView::default())
+    };
+
+    let (_tmp_dir, mut pool) = tip_in_epoch(2);
+    let snapshot = pool.cloned_snapshot();
+    let ret = check_cellbase_maturity(&pool, &snapshot, &rtx, TxStatus::Fresh);
+    assert!(
+        matches!(ret, Err(Reject::Verification(ref err)) if err.to_string().contains("CellbaseImmaturity")),
+        "{ret:?}"
+    );
+    // the cellbase matures as the chain grows, the same tx may be accepted later
+    assert!(!is_recorded_reject(&ret.unwrap_err()));
+    // the early check can be turned off, the verification still rejects the tx later
+    pool.config.reject_immature_cellbase = false;
+    assert!(check_cellbase_maturity(&pool, &snapshot, &rtx, TxStatus::Fresh).is_ok());
+
+    let (_tmp_dir, pool) = tip_in_epoch(6);
+    let snapshot = pool.cloned_snapshot();
+    assert!(check_cellbase_maturity(&pool, &snapshot, &rtx, TxStatus::Fresh).is_ok());
+}
+
+#[test]
+fn test_check_hash_types() {
+    let tx_with = |lock: ScriptHashType, type_: Option<ScriptHashType>| {
+        let script = |hash_type: ScriptHashType| {
+            Script::new_builder()
+                .code_hash(Byte32::zero())
+                .hash_type(hash_type.into())
+                .build()
+        };
+        TransactionBuilder::default()
+            .input(CellInput::new(OutPoint::new(Byte32::zero(), 0), 0))
+            .output(
+                CellOutput::new_builder()
+                    .capacity(Capacity::bytes(100).unwrap().pack())
+                    .lock(script(lock))
+                    .type_(type_.map(script).pack())
+                    .build(),
+            )
+            .output_data(Bytes::new().pack())
+            .build()
+    };
+    let data1 = tx_with(ScriptHashType::Type, Some(ScriptHashType::Data1));
+    let type_only = tx_with(ScriptHashType::Type, Some(ScriptHashType::Type));
+
+    // all hash types are allowed by default
+    let mut config = TxPoolConfig::default();
+    assert!(check_hash_types(&config, &data1).is_ok());
+
+    config.allowed_hash_types = Some(vec![ckb_jsonrpc_types::ScriptHashType::Type]);
+    assert!(check_hash_types(&config, &type_only).is_ok());
+    let ret = check_hash_types(&config, &data1);
+    assert!(
+        matches!(ret, Err(Reject::NonStandard(ref msg)) if msg == "output 0 type script hash type 2 is not allowed"),
+        "{ret:?}"
+    );
+
+    config.allowed_hash_types = Some(vec![
+        ckb_jsonrpc_types::ScriptHashType::Type,
+        ckb_jsonrpc_types::ScriptHashType::Data1,
+    ]);
+    assert!(check_hash_types(&config, &data1).is_ok());
+}
+
+#[test]
+fn test_is_snapshot_stale() {
+    let tip = BlockBuilder::default().number(100u64.pack()).build();
+    let (_tmp_dir, pool) =
+        build_tx_pool_with_chain(Default::default(), &[tip], ProposalView::default());
+
+    assert!(!pool.is_snapshot_stale(100, 0));
+    assert!(!pool.is_snapshot_stale(105, 5));
+    assert!(pool.is_snapshot_stale(106, 5));
+    // a chain tip behind the snapshot, e.g. during a reorg, is not a lag
+    assert!(!pool.is_snapshot_stale(90, 0));
+}
+
+#[test]
+fn test_detach_keeps_statics() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let parent = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let child = build_tx(vec![(&parent.hash(), 0)], 1);
+    for (tx, cycles, size) in [(&parent, 100, 200), (&child, 300, 400)] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), cycles, MOCK_FEE, size);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+        pool.update_statics_for_add_tx(size, cycles);
+    }
+    pool.set_entry_proposed(&parent.proposal_short_id());
+    pool.set_entry_gap(&child.proposal_short_id());
+    let totals = (pool.total_tx_size, pool.total_tx_cycles);
+    assert_eq!(totals, (600, 400));
+
+    pool.remove_by_detached_proposal(vec![parent.proposal_short_id()].iter());
+    for tx in [&parent, &child] {
+        let entry = pool.get_pool_entry(&tx.proposal_short_id()).unwrap();
+        assert_eq!(entry.status, Status::Pending);
+    }
+    assert_eq!((pool.total_tx_size, pool.total_tx_cycles), totals);
+}
+
+#[test]
+fn test_get_entry_by_hash() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let replaced = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let child = build_tx(vec![(&replaced.hash(), 0)], 1);
+    let replacement = build_tx(vec![(&Byte32::zero(), 0)], 2);
+    for tx in [&replaced, &child] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+    assert_eq!(
+        pool.get_entry_by_hash(&child.hash())
+            .map(|entry| entry.id.clone()),
+        Some(child.proposal_short_id())
+    );
+
+    assert_eq!(pool.remove_replaced(&replaced.proposal_short_id()).len(), 2);
+    let entry = TxEntry::dummy_resolve(replacement.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+    assert!(pool.get_entry_by_hash(&replaced.hash()).is_none());
+    assert!(pool.get_entry_by_hash(&child.hash()).is_none());
+    assert!(pool.get_entry_by_hash(&replacement.hash()).is_some());
+
+    // a hash sharing the proposal short id of an entry, only the entry's own hash finds it
+    let mut colliding = [0u8; 32];
+    colliding.copy_from_slice(replacement.hash().as_slice());
+    colliding[31] ^= 1;
+    let colliding = Byte32::new(colliding);
+    let id = ProposalShortId::from_tx_hash(&colliding);
+    assert_eq!(id, replacement.proposal_short_id());
+    assert!(pool.get_pool_entry(&id).is_some());
+    assert!(pool.get_entry_by_hash(&colliding).is_none());
+
+    assert!(pool.remove_tx(&id));
+    assert!(pool.get_entry_by_hash(&replacement.hash()).is_none());
+    assert!(pool.pool_map.hashes.is_empty());
+
+    let entry = TxEntry::dummy_resolve(replaced.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+    pool.clear(pool.cloned_snapshot());
+    assert!(pool.get_entry_by_hash(&replaced.hash()).is_none());
+    assert!(pool.pool_map.hashes.is_empty());
+}
+
+#[test]
+fn test_add_returns_short_id() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let txs: Vec<_> = (0..3u32)
+        .map(|i| build_tx(vec![(&Byte32::zero(), i)], 1))
+        .collect();
+    let entry =
+        |tx: &TransactionView| TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+
+    assert_eq!(
+        pool.add_pending(entry(&txs[0])).unwrap(),
+        Some(txs[0].proposal_short_id())
+    );
+    assert_eq!(
+        pool.add_gap(entry(&txs[1])).unwrap(),
+        Some(txs[1].proposal_short_id())
+    );
+    assert_eq!(
+        pool.add_proposed(entry(&txs[2]), false).unwrap(),
+        Some(txs[2].proposal_short_id())
+    );
+    // already in the pool
+    assert_eq!(pool.add_pending(entry(&txs[2])).unwrap(), None);
+}
+
+#[test]
+fn test_resolve_cache() {
+    let config = TxPoolConfig {
+        resolve_cache_size: 8,
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) = build_tx_pool(config);
+    let parent = build_tx(vec![(&Byte32::zero(), 0)], 2);
+    let child = build_tx(vec![(&parent.hash(), 0)], 1);
+    let entry = TxEntry::dummy_resolve(parent.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+
+    // served from the cache under the same snapshot and pool
+    let rtx = pool.resolve_tx_from_pool(child.clone(), false).unwrap();
+    let cached = pool.resolve_tx_from_pool(child.clone(), false).unwrap();
+    assert!(Arc::ptr_eq(&rtx, &cached));
+    // the RBF flag is part of the key
+    let rbf = pool.resolve_tx_from_pool(child.clone(), true).unwrap();
+    assert!(!Arc::ptr_eq(&rtx, &rbf));
+
+    // a pool change makes it stale
+    let unrelated = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let entry = TxEntry::dummy_resolve(unrelated, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+    let rtx = pool.resolve_tx_from_pool(child.clone(), false).unwrap();
+    assert!(!Arc::ptr_eq(&rtx, &cached));
+    let cached = pool.resolve_tx_from_pool(child.clone(), false).unwrap();
+    assert!(Arc::ptr_eq(&rtx, &cached));
+
+    // so does a snapshot swap
+    let (_other_dir, other) = build_tx_pool_with_chain(
+        Default::default(),
+        &[build_block(vec![], 1)],
+        ProposalView::default(),
+    );
+    pool.set_snapshot(other.cloned_snapshot());
+    let rtx = pool.resolve_tx_from_pool(child, false).unwrap();
+    assert!(!Arc::ptr_eq(&rtx, &cached));
+}
+
+#[test]
+fn test_ancestry_walks() {
+    let walk = |max_traversal_entries| {
+        let config = TxPoolConfig {
+            max_traversal_entries,
+            ..Default::default()
+        };
+        let (tmp_dir, mut pool) = build_tx_pool(config);
+        // a chain a <- b <- c <- d, and a diamond r <- (x, y) <- z
+        let a = build_tx(vec![(&Byte32::zero(), 0)], 1);
+        let b = build_tx(vec![(&a.hash(), 0)], 1);
+        let c = build_tx(vec![(&b.hash(), 0)], 1);
+        let d = build_tx(vec![(&c.hash(), 0)], 1);
+        let r = build_tx(vec![(&Byte32::zero(), 1)], 2);
+        let x = build_tx(vec![(&r.hash(), 0)], 1);
+        let y = build_tx(vec![(&r.hash(), 1)], 1);
+        let z = build_tx(vec![(&x.hash(), 0), (&y.hash(), 0)], 1);
+        let txs = vec![a, b, c, d, r, x, y, z];
+        for tx in txs.iter() {
+            let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+            assert!(pool.add_pending(entry).unwrap().is_some());
+        }
+        let ids: Vec<_> = txs.iter().map(|tx| tx.proposal_short_id()).collect();
+        (tmp_dir, pool, ids)
+    };
+    let (_tmp_dir, pool, ids) = walk(1000);
+    let [a, b, c, d, r, x, y, z] = <[ProposalShortId; 8]>::try_from(ids).unwrap();
+
+    // depth truncation
+    assert_eq!(
+        pool.ancestors_of(&d, Some(2)),
+        vec![(c.clone(), 1), (b.clone(), 2)]
+    );
+    assert_eq!(
+        pool.ancestors_of(&d, None),
+        vec![(c, 1), (b, 2), (a.clone(), 3)]
+    );
+    assert!(pool.ancestors_of(&d, Some(0)).is_empty());
+    assert!(pool.ancestors_of(&a, None).is_empty());
+
+    // the diamond bottom is reached twice but listed once, at its shortest distance
+    let descendants = pool.descendants_of(&r, None);
+    assert_eq!(descendants.len(), 3);
+    let ids: HashSet<_> = descendants.iter().map(|(id, _)| id.clone()).collect();
+    assert_eq!(ids.len(), descendants.len());
+    assert_eq!(
+        descendants[..2].iter().cloned().collect::<HashSet<_>>(),
+        HashSet::from([(x.clone(), 1), (y.clone(), 1)])
+    );
+    assert_eq!(descendants[2], (z.clone(), 2));
+    let ancestors = pool.ancestors_of(&z, None);
+    assert_eq!(ancestors.len(), 3);
+    assert_eq!(ancestors[2], (r.clone(), 2));
+    assert_eq!(pool.descendants_of(&r, Some(1)).len(), 2);
+
+    // not in the pool
+    let unknown = ProposalShortId::from_tx_hash(&Byte32::zero());
+    assert!(pool.descendants_of(&unknown, None).is_empty());
+
+    // capped by the config even without a depth limit
+    let (_tmp_dir, pool, _) = walk(2);
+    assert_eq!(pool.descendants_of(&r, None).len(), 2);
+}
+
+#[test]
+fn test_read_only_mode() {
+    let (_tmp_dir, mut pool) = build_tx_pool_with_chain(
+        Default::default(),
+        &[build_block(vec![], 1)],
+        ProposalView::default(),
+    );
+    let callbacks = Callbacks::new();
+    let tx1 = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let tx2 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    for tx in [&tx1, &tx2] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        assert!(pool.add_proposed(entry, false).unwrap().is_some());
+    }
+    let snapshot = pool.cloned_snapshot();
+    let new_tx = build_tx(vec![(&Byte32::new([1; 32]), 0)], 1);
+
+    pool.set_read_only(true);
+    assert!(pool.is_read_only());
+    let ret = pre_check_tx(&pool, &snapshot, &new_tx, 100, pool.now_ms());
+    match ret {
+        Err(reject @ Reject::Maintenance) => assert!(reject.is_retryable()),
+        _ => panic!("expected a maintenance reject"),
+    }
+
+    // the block template is still built from the pool
+    let (packaged, _, _) = pool.package_txs(Cycle::MAX, usize::MAX);
+    assert_eq!(packaged.len(), 2);
+
+    // and the committed txs still leave it
+    _update_tx_pool_for_reorg(
+        &mut pool,
+        ReorgUpdate {
+            attached_blocks: &[build_block(vec![tx1.clone()], 2)],
+            uncommitted: &[],
+            detached_headers: &HashSet::new(),
+            detached_proposal_id: HashSet::new(),
+            snapshot: Arc::clone(&snapshot),
+        },
+        &callbacks,
+        false,
+    );
+    assert!(!pool.contains_proposal_id(&tx1.proposal_short_id()));
+    assert!(pool.contains_proposal_id(&tx2.proposal_short_id()));
+
+    // back to normal, the tx goes through the usual checks again
+    pool.set_read_only(false);
+    let ret = pre_check_tx(&pool, &snapshot, &new_tx, 100, pool.now_ms());
+    assert!(matches!(
+        ret,
+        Err(Reject::Resolve(OutPointError::Unknown(_)))
+    ));
+}
+
+#[test]
+fn test_get_txs_with_cycles() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let tx1 = build_tx(vec![(&Byte32::zero(), 0)], 1);
+    let tx2 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let absent = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    for (tx, cycles) in [(&tx1, 100), (&tx2, 200)] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), cycles, MOCK_FEE, MOCK_SIZE);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+
+    let ids = [
+        tx2.proposal_short_id(),
+        absent.proposal_short_id(),
+        tx1.proposal_short_id(),
+        absent.proposal_short_id(),
+    ];
+    let found: Vec<_> = pool
+        .get_txs_with_cycles(&ids)
+        .into_iter()
+        .map(|ret| ret.map(|(tx, cycles)| (tx.hash(), cycles)))
+        .collect();
+    assert_eq!(
+        found,
+        vec![Some((tx2.hash(), 200)), None, Some((tx1.hash(), 100)), None]
+    );
+    assert!(pool.get_txs_with_cycles(&[]).is_empty());
+}
+
+#[test]
+fn test_check_output_policy() {
+    let output = |shannons: u64, typed: bool| {
+        let type_ = typed.then(|| Script::new_builder().code_hash(Byte32::zero()).build());
+        CellOutput::new_builder()
+            .capacity(Capacity::shannons(shannons).pack())
+            .type_(type_.pack())
+            .build()
+    };
+    let tx_with = |outputs: Vec<CellOutput>| {
+        let data = vec![Bytes::new().pack(); outputs.len()];
+        TransactionBuilder::default()
+            .input(CellInput::new(OutPoint::new(Byte32::zero(), 0), 0))
+            .outputs(outputs)
+            .outputs_data(data)
+            .build()
+    };
+    let dust = tx_with(vec![output(1, false), output(1, true), output(1, false)]);
+
+    // off by default
+    let mut config = TxPoolConfig::default();
+    assert!(check_output_policy(&config, &dust, Origin::Remote).is_ok());
+
+    config.min_output_capacity = Some(1000);
+    let boundary = tx_with(vec![output(1000, false), output(1000, true)]);
+    assert!(check_output_policy(&config, &boundary, Origin::Remote).is_ok());
+    let below = tx_with(vec![output(1000, false), output(999, true)]);
+    let ret = check_output_policy(&config, &below, Origin::Remote);
+    assert!(
+        matches!(
+            ret,
+            Err(Reject::OutputPolicy(OutputPolicyViolation::LowCapacity { index: 1, capacity, min_capacity }))
+                if capacity == Capacity::shannons(999) && min_capacity == Capacity::shannons(1000)
+        ),
+        "{ret:?}"
+    );
+    // local txs are exempt
+    assert!(check_output_policy(&config, &below, Origin::Local).is_ok());
+
+    // the outputs carrying a type script have their own floor
+    config.min_typed_output_capacity = Some(2000);
+    assert!(check_output_policy(&config, &boundary, Origin::Remote).is_err());
+    let typed = tx_with(vec![output(1000, false), output(2000, true)]);
+    assert!(check_output_policy(&config, &typed, Origin::Remote).is_ok());
+
+    config.max_outputs_per_tx = Some(2);
+    assert!(check_output_policy(&config, &typed, Origin::Remote).is_ok());
+    let ret = check_output_policy(&config, &dust, Origin::Remote);
+    assert!(
+        matches!(
+            ret,
+            Err(Reject::OutputPolicy(
+                OutputPolicyViolation::TooManyOutputs { count: 3, max: 2 }
+            ))
+        ),
+        "{ret:?}"
+    );
+    assert!(check_output_policy(&config, &dust, Origin::Local).is_ok());
+}
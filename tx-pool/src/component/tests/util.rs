@@ -1,9 +1,24 @@
+use crate::clock::Clock;
+use crate::pool::{TxPool, TxPoolBuilder};
+use ckb_app_config::TxPoolConfig;
+use ckb_chain_spec::consensus::Consensus;
+use ckb_db::RocksDB;
+use ckb_db_schema::COLUMNS;
+use ckb_proposal_table::ProposalView;
+use ckb_snapshot::Snapshot;
+use ckb_store::ChainDB;
 use ckb_types::{
     bytes::Bytes,
-    core::{Capacity, Cycle, TransactionBuilder, TransactionView},
+    core::{
+        BlockBuilder, BlockNumber, BlockView, Capacity, Cycle, EpochExt, HeaderBuilder, HeaderView,
+        TransactionBuilder, TransactionView,
+    },
     packed::{Byte32, CellDep, CellInput, CellOutput, OutPoint},
     prelude::*,
+    U256,
 };
+use std::sync::Arc;
+use tempfile::TempDir;
 
 pub(crate) const DEFAULT_MAX_ANCESTORS_COUNT: usize = 125;
 pub(crate) const MOCK_CYCLES: Cycle = 0;
@@ -71,3 +86,86 @@ pub(crate) fn build_tx_with_header_dep(
         .outputs_data((0..outputs_len).map(|_| Bytes::new().pack()))
         .build()
 }
+
+/// Build a tx-pool over an empty store, the returned `TempDir` must outlive the pool
+pub(crate) fn build_tx_pool(config: TxPoolConfig) -> (TempDir, TxPool) {
+    build_tx_pool_with_proposals(config, ProposalView::default())
+}
+
+pub(crate) fn build_tx_pool_with_proposals(
+    config: TxPoolConfig,
+    proposals: ProposalView,
+) -> (TempDir, TxPool) {
+    build_tx_pool_with_chain(config, &[], proposals)
+}
+
+/// Builds a tx-pool on top of a store holding `blocks` on its main chain, the last one being the
+/// tip
+pub(crate) fn build_tx_pool_with_chain(
+    config: TxPoolConfig,
+    blocks: &[BlockView],
+    proposals: ProposalView,
+) -> (TempDir, TxPool) {
+    let (tmp_dir, store) = build_store();
+    let txn = store.begin_transaction();
+    for block in blocks {
+        txn.insert_block(block).unwrap();
+        txn.attach_block(block).unwrap();
+    }
+    txn.commit().unwrap();
+    let tip = blocks
+        .last()
+        .map(|block| block.header())
+        .unwrap_or_else(|| HeaderBuilder::default().build());
+    let snapshot = build_snapshot(&store, tip, proposals);
+    (tmp_dir, TxPool::new(config, snapshot))
+}
+
+/// Builds an empty tx-pool reading the time from `clock`
+pub(crate) fn build_tx_pool_with_clock(
+    config: TxPoolConfig,
+    clock: Arc<dyn Clock>,
+) -> (TempDir, TxPool) {
+    let (tmp_dir, store) = build_store();
+    let tip = HeaderBuilder::default().build();
+    let snapshot = build_snapshot(&store, tip, ProposalView::default());
+    let pool = TxPoolBuilder::new(config, snapshot)
+        .clock(clock)
+        .build()
+        .unwrap();
+    (tmp_dir, pool)
+}
+
+/// Opens an empty store, the returned `TempDir` must outlive it
+pub(crate) fn build_store() -> (TempDir, ChainDB) {
+    let tmp_dir = TempDir::new().unwrap();
+    let db = RocksDB::open_in(&tmp_dir, COLUMNS);
+    (tmp_dir, ChainDB::new(db, Default::default()))
+}
+
+/// Builds a snapshot of `store` with the given tip and proposals
+pub(crate) fn build_snapshot(
+    store: &ChainDB,
+    tip: HeaderView,
+    proposals: ProposalView,
+) -> Arc<Snapshot> {
+    Arc::new(Snapshot::new(
+        tip,
+        U256::zero(),
+        EpochExt::default(),
+        store.get_snapshot(),
+        proposals,
+        Arc::new(Consensus::default()),
+    ))
+}
+
+/// Builds the block `number` committing `txs` after a cellbase
+pub(crate) fn build_block(txs: Vec<TransactionView>, number: BlockNumber) -> BlockView {
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(number))
+        .build();
+    BlockBuilder::default()
+        .number(number.pack())
+        .transactions(std::iter::once(cellbase).chain(txs))
+        .build()
+}
@@ -0,0 +1,260 @@
+use crate::clock::{Clock, ManualClock};
+use crate::component::entry::TxEntry;
+use crate::component::tests::util::{
+    build_tx, build_tx_pool, build_tx_pool_with_clock, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE,
+};
+use crate::error::ViewError;
+use crate::pool::{PoolDiff, SampleWeighting, TxPool};
+use ckb_app_config::TxPoolConfig;
+use ckb_types::{
+    core::{tx_pool::PoolTxFilter, Capacity, TransactionView},
+    packed::Byte32,
+};
+use ckb_verification::cache::{Completed, ForkContext, PrewarmEntry};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+#[test]
+fn test_diff_against() {
+    let tx1 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let tx2 = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    let tx3 = build_tx(vec![(&tx2.hash(), 0)], 1);
+    let tx4 = build_tx(vec![(&Byte32::zero(), 3)], 1);
+    let add = |pool: &mut TxPool, tx: &TransactionView, fee: u64, size: usize| {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, Capacity::shannons(fee), size);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    };
+    let hashes = |pool: &TxPool| -> Vec<Byte32> {
+        pool.pool_map
+            .iter()
+            .map(|entry| entry.inner.transaction().hash())
+            .collect()
+    };
+
+    let (_tmp_dir_a, mut pool_a) = build_tx_pool(Default::default());
+    add(&mut pool_a, &tx1, 100, 10);
+    add(&mut pool_a, &tx2, 200, 20);
+    add(&mut pool_a, &tx3, 300, 30);
+    let (_tmp_dir_b, mut pool_b) = build_tx_pool(Default::default());
+    add(&mut pool_b, &tx4, 400, 40);
+    add(&mut pool_b, &tx2, 200, 20);
+    add(&mut pool_b, &tx3, 300, 30);
+
+    let diff = pool_a.diff_against(&hashes(&pool_b));
+    assert_eq!(diff.missing, vec![tx4.hash()]);
+    assert_eq!(diff.extra, vec![tx1.hash()]);
+    assert_eq!(diff.common, 2);
+    assert_eq!(diff.extra_size, 10);
+    assert_eq!(diff.extra_fee, Capacity::shannons(100));
+
+    let diff = pool_b.diff_against(&hashes(&pool_a));
+    assert_eq!(diff.missing, vec![tx1.hash()]);
+    assert_eq!(diff.extra, vec![tx4.hash()]);
+    assert_eq!(diff.common, 2);
+    assert_eq!(diff.extra_size, 40);
+    assert_eq!(diff.extra_fee, Capacity::shannons(400));
+
+    // duplicated hashes are counted once
+    let diff = pool_a.diff_against(&[tx4.hash(), tx1.hash(), tx4.hash(), tx1.hash()]);
+    assert_eq!(diff.missing, vec![tx4.hash()]);
+    assert_eq!(diff.common, 1);
+    assert_eq!(diff.extra.len(), 2);
+
+    let (seq_a, fingerprint_a) = pool_a.snapshot_ids();
+    let (seq_b, fingerprint_b) = pool_b.snapshot_ids();
+    assert_eq!(seq_a, 3);
+    assert_ne!(fingerprint_a, fingerprint_b);
+
+    // same txs added in another order, the fingerprints match, the sequences don't
+    assert!(pool_b.remove_tx(&tx4.proposal_short_id()));
+    add(&mut pool_b, &tx1, 100, 10);
+    let (seq_b_after, fingerprint_b) = pool_b.snapshot_ids();
+    assert_eq!(seq_b_after, seq_b + 2);
+    assert_eq!(fingerprint_a, fingerprint_b);
+    assert_eq!(
+        pool_a.diff_against(&hashes(&pool_b)),
+        PoolDiff {
+            common: 3,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn test_get_ids_paginated() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let mut pending = Vec::new();
+    for i in 0..5u32 {
+        let tx = build_tx(vec![(&Byte32::zero(), i)], 1);
+        let fee = Capacity::shannons(1000 * (u64::from(i) + 1));
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, fee, MOCK_SIZE);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+        pending.push(tx.hash());
+    }
+    // the proposed tx pays the most, the others follow it from the highest fee down
+    let proposed = build_tx(vec![(&Byte32::zero(), 5)], 1);
+    let fee = Capacity::shannons(10_000);
+    let entry = TxEntry::dummy_resolve(proposed.clone(), MOCK_CYCLES, fee, MOCK_SIZE);
+    assert!(pool.add_proposed(entry, false).unwrap().is_some());
+    pending.reverse();
+
+    let first = pool.get_ids_paginated(PoolTxFilter::Pending, 0, 3);
+    let second = pool.get_ids_paginated(PoolTxFilter::Pending, 3, 3);
+    assert_eq!(first.len(), 3);
+    assert_eq!(second.len(), 2);
+    assert_eq!([first, second].concat(), pending);
+    assert!(pool
+        .get_ids_paginated(PoolTxFilter::Pending, 5, 3)
+        .is_empty());
+
+    let all = pool.get_ids_paginated(PoolTxFilter::All, 0, 10);
+    assert_eq!(all[0], proposed.hash());
+    assert_eq!(all[1..], pending[..]);
+    assert_eq!(
+        pool.get_ids_paginated(PoolTxFilter::Proposed, 0, 10),
+        vec![proposed.hash()]
+    );
+}
+
+#[test]
+fn test_sample_entries() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let mut fees = HashMap::new();
+    for i in 0..20u32 {
+        let tx = build_tx(vec![(&Byte32::zero(), i)], 1);
+        let fee = Capacity::shannons(if i == 0 { 300 } else { 100 });
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, fee, 10);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+        fees.insert(tx.hash(), fee);
+    }
+
+    let sample = pool.sample_entries(5, SampleWeighting::Uniform, Some(42));
+    assert_eq!(sample.len(), 5);
+    assert_eq!(
+        sample
+            .iter()
+            .map(|id| &id.hash)
+            .collect::<HashSet<_>>()
+            .len(),
+        5
+    );
+    assert!(sample.iter().all(|id| fees[&id.hash] == id.fee));
+    assert_eq!(
+        sample,
+        pool.sample_entries(5, SampleWeighting::Uniform, Some(42))
+    );
+    assert_eq!(
+        pool.sample_entries(100, SampleWeighting::BySize, None)
+            .len(),
+        20
+    );
+
+    // the 300 shannons fee entry is 3 / 22 of the total fee
+    let heavy = build_tx(vec![(&Byte32::zero(), 0)], 1).hash();
+    let rounds = 5_000;
+    let hits = (0..rounds)
+        .filter(|seed| pool.sample_entries(1, SampleWeighting::ByFee, Some(*seed))[0].hash == heavy)
+        .count();
+    let ratio = hits as f64 / rounds as f64;
+    assert!((ratio - 3.0 / 22.0).abs() < 0.02, "{ratio}");
+
+    let hits = (0..rounds)
+        .filter(|seed| {
+            pool.sample_entries(1, SampleWeighting::Uniform, Some(*seed))[0].hash == heavy
+        })
+        .count();
+    let ratio = hits as f64 / rounds as f64;
+    assert!((ratio - 1.0 / 20.0).abs() < 0.02, "{ratio}");
+}
+
+#[test]
+fn test_export_verified() {
+    let (_tmp_dir, mut pool) = build_tx_pool(Default::default());
+    let fork_context = ForkContext {
+        vm_version_2_and_syscalls_3: true,
+    };
+    let tx1 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let tx2 = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    let entry1 = TxEntry::dummy_resolve(tx1.clone(), 1000, Capacity::shannons(100), MOCK_SIZE)
+        .with_fork_context(fork_context);
+    let entry2 = TxEntry::dummy_resolve(tx2, MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(pool.add_pending(entry1).unwrap().is_some());
+    assert!(pool.add_pending(entry2).unwrap().is_some());
+
+    // only the entries whose fork context is known are exported
+    assert_eq!(
+        pool.export_verified(),
+        vec![PrewarmEntry {
+            tx_hash: tx1.hash(),
+            completed: Completed {
+                cycles: 1000,
+                fee: Capacity::shannons(100),
+            },
+            fork_context,
+        }]
+    );
+}
+
+#[test]
+fn test_consistent_view() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let config = TxPoolConfig {
+        max_view_age_millis: 5_000,
+        ..Default::default()
+    };
+    let (_tmp_dir, mut pool) =
+        build_tx_pool_with_clock(config, Arc::clone(&clock) as Arc<dyn Clock>);
+    let tx1 = build_tx(vec![(&Byte32::zero(), 1)], 1);
+    let tx2 = build_tx(vec![(&Byte32::zero(), 2)], 1);
+    let tx3 = build_tx(vec![(&Byte32::zero(), 3)], 1);
+    for tx in [&tx1, &tx2] {
+        let entry = TxEntry::dummy_resolve(tx.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+        assert!(pool.add_pending(entry).unwrap().is_some());
+    }
+
+    let view = pool.consistent_view();
+    assert_eq!(view.seq(), pool.seq());
+    assert_eq!(view.len(), 2);
+    let ids = pool.get_ids();
+    assert_eq!(ids.seq, view.seq());
+    // nothing changed, the next view shares the published copy
+    let again = pool.consistent_view();
+    assert!(Arc::ptr_eq(again.published(), view.published()));
+
+    // the pool moves on, the view does not
+    assert!(pool.remove_tx(&tx1.proposal_short_id()));
+    let entry = TxEntry::dummy_resolve(tx3.clone(), MOCK_CYCLES, MOCK_FEE, MOCK_SIZE);
+    assert!(pool.add_pending(entry).unwrap().is_some());
+    assert!(pool.seq() > view.seq());
+
+    assert_eq!(view.get_ids().unwrap(), &ids);
+    assert_eq!(
+        view.get_transaction(&tx1.hash())
+            .unwrap()
+            .map(|tx| tx.hash()),
+        Some(tx1.hash())
+    );
+    assert!(view.get_entry_info(&tx1.hash()).unwrap().is_some());
+    assert!(view.get_transaction(&tx3.hash()).unwrap().is_none());
+    assert!(view.get_entry_info(&tx3.hash()).unwrap().is_none());
+
+    // the copy taken at the old seq is still found by it, a new one is published for the current
+    let pinned = pool.view_at(view.seq()).unwrap();
+    assert!(Arc::ptr_eq(pinned.published(), view.published()));
+    assert_eq!(pinned.get_ids().unwrap(), &ids);
+    let current = pool.consistent_view();
+    assert_eq!(current.seq(), pool.seq());
+    assert!(!Arc::ptr_eq(current.published(), view.published()));
+    assert!(current.get_entry_info(&tx3.hash()).unwrap().is_some());
+
+    clock.advance(5_000);
+    assert!(view.get_ids().is_ok());
+    clock.advance(1);
+    let seq = view.seq();
+    assert!(matches!(view.get_ids(), Err(ViewError::Expired(s, 5_001)) if s == seq));
+    assert!(matches!(
+        view.get_transaction(&tx2.hash()),
+        Err(ViewError::Expired(..))
+    ));
+    assert!(matches!(pool.view_at(seq), Err(ViewError::NotPinned(s)) if s == seq));
+}
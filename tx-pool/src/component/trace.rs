@@ -0,0 +1,146 @@
+use crate::clock::Clock;
+use crate::component::changelog::RemoveReason;
+use ckb_types::{core::Cycle, packed::Byte32, prelude::*};
+use ckb_util::{LinkedHashMap, Mutex};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Max number of events kept per traced tx, older ones are dropped
+pub(crate) const MAX_TRACE_EVENTS: usize = 32;
+/// Max number of watched txs, and of sampled txs, the oldest trace of each is dropped to make room
+/// for a new one
+pub(crate) const MAX_TRACED_TXS: usize = 1000;
+
+/// A step of a tx through the pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxEvent {
+    /// Submitted locally or relayed by a peer
+    Received,
+    /// Its inputs and deps were resolved
+    Resolved,
+    /// Its scripts were verified
+    Verified {
+        /// Cycles consumed by the scripts
+        cycles: Cycle,
+    },
+    /// Entered the pool
+    Admitted,
+    /// Moved to gap
+    Gap,
+    /// Moved to proposed
+    Proposed,
+    /// Left the pool
+    Removed(RemoveReason),
+    /// Refused before entering the pool
+    Rejected(String),
+}
+
+/// A traced event with the unix timestamp in milliseconds it was recorded at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    /// Unix timestamp in milliseconds
+    pub timestamp: u64,
+    /// What happened to the tx
+    pub event: TxEvent,
+}
+
+/// Bounded lifecycle traces of the txs registered with `watch` and of a deterministic sample of
+/// all the txs, picked by hash so every event of a sampled tx is recorded.
+///
+/// The watched and the sampled traces are kept apart, a burst of sampled txs never pushes out
+/// the trace of a watched one. Whether a tx is traced at all is checked without locking.
+pub(crate) struct TxTracer {
+    clock: Arc<dyn Clock>,
+    sample_rate: f64,
+    // set once a tx is watched, until then only the sampled txs may be traced
+    watching: AtomicBool,
+    traces: Mutex<Traces>,
+}
+
+#[derive(Default)]
+struct Traces {
+    watched: LinkedHashMap<Byte32, VecDeque<TraceEvent>>,
+    sampled: LinkedHashMap<Byte32, VecDeque<TraceEvent>>,
+}
+
+impl TxTracer {
+    pub(crate) fn new(clock: Arc<dyn Clock>, sample_rate: f64) -> Self {
+        TxTracer {
+            clock,
+            sample_rate,
+            watching: AtomicBool::new(false),
+            traces: Mutex::new(Traces::default()),
+        }
+    }
+
+    /// Traces the tx `hash` from now on, whether it is sampled or not, keeping the events
+    /// recorded while it was sampled
+    pub(crate) fn watch(&self, hash: Byte32) {
+        let mut traces = self.traces.lock();
+        if traces.watched.contains_key(&hash) {
+            return;
+        }
+        let events = traces.sampled.remove(&hash).unwrap_or_default();
+        insert_bounded(&mut traces.watched, hash, events);
+        self.watching.store(true, Ordering::Release);
+    }
+
+    /// Whether the events of the tx `hash` may be recorded, checked before taking the time and
+    /// the lock of a record
+    pub(crate) fn is_traced(&self, hash: &Byte32) -> bool {
+        self.watching.load(Ordering::Acquire) || self.is_sampled(hash)
+    }
+
+    /// Records `event` if the tx `hash` is watched or sampled
+    pub(crate) fn record(&self, hash: &Byte32, event: TxEvent) {
+        if !self.is_traced(hash) {
+            return;
+        }
+        let event = TraceEvent {
+            timestamp: self.clock.now_ms(),
+            event,
+        };
+        let mut traces = self.traces.lock();
+        let Traces { watched, sampled } = &mut *traces;
+        if let Some(events) = watched.get_mut(hash).or_else(|| sampled.get_mut(hash)) {
+            if events.len() == MAX_TRACE_EVENTS {
+                events.pop_front();
+            }
+            events.push_back(event);
+        } else if self.is_sampled(hash) {
+            insert_bounded(sampled, hash.clone(), VecDeque::from(vec![event]));
+        }
+    }
+
+    /// The events recorded for the tx `hash`, oldest first, `None` if it is not traced
+    pub(crate) fn get(&self, hash: &Byte32) -> Option<Vec<TraceEvent>> {
+        let traces = self.traces.lock();
+        traces
+            .watched
+            .get(hash)
+            .or_else(|| traces.sampled.get(hash))
+            .map(|events| events.iter().cloned().collect())
+    }
+
+    fn is_sampled(&self, hash: &Byte32) -> bool {
+        if self.sample_rate <= 0.0 {
+            return false;
+        }
+        let mut prefix = [0u8; 4];
+        prefix.copy_from_slice(&hash.as_slice()[..4]);
+        (u32::from_le_bytes(prefix) as f64) < self.sample_rate * (u32::MAX as f64 + 1.0)
+    }
+}
+
+// drops the oldest trace to make room for a new one
+fn insert_bounded(
+    traces: &mut LinkedHashMap<Byte32, VecDeque<TraceEvent>>,
+    hash: Byte32,
+    events: VecDeque<TraceEvent>,
+) {
+    if traces.len() == MAX_TRACED_TXS {
+        traces.pop_front();
+    }
+    traces.insert(hash, events);
+}
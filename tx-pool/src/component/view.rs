@@ -0,0 +1,100 @@
+use crate::clock::Clock;
+use crate::component::entry::TxEntry;
+use crate::error::ViewError;
+use ckb_types::{
+    core::{
+        tx_pool::{TxEntryInfo, TxPoolIds},
+        TransactionView,
+    },
+    packed::Byte32,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A read-only copy of the pool entries, published at a version of the pool map.
+///
+/// A copy is only taken when the pool changed since the last one was published, every view taken
+/// in between shares it.
+pub(crate) struct PublishedPool {
+    pub(crate) version: u64,
+    pub(crate) ids: TxPoolIds,
+    pub(crate) entries: HashMap<Byte32, TxEntry>,
+}
+
+/// A read-only view of the pool pinned at a mutation sequence number, see
+/// `TxPool::consistent_view`.
+///
+/// Every answer of a view reflects the pool as it was when the view was taken, whatever
+/// happened to the pool since. A view refuses to answer once it is older than its max age.
+#[derive(Clone)]
+pub struct PoolView {
+    published: Arc<PublishedPool>,
+    created_at: u64,
+    max_age: u64,
+    clock: Arc<dyn Clock>,
+}
+
+impl PoolView {
+    pub(crate) fn new(published: Arc<PublishedPool>, max_age: u64, clock: Arc<dyn Clock>) -> Self {
+        PoolView {
+            published,
+            created_at: clock.now_ms(),
+            max_age,
+            clock,
+        }
+    }
+
+    pub(crate) fn published(&self) -> &Arc<PublishedPool> {
+        &self.published
+    }
+
+    /// The pool mutation sequence number the view was taken at
+    pub fn seq(&self) -> u64 {
+        self.published.ids.seq
+    }
+
+    /// Number of txs in the view
+    pub fn len(&self) -> usize {
+        self.published.entries.len()
+    }
+
+    /// Whether the pool was empty when the view was taken
+    pub fn is_empty(&self) -> bool {
+        self.published.entries.is_empty()
+    }
+
+    /// Whether the view is older than its max age
+    pub fn is_expired(&self) -> bool {
+        self.check_age().is_err()
+    }
+
+    /// Same as `TxPool::get_ids` at the time the view was taken
+    pub fn get_ids(&self) -> Result<&TxPoolIds, ViewError> {
+        self.check_age()?;
+        Ok(&self.published.ids)
+    }
+
+    /// The entry info of the tx `hash`, `None` if it was not in the pool
+    pub fn get_entry_info(&self, hash: &Byte32) -> Result<Option<TxEntryInfo>, ViewError> {
+        self.check_age()?;
+        Ok(self.published.entries.get(hash).map(TxEntry::to_info))
+    }
+
+    /// The tx `hash`, `None` if it was not in the pool
+    pub fn get_transaction(&self, hash: &Byte32) -> Result<Option<TransactionView>, ViewError> {
+        self.check_age()?;
+        Ok(self
+            .published
+            .entries
+            .get(hash)
+            .map(|entry| entry.transaction().clone()))
+    }
+
+    fn check_age(&self) -> Result<(), ViewError> {
+        let age = self.clock.now_ms().saturating_sub(self.created_at);
+        if age > self.max_age {
+            return Err(ViewError::Expired(self.seq(), age));
+        }
+        Ok(())
+    }
+}
@@ -35,6 +35,51 @@ impl_error_conversion_with_kind!(
 
 impl_error_conversion_with_adaptor!(BlockAssemblerError, InternalError, Error);
 
+/// The error type for updating tx-pool thresholds at runtime
+#[derive(Error, Debug, PartialEq, Clone, Eq)]
+pub enum ThresholdsError {
+    /// The field shapes the in-pool structures and can only be changed by a restart
+    #[error("Structural field {0} can not be changed at runtime")]
+    Structural(String),
+}
+
+/// The error type for tx-pool index inconsistencies, see `TxPool::verify_integrity`
+#[derive(Error, Debug, PartialEq, Clone, Eq)]
+pub enum IntegrityError {
+    /// An input of a live entry is not indexed to it
+    #[error("Input {0} of entry {1} is not indexed")]
+    MissingInput(String, String),
+    /// The input index points to an entry which is gone or does not spend the out point
+    #[error("Input {0} is indexed to entry {1} which does not spend it")]
+    StaleInput(String, String),
+}
+
+/// The error type for building a tx-pool, see `TxPoolBuilder::build`
+#[derive(Error, Debug, PartialEq, Clone, Eq)]
+pub enum BuildError {
+    /// An initial entry is given twice
+    #[error("Initial entry {0} is duplicated")]
+    DuplicatedEntry(String),
+    /// An initial entry is refused by the pool, e.g. its parents come after it
+    #[error("Initial entry {0} is rejected: {1}")]
+    RejectedEntry(String, String),
+    /// The indexes built from the initial entries are inconsistent
+    #[error(transparent)]
+    Integrity(#[from] IntegrityError),
+}
+
+/// The error type for the reads of a pinned pool view, see `TxPool::consistent_view`
+#[derive(Error, Debug, PartialEq, Clone, Eq)]
+pub enum ViewError {
+    /// The view is older than the max age, a new one has to be taken
+    #[error("View at sequence {0} expired, taken {1} ms ago")]
+    Expired(u64, u64),
+    /// No view is kept at the sequence number, the pool changed since and the views taken at it
+    /// expired or were dropped
+    #[error("No view is pinned at sequence {0}")]
+    NotPinned(u64),
+}
+
 pub(crate) fn handle_try_send_error<T>(error: TrySendError<T>) -> (T, OtherError) {
     let e = OtherError::new(format!("TrySendError {error}"));
     let m = match error {
@@ -4,6 +4,7 @@
 pub mod block_assembler;
 mod callback;
 mod chunk_process;
+pub mod clock;
 mod component;
 pub mod error;
 mod persisted;
@@ -14,8 +15,25 @@ pub mod service;
 mod util;
 
 pub use ckb_jsonrpc_types::BlockTemplate;
+pub use component::changelog::{PoolChange, PoolChanges, RemoveReason};
+pub use component::chunk::VerifyingTx;
+pub use component::conflict_cache::ConflictCacheStats;
+pub use component::diagnosis::{Blocker, Diagnosis, PoolDiagnosis, PoolStage};
+pub use component::double_spend::DoubleSpendAttempts;
 pub use component::entry::TxEntry;
-pub use pool::TxPool;
+pub use component::eviction::EvictionSummary;
+pub use component::fee_flow::{FeeFlow, FeeFlowReport};
+pub use component::latency::{LatencyHistogram, LATENCY_BUCKETS_MS};
+pub use component::rate_limit::PeerAdmissionStats;
+pub use component::recent_reject::RejectRecord;
+pub use component::replacement::ReplaceSummary;
+pub use component::resubmit::{ResubmitEntry, ResubmitReport};
+pub use component::trace::{TraceEvent, TxEvent};
+pub use component::view::PoolView;
+pub use pool::{
+    BlocksPreview, CommitSimulation, CommittedInfo, PackageStats, PoolDiff, PreviewBlock,
+    SampleWeighting, TxPool, TxPoolBuilder,
+};
 pub use process::PlugTarget;
 pub use service::{TxPoolController, TxPoolServiceBuilder};
 pub use tokio::sync::RwLock as TokioRwLock;
@@ -2,37 +2,215 @@
 extern crate rustc_hash;
 extern crate slab;
 use super::component::{commit_txs_scanner::CommitTxsScanner, TxEntry};
+use crate::block_assembler::BlockAssembler;
 use crate::callback::Callbacks;
+use crate::clock::{Clock, SystemClock};
+use crate::component::archive::EvictedArchive;
+use crate::component::changelog::{PoolChanges, RemoveReason};
+use crate::component::committed::{CommittedEntries, CommittedEntry};
+use crate::component::conflict_cache::{ConflictCache, ConflictCacheStats, CONFLICT_CACHE_SIZE};
+use crate::component::diagnosis::{Blocker, Diagnosis, PoolDiagnosis, PoolStage};
+use crate::component::double_spend::DoubleSpendAttempts;
+use crate::component::eviction::{eviction_policy, EvictionPolicy, EvictionSummary};
+use crate::component::fee_flow::{FeeFlowReport, FeeFlowTracker};
+use crate::component::latency::LatencyHistogram;
+use crate::component::links::Relation;
 use crate::component::pool_map::{PoolEntry, PoolMap, Status};
-use crate::component::recent_reject::RecentReject;
-use crate::error::Reject;
+use crate::component::rebroadcast::RebroadcastSchedule;
+use crate::component::recent_reject::{RecentReject, RejectRecord};
+use crate::component::replacement::{ReplaceSummary, ReplacementLedger, REPLACEMENT_LEDGER_SIZE};
+use crate::component::replay::{ReplayEvent, ReplayLog};
+use crate::component::resolve_cache::ResolveCache;
+use crate::component::resubmit::{ResubmitEntry, ResubmitList};
+use crate::component::template_refresh::TemplateRefresh;
+use crate::component::trace::{TraceEvent, TxEvent, TxTracer};
+use crate::component::view::{PoolView, PublishedPool};
+use crate::error::{BuildError, IntegrityError, Reject, ThresholdsError, ViewError};
 use crate::pool_cell::PoolCell;
-use ckb_app_config::TxPoolConfig;
-use ckb_logger::{debug, error, warn};
+use crate::util::{
+    calculate_tx_fee, check_entry_min_fee, check_min_fee, time_relative_verify, verify_rtx,
+};
+use ckb_app_config::{ConsumedDepPolicy, StatusQuota, TxPoolConfig};
+use ckb_chain_spec::consensus::Consensus;
+use ckb_hash::new_blake2b;
+use ckb_jsonrpc_types::TxPoolEntries;
+use ckb_logger::{debug, error, info, trace, warn};
 use ckb_snapshot::Snapshot;
 use ckb_store::ChainStore;
-use ckb_types::core::CapacityError;
+use ckb_traits::HeaderFieldsProvider;
+use ckb_types::core::{error::OutPointError, CapacityError};
 use ckb_types::{
     core::{
-        cell::{resolve_transaction, OverlayCellChecker, OverlayCellProvider, ResolvedTransaction},
-        tx_pool::{TxPoolEntryInfo, TxPoolIds},
-        Capacity, Cycle, TransactionView, UncleBlockView,
+        cell::{
+            resolve_transaction, OverlayCellChecker, OverlayCellProvider, ResolvedTransaction,
+            TransactionsProvider,
+        },
+        tx_pool::{
+            get_transaction_weight_with_ratio, AnnotatedTxId, Origin, PoolTxFilter, RBFRule,
+            StatusQuotaUsage, TxPoolAnnotatedIds, TxPoolEntryInfo, TxPoolIds,
+        },
+        BlockNumber, BlockView, Capacity, Cycle, FeeRate, TransactionView, UncleBlockView,
     },
-    packed::{Byte32, ProposalShortId},
+    packed::{self, Byte32, OutPoint, ProposalShortId},
+};
+use ckb_util::Mutex;
+use ckb_verification::{
+    cache::{CacheEntry, Completed, ForkContext, PrewarmEntry},
+    TxVerifyEnv,
 };
 use lru::LruCache;
-use std::collections::HashSet;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::cmp::Ordering;
+use std::collections::{HashSet, VecDeque};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 const COMMITTED_HASH_CACHE_SIZE: usize = 100_000;
 const MAX_REPLACEMENT_CANDIDATES: usize = 100;
+// views kept for `TxPool::view_at`, on top of the expiry
+const MAX_PINNED_VIEWS: usize = 16;
+// time spent projecting blocks in `TxPool::preview_blocks` at most
+const MAX_PREVIEW_DURATION: Duration = Duration::from_millis(500);
+
+/// Tx-pool thresholds which can be changed without restarting the node, `None` keeps the current
+/// value
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TxPoolThresholds {
+    /// Keep the transaction pool below <max_tx_pool_size> mb
+    pub max_tx_pool_size: Option<usize>,
+    /// txs with lower fee rate than this will not be relayed or be mined
+    pub min_fee_rate: Option<FeeRate>,
+    /// txs need to pay larger fee rate than this for RBF
+    pub min_rbf_rate: Option<FeeRate>,
+    /// The expiration time for pool transactions in hours
+    pub expiry_hours: Option<u8>,
+    /// max ancestors size limit for a single tx, structural, only the current value is accepted
+    pub max_ancestors_count: Option<usize>,
+}
+
+/// How close the tx-pool is to its size limit, see `TxPool::saturation`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PoolSaturation {
+    /// Total size of the pool txs relative to `max_tx_pool_size`, or their number relative to
+    /// `max_tx_pool_entries` if higher, from 0.0 to 1.0
+    pub ratio: f64,
+    /// The lowest fee rate a new tx can pay without being rejected or evicted right away
+    pub min_fee_rate: FeeRate,
+}
+
+/// Fee and size of a package of txs, see `TxPool::check_package`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PackageStats {
+    /// Number of txs in the package
+    pub count: usize,
+    /// Sum of the txs fees
+    pub fee: Capacity,
+    /// Sum of the txs serialized sizes
+    pub size: usize,
+    /// Fee rate of the whole package, the size is used as weight since no script is run
+    pub fee_rate: FeeRate,
+}
+
+/// A projected block, see `TxPool::preview_blocks`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PreviewBlock {
+    /// Proposed txs, committable right away, in block order
+    pub proposed: Vec<Byte32>,
+    /// Pending and gap txs, which need to be proposed first, in block order after the proposed ones
+    pub unproposed: Vec<Byte32>,
+    /// Sum of the txs fees
+    pub fee: Capacity,
+    /// Sum of the txs serialized sizes
+    pub size: usize,
+    /// Sum of the txs cycles
+    pub cycles: Cycle,
+}
+
+/// The blocks projected by `TxPool::preview_blocks`, with the snapshot tip they build on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlocksPreview {
+    /// Hash of the snapshot tip the preview was computed against
+    pub tip_hash: Byte32,
+    /// The projected blocks, the next one first
+    pub blocks: Vec<PreviewBlock>,
+}
+
+impl PreviewBlock {
+    fn push(&mut self, entry: &TxEntry, proposed: bool) {
+        let hash = entry.transaction().hash();
+        if proposed {
+            self.proposed.push(hash);
+        } else {
+            self.unproposed.push(hash);
+        }
+        self.fee = self.fee.safe_add(entry.fee).unwrap_or(self.fee);
+        self.size = self.size.saturating_add(entry.size);
+        self.cycles = self.cycles.saturating_add(entry.cycles);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.proposed.is_empty() && self.unproposed.is_empty()
+    }
+}
+
+/// The effect committing a block would have on the pool, see `TxPool::simulate_commit`
+#[derive(Clone, Debug)]
+pub struct CommitSimulation {
+    /// Hashes of the pool entries committed by the block, in block order
+    pub committed: Vec<Byte32>,
+    /// Hashes of the pool entries which would be rejected since the block consumes their inputs
+    /// or cell deps, with their descendants
+    pub conflicted: Vec<(Byte32, Reject)>,
+    /// Total size of the pool txs left after the block
+    pub total_tx_size: usize,
+    /// Total cycles of the pool txs left after the block
+    pub total_tx_cycles: Cycle,
+}
+
+/// The block a recently committed tx was committed in, see `TxPool::committed_info`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommittedInfo {
+    /// Hash of the committed tx
+    pub tx_hash: Byte32,
+    /// Hash of the committing block
+    pub block_hash: Byte32,
+    /// Number of the committing block
+    pub block_number: BlockNumber,
+}
+
+/// Difference between the pool and the tx hashes listed by another pool, see
+/// `TxPool::diff_against`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoolDiff {
+    /// Listed hashes which are not in the pool, in the listed order
+    pub missing: Vec<Byte32>,
+    /// Hashes of the pool txs which are not listed, sorted
+    pub extra: Vec<Byte32>,
+    /// Number of listed hashes which are in the pool
+    pub common: usize,
+    /// Total size of the extra txs
+    pub extra_size: usize,
+    /// Total fee of the extra txs
+    pub extra_fee: Capacity,
+}
+
+/// How entries are weighted by `TxPool::sample_entries`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleWeighting {
+    /// Every entry has the same chance to be drawn
+    Uniform,
+    /// Entries are drawn proportionally to their fee
+    ByFee,
+    /// Entries are drawn proportionally to their size
+    BySize,
+}
 
 /// Tx-pool implementation
 pub struct TxPool {
     pub(crate) config: TxPoolConfig,
     pub(crate) pool_map: PoolMap,
-    /// cache for committed transactions hash
-    pub(crate) committed_txs_hash_cache: LruCache<ProposalShortId, Byte32>,
+    /// cache for committed transactions hash, with the committing block
+    pub(crate) committed_txs_hash_cache: LruCache<ProposalShortId, CommittedInfo>,
     // sum of all tx_pool tx's virtual sizes.
     pub(crate) total_tx_size: usize,
     // sum of all tx_pool tx's cycles.
@@ -41,25 +219,280 @@ pub struct TxPool {
     pub(crate) snapshot: Arc<Snapshot>,
     /// record recent reject
     pub recent_reject: Option<RecentReject>,
+    // set once a write to recent_reject failed and it was disabled
+    pub(crate) recent_reject_failed: bool,
+    // set while the pool is read-only for maintenance
+    pub(crate) read_only: bool,
     // expiration milliseconds,
     pub(crate) expiry: u64,
+    /// local txs waiting to be re-announced
+    pub(crate) rebroadcast: RebroadcastSchedule,
+    /// txs displaced by RBF, keyed by the replacement tx
+    pub(crate) replacements: ReplacementLedger,
+    /// rejections of txs double spending in-pool entries, checked under the read lock
+    pub(crate) conflict_cache: Mutex<ConflictCache>,
+    /// source of the current time for entry timestamps, expiry and re-broadcast
+    pub(crate) clock: Arc<dyn Clock>,
+    /// fee rate floor raised by the last eviction above it, with the time it was raised
+    pub(crate) fee_floor: Option<(FeeRate, u64)>,
+    /// best paying txs evicted by the size limit, waiting for room to be re-admitted
+    pub(crate) archive: EvictedArchive,
+    /// picks the txs to evict when the pool is over its limits
+    pub(crate) eviction: Box<dyn EvictionPolicy>,
+    /// fees of the entries committed or lost, by outcome
+    pub(crate) fee_flow: FeeFlowTracker,
+    /// improvement of the last block template by the txs proposed since, updated by the block
+    /// assembler under the read lock
+    pub(crate) template_refresh: Mutex<TemplateRefresh>,
+    /// committed entries kept queryable until confirmed enough
+    pub(crate) committed: CommittedEntries,
+    /// local txs which expired, waiting to be submitted again
+    pub(crate) resubmit: ResubmitList,
+    /// recent tx resolutions, looked up under the read lock
+    pub(crate) resolve_cache: Mutex<ResolveCache>,
+    /// the log of the pool inputs, see `TxPoolConfig::replay_log`
+    pub(crate) replay_log: Option<ReplayLog>,
+    /// the views taken recently, newest last, see `consistent_view`
+    pub(crate) views: Mutex<VecDeque<PoolView>>,
 }
 
-impl TxPool {
-    /// Create new TxPool
-    pub fn new(config: TxPoolConfig, snapshot: Arc<Snapshot>) -> TxPool {
-        let recent_reject = Self::build_recent_reject(&config);
-        let expiry = config.expiry_hours as u64 * 60 * 60 * 1000;
-        TxPool {
-            pool_map: PoolMap::new(config.max_ancestors_count),
-            committed_txs_hash_cache: LruCache::new(COMMITTED_HASH_CACHE_SIZE),
+/// Builds a `TxPool`, the components which are not set are built from the config the same way
+/// `TxPool::new` does
+pub struct TxPoolBuilder {
+    config: TxPoolConfig,
+    snapshot: Arc<Snapshot>,
+    // `None` opens the store configured by `recent_reject`
+    recent_reject: Option<Option<RecentReject>>,
+    clock: Arc<dyn Clock>,
+    expiry: Option<u64>,
+    entries: Vec<(TxEntry, Status)>,
+    committed_hash_cache_size: usize,
+    replacement_ledger_size: usize,
+    conflict_cache_size: usize,
+    // `None` builds the policy chosen by `eviction_strategy`
+    eviction: Option<Box<dyn EvictionPolicy>>,
+}
+
+impl TxPoolBuilder {
+    /// Creates a builder with the default components
+    pub fn new(config: TxPoolConfig, snapshot: Arc<Snapshot>) -> Self {
+        TxPoolBuilder {
+            config,
+            snapshot,
+            recent_reject: None,
+            clock: Arc::new(SystemClock),
+            expiry: None,
+            entries: Vec::new(),
+            committed_hash_cache_size: COMMITTED_HASH_CACHE_SIZE,
+            replacement_ledger_size: REPLACEMENT_LEDGER_SIZE,
+            conflict_cache_size: CONFLICT_CACHE_SIZE,
+            eviction: None,
+        }
+    }
+
+    /// Uses a prebuilt reject store, or none at all, instead of opening the configured one
+    pub(crate) fn recent_reject(mut self, recent_reject: Option<RecentReject>) -> Self {
+        self.recent_reject = Some(recent_reject);
+        self
+    }
+
+    /// Replaces the system clock the pool reads the time from
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Overrides the expiry derived from `expiry_hours`, in milliseconds
+    pub fn expiry(mut self, expiry: u64) -> Self {
+        self.expiry = Some(expiry);
+        self
+    }
+
+    /// Entries the pool starts with, e.g. restored from the persisted data, parents must come
+    /// before their children
+    pub(crate) fn entries(mut self, entries: Vec<(TxEntry, Status)>) -> Self {
+        self.entries = entries;
+        self
+    }
+
+    /// Capacity of the cache of recently committed tx hashes
+    pub fn committed_hash_cache_size(mut self, size: usize) -> Self {
+        self.committed_hash_cache_size = size;
+        self
+    }
+
+    /// Capacity of the ledger of txs displaced by RBF
+    pub fn replacement_ledger_size(mut self, size: usize) -> Self {
+        self.replacement_ledger_size = size;
+        self
+    }
+
+    /// Capacity of the cache of rejected double spends
+    pub fn conflict_cache_size(mut self, size: usize) -> Self {
+        self.conflict_cache_size = size;
+        self
+    }
+
+    /// Uses `policy` to pick the txs to evict instead of the one chosen by `eviction_strategy`
+    pub(crate) fn eviction_policy(mut self, policy: Box<dyn EvictionPolicy>) -> Self {
+        self.eviction = Some(policy);
+        self
+    }
+
+    /// Builds the pool, the initial entries go through the same checks as the ones submitted
+    /// later and are counted in the size and cycles statistics, then the indexes they produced are
+    /// verified before the pool is returned.
+    pub fn build(self) -> Result<TxPool, BuildError> {
+        let TxPoolBuilder {
+            config,
+            snapshot,
+            recent_reject,
+            clock,
+            expiry,
+            entries,
+            committed_hash_cache_size,
+            replacement_ledger_size,
+            conflict_cache_size,
+            eviction,
+        } = self;
+
+        let recent_reject = recent_reject.unwrap_or_else(|| TxPool::build_recent_reject(&config));
+        let expiry = expiry.unwrap_or(config.expiry_hours as u64 * 60 * 60 * 1000);
+        let rebroadcast = RebroadcastSchedule::new(
+            config.rebroadcast_min_age_secs.saturating_mul(1000),
+            config.rebroadcast_batch_size,
+        );
+        let archive = EvictedArchive::new(config.evicted_archive_size);
+        let resubmit = ResubmitList::new(
+            config.resubmit_list_size,
+            config
+                .resubmit_retention_hours
+                .saturating_mul(60 * 60 * 1000),
+        );
+        let template_refresh = TemplateRefresh::new(config.template_refresh_fee);
+        let resolve_cache = ResolveCache::new(config.resolve_cache_size);
+        let eviction = eviction.unwrap_or_else(|| eviction_policy(&config));
+        let replay_log = ReplayLog::open(&config);
+        // a package which can't fit in one block could never be committed
+        let consensus = snapshot.consensus();
+        let max_package_size = config
+            .max_package_size
+            .unwrap_or(usize::MAX)
+            .min(consensus.max_block_bytes() as usize);
+        let max_package_cycles = config
+            .max_package_cycles
+            .unwrap_or(Cycle::MAX)
+            .min(consensus.max_block_cycles());
+        let mut tx_pool = TxPool {
+            pool_map: PoolMap::new(config.max_ancestors_count)
+                .with_package_limits(max_package_size, max_package_cycles)
+                .with_max_header_deps(config.max_header_deps)
+                .with_bytes_per_cycles(config.bytes_per_cycles)
+                .with_tracer(TxTracer::new(Arc::clone(&clock), config.trace_sample_rate))
+                .with_clock(Arc::clone(&clock)),
+            committed_txs_hash_cache: LruCache::new(committed_hash_cache_size),
             total_tx_size: 0,
             total_tx_cycles: 0,
             config,
             snapshot,
             recent_reject,
+            recent_reject_failed: false,
+            read_only: false,
             expiry,
+            rebroadcast,
+            replacements: ReplacementLedger::new(replacement_ledger_size),
+            conflict_cache: Mutex::new(ConflictCache::new(conflict_cache_size)),
+            archive,
+            clock,
+            fee_floor: None,
+            eviction,
+            fee_flow: FeeFlowTracker::default(),
+            template_refresh: Mutex::new(template_refresh),
+            committed: CommittedEntries::default(),
+            resubmit,
+            resolve_cache: Mutex::new(resolve_cache),
+            replay_log,
+            views: Mutex::new(VecDeque::new()),
+        };
+        tx_pool.record_replay(|tx_pool| ReplayEvent::Start(Box::new(tx_pool.config.clone())));
+
+        for (entry, status) in entries {
+            let id = entry.proposal_short_id();
+            let (size, cycles) = (entry.size, entry.cycles);
+            match tx_pool.pool_map.add_entry(entry, status) {
+                Ok(true) => tx_pool.update_statics_for_add_tx(size, cycles),
+                Ok(false) => return Err(BuildError::DuplicatedEntry(id.to_string())),
+                Err(reject) => {
+                    return Err(BuildError::RejectedEntry(
+                        id.to_string(),
+                        reject.to_string(),
+                    ))
+                }
+            }
         }
+        tx_pool.verify_integrity()?;
+        Ok(tx_pool)
+    }
+}
+
+impl TxPool {
+    /// Create new TxPool
+    pub fn new(config: TxPoolConfig, snapshot: Arc<Snapshot>) -> TxPool {
+        TxPoolBuilder::new(config, snapshot)
+            .build()
+            .expect("a tx-pool without initial entries is consistent")
+    }
+
+    /// The current unix timestamp in milliseconds, as seen by the pool clock
+    pub(crate) fn now_ms(&self) -> u64 {
+        self.clock.now_ms()
+    }
+
+    /// The config the tx-pool is currently running with
+    pub fn config(&self) -> &TxPoolConfig {
+        &self.config
+    }
+
+    /// Applies the new thresholds, evicting the txs which no longer fit into the pool size or
+    /// expiry limits, and returns what a lowered size limit evicted.
+    ///
+    /// Nothing is changed if the thresholds touch a structural field.
+    pub fn update_thresholds(
+        &mut self,
+        new: TxPoolThresholds,
+        callbacks: &Callbacks,
+    ) -> Result<EvictionSummary, ThresholdsError> {
+        if let Some(max_ancestors_count) = new.max_ancestors_count {
+            if max_ancestors_count != self.config.max_ancestors_count {
+                return Err(ThresholdsError::Structural(
+                    "max_ancestors_count".to_string(),
+                ));
+            }
+        }
+
+        self.record_replay(|_| ReplayEvent::Thresholds(new.clone()));
+
+        if let Some(min_fee_rate) = new.min_fee_rate {
+            self.config.min_fee_rate = min_fee_rate;
+        }
+        if let Some(min_rbf_rate) = new.min_rbf_rate {
+            self.config.min_rbf_rate = min_rbf_rate;
+        }
+        if let Some(expiry_hours) = new.expiry_hours {
+            self.config.expiry_hours = expiry_hours;
+            self.expiry = expiry_hours as u64 * 60 * 60 * 1000;
+            self.remove_expired(callbacks);
+        }
+        if let Some(max_tx_pool_size) = new.max_tx_pool_size {
+            self.config.max_tx_pool_size = max_tx_pool_size;
+            return Ok(self.limit_size(callbacks));
+        }
+        Ok(EvictionSummary::default())
+    }
+
+    /// Checks the in-pool indexes against the live entries
+    pub fn verify_integrity(&self) -> Result<(), IntegrityError> {
+        self.pool_map.verify_inputs_index()
     }
 
     /// Tx-pool owned snapshot, it may not consistent with chain cause tx-pool update snapshot asynchronously
@@ -67,11 +500,32 @@ impl TxPool {
         &self.snapshot
     }
 
+    /// Returns true if the pool snapshot is more than `max_gap` blocks behind `chain_tip`, the txs
+    /// are then resolved against outdated cells
+    pub fn is_snapshot_stale(&self, chain_tip: BlockNumber, max_gap: u64) -> bool {
+        chain_tip.saturating_sub(self.snapshot.tip_number()) > max_gap
+    }
+
     /// Makes a clone of the `Arc<Snapshot>`
     pub(crate) fn cloned_snapshot(&self) -> Arc<Snapshot> {
         Arc::clone(&self.snapshot)
     }
 
+    /// Swaps the snapshot, the cached resolutions against the old one are dropped
+    pub(crate) fn set_snapshot(&mut self, snapshot: Arc<Snapshot>) {
+        self.snapshot = snapshot;
+        self.resolve_cache.lock().clear();
+    }
+
+    /// Median time of the past blocks ending at the snapshot tip, the MTP used by consensus to
+    /// evaluate timestamp-based since for txs that would be packaged on top of the tip
+    pub fn median_time_past(&self) -> u64 {
+        self.snapshot.block_median_time(
+            &self.snapshot.tip_hash(),
+            self.snapshot.consensus().median_time_block_count(),
+        )
+    }
+
     fn get_by_status(&self, status: Status) -> Vec<&PoolEntry> {
         self.pool_map.get_by_status(status)
     }
@@ -87,6 +541,87 @@ impl TxPool {
         self.total_tx_cycles += cycles;
     }
 
+    /// Returns how full the pool is along with the fee rate a new tx has to pay to stay in it.
+    ///
+    /// The floor starts from `dynamic_min_fee_rate`, once the pool reaches its size or entry limit
+    /// it rises to the fee rate of the next entry to evict. Both come from maintained counters and
+    /// indexes, so the relay layer can check it before fetching tx bodies from peers.
+    pub fn saturation(&self) -> PoolSaturation {
+        let ratio_of = |used: usize, limit: usize| {
+            if limit == 0 {
+                1.0
+            } else {
+                (used as f64 / limit as f64).min(1.0)
+            }
+        };
+        let ratio = ratio_of(self.total_tx_size, self.config.max_tx_pool_size).max(ratio_of(
+            self.pool_map.size(),
+            self.config.max_tx_pool_entries,
+        ));
+        PoolSaturation {
+            ratio,
+            min_fee_rate: self.full_fee_rate_floor(),
+        }
+    }
+
+    // the dynamic min fee rate, raised to the fee rate of the next entry to evict once the pool
+    // reaches one of its limits
+    fn full_fee_rate_floor(&self) -> FeeRate {
+        let min_fee_rate = self.dynamic_min_fee_rate();
+        if self.total_tx_size < self.config.max_tx_pool_size
+            && self.pool_map.size() < self.config.max_tx_pool_entries
+        {
+            return min_fee_rate;
+        }
+        self.pool_map
+            .min_evict_fee_rate()
+            .map_or(min_fee_rate, |fee_rate| min_fee_rate.max(fee_rate))
+    }
+
+    /// Rejects a tx paying `fee` for `weight` when the pool is at its entry limit, unless it pays
+    /// more than the entry which would be evicted in its place.
+    ///
+    /// The evict floor is a package fee rate over weight, a new tx has no descendants in the pool
+    /// so its package fee rate is its own fee rate over its weight.
+    pub(crate) fn check_entry_limit(&self, fee: Capacity, weight: u64) -> Result<(), Reject> {
+        if self.pool_map.size() < self.config.max_tx_pool_entries {
+            return Ok(());
+        }
+        let floor = self.full_fee_rate_floor();
+        let fee_rate = FeeRate::calculate(fee, weight);
+        if fee_rate <= floor {
+            return Err(Reject::Full(format!(
+                "the tx-pool is at its entry limit {}, the fee_rate for this transaction is: {}, \
+                 expect more than {}",
+                self.config.max_tx_pool_entries, fee_rate, floor
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the configured min fee rate, or the fee rate floor raised by evictions if higher.
+    ///
+    /// Each eviction raises the floor to the fee rate of the evicted package, then the floor
+    /// halves every `min_fee_decay_half_life_s` seconds once the pressure subsides.
+    pub fn dynamic_min_fee_rate(&self) -> FeeRate {
+        let base = self.config.min_fee_rate;
+        let half_life_ms = self.config.min_fee_decay_half_life_s.saturating_mul(1000);
+        match self.fee_floor {
+            Some((floor, raised_at)) if half_life_ms > 0 => {
+                let elapsed = self.now_ms().saturating_sub(raised_at);
+                let decay = 0.5f64.powf(elapsed as f64 / half_life_ms as f64);
+                FeeRate::from_u64((floor.as_u64() as f64 * decay) as u64).max(base)
+            }
+            _ => base,
+        }
+    }
+
+    fn raise_fee_floor(&mut self, fee_rate: FeeRate) {
+        if fee_rate > self.dynamic_min_fee_rate() {
+            self.fee_floor = Some((fee_rate, self.now_ms()));
+        }
+    }
+
     /// Check whether tx-pool enable RBF
     pub fn enable_rbf(&self) -> bool {
         self.config.min_rbf_rate > self.config.min_fee_rate
@@ -97,8 +632,38 @@ impl TxPool {
         if !self.enable_rbf() {
             return None;
         }
-        let entry = vec![self.get_pool_entry(&tx.proposal_short_id()).unwrap()];
-        self.calculate_min_replace_fee(&entry, tx.size)
+        let entry = self.get_pool_entry(&tx.proposal_short_id())?;
+        self.calculate_min_replace_fee(&[entry], tx.size)
+    }
+
+    /// The fee a replacement of `candidate_size` bytes must pay on top of the fee of the pool tx
+    /// `target` to replace it, its descendants are evicted without being paid for, see `check_rbf`.
+    /// `None` if RBF is disabled, `target` is not in the pool or can't be replaced since it or one
+    /// of its descendants is proposed.
+    pub fn fee_delta_to_replace(
+        &self,
+        target: &ProposalShortId,
+        candidate_size: usize,
+    ) -> Option<Capacity> {
+        if !self.enable_rbf() {
+            return None;
+        }
+        let entry = self.get_pool_entry(target)?;
+        // Rule #6 of `check_rbf`
+        let replaceable =
+            |entry: &PoolEntry| [Status::Pending, Status::Gap].contains(&entry.status);
+        if !replaceable(entry)
+            || !self
+                .pool_map
+                .calc_descendants(target)
+                .iter()
+                .filter_map(|id| self.get_pool_entry(id))
+                .all(replaceable)
+        {
+            return None;
+        }
+        let min_replace_fee = self.calculate_min_replace_fee(&[entry], candidate_size)?;
+        min_replace_fee.safe_sub(entry.inner.fee).ok()
     }
 
     /// min_replace_fee = sum(replaced_txs.fee) + extra_rbf_fee
@@ -147,144 +712,1322 @@ impl TxPool {
     }
 
     /// Add tx with pending status
-    /// If did have this value present, false is returned.
-    pub(crate) fn add_pending(&mut self, entry: TxEntry) -> Result<bool, Reject> {
-        self.pool_map.add_entry(entry, Status::Pending)
+    /// Returns the short id the entry is indexed by, `None` if the pool already had it.
+    pub(crate) fn add_pending(
+        &mut self,
+        entry: TxEntry,
+    ) -> Result<Option<ProposalShortId>, Reject> {
+        self.add_entry(entry, Status::Pending)
     }
 
     /// Add tx which proposed but still uncommittable to gap
-    pub(crate) fn add_gap(&mut self, entry: TxEntry) -> Result<bool, Reject> {
-        self.pool_map.add_entry(entry, Status::Gap)
+    pub(crate) fn add_gap(&mut self, entry: TxEntry) -> Result<Option<ProposalShortId>, Reject> {
+        self.add_entry(entry, Status::Gap)
+    }
+
+    /// Add tx with proposed status
+    ///
+    /// With `check_proposal`, the tx is rejected unless the snapshot's recent proposals contain it,
+    /// callers which already derived the status from the proposals can skip the check.
+    pub(crate) fn add_proposed(
+        &mut self,
+        entry: TxEntry,
+        check_proposal: bool,
+    ) -> Result<Option<ProposalShortId>, Reject> {
+        if check_proposal
+            && !self
+                .snapshot
+                .proposals()
+                .contains_proposed(&entry.proposal_short_id())
+        {
+            return Err(Reject::NotProposed(entry.transaction().hash()));
+        }
+        self.add_entry(entry, Status::Proposed)
+    }
+
+    fn add_entry(
+        &mut self,
+        entry: TxEntry,
+        status: Status,
+    ) -> Result<Option<ProposalShortId>, Reject> {
+        let id = entry.proposal_short_id();
+        self.pool_map
+            .add_entry(entry, status)
+            .map(|inserted| inserted.then_some(id))
+    }
+
+    /// Returns true if the tx-pool contains a tx with specified id.
+    pub(crate) fn contains_proposal_id(&self, id: &ProposalShortId) -> bool {
+        self.pool_map.get_by_id(id).is_some()
+    }
+
+    pub(crate) fn set_entry_proposed(&mut self, short_id: &ProposalShortId) {
+        // a pending or gap entry proposed by a block improves the template as much as a tx
+        // submitted proposed
+        if let Some(entry) = self.pool_map.get_by_id(short_id) {
+            if entry.status != Status::Proposed {
+                self.record_template_candidate(&entry.inner);
+            }
+        }
+        self.pool_map.set_entry(short_id, Status::Proposed)
+    }
+
+    pub(crate) fn set_entry_gap(&mut self, short_id: &ProposalShortId) {
+        self.pool_map.set_entry(short_id, Status::Gap)
+    }
+
+    /// Accounts a newly proposed entry against the last block template, returns true if the
+    /// template became worth rebuilding
+    pub(crate) fn record_template_candidate(&self, entry: &TxEntry) -> bool {
+        self.template_refresh.lock().record(entry)
+    }
+
+    /// Records that a block template holding `txs` within `txs_size_limit` bytes was just built
+    pub(crate) fn template_built(&self, txs: &[TxEntry], txs_size_limit: usize) {
+        self.template_refresh.lock().reset(txs, txs_size_limit);
+    }
+
+    /// Whether the txs proposed since the last block template pay enough above it for the
+    /// template to be rebuilt, see `template_refresh_fee`
+    pub fn is_template_dirty(&self) -> bool {
+        self.template_refresh.lock().is_dirty()
+    }
+
+    /// Returns how long in milliseconds the tx has been proposed, `None` if it is not in the pool
+    /// or not proposed
+    pub fn time_in_proposed(&self, id: &ProposalShortId) -> Option<u64> {
+        self.pool_map
+            .get_by_id(id)
+            .filter(|entry| entry.status == Status::Proposed)
+            .map(|entry| self.now_ms().saturating_sub(entry.status_since))
+    }
+
+    /// Returns tx with cycles corresponding to the id.
+    pub(crate) fn get_tx_with_cycles(
+        &self,
+        id: &ProposalShortId,
+    ) -> Option<(TransactionView, Cycle)> {
+        self.pool_map
+            .get_by_id(id)
+            .map(|entry| (entry.inner.transaction().clone(), entry.inner.cycles))
+    }
+
+    /// Keeps the tx out of the local proposals and block templates, or lets it back in, while it
+    /// stays in the pool and is still relayed. Its descendants are left out of the blocks too.
+    ///
+    /// Returns false if the tx is not in the pool.
+    pub fn set_do_not_mine(&mut self, id: &ProposalShortId, do_not_mine: bool) -> bool {
+        self.pool_map.set_do_not_mine(id, do_not_mine)
+    }
+
+    /// Batch version of `get_tx_with_cycles`, the result at each position is the one of the id at
+    /// the same position, `None` for the ids not in the pool
+    pub fn get_txs_with_cycles(
+        &self,
+        ids: &[ProposalShortId],
+    ) -> Vec<Option<(TransactionView, Cycle)>> {
+        ids.iter().map(|id| self.get_tx_with_cycles(id)).collect()
+    }
+
+    /// Returns the fee rate of the tx, or of the package made of the tx and its in-pool ancestors
+    /// if `with_ancestors` is true.
+    pub fn fee_rate_of(&self, id: &ProposalShortId, with_ancestors: bool) -> Option<FeeRate> {
+        self.pool_map.get(id).map(|entry| {
+            if with_ancestors {
+                FeeRate::calculate(entry.ancestors_fee, entry.ancestors_weight())
+            } else {
+                entry.fee_rate()
+            }
+        })
+    }
+
+    /// The in-pool ancestors of the tx with their distance, 1 for the parents, in breadth-first
+    /// order, at most `max_depth` links away if set and at most `max_traversal_entries` of them.
+    ///
+    /// Returns an empty list if the tx is not in the pool.
+    pub fn ancestors_of(
+        &self,
+        id: &ProposalShortId,
+        max_depth: Option<usize>,
+    ) -> Vec<(ProposalShortId, usize)> {
+        self.pool_map.links.walk_relatives(
+            id,
+            Relation::Parents,
+            max_depth,
+            self.config.max_traversal_entries,
+        )
+    }
+
+    /// The in-pool descendants of the tx with their distance, 1 for the children, the same way
+    /// as `ancestors_of`
+    pub fn descendants_of(
+        &self,
+        id: &ProposalShortId,
+        max_depth: Option<usize>,
+    ) -> Vec<(ProposalShortId, usize)> {
+        self.pool_map.links.walk_relatives(
+            id,
+            Relation::Children,
+            max_depth,
+            self.config.max_traversal_entries,
+        )
+    }
+
+    /// Compares two pool txs by the order the block assembler would pick them: the higher
+    /// package fee rate first, then the older tx. `Ordering::Less` means `a` goes before `b`, so
+    /// the result can be passed straight to `sort_by`.
+    ///
+    /// Returns `None` if either tx is not in the pool.
+    pub fn compare_for_mining(&self, a: &ProposalShortId, b: &ProposalShortId) -> Option<Ordering> {
+        let a = self.pool_map.get(a)?;
+        let b = self.pool_map.get(b)?;
+        Some(
+            b.as_score_key()
+                .cmp(&a.as_score_key())
+                .then_with(|| a.timestamp.cmp(&b.timestamp)),
+        )
+    }
+
+    /// Returns the txs which would become invalid if `out_point` were spent by a tx outside of
+    /// the pool, i.e. the in-pool spenders of `out_point` and all their descendants
+    pub fn dependents_of_outpoint(&self, out_point: &OutPoint) -> Vec<ProposalShortId> {
+        self.pool_map
+            .dependents_of_outpoint(out_point)
+            .into_iter()
+            .collect()
+    }
+
+    /// Summarizes how long the current entries took to resolve and verify before they were added
+    pub fn acceptance_latency_histogram(&self) -> LatencyHistogram {
+        let mut histogram = LatencyHistogram::default();
+        for entry in self.pool_map.iter() {
+            histogram.record(entry.inner.verify_latency);
+        }
+        histogram
+    }
+
+    /// The fee depth of the pool: for each distinct fee rate paid by the entries, from the highest
+    /// to the lowest, the total size of the entries paying at least that rate.
+    ///
+    /// The sizes grow as the rate decreases, the last one is the size of the whole pool.
+    pub fn fee_depth(&self) -> Vec<(FeeRate, usize)> {
+        let mut rates: Vec<_> = self
+            .pool_map
+            .iter()
+            .map(|entry| (entry.inner.fee_rate(), entry.inner.size))
+            .collect();
+        rates.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+        let mut depth: Vec<(FeeRate, usize)> = Vec::new();
+        let mut cumulative_size = 0usize;
+        for (fee_rate, size) in rates {
+            cumulative_size = cumulative_size.saturating_add(size);
+            match depth.last_mut() {
+                Some(last) if last.0 == fee_rate => last.1 = cumulative_size,
+                _ => depth.push((fee_rate, cumulative_size)),
+            }
+        }
+        depth
+    }
+
+    /// Exports the cycles and fee of the entries whose fork context is known, to prewarm the
+    /// verification cache used by the blocks committing them
+    pub fn export_verified(&self) -> Vec<PrewarmEntry> {
+        self.pool_map
+            .iter()
+            .filter_map(|entry| {
+                let entry = &entry.inner;
+                entry.fork_context.map(|fork_context| PrewarmEntry {
+                    tx_hash: entry.transaction().hash(),
+                    completed: Completed {
+                        cycles: entry.cycles,
+                        fee: entry.fee,
+                    },
+                    fork_context,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the entries whose size is within `min_bytes..=max_bytes`
+    pub fn entries_in_size_range(&self, min_bytes: usize, max_bytes: usize) -> Vec<&PoolEntry> {
+        self.pool_map
+            .iter()
+            .filter(|entry| (min_bytes..=max_bytes).contains(&entry.inner.size))
+            .collect()
+    }
+
+    /// Returns the pool membership changes after `seq`, at most `limit` of them.
+    ///
+    /// `truncated` is set if some of the changes were already dropped from the bounded log, the
+    /// caller has to rebuild its view from the full pool then.
+    pub fn changes_since(&self, seq: u64, limit: usize) -> PoolChanges {
+        self.pool_map.changelog.changes_since(seq, limit)
+    }
+
+    /// Traces the lifecycle of the tx `tx_hash` from now on, see `TxPool::trace`
+    pub fn trace_tx(&self, tx_hash: Byte32) {
+        self.pool_map.tracer.watch(tx_hash);
+    }
+
+    /// The lifecycle events recorded for the tx `tx_hash`, oldest first, `None` if it was neither
+    /// registered with `trace_tx` nor sampled.
+    ///
+    /// A trace keeps the last `MAX_TRACE_EVENTS` events. The last `MAX_TRACED_TXS` watched traces
+    /// are kept, and as many sampled ones apart from them.
+    pub fn trace(&self, tx_hash: &Byte32) -> Option<Vec<TraceEvent>> {
+        self.pool_map.tracer.get(tx_hash)
+    }
+
+    pub(crate) fn tracer(&self) -> Arc<TxTracer> {
+        Arc::clone(&self.pool_map.tracer)
+    }
+
+    pub(crate) fn record_trace(&self, tx_hash: &Byte32, event: TxEvent) {
+        self.pool_map.tracer.record(tx_hash, event);
+    }
+
+    /// The sequence number of the last pool membership change
+    pub fn seq(&self) -> u64 {
+        self.pool_map.changelog.seq()
+    }
+
+    /// Pins a read-only view of the pool at the current sequence number, so a caller can list the
+    /// ids then look up their details without seeing the pool change in between. The view answers
+    /// for `max_view_age_millis`, then only returns `ViewError::Expired`.
+    ///
+    /// The view shares the copy of the entries published by the last call, the entries are only
+    /// copied again once the pool changed.
+    pub fn consistent_view(&self) -> PoolView {
+        let mut views = self.views.lock();
+        while views.front().map_or(false, PoolView::is_expired) {
+            views.pop_front();
+        }
+        let version = self.pool_map.version();
+        let published = match views.back() {
+            Some(view) if view.published().version == version => {
+                let published = Arc::clone(view.published());
+                // the newest view at a version is the one `view_at` finds
+                views.pop_back();
+                published
+            }
+            _ => {
+                let entries = self
+                    .pool_map
+                    .iter()
+                    .map(|entry| (entry.inner.transaction().hash(), entry.inner.clone()))
+                    .collect();
+                Arc::new(PublishedPool {
+                    version,
+                    ids: self.get_ids(),
+                    entries,
+                })
+            }
+        };
+        let view = PoolView::new(
+            published,
+            self.config.max_view_age_millis,
+            Arc::clone(&self.clock),
+        );
+        views.push_back(view.clone());
+        if views.len() > MAX_PINNED_VIEWS {
+            views.pop_front();
+        }
+        view
+    }
+
+    /// The last view taken at the sequence number `seq` and not expired yet, or a new one if the
+    /// pool is still at `seq`
+    pub fn view_at(&self, seq: u64) -> Result<PoolView, ViewError> {
+        if seq == self.seq() {
+            return Ok(self.consistent_view());
+        }
+        self.views
+            .lock()
+            .iter()
+            .rev()
+            .find(|view| view.seq() == seq && !view.is_expired())
+            .cloned()
+            .ok_or(ViewError::NotPinned(seq))
+    }
+
+    /// Returns the current changelog sequence number and a fingerprint of the pool tx hashes.
+    ///
+    /// The fingerprint is the blake2b hash of the sorted tx hashes, two pools holding the same txs
+    /// have the same fingerprint whatever their sequence numbers are.
+    pub fn snapshot_ids(&self) -> (u64, Byte32) {
+        let hashes = self.sorted_hashes(|_| true);
+        let mut hasher = new_blake2b();
+        for hash in &hashes {
+            hasher.update(hash.as_slice());
+        }
+        let mut fingerprint = [0u8; 32];
+        hasher.finalize(&mut fingerprint);
+        (self.seq(), Byte32::new(fingerprint))
+    }
+
+    /// Compares the pool with the tx hashes of another pool, looking each of them up by its
+    /// proposal short id instead of listing the whole pool
+    pub fn diff_against(&self, other_hashes: &[Byte32]) -> PoolDiff {
+        let mut diff = PoolDiff::default();
+        let mut listed = HashSet::with_capacity(other_hashes.len());
+        for hash in other_hashes {
+            let id = ProposalShortId::from_tx_hash(hash);
+            if !listed.insert(id.clone()) {
+                continue;
+            }
+            match self.pool_map.get(&id) {
+                Some(entry) if &entry.transaction().hash() == hash => diff.common += 1,
+                _ => diff.missing.push(hash.clone()),
+            }
+        }
+        diff.extra = self.sorted_hashes(|entry| {
+            if listed.contains(&entry.proposal_short_id()) {
+                return false;
+            }
+            diff.extra_size += entry.size;
+            diff.extra_fee = diff.extra_fee.safe_add(entry.fee).unwrap_or(diff.extra_fee);
+            true
+        });
+        diff
+    }
+
+    fn sorted_hashes<F>(&self, mut filter: F) -> Vec<Byte32>
+    where
+        F: FnMut(&TxEntry) -> bool,
+    {
+        let mut hashes: Vec<_> = self
+            .pool_map
+            .iter()
+            .filter(|entry| filter(&entry.inner))
+            .map(|entry| entry.inner.transaction().hash())
+            .collect();
+        hashes.sort_unstable_by(|a, b| a.as_slice().cmp(b.as_slice()));
+        hashes
+    }
+
+    /// Draws `n` distinct entries at random, all of them if the pool is smaller, without
+    /// listing the whole pool to the caller.
+    ///
+    /// Uses weighted reservoir sampling, an entry whose weight is zero is never drawn. The same
+    /// `seed` over the same pool draws the same entries, in the same order.
+    ///
+    /// The pool is walked once keeping at most `2 * n` candidates, only the drawn entries are
+    /// sorted.
+    pub fn sample_entries(
+        &self,
+        n: usize,
+        weighting: SampleWeighting,
+        seed: Option<u64>,
+    ) -> Vec<AnnotatedTxId> {
+        if n == 0 {
+            return Vec::new();
+        }
+        let seed = seed.unwrap_or_else(|| StdRng::from_entropy().gen());
+        // the random number of an entry is derived from its hash, so a seeded draw doesn't depend
+        // on the order the pool is walked in
+        let uniform = |hash: &Byte32| {
+            let mut hasher = new_blake2b();
+            hasher.update(&seed.to_le_bytes());
+            hasher.update(hash.as_slice());
+            let mut digest = [0u8; 32];
+            hasher.finalize(&mut digest);
+            let mut bits = [0u8; 8];
+            bits.copy_from_slice(&digest[..8]);
+            // 53 random bits in (0, 1]
+            ((u64::from_le_bytes(bits) >> 11) + 1) as f64 / (1u64 << 53) as f64
+        };
+        // key = ln(u) / weight, the `n` largest keys are a weighted sample without replacement,
+        // ties are broken by hash
+        let by_key_desc = |a: &(f64, Byte32, &TxEntry), b: &(f64, Byte32, &TxEntry)| {
+            b.0.total_cmp(&a.0)
+                .then_with(|| a.1.as_slice().cmp(b.1.as_slice()))
+        };
+        let mut keyed = Vec::with_capacity(n.saturating_mul(2).min(self.pool_map.size()));
+        for entry in self.pool_map.iter().map(|entry| &entry.inner) {
+            let weight = match weighting {
+                SampleWeighting::Uniform => 1.0,
+                SampleWeighting::ByFee => entry.fee.as_u64() as f64,
+                SampleWeighting::BySize => entry.size as f64,
+            };
+            if weight <= 0.0 {
+                continue;
+            }
+            let hash = entry.transaction().hash();
+            keyed.push((uniform(&hash).ln() / weight, hash, entry));
+            if keyed.len() >= n.saturating_mul(2) {
+                keyed.select_nth_unstable_by(n, by_key_desc);
+                keyed.truncate(n);
+            }
+        }
+        if n < keyed.len() {
+            keyed.select_nth_unstable_by(n, by_key_desc);
+            keyed.truncate(n);
+        }
+        keyed.sort_unstable_by(by_key_desc);
+        keyed
+            .into_iter()
+            .map(|(_, hash, entry)| AnnotatedTxId {
+                hash,
+                fee: entry.fee,
+                size: entry.size as u64,
+                fee_rate: entry.fee_rate(),
+            })
+            .collect()
+    }
+
+    /// Returns the entry of a tx with the same hash as `tx`, so the same inputs and outputs, but
+    /// other witnesses
+    pub(crate) fn witness_variant(&self, tx: &TransactionView) -> Option<&TxEntry> {
+        self.pool_map.get(&tx.proposal_short_id()).filter(|entry| {
+            let stored = entry.transaction();
+            stored.hash() == tx.hash() && stored.witness_hash() != tx.witness_hash()
+        })
+    }
+
+    /// Checks the entry of `tx` may swap its witnesses for the ones of `tx`, weighing `size` and
+    /// `cycles` once swapped, and returns the entry.
+    ///
+    /// The swap goes through the checks of a new tx the weight change matters to: the pool must
+    /// be writable and have room for the size change, the fee must still pay the min fee rate and
+    /// neither the entry nor its descendants may exceed the package limits. The cycles are unknown
+    /// before the scripts run, `None` checks the size alone.
+    pub(crate) fn check_witness_swap(
+        &self,
+        tx: &TransactionView,
+        size: usize,
+        cycles: Option<Cycle>,
+    ) -> Result<&TxEntry, Reject> {
+        if self.is_read_only() {
+            return Err(Reject::Maintenance);
+        }
+        let stored = self
+            .witness_variant(tx)
+            .ok_or_else(|| Reject::Duplicated(tx.hash()))?;
+
+        let total_size = self.total_tx_size - stored.size + size;
+        if size > stored.size && total_size > self.config.max_tx_pool_size {
+            return Err(Reject::Full(format!(
+                "the witnesses of tx {} grow the tx-pool to {} bytes over its limit {}",
+                tx.hash(),
+                total_size,
+                self.config.max_tx_pool_size
+            )));
+        }
+
+        let weight = match cycles {
+            Some(cycles) => {
+                get_transaction_weight_with_ratio(size, cycles, self.pool_map.bytes_per_cycles)
+            }
+            None => size as u64,
+        };
+        check_min_fee(
+            &self.config,
+            self.dynamic_min_fee_rate(),
+            stored.fee,
+            weight,
+        )?;
+
+        let id = tx.proposal_short_id();
+        for package in self
+            .pool_map
+            .calc_descendants(&id)
+            .iter()
+            .chain(std::iter::once(&id))
+            .filter_map(|id| self.pool_map.get(id))
+        {
+            // the package of each descendant holds the entry among its ancestors
+            let package_size = package.ancestors_size - stored.size + size;
+            if package_size > self.pool_map.max_package_size {
+                return Err(Reject::ExceededPackageLimit(
+                    "size".to_owned(),
+                    package_size as u64,
+                    self.pool_map.max_package_size as u64,
+                ));
+            }
+            let package_cycles = cycles.map_or(package.ancestors_cycles, |cycles| {
+                (package.ancestors_cycles - stored.cycles).saturating_add(cycles)
+            });
+            if package_cycles > self.pool_map.max_package_cycles {
+                return Err(Reject::ExceededPackageLimit(
+                    "cycles".to_owned(),
+                    package_cycles,
+                    self.pool_map.max_package_cycles,
+                ));
+            }
+        }
+        Ok(stored)
+    }
+
+    /// Swaps the witnesses of an entry for the ones of `rtx`, a verified variant of the same tx,
+    /// once `check_witness_swap` passes with the verified weight.
+    ///
+    /// Only the size and cycles totals change, the entry keeps its place in the pool.
+    pub(crate) fn swap_witnesses(
+        &mut self,
+        rtx: Arc<ResolvedTransaction>,
+        cycles: Cycle,
+        size: usize,
+    ) -> Result<TxEntry, Reject> {
+        let tx_hash = rtx.transaction.hash();
+        self.check_witness_swap(&rtx.transaction, size, Some(cycles))?;
+        let old = self
+            .pool_map
+            .swap_witnesses(rtx, cycles, size)
+            .ok_or_else(|| Reject::Duplicated(tx_hash.clone()))?;
+        self.update_statics_for_remove_tx(old.size, old.cycles);
+        self.update_statics_for_add_tx(size, cycles);
+        debug!(
+            "swap_witnesses {} size {} -> {} cycles {} -> {}",
+            tx_hash, old.size, size, old.cycles, cycles
+        );
+        Ok(old)
+    }
+
+    pub(crate) fn get_pool_entry(&self, id: &ProposalShortId) -> Option<&PoolEntry> {
+        self.pool_map.get_by_id(id)
+    }
+
+    /// The entry of the tx `hash`, unlike `get_pool_entry` it never answers with another tx
+    /// sharing the proposal short id
+    pub(crate) fn get_entry_by_hash(&self, hash: &Byte32) -> Option<&PoolEntry> {
+        self.pool_map.get_by_hash(hash)
+    }
+
+    pub(crate) fn get_tx_from_pool(&self, id: &ProposalShortId) -> Option<&TransactionView> {
+        self.pool_map
+            .get_by_id(id)
+            .map(|entry| entry.inner.transaction())
+    }
+
+    pub(crate) fn remove_committed_txs(
+        &mut self,
+        blocks: &[BlockView],
+        callbacks: &Callbacks,
+        detached_headers: &HashSet<Byte32>,
+    ) {
+        for block in blocks {
+            for tx in block.transactions().iter().skip(1) {
+                let info = CommittedInfo {
+                    tx_hash: tx.hash(),
+                    block_hash: block.hash(),
+                    block_number: block.number(),
+                };
+                debug!("try remove_committed_tx {}", info.tx_hash);
+                self.remove_committed_tx(tx, &info, callbacks);
+
+                self.committed_txs_hash_cache
+                    .put(tx.proposal_short_id(), info);
+            }
+        }
+        if let Some(tip) = blocks.last() {
+            self.committed
+                .prune(tip.number(), self.config.keep_committed_confirmations);
+        }
+
+        if !detached_headers.is_empty() {
+            self.resolve_conflict_header_dep(detached_headers, callbacks)
+        }
+    }
+
+    /// Previews `remove_committed_txs` for the txs of a block, without changing the pool or
+    /// firing any callback.
+    ///
+    /// The entries are categorized the same way: a committed entry leaves the pool first, then
+    /// the entries spending the inputs of the block tx, as input or cell dep, are rejected
+    /// together with their descendants. Detached header deps are not considered.
+    pub fn simulate_commit(&self, block_txs: &[TransactionView]) -> CommitSimulation {
+        let mut removed = HashSet::new();
+        let mut committed = Vec::new();
+        let mut conflicted = Vec::new();
+        let (mut total_tx_size, mut total_tx_cycles) = (self.total_tx_size, self.total_tx_cycles);
+        let mut remove = |entry: &TxEntry| {
+            total_tx_size = total_tx_size.saturating_sub(entry.size);
+            total_tx_cycles = total_tx_cycles.saturating_sub(entry.cycles);
+        };
+
+        for tx in block_txs {
+            let short_id = tx.proposal_short_id();
+            if let Some(entry) = self.pool_map.get(&short_id) {
+                if removed.insert(short_id) {
+                    remove(entry);
+                    committed.push(tx.hash());
+                }
+            }
+
+            for out_point in tx.input_pts_iter() {
+                let spenders: Vec<_> = self
+                    .pool_map
+                    .edges
+                    .get_input_ref(&out_point)
+                    .into_iter()
+                    .chain(
+                        self.pool_map
+                            .edges
+                            .get_deps_ref(&out_point)
+                            .into_iter()
+                            .flatten(),
+                    )
+                    .filter(|id| !removed.contains(*id))
+                    .cloned()
+                    .collect();
+                for id in spenders {
+                    let descendants = self.pool_map.calc_descendants(&id);
+                    for id in std::iter::once(id).chain(descendants) {
+                        if !removed.insert(id.clone()) {
+                            continue;
+                        }
+                        if let Some(entry) = self.pool_map.get(&id) {
+                            remove(entry);
+                            let reject = Reject::Resolve(OutPointError::Dead(out_point.clone()));
+                            conflicted.push((entry.transaction().hash(), reject));
+                        }
+                    }
+                }
+            }
+        }
+
+        CommitSimulation {
+            committed,
+            conflicted,
+            total_tx_size,
+            total_tx_cycles,
+        }
+    }
+
+    fn resolve_conflict_header_dep(
+        &mut self,
+        detached_headers: &HashSet<Byte32>,
+        callbacks: &Callbacks,
+    ) {
+        for (entry, reject) in self.pool_map.resolve_conflict_header_dep(detached_headers) {
+            callbacks.call_reject(self, &entry, reject);
+        }
+    }
+
+    fn remove_committed_tx(
+        &mut self,
+        tx: &TransactionView,
+        info: &CommittedInfo,
+        callbacks: &Callbacks,
+    ) {
+        let short_id = tx.proposal_short_id();
+        if let Some(entry) = self
+            .pool_map
+            .remove_entry(&short_id, RemoveReason::Committed)
+        {
+            debug!("remove_committed_tx for {}", tx.hash());
+            self.record_fee_flow(RemoveReason::Committed, &entry);
+            callbacks.call_committed(self, &entry);
+            if self.config.keep_committed_confirmations > 0 {
+                self.committed.insert(short_id, entry, info.clone());
+            }
+        }
+        self.replace_summary_log(tx);
+        {
+            let conflicts = self.pool_map.resolve_conflict(tx);
+            for (entry, reject) in conflicts {
+                self.record_fee_flow(RemoveReason::Conflicted, &entry);
+                callbacks.call_reject(self, &entry, reject);
+            }
+        }
+    }
+
+    /// Removes the conflicting entry `id` and its descendants for a replacement tx, the caller runs
+    /// the reject callbacks
+    pub(crate) fn remove_replaced(&mut self, id: &ProposalShortId) -> Vec<TxEntry> {
+        self.record_replay(|_| ReplayEvent::Replaced(id.clone()));
+        let removed = self
+            .pool_map
+            .remove_entry_and_descendants(id, RemoveReason::Replaced);
+        for entry in removed.iter() {
+            self.record_fee_flow(RemoveReason::Replaced, entry);
+        }
+        removed
+    }
+
+    pub(crate) fn record_fee_flow(&mut self, reason: RemoveReason, entry: &TxEntry) {
+        let now_ms = self.now_ms();
+        self.fee_flow.record(reason, entry.fee, now_ms);
+    }
+
+    /// The fees of the entries which were committed, evicted by the size limit, expired, replaced
+    /// or conflicted, today, the previous day and since the pool started
+    pub fn fee_flow_report(&self) -> FeeFlowReport {
+        self.fee_flow.report(self.now_ms())
+    }
+
+    /// Checks `tx` spending cells which pool entries use as cell dep against
+    /// `consumed_dep_policy`, under `Evict` it has to pay for those entries and their descendants
+    /// as much as a replacement of them would, see `calculate_min_replace_fee`
+    pub(crate) fn check_dep_consumers(
+        &self,
+        tx: &TransactionView,
+        fee: Capacity,
+        tx_size: usize,
+    ) -> Result<(), Reject> {
+        if self.config.consumed_dep_policy == ConsumedDepPolicy::Keep {
+            return Ok(());
+        }
+        let consumers = self.pool_map.find_dep_consumers(tx);
+        let (id, out_point) = match consumers.first() {
+            Some(consumer) => consumer,
+            None => return Ok(()),
+        };
+        if self.config.consumed_dep_policy == ConsumedDepPolicy::Reject {
+            return Err(Reject::DepConsumed(format!(
+                "spends {} used as cell dep by pool tx {}",
+                out_point, id
+            )));
+        }
+        let mut evicted = HashSet::new();
+        for (id, _) in consumers.iter() {
+            evicted.extend(self.pool_map.calc_descendants(id));
+            evicted.insert(id.clone());
+        }
+        let entries: Vec<_> = evicted
+            .iter()
+            .filter_map(|id| self.get_pool_entry(id))
+            .collect();
+        match self.calculate_min_replace_fee(&entries, tx_size) {
+            Some(min_fee) if fee >= min_fee => Ok(()),
+            Some(min_fee) => Err(Reject::DepConsumed(format!(
+                "spends {} used as cell dep by pool tx {}, fee is {}, expect it to >= {} to evict \
+                 {} txs",
+                out_point,
+                id,
+                fee,
+                min_fee,
+                entries.len()
+            ))),
+            None => Err(Reject::DepConsumed(
+                "calculate_min_replace_fee failed".to_string(),
+            )),
+        }
+    }
+
+    /// Removes the entries using a cell spent by `tx` as cell dep, with their descendants, the
+    /// caller runs the reject callbacks
+    pub(crate) fn evict_dep_consumers(&mut self, tx: &TransactionView) -> Vec<(TxEntry, Reject)> {
+        let mut evicted = Vec::new();
+        for (id, out_point) in self.pool_map.find_dep_consumers(tx) {
+            let entries = self
+                .pool_map
+                .remove_entry_and_descendants(&id, RemoveReason::Conflicted);
+            let reject = Reject::DepConsumed(format!(
+                "cell dep {} is spent by tx {}",
+                out_point,
+                tx.hash()
+            ));
+            // the reject callbacks update the statics, like for the replaced entries
+            for entry in entries {
+                self.record_fee_flow(RemoveReason::Conflicted, &entry);
+                evicted.push((entry, reject.clone()));
+            }
+        }
+        evicted
+    }
+
+    /// Remembers the entries displaced from the pool by the replacement tx `tx_hash`
+    pub(crate) fn record_replacement(&mut self, tx_hash: Byte32, removed: &[TxEntry]) {
+        self.replacements.record(tx_hash, removed);
+    }
+
+    /// Logs what a committed tx displaced if it was a replacement, the ancestry is forgotten
+    /// afterwards
+    pub(crate) fn replace_summary_log(&mut self, tx: &TransactionView) -> Option<ReplaceSummary> {
+        let summary = self.replacements.take(&tx.hash())?;
+        info!(
+            "replace_summary_log committed {} displaced {} txs with total fee {}: {:?}",
+            summary.tx_hash,
+            summary.replaced.len(),
+            summary.displaced_fee,
+            summary.replaced
+        );
+        Some(summary)
+    }
+
+    /// Returns the cached rejection of a tx which double spends in-pool entries
+    pub(crate) fn check_conflict_cache(&self, tx_hash: &Byte32, now: u64) -> Option<Reject> {
+        self.conflict_cache
+            .lock()
+            .check(tx_hash, now, |id| self.pool_map.get_by_id(id).is_some())
+    }
+
+    /// Turns a failure to resolve `tx` because in-pool entries spend the same inputs into
+    /// `Reject::Conflict`, other rejects are returned unchanged
+    pub(crate) fn conflict_reject(&self, tx: &TransactionView, reject: Reject) -> Reject {
+        if !matches!(reject, Reject::Resolve(OutPointError::Dead(_))) {
+            return reject;
+        }
+        let conflicting_ids = self.pool_map.find_conflict_tx(tx);
+        if conflicting_ids.is_empty() {
+            reject
+        } else {
+            Reject::Conflict { conflicting_ids }
+        }
+    }
+
+    /// Caches the rejection of a tx which lost against in-pool entries, and counts it as a
+    /// double spend attempt against each of them
+    pub(crate) fn cache_conflict(
+        &self,
+        tx_hash: Byte32,
+        conflicts: HashSet<ProposalShortId>,
+        reject: Reject,
+        now: u64,
+    ) {
+        self.record_double_spend(conflicts.iter(), &tx_hash);
+        self.conflict_cache
+            .lock()
+            .insert(tx_hash, conflicts, reject, now);
+    }
+
+    /// Counts `tx_hash` as a rejected attempt to double spend each of `conflicts`
+    pub(crate) fn record_double_spend<'a>(
+        &self,
+        conflicts: impl Iterator<Item = &'a ProposalShortId>,
+        tx_hash: &Byte32,
+    ) {
+        self.pool_map
+            .double_spends
+            .lock()
+            .record(conflicts, tx_hash);
     }
 
-    /// Add tx with proposed status
-    pub(crate) fn add_proposed(&mut self, entry: TxEntry) -> Result<bool, Reject> {
-        self.pool_map.add_entry(entry, Status::Proposed)
+    /// Rejected attempts to double spend the entry `id`, `None` if there was none
+    pub fn double_spend_attempts(&self, id: &ProposalShortId) -> Option<DoubleSpendAttempts> {
+        self.pool_map.double_spends.lock().get(id).cloned()
     }
 
-    /// Returns true if the tx-pool contains a tx with specified id.
-    pub(crate) fn contains_proposal_id(&self, id: &ProposalShortId) -> bool {
-        self.pool_map.get_by_id(id).is_some()
-    }
+    /// Explains why the tx `tx_hash` is, or is not, on its way into the next block: where it is
+    /// in the pool and what holds it back, or what happened to it if it left the pool.
+    pub fn diagnose(&self, tx_hash: &Byte32) -> Diagnosis {
+        let entry = match self.get_entry_by_hash(tx_hash) {
+            Some(entry) => entry,
+            None => return self.diagnose_absent(tx_hash),
+        };
+        let stage = match entry.status {
+            Status::Pending => PoolStage::Pending,
+            Status::Gap => PoolStage::Gap,
+            Status::Proposed => PoolStage::Proposed,
+        };
 
-    pub(crate) fn set_entry_proposed(&mut self, short_id: &ProposalShortId) {
-        self.pool_map.set_entry(short_id, Status::Proposed)
-    }
+        let consensus = self.snapshot.consensus();
+        let (packaged, _, _, _) = CommitTxsScanner::new(&self.pool_map)
+            .with_soft_cycles_target(self.config.soft_cycle_target)
+            .txs_to_commit(
+                consensus.max_block_bytes() as usize,
+                consensus.max_block_cycles(),
+                self.config
+                    .max_package_duration_millis
+                    .map(Duration::from_millis),
+            );
+        let position = packaged
+            .iter()
+            .position(|packaged| packaged.proposal_short_id() == entry.id);
 
-    pub(crate) fn set_entry_gap(&mut self, short_id: &ProposalShortId) {
-        self.pool_map.set_entry(short_id, Status::Gap)
-    }
+        let fee_rate = entry.inner.fee_rate();
+        let blocker = if position.is_some() {
+            None
+        } else {
+            let unproposed: Vec<_> = self
+                .pool_map
+                .calc_ancestors(&entry.id)
+                .iter()
+                .filter_map(|id| self.pool_map.get_by_id(id))
+                .filter(|ancestor| ancestor.status != Status::Proposed)
+                .map(|ancestor| ancestor.inner.transaction().hash())
+                .collect();
+            let tx_env = TxVerifyEnv::new_proposed(self.snapshot.tip_header(), 1);
+            if !unproposed.is_empty() {
+                Some(Blocker::AncestorsUnproposed(unproposed))
+            } else if stage == PoolStage::Pending {
+                Some(Blocker::NotProposed)
+            } else if stage == PoolStage::Gap {
+                Some(Blocker::AwaitingProposalWindow)
+            } else if let Err(err) =
+                time_relative_verify(self.cloned_snapshot(), Arc::clone(&entry.inner.rtx), tx_env)
+            {
+                Some(Blocker::SinceLocked(err.to_string()))
+            } else {
+                let cutoff = packaged
+                    .iter()
+                    .map(TxEntry::fee_rate)
+                    .min()
+                    .unwrap_or(fee_rate);
+                Some(Blocker::FeeBelowCutoff { fee_rate, cutoff })
+            }
+        };
 
-    /// Returns tx with cycles corresponding to the id.
-    pub(crate) fn get_tx_with_cycles(
-        &self,
-        id: &ProposalShortId,
-    ) -> Option<(TransactionView, Cycle)> {
-        self.pool_map
-            .get_by_id(id)
-            .map(|entry| (entry.inner.transaction().clone(), entry.inner.cycles))
+        let expires_in = self.expires_at(&entry.inner).saturating_sub(self.now_ms());
+        let eviction_floor = self.dynamic_min_fee_rate();
+        Diagnosis::InPool(PoolDiagnosis {
+            stage,
+            position,
+            blocker,
+            expires_in,
+            expiring_soon: expires_in < self.expiry / 10,
+            fee_rate,
+            eviction_floor,
+            eviction_risk: fee_rate < eviction_floor,
+            double_spend_attempts: self
+                .double_spend_attempts(&entry.id)
+                .map_or(0, |attempts| attempts.count),
+        })
     }
 
-    pub(crate) fn get_pool_entry(&self, id: &ProposalShortId) -> Option<&PoolEntry> {
-        self.pool_map.get_by_id(id)
+    fn diagnose_absent(&self, tx_hash: &Byte32) -> Diagnosis {
+        let id = ProposalShortId::from_tx_hash(tx_hash);
+        if let Some(info) = self
+            .committed_info(&id)
+            .filter(|info| &info.tx_hash == tx_hash)
+        {
+            return Diagnosis::Committed {
+                block_hash: info.block_hash,
+                block_number: info.block_number,
+            };
+        }
+        match self
+            .recent_reject
+            .as_ref()
+            .and_then(|recent_reject| recent_reject.get(tx_hash).ok().flatten())
+        {
+            Some(reason) => Diagnosis::Rejected(reason),
+            None => Diagnosis::Unknown,
+        }
     }
 
-    pub(crate) fn get_tx_from_pool(&self, id: &ProposalShortId) -> Option<&TransactionView> {
-        self.pool_map
-            .get_by_id(id)
-            .map(|entry| entry.inner.transaction())
+    /// The recent reject record of the tx `tx_hash`, `None` if it was not rejected recently or
+    /// the recent reject db is disabled
+    pub fn recent_reject_record(&self, tx_hash: &Byte32) -> Option<RejectRecord> {
+        self.recent_reject
+            .as_ref()
+            .and_then(|recent_reject| recent_reject.get_record(tx_hash).ok().flatten())
     }
 
-    pub(crate) fn remove_committed_txs<'a>(
-        &mut self,
-        txs: impl Iterator<Item = &'a TransactionView>,
-        callbacks: &Callbacks,
-        detached_headers: &HashSet<Byte32>,
-    ) {
-        for tx in txs {
-            let tx_hash = tx.hash();
-            debug!("try remove_committed_tx {}", tx_hash);
-            self.remove_committed_tx(tx, callbacks);
+    /// Hit counters of the conflict cache
+    pub fn conflict_cache_stats(&self) -> ConflictCacheStats {
+        self.conflict_cache.lock().stats()
+    }
 
-            self.committed_txs_hash_cache
-                .put(tx.proposal_short_id(), tx_hash);
-        }
+    // The unix timestamp in milliseconds after which the entry is expired
+    fn expires_at(&self, entry: &TxEntry) -> u64 {
+        entry.timestamp.saturating_add(self.expiry)
+    }
 
-        if !detached_headers.is_empty() {
-            self.resolve_conflict_header_dep(detached_headers, callbacks)
-        }
+    /// Lists the txs which will expire within `duration` with their remaining lifetime in milliseconds,
+    /// sorted soonest first and at most `limit` of them.
+    pub fn expiring_within(&self, duration: Duration, limit: usize) -> Vec<(Byte32, u64)> {
+        let within = duration.as_millis() as u64;
+        self.entries_expiring_within(None, within)
+            .into_iter()
+            .take(limit)
+            .map(|(entry, remaining)| (entry.inner.transaction().hash(), remaining))
+            .collect()
     }
 
-    fn resolve_conflict_header_dep(
-        &mut self,
-        detached_headers: &HashSet<Byte32>,
-        callbacks: &Callbacks,
-    ) {
-        for (entry, reject) in self.pool_map.resolve_conflict_header_dep(detached_headers) {
-            callbacks.call_reject(self, &entry, reject);
-        }
+    /// Lists the proposed txs which will expire within `window_ms` without being committed,
+    /// sorted soonest first.
+    pub fn proposed_expiring_within(&self, window_ms: u64) -> Vec<ProposalShortId> {
+        self.entries_expiring_within(Some(Status::Proposed), window_ms)
+            .into_iter()
+            .map(|(entry, _)| entry.id.clone())
+            .collect()
     }
 
-    fn remove_committed_tx(&mut self, tx: &TransactionView, callbacks: &Callbacks) {
-        let short_id = tx.proposal_short_id();
-        if let Some(entry) = self.pool_map.remove_entry(&short_id) {
-            debug!("remove_committed_tx for {}", tx.hash());
-            callbacks.call_committed(self, &entry)
-        }
-        {
-            let conflicts = self.pool_map.resolve_conflict(tx);
-            for (entry, reject) in conflicts {
-                callbacks.call_reject(self, &entry, reject);
-            }
-        }
+    // the entries, of `status` if given, expiring within `within_ms` along with their remaining
+    // lifetime, sorted soonest first
+    fn entries_expiring_within(
+        &self,
+        status: Option<Status>,
+        within_ms: u64,
+    ) -> Vec<(&PoolEntry, u64)> {
+        let now_ms = self.now_ms();
+        let entries = match status {
+            Some(status) => self.pool_map.get_by_status(status),
+            None => self.pool_map.iter().collect(),
+        };
+        let mut expiring: Vec<_> = entries
+            .into_iter()
+            .map(|entry| {
+                let remaining = self.expires_at(&entry.inner).saturating_sub(now_ms);
+                (entry, remaining)
+            })
+            .filter(|(_, remaining)| *remaining < within_ms)
+            .collect();
+        expiring.sort_unstable_by_key(|(_, remaining)| *remaining);
+        expiring
     }
 
     // Expire all transaction (and their dependencies) in the pool.
     pub(crate) fn remove_expired(&mut self, callbacks: &Callbacks) {
-        let now_ms = ckb_systemtime::unix_time_as_millis();
+        let now_ms = self.now_ms();
         let removed: Vec<_> = self
             .pool_map
             .iter()
-            .filter(|&entry| self.expiry + entry.inner.timestamp < now_ms)
+            .filter(|&entry| self.expires_at(&entry.inner) < now_ms)
             .map(|entry| entry.inner.clone())
             .collect();
 
         for entry in removed {
             let tx_hash = entry.transaction().hash();
             debug!("remove_expired {} timestamp({})", tx_hash, entry.timestamp);
-            self.pool_map.remove_entry(&entry.proposal_short_id());
+            self.pool_map
+                .remove_entry(&entry.proposal_short_id(), RemoveReason::Expired);
+            self.record_fee_flow(RemoveReason::Expired, &entry);
+            if entry.origin == Origin::Local {
+                self.resubmit.insert(&entry, now_ms);
+            }
             let reject = Reject::Expiry(entry.timestamp);
             callbacks.call_reject(self, &entry, reject);
         }
     }
 
-    // Remove transactions from the pool until total size <= size_limit.
-    pub(crate) fn limit_size(&mut self, callbacks: &Callbacks) {
-        while self.total_tx_size > self.config.max_tx_pool_size {
-            let next_evict_entry = || {
-                self.pool_map
-                    .next_evict_entry(Status::Pending)
-                    .or_else(|| self.pool_map.next_evict_entry(Status::Gap))
-                    .or_else(|| self.pool_map.next_evict_entry(Status::Proposed))
+    fn status_quota(&self, status: Status) -> &StatusQuota {
+        match status {
+            Status::Pending => &self.config.pending_quota,
+            Status::Gap => &self.config.gap_quota,
+            Status::Proposed => &self.config.proposed_quota,
+        }
+    }
+
+    // the first status whose entries go over its quota
+    fn over_quota_status(&self) -> Option<Status> {
+        [Status::Pending, Status::Gap, Status::Proposed]
+            .into_iter()
+            .find(|status| {
+                let quota = self.status_quota(*status);
+                if quota.is_unlimited() {
+                    return false;
+                }
+                let (size, count) = self.pool_map.status_usage(*status);
+                quota.is_exceeded(size, count)
+            })
+    }
+
+    /// Total size and count of the entries in `status` along with its quota
+    pub fn quota_usage(&self, status: Status) -> StatusQuotaUsage {
+        let (size, count) = self.pool_map.status_usage(status);
+        let quota = self.status_quota(status);
+        StatusQuotaUsage {
+            size,
+            count,
+            max_size: quota.max_size,
+            max_count: quota.max_count,
+        }
+    }
+
+    // Remove transactions until every status is within its quota and total size <= size_limit,
+    // the eviction policy picks the entries, evicted with their descendants.
+    pub(crate) fn limit_size(&mut self, callbacks: &Callbacks) -> EvictionSummary {
+        let started_at = Instant::now();
+        let mut summary = EvictionSummary::default();
+        loop {
+            let next_evict_entry = if let Some(status) = self.over_quota_status() {
+                trace!("evict from {:?} which is over its quota", status);
+                let (size, _) = self.pool_map.status_usage(status);
+                let overage = self
+                    .status_quota(status)
+                    .max_size
+                    .map_or(0, |max_size| size.saturating_sub(max_size));
+                self.eviction.select(&self.pool_map, status, overage)
+            } else if self.total_tx_size > self.config.max_tx_pool_size
+                || self.pool_map.size() > self.config.max_tx_pool_entries
+            {
+                let overage = self
+                    .total_tx_size
+                    .saturating_sub(self.config.max_tx_pool_size);
+                [Status::Pending, Status::Gap, Status::Proposed]
+                    .into_iter()
+                    .find_map(|status| self.eviction.select(&self.pool_map, status, overage))
+            } else {
+                break;
             };
 
-            if let Some(id) = next_evict_entry() {
-                let removed = self.pool_map.remove_entry_and_descendants(&id);
+            if let Some(id) = next_evict_entry {
+                let package_fee_rate = match self.pool_map.get_by_id(&id) {
+                    Some(entry) => entry.evict_key.fee_rate,
+                    None => break,
+                };
+                let removed = self
+                    .pool_map
+                    .remove_entry_and_descendants(&id, RemoveReason::Evicted);
                 for entry in removed {
-                    let tx_hash = entry.transaction().hash();
-                    debug!(
+                    trace!(
                         "removed by size limit {} timestamp({})",
-                        tx_hash, entry.timestamp
+                        entry.transaction().hash(),
+                        entry.timestamp
                     );
+                    summary.record(&entry, package_fee_rate);
                     let reject = Reject::Full(format!(
                         "the fee_rate for this transaction is: {}",
                         entry.fee_rate()
                     ));
+                    self.record_fee_flow(RemoveReason::Evicted, &entry);
+                    // an archived tx coming back is announced again by `reconsider_archived`
                     callbacks.call_reject(self, &entry, reject);
+                    self.archive.insert(entry);
                 }
+            } else {
+                break;
             }
         }
         self.pool_map.entries.shrink_to_fit();
+        summary.duration = started_at.elapsed();
+
+        if !summary.is_empty() {
+            info!(
+                "size limit evicted {} txs, {} bytes, {} cycles, fee rates {} to {}, in {:?}",
+                summary.removed_count,
+                summary.removed_bytes,
+                summary.removed_cycles,
+                summary.min_fee_rate_removed.unwrap_or_default(),
+                summary.max_fee_rate_removed.unwrap_or_default(),
+                summary.duration
+            );
+            if let Some(fee_rate) = summary.max_fee_rate_removed {
+                self.raise_fee_floor(fee_rate);
+            }
+            if let Some(metrics) = ckb_metrics::handle() {
+                metrics
+                    .ckb_tx_pool_evicted_txs
+                    .inc_by(summary.removed_count as u64);
+                metrics
+                    .ckb_tx_pool_evicted_bytes
+                    .inc_by(summary.removed_bytes as u64);
+            }
+        }
+        summary
+    }
+
+    /// Number of evicted txs kept in the archive
+    pub fn archived_count(&self) -> usize {
+        self.archive.len()
+    }
+
+    /// Re-adds archived txs as pending, the best paying first, as long as the pool stays within
+    /// its size and entries limits and the pending quota, runs the pending callbacks for them,
+    /// which count them in the pool statistics and announce them again, and returns them.
+    ///
+    /// Like the txs of detached blocks, archived txs are resolved and verified again against the
+    /// current chain: the time locks are always checked, the scripts run again only if the
+    /// rules they ran under changed. Archived txs already back in the pool, expired, whose inputs
+    /// were consumed or which fail verification are dropped, the ones whose parents are still
+    /// archived, which don't fit yet or pay less than the fee floor stay in the archive.
+    pub fn reconsider_archived(&mut self, callbacks: &Callbacks) -> Vec<TxEntry> {
+        let now_ms = self.now_ms();
+        let tx_env = TxVerifyEnv::new_submit(self.snapshot.tip_header());
+        let fork_context = ForkContext::new(self.snapshot.consensus(), &tx_env);
+        let tx_env = Arc::new(tx_env);
+        let pending_quota = self.config.pending_quota.clone();
+        let mut archived = self.archive.take_sorted();
+        let mut readmitted = Vec::new();
+        let mut total_size = self.total_tx_size;
+        // an archived child can only be re-added after its archived parent, retry until no
+        // progress is made
+        loop {
+            let mut progress = false;
+            let mut waiting = Vec::with_capacity(archived.len());
+            for entry in archived {
+                if self.pool_map.contains_key(&entry.proposal_short_id())
+                    || self.expires_at(&entry) < now_ms
+                {
+                    continue;
+                }
+                let (pending_size, pending_count) = self.pool_map.status_usage(Status::Pending);
+                if total_size.saturating_add(entry.size) > self.config.max_tx_pool_size
+                    || self.pool_map.size() >= self.config.max_tx_pool_entries
+                    || pending_quota
+                        .is_exceeded(pending_size.saturating_add(entry.size), pending_count + 1)
+                    || check_entry_min_fee(self, &entry).is_err()
+                {
+                    waiting.push(entry);
+                    continue;
+                }
+                let rtx = match self.resolve_tx_from_pool(entry.transaction().clone(), false) {
+                    Ok(rtx) => rtx,
+                    Err(Reject::Resolve(OutPointError::Unknown(_))) => {
+                        waiting.push(entry);
+                        continue;
+                    }
+                    Err(err) => {
+                        debug!("drop archived tx {}: {}", entry.transaction().hash(), err);
+                        continue;
+                    }
+                };
+                let cached = (entry.fork_context == Some(fork_context)).then(|| {
+                    CacheEntry::Completed(Completed {
+                        cycles: entry.cycles,
+                        fee: entry.fee,
+                    })
+                });
+                let verified = match verify_rtx(
+                    self.cloned_snapshot(),
+                    Arc::clone(&rtx),
+                    Arc::clone(&tx_env),
+                    &cached,
+                    self.config.max_tx_verify_cycles,
+                ) {
+                    Ok(verified) => verified,
+                    Err(err) => {
+                        debug!("drop archived tx {}: {}", entry.transaction().hash(), err);
+                        continue;
+                    }
+                };
+                let mut entry = TxEntry {
+                    rtx,
+                    cycles: verified.cycles,
+                    fork_context: Some(fork_context),
+                    ..entry
+                };
+                entry.reset_statistic_state();
+                if let Ok(Some(_)) = self.add_pending(entry.clone()) {
+                    total_size = total_size.saturating_add(entry.size);
+                    // counted as evicted when archived
+                    self.fee_flow.undo(RemoveReason::Evicted, entry.fee, now_ms);
+                    callbacks.call_pending(self, &entry);
+                    readmitted.push(entry);
+                    progress = true;
+                }
+            }
+            archived = waiting;
+            if !progress || archived.is_empty() {
+                break;
+            }
+        }
+        for entry in archived {
+            self.archive.insert(entry);
+        }
+        readmitted
+    }
+
+    /// The local txs which expired in the pool, kept to be submitted again, the oldest expiry first
+    pub fn resubmit_list(&self) -> Vec<ResubmitEntry> {
+        self.resubmit.entries()
+    }
+
+    /// Takes the expired local txs out of the resubmission list, to be submitted again through the
+    /// whole verification. The ones whose inputs were spent since are dropped with the reason.
+    pub(crate) fn take_resubmit_list(&mut self) -> (Vec<ResubmitEntry>, Vec<(Byte32, Reject)>) {
+        self.resubmit.prune(self.now_ms());
+        let mut resubmit = Vec::new();
+        let mut dropped = Vec::new();
+        for entry in self.resubmit.take() {
+            let tx_hash = entry.transaction.hash();
+            match self.resolve_tx_from_pool(entry.transaction.clone(), false) {
+                Err(reject @ Reject::Resolve(OutPointError::Dead(_))) => {
+                    debug!("drop expired tx {} from resubmission: {}", tx_hash, reject);
+                    dropped.push((tx_hash, reject));
+                }
+                _ => resubmit.push(entry),
+            }
+        }
+        (resubmit, dropped)
+    }
+
+    /// Puts back an entry of `take_resubmit_list` which could not be submitted yet
+    pub(crate) fn keep_for_resubmit(&mut self, entry: ResubmitEntry) {
+        self.resubmit.push(entry);
     }
 
     // remove transaction with detached proposal from gap and proposed
@@ -299,12 +2042,25 @@ impl TxPool {
                 if status == Status::Pending {
                     continue;
                 }
-                let mut entries = self.pool_map.remove_entry_and_descendants(id);
+                let mut entries = self
+                    .pool_map
+                    .remove_entry_and_descendants(id, RemoveReason::Detached);
                 entries.sort_unstable_by_key(|entry| entry.ancestors_count);
                 for mut entry in entries {
                     let tx_hash = entry.transaction().hash();
+                    let entry_id = entry.proposal_short_id();
+                    let (size, cycles) = (entry.size, entry.cycles);
+                    // account the removal and the re-add separately, an entry which fails to be
+                    // re-added must not stay in the totals
+                    self.update_statics_for_remove_tx(size, cycles);
                     entry.reset_statistic_state();
                     let ret = self.add_pending(entry);
+                    if let Ok(Some(_)) = ret {
+                        self.update_statics_for_add_tx(size, cycles);
+                    } else {
+                        // out of the pool, the record kept across the detach goes too
+                        self.pool_map.double_spends.get_mut().remove(&entry_id);
+                    }
                     debug!(
                         "remove_by_detached_proposal from {:?} {} add_pending {:?}",
                         status, tx_hash, ret
@@ -315,7 +2071,9 @@ impl TxPool {
     }
 
     pub(crate) fn remove_tx(&mut self, id: &ProposalShortId) -> bool {
-        let entries = self.pool_map.remove_entry_and_descendants(id);
+        let entries = self
+            .pool_map
+            .remove_entry_and_descendants(id, RemoveReason::Removed);
         if !entries.is_empty() {
             for entry in entries {
                 self.update_statics_for_remove_tx(entry.size, entry.cycles);
@@ -323,7 +2081,7 @@ impl TxPool {
             return true;
         }
 
-        if let Some(entry) = self.pool_map.remove_entry(id) {
+        if let Some(entry) = self.pool_map.remove_entry(id, RemoveReason::Removed) {
             self.update_statics_for_remove_tx(entry.size, entry.cycles);
             return true;
         }
@@ -339,18 +2097,77 @@ impl TxPool {
             .map_err(Reject::Resolve)
     }
 
+    /// Resolves `tx` against the snapshot and the pool, the result is cached until the next
+    /// snapshot swap or pool membership change
     pub(crate) fn resolve_tx_from_pool(
         &self,
         tx: TransactionView,
         rbf: bool,
     ) -> Result<Arc<ResolvedTransaction>, Reject> {
         let snapshot = self.snapshot();
+        let tip_hash = snapshot.tip_hash();
+        let pool_seq = self.pool_map.changelog.seq();
+        if let Some(rtx) = self
+            .resolve_cache
+            .lock()
+            .get(&tx.hash(), rbf, &tip_hash, pool_seq)
+        {
+            return Ok(rtx);
+        }
         let pool_cell = PoolCell::new(&self.pool_map, rbf);
         let provider = OverlayCellProvider::new(&pool_cell, snapshot);
         let mut seen_inputs = HashSet::new();
-        resolve_transaction(tx, &mut seen_inputs, &provider, snapshot)
+        let rtx = resolve_transaction(tx, &mut seen_inputs, &provider, snapshot)
             .map(Arc::new)
-            .map_err(Reject::Resolve)
+            .map_err(Reject::Resolve)?;
+        self.resolve_cache
+            .lock()
+            .insert(rbf, tip_hash, pool_seq, Arc::clone(&rtx));
+        Ok(rtx)
+    }
+
+    /// Resolves a package of txs, e.g. a parent with the children paying for it, as if they
+    /// were submitted one after another, without adding them to the pool.
+    ///
+    /// Each tx can spend the outputs of the pool txs and of the package txs before it, a cell
+    /// spent twice within the package is rejected as dead. Scripts are not verified.
+    pub fn check_package(&self, txs: &[TransactionView]) -> Result<PackageStats, Reject> {
+        if txs.is_empty() {
+            return Err(Reject::Malformed(
+                "empty package".to_owned(),
+                "expect at least one transaction".to_owned(),
+            ));
+        }
+        let snapshot = self.snapshot();
+        let pool_cell = PoolCell::new(&self.pool_map, false);
+        let pool_provider = OverlayCellProvider::new(&pool_cell, snapshot);
+        let mut package = TransactionsProvider::default();
+        let mut package_ids = HashSet::new();
+        let mut seen_inputs = HashSet::new();
+        let mut fee = Capacity::zero();
+        let mut size = 0;
+        for tx in txs {
+            let short_id = tx.proposal_short_id();
+            if self.contains_proposal_id(&short_id) || !package_ids.insert(short_id) {
+                return Err(Reject::Duplicated(tx.hash()));
+            }
+            let provider = OverlayCellProvider::new(&package, &pool_provider);
+            let rtx = resolve_transaction(tx.clone(), &mut seen_inputs, &provider, snapshot)
+                .map_err(|err| self.conflict_reject(tx, Reject::Resolve(err)))?;
+            fee = fee
+                .safe_add(calculate_tx_fee(snapshot, &rtx)?)
+                .map_err(|err| {
+                    Reject::Malformed(format!("{err}"), "package fee overflow".to_owned())
+                })?;
+            size += tx.data().serialized_size_in_block();
+            package.insert(tx);
+        }
+        Ok(PackageStats {
+            count: txs.len(),
+            fee,
+            size,
+            fee_rate: FeeRate::calculate(fee, size as u64),
+        })
     }
 
     pub(crate) fn gap_rtx(&mut self, short_id: &ProposalShortId) -> Result<(), Reject> {
@@ -403,15 +2220,61 @@ impl TxPool {
         proposals
     }
 
+    /// Returns the hash cached for a recently committed tx, used to look it up in the store on a
+    /// pool miss
+    pub fn cached_committed_hash(&self, id: &ProposalShortId) -> Option<Byte32> {
+        self.committed_txs_hash_cache
+            .peek(id)
+            .map(|info| info.tx_hash.clone())
+    }
+
+    /// Returns the block a recently committed tx was committed in, without reading the store
+    pub fn committed_info(&self, id: &ProposalShortId) -> Option<CommittedInfo> {
+        self.committed_txs_hash_cache.peek(id).cloned()
+    }
+
+    /// Returns a committed entry kept in the pool until confirmed enough, with the block it was
+    /// committed in
+    pub(crate) fn committed_entry(&self, id: &ProposalShortId) -> Option<&CommittedEntry> {
+        self.committed.get(id)
+    }
+
+    /// Number of committed entries kept in the pool until confirmed enough
+    pub fn committed_count(&self) -> usize {
+        self.committed.len()
+    }
+
+    /// Drops the cached hashes and the kept entries of txs which are no longer committed, e.g.
+    /// detached by a reorg
+    pub(crate) fn invalidate_committed_cache(&mut self, ids: &[ProposalShortId]) {
+        for id in ids {
+            self.committed_txs_hash_cache.pop(id);
+            self.committed.remove(id);
+        }
+    }
+
     /// Returns tx from tx-pool or storage corresponding to the id.
+    ///
+    /// The pool miss callback is called with the id when it is found neither in the pool nor in
+    /// the store, so the lookups which come up empty can be recorded.
     pub(crate) fn get_tx_from_pool_or_store(
         &self,
         proposal_id: &ProposalShortId,
+        callbacks: &Callbacks,
     ) -> Option<TransactionView> {
         self.get_tx_from_pool(proposal_id).cloned().or_else(|| {
-            self.committed_txs_hash_cache
+            let committed = self
+                .committed_txs_hash_cache
                 .peek(proposal_id)
-                .and_then(|tx_hash| self.snapshot().get_transaction(tx_hash).map(|(tx, _)| tx))
+                .and_then(|info| {
+                    self.snapshot()
+                        .get_transaction(&info.tx_hash)
+                        .map(|(tx, _)| tx)
+                });
+            if committed.is_none() {
+                callbacks.call_pool_miss(proposal_id);
+            }
+            committed
         })
     }
 
@@ -428,28 +2291,112 @@ impl TxPool {
             .map(|entry| entry.transaction().hash())
             .collect();
 
-        TxPoolIds { pending, proposed }
+        TxPoolIds {
+            pending,
+            proposed,
+            seq: self.seq(),
+        }
+    }
+
+    /// Returns at most `limit` hashes of the txs passing `filter`, skipping the first `offset`.
+    ///
+    /// Hashes are taken in the order of the score index, highest first, so the pages of a pool
+    /// which doesn't change neither overlap nor miss any tx.
+    pub fn get_ids_paginated(
+        &self,
+        filter: PoolTxFilter,
+        offset: usize,
+        limit: usize,
+    ) -> Vec<Byte32> {
+        let statuses = match filter {
+            PoolTxFilter::All => vec![Status::Pending, Status::Gap, Status::Proposed],
+            PoolTxFilter::Pending => vec![Status::Pending, Status::Gap],
+            PoolTxFilter::Proposed => vec![Status::Proposed],
+        };
+        self.pool_map
+            .score_sorted_iter_by(statuses)
+            .skip(offset)
+            .take(limit)
+            .map(|entry| entry.transaction().hash())
+            .collect()
+    }
+
+    /// Same as `get_ids`, with each id carrying the fee, size and fee rate of its tx
+    pub(crate) fn get_annotated_ids(&self) -> TxPoolAnnotatedIds {
+        let annotate = |entry: &TxEntry| AnnotatedTxId {
+            hash: entry.transaction().hash(),
+            fee: entry.fee,
+            size: entry.size as u64,
+            fee_rate: entry.fee_rate(),
+        };
+        let pending = self
+            .pool_map
+            .score_sorted_iter_by(vec![Status::Pending, Status::Gap])
+            .map(annotate)
+            .collect();
+
+        let proposed = self.pool_map.sorted_proposed_iter().map(annotate).collect();
+
+        TxPoolAnnotatedIds {
+            pending,
+            proposed,
+            seq: self.seq(),
+        }
+    }
+
+    /// Proposed txs in the order of `sorted_proposed_iter`, skipping the ones whose time locks are
+    /// not satisfied at the snapshot tip, i.e. the txs which could be committed in the next block
+    pub fn sorted_ready_proposed_iter(&self) -> impl Iterator<Item = &TxEntry> {
+        let tx_env = TxVerifyEnv::new_proposed(self.snapshot.tip_header(), 1);
+        self.pool_map.sorted_proposed_iter().filter(move |entry| {
+            time_relative_verify(
+                self.cloned_snapshot(),
+                Arc::clone(&entry.rtx),
+                tx_env.clone(),
+            )
+            .is_ok()
+        })
     }
 
-    pub(crate) fn get_all_entry_info(&self) -> TxPoolEntryInfo {
+    /// Entry info of txs submitted from `origin`, or of all txs if it's `None`, anchored to the
+    /// snapshot at the start of the call
+    pub(crate) fn get_all_entry_info(&self, origin: Option<Origin>) -> TxPoolEntryInfo {
+        let snapshot = self.cloned_snapshot();
+        let matched = |entry: &&TxEntry| origin.map_or(true, |origin| entry.origin == origin);
+        let double_spends = self.pool_map.double_spends.lock();
+        let info = |entry: &TxEntry| {
+            let mut info = entry.to_info();
+            if let Some(attempts) = double_spends.get(&entry.proposal_short_id()) {
+                info.double_spend_attempts = attempts.count;
+                info.double_spend_tx_hashes = attempts.tx_hashes.iter().cloned().collect();
+            }
+            (entry.transaction().hash(), info)
+        };
         let pending = self
             .pool_map
             .score_sorted_iter_by(vec![Status::Pending, Status::Gap])
-            .map(|entry| (entry.transaction().hash(), entry.to_info()))
+            .filter(matched)
+            .map(info)
             .collect();
 
         let proposed = self
             .pool_map
             .sorted_proposed_iter()
-            .map(|entry| (entry.transaction().hash(), entry.to_info()))
+            .filter(matched)
+            .map(info)
             .collect();
 
-        TxPoolEntryInfo { pending, proposed }
+        TxPoolEntryInfo {
+            pending,
+            proposed,
+            tip_hash: snapshot.tip_hash(),
+            seq: self.seq(),
+        }
     }
 
     pub(crate) fn drain_all_transactions(&mut self) -> Vec<TransactionView> {
         let mut txs = CommitTxsScanner::new(&self.pool_map)
-            .txs_to_commit(self.total_tx_size, self.total_tx_cycles)
+            .txs_to_commit(self.total_tx_size, self.total_tx_cycles, None)
             .0
             .into_iter()
             .map(|tx_entry| tx_entry.into_transaction())
@@ -477,13 +2424,72 @@ impl TxPool {
     }
 
     pub(crate) fn clear(&mut self, snapshot: Arc<Snapshot>) {
+        self.record_replay(|_| ReplayEvent::Clear);
         self.pool_map.clear();
-        self.snapshot = snapshot;
-        self.committed_txs_hash_cache = LruCache::new(COMMITTED_HASH_CACHE_SIZE);
+        self.set_snapshot(snapshot);
+        self.committed_txs_hash_cache.clear();
+        self.committed.clear();
+        self.rebroadcast.clear();
+        self.replacements.clear();
+        self.conflict_cache.lock().clear();
+        self.fee_floor = None;
+        self.archive.clear();
+        self.resubmit.clear();
         self.total_tx_size = 0;
         self.total_tx_cycles = 0;
     }
 
+    /// Whether a reorg detaching `depth` blocks is deeper than `reorg_clear_depth`, so the pool
+    /// is cleared rather than reconciled
+    pub(crate) fn is_clearing_reorg(&self, depth: usize) -> bool {
+        self.config
+            .reorg_clear_depth
+            .map_or(false, |clear_depth| depth as u64 > clear_depth)
+    }
+
+    /// Same as `clear`, but returns the dropped entries with parents before their children, so
+    /// the caller can re-submit the ones still valid against the new snapshot.
+    ///
+    /// The resubmission list is kept, and so are the pool statics, the caller notifies the reject
+    /// callbacks of the returned entries, which take them off.
+    pub(crate) fn clear_returning(&mut self, snapshot: Arc<Snapshot>) -> Vec<TxEntry> {
+        // a tx always has more in-pool ancestors than any of its parents
+        let mut entries: Vec<_> = self
+            .pool_map
+            .iter()
+            .map(|entry| entry.inner.clone())
+            .collect();
+        entries.sort_unstable_by_key(|entry| (entry.ancestors_count, entry.timestamp));
+        let resubmit = self.resubmit.take();
+        let (total_tx_size, total_tx_cycles) = (self.total_tx_size, self.total_tx_cycles);
+        self.clear(snapshot);
+        for entry in resubmit {
+            self.resubmit.push(entry);
+        }
+        self.total_tx_size = total_tx_size;
+        self.total_tx_cycles = total_tx_cycles;
+        entries
+    }
+
+    /// Track a locally submitted tx for re-broadcast
+    pub(crate) fn track_local_tx(&mut self, tx: &TransactionView) {
+        let now = self.now_ms();
+        self.rebroadcast
+            .track(tx.proposal_short_id(), tx.hash(), now);
+    }
+
+    /// Pick the local txs due for re-announcement at `now`,
+    /// txs which are no longer pending drop out of the schedule.
+    pub(crate) fn rebroadcast_candidates(&mut self) -> Vec<Byte32> {
+        let now = self.now_ms();
+        let pool_map = &self.pool_map;
+        self.rebroadcast.due(now, |id| {
+            pool_map
+                .get_by_id(id)
+                .map_or(false, |entry| entry.status == Status::Pending)
+        })
+    }
+
     pub(crate) fn package_proposals(
         &self,
         proposals_limit: u64,
@@ -501,9 +2507,21 @@ impl TxPool {
         max_block_cycles: Cycle,
         txs_size_limit: usize,
     ) -> (Vec<TxEntry>, usize, Cycle) {
-        let (entries, size, cycles) =
-            CommitTxsScanner::new(&self.pool_map).txs_to_commit(txs_size_limit, max_block_cycles);
+        let max_duration = self
+            .config
+            .max_package_duration_millis
+            .map(Duration::from_millis);
+        let (entries, size, cycles, timed_out) = CommitTxsScanner::new(&self.pool_map)
+            .with_soft_cycles_target(self.config.soft_cycle_target)
+            .txs_to_commit(txs_size_limit, max_block_cycles, max_duration);
 
+        if timed_out {
+            warn!(
+                "[get_block_template] package txs timed out after {:?}, {} txs selected",
+                max_duration,
+                entries.len()
+            );
+        }
         if !entries.is_empty() {
             ckb_logger::info!(
                 "[get_block_template] candidate txs count: {}, size: {}/{}, cycles:{}/{}",
@@ -517,6 +2535,97 @@ impl TxPool {
         (entries, size, cycles)
     }
 
+    /// Projects the next `k` blocks within the size left for txs in the last block template and
+    /// the consensus cycles limit.
+    ///
+    /// Each block is filled with the proposed txs the way `package_txs` fills the block template,
+    /// assuming the previous blocks committed, then from the second block on with the pending and
+    /// gap txs by score, once their in-pool parents are placed, they have to be proposed before
+    /// being committed. Stops early when the pool is exhausted or after `MAX_PREVIEW_DURATION`,
+    /// the pool is left untouched.
+    pub fn preview_blocks(&self, k: usize) -> BlocksPreview {
+        let snapshot = self.cloned_snapshot();
+        let consensus = snapshot.consensus();
+        let size_limit = self.template_txs_size_limit(consensus);
+        let cycles_limit = consensus.max_block_cycles();
+        let deadline = Instant::now().checked_add(MAX_PREVIEW_DURATION);
+        let timed_out = || deadline.map_or(false, |deadline| Instant::now() >= deadline);
+        let mut scanner = CommitTxsScanner::new(&self.pool_map)
+            .with_soft_cycles_target(self.config.soft_cycle_target);
+        let unproposed: Vec<_> = self
+            .pool_map
+            .score_sorted_iter_by(vec![Status::Pending, Status::Gap])
+            .collect();
+        let mut placed = HashSet::new();
+        let mut blocks = Vec::with_capacity(k);
+        while blocks.len() < k && !timed_out() {
+            let mut block = PreviewBlock::default();
+            let (entries, _, _, _) = scanner.next_block(size_limit, cycles_limit, deadline);
+            for entry in entries.iter() {
+                placed.insert(entry.proposal_short_id());
+                block.push(entry, true);
+            }
+            // the unproposed txs can't make it in the next block
+            let candidates = if blocks.is_empty() {
+                &[][..]
+            } else {
+                &unproposed[..]
+            };
+            for entry in candidates.iter() {
+                if timed_out() {
+                    break;
+                }
+                let id = entry.proposal_short_id();
+                if placed.contains(&id)
+                    || block.size.saturating_add(entry.size) > size_limit
+                    || block.cycles.saturating_add(entry.cycles) > cycles_limit
+                {
+                    continue;
+                }
+                let parents_placed = self
+                    .pool_map
+                    .links
+                    .get_parents(&id)
+                    .map_or(true, |parents| parents.iter().all(|p| placed.contains(p)));
+                if parents_placed {
+                    placed.insert(id);
+                    block.push(entry, false);
+                }
+            }
+            if block.is_empty() && (!blocks.is_empty() || unproposed.is_empty()) {
+                break;
+            }
+            blocks.push(block);
+        }
+        BlocksPreview {
+            tip_hash: snapshot.tip_hash(),
+            blocks,
+        }
+    }
+
+    // the bytes left for txs in the last block template, or in an empty block with no proposal
+    // until one is built
+    fn template_txs_size_limit(&self, consensus: &Consensus) -> usize {
+        self.template_refresh
+            .lock()
+            .txs_size_limit()
+            .unwrap_or_else(|| {
+                let basic_size = BlockAssembler::basic_block_size(
+                    packed::Transaction::default(),
+                    &[],
+                    std::iter::empty(),
+                    None,
+                );
+                (consensus.max_block_bytes() as usize).saturating_sub(basic_size)
+            })
+    }
+
+    /// Checks whether the tx can replace its conflicting entries, the RBF rules are checked in
+    /// the order of `RBFRule`, rule #2 before the fee checks, and the first one failing rejects
+    /// the tx. Rules #5 and #6 are checked together, conflict by conflict.
+    ///
+    /// If `detailed`, also returns the rules checked with whether each one passed, ending with
+    /// the failing rule if any.
     pub(crate) fn check_rbf(
         &self,
         snapshot: &Snapshot,
@@ -524,6 +2633,26 @@ impl TxPool {
         conflict_ids: &HashSet<ProposalShortId>,
         fee: Capacity,
         tx_size: usize,
+        detailed: bool,
+    ) -> (Result<(), Reject>, Vec<(RBFRule, bool)>) {
+        let mut rules = Vec::new();
+        let ret =
+            self.check_rbf_rules(snapshot, rtx, conflict_ids, fee, tx_size, |rule, passed| {
+                if detailed {
+                    rules.push((rule, passed));
+                }
+            });
+        (ret, rules)
+    }
+
+    fn check_rbf_rules(
+        &self,
+        snapshot: &Snapshot,
+        rtx: &ResolvedTransaction,
+        conflict_ids: &HashSet<ProposalShortId>,
+        fee: Capacity,
+        tx_size: usize,
+        mut record: impl FnMut(RBFRule, bool),
     ) -> Result<(), Reject> {
         assert!(self.enable_rbf());
         assert!(!conflict_ids.is_empty());
@@ -535,21 +2664,6 @@ impl TxPool {
         assert!(conflicts.len() == conflict_ids.len());
 
         let short_id = rtx.transaction.proposal_short_id();
-        // Rule #4, new tx's fee need to higher than min_rbf_fee computed from the tx_pool configuration
-        // Rule #3, new tx's fee need to higher than conflicts, here we only check the root tx
-        if let Some(min_replace_fee) = self.calculate_min_replace_fee(&conflicts, tx_size) {
-            if fee < min_replace_fee {
-                return Err(Reject::RBFRejected(format!(
-                    "Tx's current fee is {}, expect it to >= {} to replace old txs",
-                    fee, min_replace_fee,
-                )));
-            }
-        } else {
-            return Err(Reject::RBFRejected(
-                "calculate_min_replace_fee failed".to_string(),
-            ));
-        }
-
         // Rule #2, new tx don't contain any new unconfirmed inputs
         let mut inputs = HashSet::new();
         let mut outputs = HashSet::new();
@@ -563,6 +2677,7 @@ impl TxPool {
             .input_pts_iter()
             .any(|pt| !inputs.contains(&pt) && !snapshot.transaction_exists(&pt.tx_hash()))
         {
+            record(RBFRule::NoNewUnconfirmedInputs, false);
             return Err(Reject::RBFRejected(
                 "new Tx contains unconfirmed inputs".to_string(),
             ));
@@ -573,19 +2688,52 @@ impl TxPool {
             .cell_deps_iter()
             .any(|dep| outputs.contains(&dep.out_point()))
         {
+            record(RBFRule::NoNewUnconfirmedInputs, false);
             return Err(Reject::RBFRejected(
                 "new Tx contains cell deps from conflicts".to_string(),
             ));
         }
+        record(RBFRule::NoNewUnconfirmedInputs, true);
+
+        // Rule #4, new tx's fee need to higher than min_rbf_fee computed from the tx_pool configuration
+        // Rule #3, new tx's fee need to higher than conflicts, here we only check the root tx
+        let min_replace_fee = match self.calculate_min_replace_fee(&conflicts, tx_size) {
+            Some(min_replace_fee) => min_replace_fee,
+            None => {
+                record(RBFRule::PaysReplacedFees, false);
+                return Err(Reject::RBFRejected(
+                    "calculate_min_replace_fee failed".to_string(),
+                ));
+            }
+        };
+        if fee < min_replace_fee {
+            // can't overflow, the sum plus the extra rbf fee did not
+            let replaced_fee =
+                Capacity::shannons(conflicts.iter().map(|c| c.inner.fee.as_u64()).sum());
+            if fee < replaced_fee {
+                record(RBFRule::PaysReplacedFees, false);
+            } else {
+                record(RBFRule::PaysReplacedFees, true);
+                record(RBFRule::PaysMinRbfFee, false);
+            }
+            return Err(Reject::RBFRejected(format!(
+                "Tx's current fee is {}, expect it to >= {} to replace old txs",
+                fee, min_replace_fee,
+            )));
+        }
+        record(RBFRule::PaysReplacedFees, true);
+        record(RBFRule::PaysMinRbfFee, true);
 
         // Rule #5, the replaced tx's descendants can not more than 100
         // and the ancestor of the new tx don't have common set with the replaced tx's descendants
         let mut replace_count: usize = 0;
+        let mut removed = HashSet::new();
         let ancestors = self.pool_map.calc_ancestors(&short_id);
         for conflict in conflicts.iter() {
             let descendants = self.pool_map.calc_descendants(&conflict.id);
             replace_count += descendants.len() + 1;
             if replace_count > MAX_REPLACEMENT_CANDIDATES {
+                record(RBFRule::LimitedReplacement, false);
                 return Err(Reject::RBFRejected(format!(
                     "Tx conflict too many txs, conflict txs count: {}",
                     replace_count,
@@ -593,6 +2741,7 @@ impl TxPool {
             }
 
             if !descendants.is_disjoint(&ancestors) {
+                record(RBFRule::LimitedReplacement, false);
                 return Err(Reject::RBFRejected(
                     "Tx ancestors have common with conflict Tx descendants".to_string(),
                 ));
@@ -610,6 +2759,7 @@ impl TxPool {
                     .input_pts_iter()
                     .any(|pt| pt.tx_hash() == hash)
                 {
+                    record(RBFRule::LimitedReplacement, false);
                     return Err(Reject::RBFRejected(
                         "new Tx contains inputs in descendants of to be replaced Tx".to_string(),
                     ));
@@ -623,16 +2773,114 @@ impl TxPool {
                 .iter()
                 .any(|s| ![Status::Pending, Status::Gap].contains(s))
             {
+                record(RBFRule::ReplacesPendingOnly, false);
                 // Here we only refer to `Pending` status, since `Gap` is an internal status
                 return Err(Reject::RBFRejected(
                     "all conflict Txs should be in Pending status".to_string(),
                 ));
             }
+            removed.extend(descendants);
+            removed.insert(conflict.id.clone());
+        }
+        record(RBFRule::LimitedReplacement, true);
+        record(RBFRule::ReplacesPendingOnly, true);
+
+        // Rule #7, the tx would be rejected by the ancestors check once the replaced txs are
+        // gone, both would be lost then
+        let ancestors = self.pool_map.calc_tx_ancestors(&rtx.transaction, &removed);
+        let ancestors_count = ancestors.len() + 1;
+        if ancestors_count > self.config.max_ancestors_count {
+            record(RBFRule::WithinAncestorLimits, false);
+            return Err(Reject::RBFRejected(format!(
+                "Tx would have {} ancestors, more than max_ancestors_count {}",
+                ancestors_count, self.config.max_ancestors_count,
+            )));
         }
+        let ancestors_size = ancestors
+            .iter()
+            .filter_map(|id| self.get_pool_entry(id))
+            .fold(tx_size, |size, entry| size.saturating_add(entry.inner.size));
+        if ancestors_size > self.pool_map.max_package_size {
+            record(RBFRule::WithinAncestorLimits, false);
+            return Err(Reject::RBFRejected(format!(
+                "Tx would have ancestors of {} bytes, more than the package limit {}",
+                ancestors_size, self.pool_map.max_package_size,
+            )));
+        }
+        record(RBFRule::WithinAncestorLimits, true);
 
         Ok(())
     }
 
+    /// Records the reject of a tx in `recent_reject`.
+    ///
+    /// A failed write, e.g. when the disk is full, disables `recent_reject` for the rest of the
+    /// run instead of failing every later reject the same way.
+    pub fn put_recent_reject(&mut self, tx_hash: &Byte32, reject: &Reject) {
+        let now = self.now_ms();
+        if let Some(ref mut recent_reject) = self.recent_reject {
+            if let Err(e) = recent_reject.put(tx_hash, reject.clone(), now) {
+                error!(
+                    "record recent_reject failed {} {} {}, recent_reject is disabled",
+                    tx_hash, reject, e
+                );
+                self.recent_reject = None;
+                self.recent_reject_failed = true;
+            }
+        }
+    }
+
+    /// Returns false if `recent_reject` was disabled after a write failure
+    pub fn recent_reject_healthy(&self) -> bool {
+        !self.recent_reject_failed
+    }
+
+    /// Makes the pool read-only for maintenance, or writable again.
+    ///
+    /// While read-only, new txs are rejected with `Reject::Maintenance`, relayed or submitted
+    /// locally alike, the pool entries are still packaged, committed and expired as usual.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        if self.read_only != read_only {
+            info!(
+                "tx-pool read-only mode {}",
+                if read_only { "on" } else { "off" }
+            );
+        }
+        self.read_only = read_only;
+    }
+
+    /// Whether the pool is read-only for maintenance, see `set_read_only`
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Appends the event built by `event` to the replay log, the event is only built if the log
+    /// is open. Returns false once the log is full, it's closed by the next `record_replay`.
+    pub(crate) fn try_record_replay(&self, event: impl FnOnce(&TxPool) -> ReplayEvent) -> bool {
+        match &self.replay_log {
+            Some(log) if log.is_full() => false,
+            Some(log) => log.append(&event(self).encode(self.now_ms())),
+            None => true,
+        }
+    }
+
+    /// Appends the event built by `event` to the replay log like `try_record_replay`, the log is
+    /// closed once full.
+    pub(crate) fn record_replay(&mut self, event: impl FnOnce(&TxPool) -> ReplayEvent) {
+        if !self.try_record_replay(event) {
+            self.close_replay_log();
+        }
+    }
+
+    /// Closes the replay log, the entries the pool holds are written as the last record, the
+    /// expectation a replay of the log is checked against. Waits for the log to be flushed.
+    pub(crate) fn close_replay_log(&mut self) {
+        if let Some(log) = self.replay_log.take() {
+            let entries = TxPoolEntries::from(self.get_all_entry_info(None));
+            log.close(&ReplayEvent::Expect(Box::new(entries)).encode(self.now_ms()));
+        }
+    }
+
     fn build_recent_reject(config: &TxPoolConfig) -> Option<RecentReject> {
         if !config.recent_reject.as_os_str().is_empty() {
             let recent_reject_ttl =
@@ -642,7 +2890,12 @@ impl TxPool {
                 config.keep_rejected_tx_hashes_count,
                 recent_reject_ttl,
             ) {
-                Ok(recent_reject) => Some(recent_reject),
+                Ok(recent_reject) => match config.keep_rejected_tx_hashes_count_max {
+                    Some(max_count_limit) => {
+                        Some(recent_reject.with_max_count_limit(max_count_limit))
+                    }
+                    None => Some(recent_reject),
+                },
                 Err(err) => {
                     error!(
                         "Failed to open recent reject database {:?} {}",
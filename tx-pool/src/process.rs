@@ -1,43 +1,57 @@
 use crate::callback::Callbacks;
+use crate::component::changelog::RemoveReason;
 use crate::component::entry::TxEntry;
+use crate::component::eviction::EvictionSummary;
 use crate::component::orphan::Entry as OrphanEntry;
 use crate::component::pool_map::Status;
+use crate::component::replay::ReplayEvent;
+use crate::component::resubmit::{ResubmitEntry, ResubmitReport};
+use crate::component::trace::TxEvent;
 use crate::error::Reject;
 use crate::pool::TxPool;
 use crate::service::{BlockAssemblerMessage, TxPoolService, TxVerificationResult};
 use crate::try_or_return_with_snapshot;
 use crate::util::{
-    after_delay_window, check_tx_fee, check_txid_collision, is_missing_input,
-    non_contextual_verify, time_relative_verify, verify_rtx,
+    after_delay_window, check_entry_min_fee, check_hash_types, check_output_policy, check_tx_fee,
+    check_txid_collision, is_missing_input, is_recorded_reject, non_contextual_verify, origin_of,
+    time_relative_verify, verify_rtx,
 };
+use ckb_app_config::ConsumedDepPolicy;
 use ckb_chain_spec::consensus::MAX_BLOCK_PROPOSALS_LIMIT;
 use ckb_error::{AnyError, InternalErrorKind};
 use ckb_jsonrpc_types::BlockTemplate;
 use ckb_logger::Level::Trace;
-use ckb_logger::{debug, error, info, log_enabled_target, trace_target};
+use ckb_logger::{debug, error, info, log_enabled_target, trace_target, warn};
 use ckb_network::PeerIndex;
 use ckb_snapshot::Snapshot;
 use ckb_store::data_loader_wrapper::AsDataLoader;
 use ckb_store::ChainStore;
 use ckb_types::core::error::OutPointError;
 use ckb_types::{
-    core::{cell::ResolvedTransaction, BlockView, Capacity, Cycle, HeaderView, TransactionView},
+    core::{
+        cell::ResolvedTransaction,
+        tx_pool::{Origin, RBFRule},
+        BlockView, Capacity, Cycle, HeaderView, TransactionView,
+    },
     packed::{Byte32, ProposalShortId},
 };
 use ckb_util::LinkedHashSet;
 use ckb_verification::{
-    cache::{CacheEntry, Completed},
-    ContextualTransactionVerifier, DaoScriptSizeVerifier, ScriptVerifyResult,
+    cache::{prewarm, CacheEntry, Completed, ForkContext},
+    ContextualTransactionVerifier, DaoScriptSizeVerifier, MaturityVerifier, ScriptVerifyResult,
     TimeRelativeTransactionVerifier, TxVerifyEnv,
 };
 use std::collections::HashSet;
 use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::task::block_in_place;
 
 const DELAY_LIMIT: usize = 1_500 * 21; // 1_500 per block, 21 blocks
 
+// blocks the pool snapshot may lag behind the new tip before it is reported as stale
+const MAX_SNAPSHOT_GAP: u64 = 10;
+
 /// A list for plug target for `plug_entry` method
 pub enum PlugTarget {
     /// Pending pool
@@ -72,6 +86,11 @@ impl TxStatus {
 impl TxPoolService {
     pub(crate) async fn get_block_template(&self) -> Result<BlockTemplate, AnyError> {
         if let Some(ref block_assembler) = self.block_assembler {
+            if self.tx_pool.read().await.is_template_dirty() {
+                if let Err(e) = block_assembler.update_transactions(&self.tx_pool).await {
+                    error!("block_assembler update_transactions error {}", e);
+                }
+            }
             Ok(block_assembler.get_current().await)
         } else {
             Err(InternalErrorKind::Config
@@ -103,7 +122,9 @@ impl TxPoolService {
         entry: TxEntry,
         mut status: TxStatus,
         conflicts: HashSet<ProposalShortId>,
-    ) -> (Result<(), Reject>, Arc<Snapshot>) {
+    ) -> (Result<TxStatus, Reject>, Arc<Snapshot>) {
+        let mut replaced_txs = vec![];
+        let replaced = &mut replaced_txs;
         let (ret, snapshot) = self
             .with_tx_pool_write_lock(move |tx_pool, snapshot| {
                 // if snapshot changed by context switch
@@ -125,10 +146,16 @@ impl TxPoolService {
                     time_relative_verify(snapshot, Arc::clone(&entry.rtx), tx_env)?;
                 }
 
+                // the cycles are known now, the fee rate is checked over the weight
+                check_entry_min_fee(tx_pool, &entry)?;
+                if conflicts.is_empty() {
+                    tx_pool.check_entry_limit(entry.fee, entry.weight())?;
+                }
+
                 // try to remove conflicted tx here
                 for id in conflicts.iter() {
-                    let removed = tx_pool.pool_map.remove_entry_and_descendants(id);
-                    for old in removed {
+                    let removed = tx_pool.remove_replaced(id);
+                    for old in removed.iter() {
                         debug!(
                             "remove conflict tx {} for RBF by new tx {}",
                             old.transaction().hash(),
@@ -140,14 +167,19 @@ impl TxPoolService {
                         ));
                         // remove old tx from tx_pool, not happened in service so we didn't call reject callbacks
                         // here we call them manually
-                        self.callbacks.call_reject(tx_pool, &old, reject)
+                        self.callbacks.call_reject(tx_pool, old, reject)
                     }
+                    tx_pool.record_replacement(entry.transaction().hash(), &removed);
+                    replaced.extend(removed.into_iter().map(|old| old.transaction().clone()));
                 }
-                _submit_entry(tx_pool, status, entry.clone(), &self.callbacks)?;
-                Ok(())
+                _submit_entry(tx_pool, status, entry.clone(), &self.callbacks)
             })
             .await;
 
+        if !replaced_txs.is_empty() {
+            self.remove_orphans_by_previous(&replaced_txs).await;
+        }
+
         (ret, snapshot)
     }
 
@@ -214,47 +246,24 @@ impl TxPoolService {
     pub(crate) async fn pre_check(
         &self,
         tx: &TransactionView,
+        origin: Origin,
     ) -> (Result<PreCheckedTx, Reject>, Arc<Snapshot>) {
         // Acquire read lock for cheap check
-        let tx_size = tx.data().serialized_size_in_block();
-
-        let (ret, snapshot) = self
+        let mut lost_double_spend = false;
+        let ((ret, now), snapshot) = self
             .with_tx_pool_read_lock(|tx_pool, snapshot| {
-                let tip_hash = snapshot.tip_hash();
-
-                // Same txid means exactly the same transaction, including inputs, outputs, witnesses, etc.
-                // It's also not possible for RBF, reject it directly
-                check_txid_collision(tx_pool, tx)?;
-
-                // Try normal path first, if double-spending check success we don't need RBF check
-                // this make sure RBF won't introduce extra performance cost for hot path
-                let res = resolve_tx(tx_pool, &snapshot, tx.clone(), false);
-                match res {
-                    Ok((rtx, status)) => {
-                        let fee = check_tx_fee(tx_pool, &snapshot, &rtx, tx_size)?;
-                        Ok((tip_hash, rtx, status, fee, tx_size, HashSet::new()))
-                    }
-                    Err(err) => {
-                        if tx_pool.enable_rbf()
-                            && matches!(err, Reject::Resolve(OutPointError::Dead(_)))
-                        {
-                            // Try RBF check
-                            let conflicts = tx_pool.pool_map.find_conflict_tx(tx);
-                            if conflicts.is_empty() {
-                                return Err(err);
-                            }
-                            let (rtx, status) = resolve_tx(tx_pool, &snapshot, tx.clone(), true)?;
-                            let fee = check_tx_fee(tx_pool, &snapshot, &rtx, tx_size)?;
-                            tx_pool.check_rbf(&snapshot, &rtx, &conflicts, fee, tx_size)?;
-                            Ok((tip_hash, rtx, status, fee, tx_size, conflicts))
-                        } else {
-                            Err(err)
-                        }
-                    }
-                }
+                let now = tx_pool.now_ms();
+                let ret =
+                    pre_check_attempt(tx_pool, &snapshot, tx, origin, now, &mut lost_double_spend);
+                (ret, now)
             })
             .await;
 
+        if let Err(ref reject) = ret {
+            if lost_double_spend {
+                self.callbacks.call_double_spend(tx, reject.clone(), now);
+            }
+        }
         (ret, snapshot)
     }
 
@@ -274,14 +283,41 @@ impl TxPoolService {
         Ok(())
     }
 
+    /// Throttles the txs relayed by a peer above its admission rate, local txs are exempt
+    pub(crate) fn check_peer_rate(
+        &self,
+        tx: &TransactionView,
+        remote: Option<(Cycle, PeerIndex)>,
+    ) -> Result<(), Reject> {
+        if let Some((_, peer)) = remote {
+            let now = self.clock.now_ms();
+            if !self.peer_rate_limiter.lock().try_admit(peer, now) {
+                debug!("reject tx {} from peer {}, rate limited", tx.hash(), peer);
+                // the relayer forgets the tx so it may be fetched again later, the peer is not banned
+                self.send_result_to_relayer(TxVerificationResult::Reject { tx_hash: tx.hash() });
+                return Err(Reject::RateLimited(peer.to_string()));
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) async fn resumeble_process_tx(
         &self,
         tx: TransactionView,
         remote: Option<(Cycle, PeerIndex)>,
     ) -> Result<(), Reject> {
+        self.check_peer_rate(&tx, remote)?;
+
         // non contextual verify first
         self.non_contextual_verify(&tx, None)?;
 
+        // a local tx may replace the witnesses of the same tx in the pool
+        if remote.is_none() {
+            if let Some(ret) = self.swap_witnesses(&tx).await {
+                return ret;
+            }
+        }
+
         if self.chunk_contains(&tx).await {
             return Err(Reject::Duplicated(tx.hash()));
         }
@@ -311,11 +347,81 @@ impl TxPoolService {
         }
     }
 
+    /// Replaces the pool entry of a tx with the same hash as `tx` but other witnesses once `tx`
+    /// passes the verification, returns `None` if there is no such entry.
+    ///
+    /// Both variants spend the same inputs and create the same outputs so they pay the same fee,
+    /// the first seen variant is kept when a variant is relayed. The swap is checked like a new
+    /// tx before and after the scripts run, then the new variant is relayed.
+    async fn swap_witnesses(&self, tx: &TransactionView) -> Option<Result<(), Reject>> {
+        let tx_size = tx.data().serialized_size_in_block();
+        let (checked, snapshot) = self
+            .with_tx_pool_read_lock(|tx_pool, _| {
+                tx_pool.witness_variant(tx)?;
+                tx_pool.record_trace(&tx.hash(), TxEvent::Received);
+                let checked = tx_pool
+                    .check_witness_swap(tx, tx_size, None)
+                    .map(|stored| Arc::clone(&stored.rtx));
+                Some(checked)
+            })
+            .await;
+        let ret = match checked? {
+            Ok(stored) => {
+                self.verify_witness_swap(tx, stored, tx_size, &snapshot)
+                    .await
+            }
+            Err(reject) => Err(reject),
+        };
+        self.after_process(tx.clone(), None, &snapshot, &ret).await;
+        Some(ret.map(|_| ()))
+    }
+
+    // the witnesses are not covered by the verify cache of the stored variant, the scripts run
+    // again on the blocking pool rather than on the service executor
+    async fn verify_witness_swap(
+        &self,
+        tx: &TransactionView,
+        stored: Arc<ResolvedTransaction>,
+        tx_size: usize,
+        snapshot: &Arc<Snapshot>,
+    ) -> Result<Completed, Reject> {
+        let rtx = Arc::new(ResolvedTransaction {
+            transaction: tx.clone(),
+            ..(*stored).clone()
+        });
+        let status = get_tx_status(snapshot, &tx.proposal_short_id());
+        let tx_env = Arc::new(status.with_env(snapshot.tip_header()));
+        let max_cycles = self.consensus.max_block_cycles();
+        let verify = {
+            let snapshot = Arc::clone(snapshot);
+            let rtx = Arc::clone(&rtx);
+            move || verify_rtx(snapshot, rtx, tx_env, &None, max_cycles)
+        };
+        let verified = tokio::task::spawn_blocking(verify).await.map_err(|err| {
+            Reject::Verification(InternalErrorKind::System.other(err.to_string()).into())
+        })??;
+        self.tracer.record(
+            &tx.hash(),
+            TxEvent::Verified {
+                cycles: verified.cycles,
+            },
+        );
+
+        let (ret, _) = self
+            .with_tx_pool_write_lock(|tx_pool, _| {
+                tx_pool.swap_witnesses(Arc::clone(&rtx), verified.cycles, tx_size)
+            })
+            .await;
+        ret.map(|_| verified)
+    }
+
     pub(crate) async fn process_tx(
         &self,
         tx: TransactionView,
         remote: Option<(Cycle, PeerIndex)>,
     ) -> Result<Completed, Reject> {
+        self.check_peer_rate(&tx, remote)?;
+
         // non contextual verify first
         self.non_contextual_verify(&tx, remote)?;
 
@@ -337,11 +443,79 @@ impl TxPoolService {
 
     pub(crate) async fn put_recent_reject(&self, tx_hash: &Byte32, reject: &Reject) {
         let mut tx_pool = self.tx_pool.write().await;
-        if let Some(ref mut recent_reject) = tx_pool.recent_reject {
-            if let Err(e) = recent_reject.put(tx_hash, reject.clone()) {
-                error!("record recent_reject failed {} {} {}", tx_hash, reject, e);
+        tx_pool.put_recent_reject(tx_hash, reject);
+    }
+
+    /// Checks the RBF rules of the tx against its conflicts in the pool without submitting it,
+    /// see `TxPool::check_rbf`, empty if the tx conflicts with nothing
+    pub(crate) async fn check_rbf_report(
+        &self,
+        tx: TransactionView,
+    ) -> Result<Vec<(RBFRule, bool)>, Reject> {
+        self.with_tx_pool_read_lock(|tx_pool, snapshot| {
+            if !tx_pool.enable_rbf() {
+                return Err(Reject::RBFRejected("RBF is disabled".to_string()));
+            }
+            check_txid_collision(tx_pool, &tx)?;
+            let conflicts: HashSet<_> =
+                tx_pool.pool_map.find_conflict_tx(&tx).into_iter().collect();
+            if conflicts.is_empty() {
+                return Ok(Vec::new());
+            }
+            let tx_size = tx.data().serialized_size_in_block();
+            let (rtx, _) = resolve_tx(tx_pool, &snapshot, tx.clone(), true)?;
+            let fee = check_tx_fee(tx_pool, &snapshot, &rtx, tx_size)?;
+            let (_, rules) = tx_pool.check_rbf(&snapshot, &rtx, &conflicts, fee, tx_size, true);
+            Ok(rules)
+        })
+        .await
+    }
+
+    pub(crate) async fn resubmit_expired(&self) -> ResubmitReport {
+        let (entries, dropped) = {
+            let mut tx_pool = self.tx_pool.write().await;
+            tx_pool.take_resubmit_list()
+        };
+        self.resubmit(entries, dropped).await
+    }
+
+    /// Submits `entries` again as local txs, the ones which may pass later are put in the
+    /// resubmission list
+    async fn resubmit(
+        &self,
+        entries: Vec<ResubmitEntry>,
+        dropped: Vec<(Byte32, Reject)>,
+    ) -> ResubmitReport {
+        let mut report = ResubmitReport {
+            dropped,
+            ..Default::default()
+        };
+        for entry in entries {
+            let tx_hash = entry.transaction.hash();
+            match self
+                .resumeble_process_tx(entry.transaction.clone(), None)
+                .await
+            {
+                Ok(()) => report.resubmitted.push(tx_hash),
+                // spent or already back, no point in trying again
+                Err(
+                    reject @ (Reject::Resolve(OutPointError::Dead(_))
+                    | Reject::Conflict { .. }
+                    | Reject::Duplicated(_)),
+                ) => report.dropped.push((tx_hash, reject)),
+                Err(reject) => {
+                    self.tx_pool.write().await.keep_for_resubmit(entry);
+                    report.kept.push((tx_hash, reject));
+                }
             }
         }
+        info!(
+            "resubmit txs, resubmitted {} dropped {} kept {}",
+            report.resubmitted.len(),
+            report.dropped.len(),
+            report.kept.len()
+        );
+        report
     }
 
     pub(crate) async fn remove_tx(&self, tx_hash: Byte32) -> bool {
@@ -371,19 +545,13 @@ impl TxPoolService {
     ) {
         let tx_hash = tx.hash();
 
-        // The network protocol is switched after tx-pool confirms the cache,
-        // there will be no problem with the current state as the choice of the broadcast protocol.
-        let with_vm_2023 = {
-            let epoch = snapshot
-                .tip_header()
-                .epoch()
-                .minimum_epoch_number_after_n_blocks(1);
-
-            self.consensus
-                .hardfork_switch
-                .ckb2023
-                .is_vm_version_2_and_syscalls_3_enabled(epoch)
-        };
+        let with_vm_2023 = self.is_vm_2023_enabled(snapshot);
+
+        if let Err(reject) = ret {
+            self.tracer
+                .record(&tx_hash, TxEvent::Rejected(reject.to_string()));
+            self.record_rejected(&tx_hash, reject).await;
+        }
 
         // log tx verification result for monitor node
         if log_enabled_target!("ckb_tx_monitor", Trace) {
@@ -409,7 +577,7 @@ impl TxPoolService {
                 }
                 Err(reject) => {
                     debug!("after_process {} remote reject: {} ", tx_hash, reject);
-                    if is_missing_input(reject) && all_inputs_is_unknown(snapshot, &tx) {
+                    if is_missing_input(reject, &tx) && all_inputs_is_unknown(snapshot, &tx) {
                         self.add_orphan(tx, peer, declared_cycle).await;
                     } else {
                         if reject.is_malformed_tx() {
@@ -421,12 +589,7 @@ impl TxPoolService {
                             });
                         }
 
-                        if matches!(
-                            reject,
-                            Reject::Resolve(..)
-                                | Reject::Verification(..)
-                                | Reject::RBFRejected(..)
-                        ) {
+                        if is_recorded_reject(reject) {
                             self.put_recent_reject(&tx_hash, reject).await;
                         }
                     }
@@ -440,6 +603,10 @@ impl TxPoolService {
                             with_vm_2023,
                             tx_hash,
                         });
+                        {
+                            let mut tx_pool = self.tx_pool.write().await;
+                            tx_pool.track_local_tx(&tx);
+                        }
                         self.process_orphan_tx(&tx).await;
                     }
                     Err(Reject::Duplicated(_)) => {
@@ -452,12 +619,7 @@ impl TxPoolService {
                     }
                     Err(reject) => {
                         debug!("after_process {} reject: {} ", tx_hash, reject);
-                        if matches!(
-                            reject,
-                            Reject::Resolve(..)
-                                | Reject::Verification(..)
-                                | Reject::RBFRejected(..)
-                        ) {
+                        if is_recorded_reject(reject) {
                             self.put_recent_reject(&tx_hash, reject).await;
                         }
                     }
@@ -466,6 +628,18 @@ impl TxPoolService {
         }
     }
 
+    /// Records the reject an admission attempt ended with in the replay log
+    async fn record_rejected(&self, tx_hash: &Byte32, reject: &Reject) {
+        if self.tx_pool_config.replay_log.is_none() {
+            return;
+        }
+        let tx_pool = self.tx_pool.read().await;
+        tx_pool.try_record_replay(|_| ReplayEvent::Rejected {
+            tx_hash: tx_hash.clone(),
+            reason: reject.to_string(),
+        });
+    }
+
     pub(crate) async fn add_orphan(
         &self,
         tx: TransactionView,
@@ -493,6 +667,24 @@ impl TxPoolService {
             .collect::<Vec<_>>()
     }
 
+    /// Drops the orphans rooted at the outputs of replaced txs, they could never be resolved
+    pub(crate) async fn remove_orphans_by_previous(&self, replaced: &[TransactionView]) {
+        let removed = self
+            .orphan
+            .write()
+            .await
+            .remove_orphans_by_previous(replaced.iter());
+        for entry in removed {
+            debug!(
+                "remove orphan tx {} rooted at a replaced tx",
+                entry.tx.hash()
+            );
+            self.send_result_to_relayer(TxVerificationResult::Reject {
+                tx_hash: entry.tx.hash(),
+            });
+        }
+    }
+
     pub(crate) async fn remove_orphan_tx(&self, id: &ProposalShortId) {
         self.orphan.write().await.remove_orphan_tx(id);
     }
@@ -514,10 +706,11 @@ impl TxPoolService {
                         tx.hash(),
                     );
                     self.remove_orphan_tx(&orphan.tx.proposal_short_id()).await;
-                    self.chunk
-                        .write()
-                        .await
-                        .add_tx(orphan.tx, Some((orphan.cycle, orphan.peer)));
+                    self.chunk.write().await.add_tx(
+                        orphan.tx,
+                        Some((orphan.cycle, orphan.peer)),
+                        self.clock.now_ms(),
+                    );
                 } else if let Some((ret, snapshot)) = self
                     ._process_tx(orphan.tx.clone(), Some(orphan.cycle))
                     .await
@@ -555,8 +748,9 @@ impl TxPoolService {
                                 reject,
                                 tx.hash(),
                             );
+                            self.record_rejected(&orphan.tx.hash(), &reject).await;
 
-                            if !is_missing_input(&reject) {
+                            if !is_missing_input(&reject, &orphan.tx) {
                                 self.remove_orphan_tx(&orphan.tx.proposal_short_id()).await;
                                 if reject.is_malformed_tx() {
                                     self.ban_malformed(orphan.peer, format!("reject {reject}"));
@@ -566,12 +760,7 @@ impl TxPoolService {
                                         tx_hash: orphan.tx.hash(),
                                     });
                                 }
-                                if matches!(
-                                    reject,
-                                    Reject::Resolve(..)
-                                        | Reject::Verification(..)
-                                        | Reject::RBFRejected(..)
-                                ) {
+                                if is_recorded_reject(&reject) {
                                     self.put_recent_reject(&orphan.tx.hash(), &reject).await;
                                 }
                             }
@@ -582,6 +771,41 @@ impl TxPoolService {
         }
     }
 
+    // The network protocol is switched after tx-pool confirms the cache,
+    // there will be no problem with the current state as the choice of the broadcast protocol.
+    fn is_vm_2023_enabled(&self, snapshot: &Snapshot) -> bool {
+        let epoch = snapshot
+            .tip_header()
+            .epoch()
+            .minimum_epoch_number_after_n_blocks(1);
+
+        self.consensus
+            .hardfork_switch
+            .ckb2023
+            .is_vm_version_2_and_syscalls_3_enabled(epoch)
+    }
+
+    /// Re-announce local txs which are still pending after the configured age
+    pub(crate) async fn rebroadcast_local_txs(&self) {
+        let (tx_hashes, snapshot) = {
+            let mut tx_pool = self.tx_pool.write().await;
+            (tx_pool.rebroadcast_candidates(), tx_pool.cloned_snapshot())
+        };
+        if tx_hashes.is_empty() {
+            return;
+        }
+
+        debug!("re-broadcast {} local pending txs", tx_hashes.len());
+        let with_vm_2023 = self.is_vm_2023_enabled(&snapshot);
+        for tx_hash in tx_hashes {
+            self.send_result_to_relayer(TxVerificationResult::Ok {
+                original_peer: None,
+                with_vm_2023,
+                tx_hash,
+            });
+        }
+    }
+
     pub(crate) fn send_result_to_relayer(&self, result: TxVerificationResult) {
         if let Err(e) = self.tx_relay_sender.send(result) {
             error!("tx-pool tx_relay_sender internal error {}", e);
@@ -620,8 +844,9 @@ impl TxPoolService {
     ) -> Option<(Result<ProcessResult, Reject>, Arc<Snapshot>)> {
         let limit_cycles = self.tx_pool_config.max_tx_verify_cycles;
         let tx_hash = tx.hash();
+        let started = Instant::now();
 
-        let (ret, snapshot) = self.pre_check(&tx).await;
+        let (ret, snapshot) = self.pre_check(&tx, origin_of(&remote)).await;
         let (tip_hash, rtx, status, fee, tx_size, conflicts) =
             try_or_return_with_snapshot!(ret, snapshot);
 
@@ -636,12 +861,13 @@ impl TxPoolService {
         let cached = self.fetch_tx_verify_cache(&tx_hash).await;
         let tip_header = snapshot.tip_header();
         let tx_env = Arc::new(status.with_env(tip_header));
+        let fork_context = ForkContext::new(&self.consensus, &tx_env);
 
         let data_loader = snapshot.as_data_loader();
 
         let completed = if let Some(ref entry) = cached {
             match entry {
-                CacheEntry::Completed(completed) => {
+                CacheEntry::Completed(completed) | CacheEntry::Prewarmed(completed, _) => {
                     let ret = TimeRelativeTransactionVerifier::new(
                         Arc::clone(&rtx),
                         Arc::clone(&self.consensus),
@@ -658,7 +884,7 @@ impl TxPoolService {
                 }
             }
         } else {
-            let is_chunk_full = self.is_chunk_full().await;
+            let chunk_full_limit = self.chunk_full_limit().await;
 
             let ret = block_in_place(|| {
                 let verifier = ContextualTransactionVerifier::new(
@@ -691,8 +917,8 @@ impl TxPoolService {
                         Ok(CacheEntry::completed(cycles, fee))
                     }
                     ScriptVerifyResult::Suspended(state) => {
-                        if is_chunk_full {
-                            Err(Reject::Full("chunk".to_owned()))
+                        if let Some(limit) = chunk_full_limit {
+                            Err(Reject::VerificationQueueFull(limit))
                         } else {
                             let snap = Arc::new(state.try_into().map_err(Reject::Verification)?);
                             Ok(CacheEntry::suspended(snap, fee))
@@ -710,14 +936,18 @@ impl TxPoolService {
                     try_or_return_with_snapshot!(ret, snapshot);
                     return Some((Ok(ProcessResult::Suspended), snapshot));
                 }
-                CacheEntry::Completed(completed) => completed,
+                CacheEntry::Completed(completed) | CacheEntry::Prewarmed(completed, _) => completed,
             }
         };
 
-        let entry = TxEntry::new(rtx, completed.cycles, fee, tx_size);
+        let entry =
+            TxEntry::new_with_timestamp(rtx, completed.cycles, fee, tx_size, self.clock.now_ms())
+                .with_origin(origin_of(&remote))
+                .with_verify_latency(started.elapsed())
+                .with_fork_context(fork_context);
 
         let (ret, submit_snapshot) = self.submit_entry(tip_hash, entry, status, conflicts).await;
-        try_or_return_with_snapshot!(ret, submit_snapshot);
+        let status = try_or_return_with_snapshot!(ret, submit_snapshot);
 
         self.notify_block_assembler(status).await;
         if cached.is_none() {
@@ -732,8 +962,10 @@ impl TxPoolService {
         Some((Ok(ProcessResult::Completed(completed)), submit_snapshot))
     }
 
-    pub(crate) async fn is_chunk_full(&self) -> bool {
-        self.chunk.read().await.is_full()
+    /// Returns the queue limit if the chunked verification queue is full
+    pub(crate) async fn chunk_full_limit(&self) -> Option<usize> {
+        let chunk = self.chunk.read().await;
+        chunk.is_full().then(|| chunk.max_len())
     }
 
     pub(crate) async fn enqueue_suspended_tx(
@@ -744,7 +976,7 @@ impl TxPoolService {
     ) -> Result<(), Reject> {
         let tx_hash = tx.hash();
         let mut chunk = self.chunk.write().await;
-        if chunk.add_tx(tx, remote) {
+        if chunk.add_tx(tx, remote, self.clock.now_ms()) {
             let mut guard = self.txs_verify_cache.write().await;
             guard.put(tx_hash, cached);
         }
@@ -758,8 +990,9 @@ impl TxPoolService {
         declared_cycles: Option<Cycle>,
     ) -> Option<(Result<Completed, Reject>, Arc<Snapshot>)> {
         let tx_hash = tx.hash();
+        let started = Instant::now();
 
-        let (ret, snapshot) = self.pre_check(&tx).await;
+        let (ret, snapshot) = self.pre_check(&tx, origin_of(&declared_cycles)).await;
 
         let (tip_hash, rtx, status, fee, tx_size, conflicts) =
             try_or_return_with_snapshot!(ret, snapshot);
@@ -776,6 +1009,7 @@ impl TxPoolService {
         let max_cycles = declared_cycles.unwrap_or_else(|| self.consensus.max_block_cycles());
         let tip_header = snapshot.tip_header();
         let tx_env = Arc::new(status.with_env(tip_header));
+        let fork_context = ForkContext::new(&self.consensus, &tx_env);
 
         let verified_ret = verify_rtx(
             Arc::clone(&snapshot),
@@ -786,6 +1020,12 @@ impl TxPoolService {
         );
 
         let verified = try_or_return_with_snapshot!(verified_ret, snapshot);
+        self.tracer.record(
+            &tx_hash,
+            TxEvent::Verified {
+                cycles: verified.cycles,
+            },
+        );
 
         if let Some(declared) = declared_cycles {
             if declared != verified.cycles {
@@ -796,10 +1036,14 @@ impl TxPoolService {
             }
         }
 
-        let entry = TxEntry::new(rtx, verified.cycles, fee, tx_size);
+        let entry =
+            TxEntry::new_with_timestamp(rtx, verified.cycles, fee, tx_size, self.clock.now_ms())
+                .with_origin(origin_of(&declared_cycles))
+                .with_verify_latency(started.elapsed())
+                .with_fork_context(fork_context);
 
         let (ret, submit_snapshot) = self.submit_entry(tip_hash, entry, status, conflicts).await;
-        try_or_return_with_snapshot!(ret, submit_snapshot);
+        let status = try_or_return_with_snapshot!(ret, submit_snapshot);
 
         self.notify_block_assembler(status).await;
 
@@ -821,7 +1065,7 @@ impl TxPoolService {
         attached_blocks: VecDeque<BlockView>,
         detached_proposal_id: HashSet<ProposalShortId>,
         snapshot: Arc<Snapshot>,
-    ) {
+    ) -> EvictionSummary {
         let mine_mode = self.block_assembler.is_some();
         let mut detached = LinkedHashSet::default();
         let mut attached = LinkedHashSet::default();
@@ -843,7 +1087,8 @@ impl TxPoolService {
             detached.extend(blk.transactions().into_iter().skip(1))
         }
 
-        for blk in attached_blocks {
+        let attached_blocks: Vec<BlockView> = attached_blocks.into();
+        for blk in attached_blocks.iter() {
             attached.extend(blk.transactions().into_iter().skip(1));
         }
         let retain: Vec<TransactionView> = detached.difference(&attached).cloned().collect();
@@ -859,10 +1104,18 @@ impl TxPoolService {
         //
         // At present, there is only one situation:
         // - If the hardfork was happened, then re-process all transactions.
-        let txs_opt = {
+        let (txs_opt, outcome) = {
             // This closure is used to limit the lifetime of mutable tx_pool.
             let mut tx_pool = self.tx_pool.write().await;
 
+            if tx_pool.is_snapshot_stale(snapshot.tip_number(), MAX_SNAPSHOT_GAP) {
+                warn!(
+                    "tx-pool snapshot at block {} fell behind the chain tip {}",
+                    tx_pool.snapshot().tip_number(),
+                    snapshot.tip_number()
+                );
+            }
+
             let txs_opt = if is_in_delay_window {
                 {
                     self.chunk.write().await.clear();
@@ -872,12 +1125,15 @@ impl TxPoolService {
                 None
             };
 
-            _update_tx_pool_for_reorg(
+            let outcome = _update_tx_pool_for_reorg(
                 &mut tx_pool,
-                &attached,
-                &detached_headers,
-                detached_proposal_id,
-                snapshot,
+                ReorgUpdate {
+                    attached_blocks: &attached_blocks,
+                    uncommitted: &retain,
+                    detached_headers: &detached_headers,
+                    detached_proposal_id,
+                    snapshot,
+                },
                 &self.callbacks,
                 mine_mode,
             );
@@ -899,7 +1155,7 @@ impl TxPoolService {
             // notice: readd_detached_tx don't update cache
             self.readd_detached_tx(&mut tx_pool, retain, fetched_cache);
 
-            txs_opt
+            (txs_opt, outcome)
         };
 
         if let Some(txs) = txs_opt {
@@ -911,6 +1167,11 @@ impl TxPoolService {
             }
         }
 
+        // the local txs dropped by a clear, the resubmission list waits for its own flush
+        if !outcome.cleared.is_empty() {
+            self.resubmit(outcome.cleared, Vec::new()).await;
+        }
+
         {
             let delay_txs = if !self.after_delay() && new_tip_after_delay {
                 let limit = MAX_BLOCK_PROPOSALS_LIMIT as usize;
@@ -939,6 +1200,7 @@ impl TxPoolService {
             let mut chunk = self.chunk.write().await;
             chunk.remove_chunk_txs(attached.iter().map(|tx| tx.proposal_short_id()));
         }
+        outcome.eviction
     }
 
     async fn remove_orphan_txs_by_attach<'a>(&self, txs: &LinkedHashSet<TransactionView>) {
@@ -965,6 +1227,7 @@ impl TxPoolService {
                     let snapshot = tx_pool.cloned_snapshot();
                     let tip_header = snapshot.tip_header();
                     let tx_env = Arc::new(status.with_env(tip_header));
+                    let fork_context = ForkContext::new(&self.consensus, &tx_env);
                     if let Ok(verified) = verify_rtx(
                         snapshot,
                         Arc::clone(&rtx),
@@ -972,7 +1235,21 @@ impl TxPoolService {
                         &verify_cache,
                         max_cycles,
                     ) {
-                        let entry = TxEntry::new(rtx, verified.cycles, fee, tx_size);
+                        tx_pool.record_trace(
+                            &tx_hash,
+                            TxEvent::Verified {
+                                cycles: verified.cycles,
+                            },
+                        );
+                        let entry = TxEntry::new_with_timestamp(
+                            rtx,
+                            verified.cycles,
+                            fee,
+                            tx_size,
+                            tx_pool.now_ms(),
+                        )
+                        .with_origin(Origin::Reorg)
+                        .with_fork_context(fork_context);
                         if let Err(e) = _submit_entry(tx_pool, status, entry, &self.callbacks) {
                             error!("readd_detached_tx submit_entry {} error {}", tx_hash, e);
                         } else {
@@ -1000,8 +1277,35 @@ impl TxPoolService {
         }
     }
 
+    /// Feeds the verification results of the pool entries verified under the current fork
+    /// context into the verification cache, returns how many were inserted
+    pub(crate) async fn prewarm_verify_cache(&self) -> usize {
+        let (entries, context) = {
+            let tx_pool = self.tx_pool.read().await;
+            let tx_env = TxVerifyEnv::new_submit(tx_pool.snapshot().tip_header());
+            (
+                tx_pool.export_verified(),
+                ForkContext::new(&self.consensus, &tx_env),
+            )
+        };
+        let mut guard = self.txs_verify_cache.write().await;
+        let stats = prewarm(&mut guard, entries, context);
+        debug!(
+            "prewarmed {} verify cache entries, {} verified under other rules",
+            stats.inserted, stats.mismatched
+        );
+        if let Some(metrics) = ckb_metrics::handle() {
+            let prewarm = &metrics.ckb_verify_cache_prewarm;
+            prewarm.inserted.inc_by(stats.inserted as u64);
+            prewarm.mismatched.inc_by(stats.mismatched as u64);
+        }
+        stats.inserted
+    }
+
     pub(crate) async fn save_pool(&self) {
         let mut tx_pool = self.tx_pool.write().await;
+        // saving drains the pool, close the log with the entries it held
+        tx_pool.close_replay_log();
         if let Err(err) = tx_pool.save_into_file() {
             error!("failed to save pool, error: {:?}", err)
         } else {
@@ -1059,6 +1363,140 @@ fn get_tx_status(snapshot: &Snapshot, short_id: &ProposalShortId) -> TxStatus {
     }
 }
 
+/// The pre-check of an attempt to admit `tx`, `pre_check_tx_with` then the local relay policy on
+/// the created outputs. The attempt is recorded in the replay log, a full log is closed by the
+/// next record taken under the write lock.
+pub(crate) fn pre_check_attempt(
+    tx_pool: &TxPool,
+    snapshot: &Snapshot,
+    tx: &TransactionView,
+    origin: Origin,
+    now: u64,
+    lost_double_spend: &mut bool,
+) -> Result<PreCheckedTx, Reject> {
+    let tx_size = tx.data().serialized_size_in_block();
+    let ret = pre_check_tx_with(tx_pool, snapshot, tx, tx_size, now, lost_double_spend).and_then(
+        |checked| {
+            // local relay policy on the created outputs, once the tx resolved
+            check_output_policy(&tx_pool.config, tx, origin).map(|_| checked)
+        },
+    );
+    tx_pool.try_record_replay(|_| ReplayEvent::attempt(snapshot, tx, origin, ret.as_ref().err()));
+    ret
+}
+
+pub(crate) fn pre_check_tx(
+    tx_pool: &TxPool,
+    snapshot: &Snapshot,
+    tx: &TransactionView,
+    tx_size: usize,
+    now: u64,
+) -> Result<PreCheckedTx, Reject> {
+    pre_check_tx_with(tx_pool, snapshot, tx, tx_size, now, &mut false)
+}
+
+/// `pre_check_tx`, setting `lost_double_spend` when the tx is rejected after conflicting with
+/// pool entries it could not replace
+pub(crate) fn pre_check_tx_with(
+    tx_pool: &TxPool,
+    snapshot: &Snapshot,
+    tx: &TransactionView,
+    tx_size: usize,
+    now: u64,
+    lost_double_spend: &mut bool,
+) -> Result<PreCheckedTx, Reject> {
+    if tx_pool.is_read_only() {
+        return Err(Reject::Maintenance);
+    }
+    let tip_hash = snapshot.tip_hash();
+    tx_pool.record_trace(&tx.hash(), TxEvent::Received);
+
+    // Same txid means exactly the same transaction, including inputs, outputs, witnesses, etc.
+    // It's also not possible for RBF, reject it directly
+    check_txid_collision(tx_pool, tx)?;
+
+    // Local relay policy, checked before spending any effort on resolving
+    check_hash_types(&tx_pool.config, tx)?;
+
+    // The same losing double spend relayed again, reject it without resolving
+    if let Some(reject) = tx_pool.check_conflict_cache(&tx.hash(), now) {
+        return Err(reject);
+    }
+
+    // Try normal path first, if double-spending check success we don't need RBF check
+    // this make sure RBF won't introduce extra performance cost for hot path
+    let res = resolve_tx(tx_pool, snapshot, tx.clone(), false)
+        .map_err(|reject| tx_pool.conflict_reject(tx, reject));
+    match res {
+        Ok((rtx, status)) => {
+            tx_pool.record_trace(&tx.hash(), TxEvent::Resolved);
+            check_cellbase_maturity(tx_pool, snapshot, &rtx, status)?;
+            let fee = check_tx_fee(tx_pool, snapshot, &rtx, tx_size)?;
+            tx_pool.check_dep_consumers(tx, fee, tx_size)?;
+            // the size is the least weight the tx may have, checked again over its weight once
+            // the cycles are known
+            tx_pool.check_entry_limit(fee, tx_size as u64)?;
+            Ok((tip_hash, rtx, status, fee, tx_size, HashSet::new()))
+        }
+        Err(Reject::Conflict { conflicting_ids }) => {
+            let conflicts: HashSet<_> = conflicting_ids.iter().cloned().collect();
+            if !tx_pool.enable_rbf() {
+                let reject = Reject::Conflict { conflicting_ids };
+                *lost_double_spend = true;
+                tx_pool.cache_conflict(tx.hash(), conflicts, reject.clone(), now);
+                return Err(reject);
+            }
+            // Try RBF check
+            let checked =
+                resolve_tx(tx_pool, snapshot, tx.clone(), true).and_then(|(rtx, status)| {
+                    tx_pool.record_trace(&tx.hash(), TxEvent::Resolved);
+                    check_cellbase_maturity(tx_pool, snapshot, &rtx, status)?;
+                    let fee = check_tx_fee(tx_pool, snapshot, &rtx, tx_size)?;
+                    Ok((rtx, status, fee))
+                });
+            let (rtx, status, fee) = match checked {
+                Ok(checked) => checked,
+                Err(reject) => {
+                    // not cached, unlike the RBF rules these may pass once the tx is relayed again
+                    *lost_double_spend = true;
+                    tx_pool.record_double_spend(conflicts.iter(), &tx.hash());
+                    return Err(reject);
+                }
+            };
+            let (ret, _) = tx_pool.check_rbf(snapshot, &rtx, &conflicts, fee, tx_size, false);
+            if let Err(reject) = ret {
+                *lost_double_spend = true;
+                tx_pool.cache_conflict(tx.hash(), conflicts, reject.clone(), now);
+                return Err(reject);
+            }
+            tx_pool.check_dep_consumers(tx, fee, tx_size)?;
+            Ok((tip_hash, rtx, status, fee, tx_size, conflicts))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Rejects a tx spending or depending on an immature cellbase output before its scripts run, the
+/// time-relative verification would reject it anyway
+pub(crate) fn check_cellbase_maturity(
+    tx_pool: &TxPool,
+    snapshot: &Snapshot,
+    rtx: &Arc<ResolvedTransaction>,
+    status: TxStatus,
+) -> Result<(), Reject> {
+    if !tx_pool.config.reject_immature_cellbase {
+        return Ok(());
+    }
+    let tx_env = status.with_env(snapshot.tip_header());
+    MaturityVerifier::new(
+        Arc::clone(rtx),
+        tx_env.epoch(),
+        snapshot.consensus().cellbase_maturity(),
+    )
+    .verify()
+    .map_err(Reject::Verification)
+}
+
 fn check_rtx(
     tx_pool: &TxPool,
     snapshot: &Snapshot,
@@ -1082,53 +1520,136 @@ fn resolve_tx(
         .map(|rtx| (rtx, tx_status))
 }
 
-fn _submit_entry(
+pub(crate) fn _submit_entry(
     tx_pool: &mut TxPool,
     status: TxStatus,
     entry: TxEntry,
     callbacks: &Callbacks,
-) -> Result<(), Reject> {
+) -> Result<TxStatus, Reject> {
+    tx_pool.record_replay(|_| ReplayEvent::submit(&entry, status));
     let tx_hash = entry.transaction().hash();
-    match status {
+    let added = match status {
         TxStatus::Fresh => {
-            if tx_pool.add_pending(entry.clone())? {
+            let added = tx_pool.add_pending(entry.clone())?.is_some();
+            if added {
                 debug!("submit_entry pending {}", tx_hash);
                 callbacks.call_pending(tx_pool, &entry);
             }
+            added
         }
         TxStatus::Gap => {
-            if tx_pool.add_gap(entry.clone())? {
+            let added = tx_pool.add_gap(entry.clone())?.is_some();
+            if added {
                 debug!("submit_entry gap {}", tx_hash);
                 callbacks.call_pending(tx_pool, &entry);
             }
+            added
         }
         TxStatus::Proposed => {
-            if tx_pool.add_proposed(entry.clone())? {
+            let added = tx_pool.add_proposed(entry.clone(), false)?.is_some();
+            if added {
                 debug!("submit_entry proposed {}", tx_hash);
+                tx_pool.record_template_candidate(&entry);
                 callbacks.call_proposed(tx_pool, &entry, true);
             }
+            added
+        }
+    };
+    // the tx paid for them in `pre_check_tx`, see `TxPool::check_dep_consumers`
+    if added && tx_pool.config.consumed_dep_policy == ConsumedDepPolicy::Evict {
+        for (evicted, reject) in tx_pool.evict_dep_consumers(entry.transaction()) {
+            debug!(
+                "evict {} whose cell dep is spent by {}",
+                evicted.transaction().hash(),
+                tx_hash
+            );
+            callbacks.call_reject(tx_pool, &evicted, reject);
         }
     }
-    Ok(())
+    Ok(status)
+}
+
+/// The chain update the tx-pool catches up with, see `_update_tx_pool_for_reorg`
+pub(crate) struct ReorgUpdate<'a> {
+    /// The blocks attached to the main chain, in order
+    pub(crate) attached_blocks: &'a [BlockView],
+    /// The detached txs which were not attached again
+    pub(crate) uncommitted: &'a [TransactionView],
+    /// Hashes of the detached headers
+    pub(crate) detached_headers: &'a HashSet<Byte32>,
+    /// The proposals of the detached blocks
+    pub(crate) detached_proposal_id: HashSet<ProposalShortId>,
+    /// The snapshot of the new tip
+    pub(crate) snapshot: Arc<Snapshot>,
 }
 
-fn _update_tx_pool_for_reorg(
+/// What the tx-pool dropped catching up with the chain, see `_update_tx_pool_for_reorg`
+#[derive(Debug, Default)]
+pub(crate) struct ReorgOutcome {
+    /// What the size limit evicted
+    pub(crate) eviction: EvictionSummary,
+    /// The local txs dropped by a clearing reorg, parents first, to be submitted again
+    pub(crate) cleared: Vec<ResubmitEntry>,
+}
+
+pub(crate) fn _update_tx_pool_for_reorg(
     tx_pool: &mut TxPool,
-    attached: &LinkedHashSet<TransactionView>,
-    detached_headers: &HashSet<Byte32>,
-    detached_proposal_id: HashSet<ProposalShortId>,
-    snapshot: Arc<Snapshot>,
+    update: ReorgUpdate,
     callbacks: &Callbacks,
     mine_mode: bool,
-) {
-    tx_pool.snapshot = Arc::clone(&snapshot);
+) -> ReorgOutcome {
+    let ReorgUpdate {
+        attached_blocks,
+        uncommitted,
+        detached_headers,
+        detached_proposal_id,
+        snapshot,
+    } = update;
+    tx_pool.record_replay(|tx_pool| {
+        ReplayEvent::commit(
+            tx_pool,
+            attached_blocks,
+            detached_headers,
+            &snapshot,
+            mine_mode,
+        )
+    });
+    if tx_pool.is_clearing_reorg(detached_headers.len()) {
+        info!(
+            "reorg detached {} blocks, deeper than reorg_clear_depth, clear the tx-pool",
+            detached_headers.len()
+        );
+        // the local txs are returned, the service submits them again right after
+        let now_ms = tx_pool.now_ms();
+        let mut cleared = Vec::new();
+        for entry in tx_pool.clear_returning(snapshot) {
+            tx_pool.record_fee_flow(RemoveReason::Cleared, &entry);
+            if entry.origin == Origin::Local {
+                cleared.push(ResubmitEntry::new(&entry, now_ms));
+            }
+            let reject = Reject::ReorgCleared(detached_headers.len());
+            callbacks.call_reject(tx_pool, &entry, reject);
+        }
+        return ReorgOutcome {
+            cleared,
+            ..Default::default()
+        };
+    }
+    tx_pool.set_snapshot(Arc::clone(&snapshot));
+
+    // the detached txs which are not attached again must not be found in the store any more
+    let uncommitted: Vec<_> = uncommitted
+        .iter()
+        .map(|tx| tx.proposal_short_id())
+        .collect();
+    tx_pool.invalidate_committed_cache(&uncommitted);
 
     // NOTE: `remove_by_detached_proposal` will try to re-put the given expired/detached proposals into
     // pending-pool if they can be found within txpool. As for a transaction
     // which is both expired and committed at the one time(commit at its end of commit-window),
     // we should treat it as a committed and not re-put into pending-pool. So we should ensure
     // that involves `remove_committed_txs` before `remove_expired`.
-    tx_pool.remove_committed_txs(attached.iter(), callbacks, detached_headers);
+    tx_pool.remove_committed_txs(attached_blocks, callbacks, detached_headers);
     tx_pool.remove_by_detached_proposal(detached_proposal_id.iter());
 
     // mine mode:
@@ -1186,7 +1707,14 @@ fn _update_tx_pool_for_reorg(
     tx_pool.remove_expired(callbacks);
 
     // Remove transactions from the pool until its size <= size_limit.
-    tx_pool.limit_size(callbacks);
+    let eviction = tx_pool.limit_size(callbacks);
+
+    // Re-admit the archived evicted transactions the committed ones made room for.
+    tx_pool.reconsider_archived(callbacks);
+    ReorgOutcome {
+        eviction,
+        ..Default::default()
+    }
 }
 
 pub fn all_inputs_is_unknown(snapshot: &Snapshot, tx: &TransactionView) -> bool {
@@ -1,12 +1,26 @@
 //! Tx-pool background service
 
 use crate::block_assembler::{self, BlockAssembler};
-use crate::callback::{Callback, Callbacks, ProposedCallback, RejectCallback};
+use crate::callback::{
+    Callback, Callbacks, DoubleSpendCallback, PoolMissCallback, ProposedCallback, RejectCallback,
+};
 use crate::chunk_process::ChunkCommand;
+use crate::clock::Clock;
 use crate::component::pool_map::{PoolEntry, Status};
-use crate::component::{chunk::ChunkQueue, orphan::OrphanPool};
-use crate::error::{handle_recv_error, handle_send_cmd_error, handle_try_send_error};
-use crate::pool::TxPool;
+use crate::component::{
+    chunk::{ChunkQueue, VerifyingTx},
+    eviction::EvictionSummary,
+    latency::LatencyHistogram,
+    orphan::OrphanPool,
+    rate_limit::{PeerAdmissionStats, PeerRateLimiter},
+    resubmit::{ResubmitEntry, ResubmitReport},
+    trace::{TraceEvent, TxTracer},
+    view::PoolView,
+};
+use crate::error::{
+    handle_recv_error, handle_send_cmd_error, handle_try_send_error, ThresholdsError, ViewError,
+};
+use crate::pool::{PoolSaturation, TxPool, TxPoolThresholds};
 use crate::util::after_delay_window;
 use ckb_app_config::{BlockAssemblerConfig, TxPoolConfig};
 use ckb_async_runtime::Handle;
@@ -22,12 +36,16 @@ use ckb_stop_handler::new_tokio_exit_rx;
 use ckb_types::core::tx_pool::{TransactionWithStatus, TxStatus};
 use ckb_types::{
     core::{
-        tx_pool::{Reject, TxPoolEntryInfo, TxPoolIds, TxPoolInfo, TRANSACTION_SIZE_LIMIT},
+        tx_pool::{
+            Origin, PoolTxFilter, RBFRule, Reject, TxPoolAnnotatedIds, TxPoolEntryInfo, TxPoolIds,
+            TxPoolInfo, TRANSACTION_SIZE_LIMIT,
+        },
         BlockView, Cycle, TransactionView, UncleBlockView, Version,
     },
     packed::{Byte32, ProposalShortId},
+    prelude::*,
 };
-use ckb_util::{LinkedHashMap, LinkedHashSet};
+use ckb_util::{LinkedHashMap, LinkedHashSet, Mutex};
 use ckb_verification::cache::TxVerificationCache;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{
@@ -45,6 +63,7 @@ use crate::{component::entry::TxEntry, process::PlugTarget};
 
 pub(crate) const DEFAULT_CHANNEL_SIZE: usize = 512;
 pub(crate) const BLOCK_ASSEMBLER_CHANNEL_SIZE: usize = 100;
+const REBROADCAST_INTERVAL: Duration = Duration::from_secs(30);
 
 pub(crate) struct Request<A, R> {
     pub responder: oneshot::Sender<R>,
@@ -102,9 +121,24 @@ pub(crate) enum Message {
     GetTransactionWithStatus(Request<Byte32, GetTransactionWithStatusResult>),
     NewUncle(Notify<UncleBlockView>),
     ClearPool(Request<Arc<Snapshot>, ()>),
-    GetAllEntryInfo(Request<(), TxPoolEntryInfo>),
+    GetAllEntryInfo(Request<Option<Origin>, TxPoolEntryInfo>),
     GetAllIds(Request<(), TxPoolIds>),
+    GetAllAnnotatedIds(Request<(), TxPoolAnnotatedIds>),
+    GetIdsPaginated(Request<(PoolTxFilter, usize, usize), Vec<Byte32>>),
+    GetPoolView(Request<Option<u64>, Result<PoolView, ViewError>>),
+    CheckRbf(Request<TransactionView, Result<Vec<(RBFRule, bool)>, Reject>>),
     SavePool(Request<(), ()>),
+    UpdateThresholds(Request<TxPoolThresholds, Result<EvictionSummary, ThresholdsError>>),
+    PendingVerification(Request<(), Vec<VerifyingTx>>),
+    GetPeerAdmissionStats(Request<(), HashMap<PeerIndex, PeerAdmissionStats>>),
+    GetAcceptanceLatency(Request<(), LatencyHistogram>),
+    GetSaturation(Request<(), PoolSaturation>),
+    SetReadOnly(Request<bool, ()>),
+    PrewarmVerifyCache(Request<(), usize>),
+    TraceTx(Request<Byte32, ()>),
+    GetTxTrace(Request<Byte32, Option<Vec<TraceEvent>>>),
+    GetResubmitList(Request<(), Vec<ResubmitEntry>>),
+    ResubmitExpired(Request<(), ResubmitReport>),
 
     // test
     #[cfg(feature = "internal")]
@@ -296,7 +330,12 @@ impl TxPoolController {
 
     /// TODO(doc): @zhangsoledad
     pub fn get_all_entry_info(&self) -> Result<TxPoolEntryInfo, AnyError> {
-        send_message!(self, GetAllEntryInfo, ())
+        send_message!(self, GetAllEntryInfo, None)
+    }
+
+    /// Return the entry info of txs submitted from the given origin
+    pub fn get_entry_info_by_origin(&self, origin: Origin) -> Result<TxPoolEntryInfo, AnyError> {
+        send_message!(self, GetAllEntryInfo, Some(origin))
     }
 
     /// TODO(doc): @zhangsoledad
@@ -304,12 +343,110 @@ impl TxPoolController {
         send_message!(self, GetAllIds, ())
     }
 
+    /// Return the ids of all txs, along with the fee, size and fee rate of each one
+    pub fn get_all_annotated_ids(&self) -> Result<TxPoolAnnotatedIds, AnyError> {
+        send_message!(self, GetAllAnnotatedIds, ())
+    }
+
+    /// Return a page of the ids of the txs passing `filter`, highest score first
+    pub fn get_ids_paginated(
+        &self,
+        filter: PoolTxFilter,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<Byte32>, AnyError> {
+        send_message!(self, GetIdsPaginated, (filter, offset, limit))
+    }
+
+    /// `TxPool::consistent_view`
+    pub fn consistent_view(&self) -> Result<PoolView, AnyError> {
+        send_message!(self, GetPoolView, None).map(|view| view.expect("a new view is always taken"))
+    }
+
+    /// `TxPool::view_at`
+    pub fn view_at(&self, seq: u64) -> Result<Result<PoolView, ViewError>, AnyError> {
+        send_message!(self, GetPoolView, Some(seq))
+    }
+
+    /// Checks whether the tx could replace its conflicts in the pool without submitting it,
+    /// returns the RBF rules checked with whether each one passed, ending with the failing rule
+    /// if any
+    pub fn check_rbf(
+        &self,
+        tx: TransactionView,
+    ) -> Result<Result<Vec<(RBFRule, bool)>, Reject>, AnyError> {
+        send_message!(self, CheckRbf, tx)
+    }
+
+    /// Feeds the verification results of the pool txs into the verification cache, so the blocks
+    /// committing them don't run their scripts again, returns how many were inserted
+    pub fn prewarm_verify_cache(&self) -> Result<usize, AnyError> {
+        send_message!(self, PrewarmVerifyCache, ())
+    }
+
+    /// Traces the lifecycle of the tx `tx_hash` from now on, see `get_tx_trace`
+    pub fn trace_tx(&self, tx_hash: Byte32) -> Result<(), AnyError> {
+        send_message!(self, TraceTx, tx_hash)
+    }
+
+    /// The lifecycle events recorded for the tx `tx_hash`, `None` if it is not traced
+    pub fn get_tx_trace(&self, tx_hash: Byte32) -> Result<Option<Vec<TraceEvent>>, AnyError> {
+        send_message!(self, GetTxTrace, tx_hash)
+    }
+
+    /// The local txs which expired in the pool, kept to be submitted again
+    pub fn get_resubmit_list(&self) -> Result<Vec<ResubmitEntry>, AnyError> {
+        send_message!(self, GetResubmitList, ())
+    }
+
+    /// Submits the expired local txs kept in the resubmission list again, through the whole
+    /// resolution and verification, see `ResubmitReport`
+    pub fn resubmit_expired(&self) -> Result<ResubmitReport, AnyError> {
+        send_message!(self, ResubmitExpired, ())
+    }
+
     /// Saves tx pool into disk.
     pub fn save_pool(&self) -> Result<(), AnyError> {
         info!("Please be patient, tx-pool are saving data into disk ...");
         send_message!(self, SavePool, ())
     }
 
+    /// Hot-reloads the tx-pool thresholds, evicting txs which no longer fit, returns what was
+    /// evicted by a lowered size limit
+    pub fn update_thresholds(
+        &self,
+        thresholds: TxPoolThresholds,
+    ) -> Result<Result<EvictionSummary, ThresholdsError>, AnyError> {
+        send_message!(self, UpdateThresholds, thresholds)
+    }
+
+    /// Lists the large txs accepted but still waiting for or going through the chunked
+    /// verification
+    pub fn pending_verification(&self) -> Result<Vec<VerifyingTx>, AnyError> {
+        send_message!(self, PendingVerification, ())
+    }
+
+    /// Returns the admission counters of the peers relaying txs
+    pub fn peer_admission_stats(&self) -> Result<HashMap<PeerIndex, PeerAdmissionStats>, AnyError> {
+        send_message!(self, GetPeerAdmissionStats, ())
+    }
+
+    /// Returns how long the current entries took to resolve and verify before they were added
+    pub fn acceptance_latency_histogram(&self) -> Result<LatencyHistogram, AnyError> {
+        send_message!(self, GetAcceptanceLatency, ())
+    }
+
+    /// Returns how full the tx-pool is and the fee rate a new tx has to pay to stay in it
+    pub fn saturation(&self) -> Result<PoolSaturation, AnyError> {
+        send_message!(self, GetSaturation, ())
+    }
+
+    /// Makes the tx-pool read-only for maintenance, or writable again, see
+    /// `TxPool::set_read_only`
+    pub fn set_read_only(&self, read_only: bool) -> Result<(), AnyError> {
+        send_message!(self, SetReadOnly, read_only)
+    }
+
     /// Sends suspend chunk process cmd
     pub fn suspend_chunk_process(&self) -> Result<(), AnyError> {
         self.chunk_tx
@@ -398,7 +535,9 @@ impl TxPoolServiceBuilder {
         let (reorg_sender, reorg_receiver) = mpsc::channel(DEFAULT_CHANNEL_SIZE);
         let signal_receiver: CancellationToken = new_tokio_exit_rx();
         let (chunk_tx, chunk_rx) = watch::channel(ChunkCommand::Resume);
-        let chunk = Arc::new(RwLock::new(ChunkQueue::new()));
+        let chunk = Arc::new(RwLock::new(ChunkQueue::new(
+            tx_pool_config.max_chunk_transactions,
+        )));
         let started = Arc::new(AtomicBool::new(false));
 
         let controller = TxPoolController {
@@ -457,6 +596,16 @@ impl TxPoolServiceBuilder {
         self.callbacks.register_reject(callback);
     }
 
+    /// Register new pool miss callback
+    pub fn register_pool_miss(&mut self, callback: PoolMissCallback) {
+        self.callbacks.register_pool_miss(callback);
+    }
+
+    /// Register new double spend callback
+    pub fn register_double_spend(&mut self, callback: DoubleSpendCallback) {
+        self.callbacks.register_double_spend(callback);
+    }
+
     /// Start a background thread tx-pool service by taking ownership of the Builder, and returns a TxPoolController.
     pub fn start(self, network: NetworkController) {
         let consensus = self.snapshot.cloned_consensus();
@@ -473,8 +622,14 @@ impl TxPoolServiceBuilder {
         };
 
         let (block_assembler_sender, mut block_assembler_receiver) = self.block_assembler_channel;
+        let peer_rate_limiter = PeerRateLimiter::new(
+            tx_pool.config.max_peer_tx_rate,
+            tx_pool.config.max_peer_tx_burst,
+        );
         let service = TxPoolService {
             tx_pool_config: Arc::new(tx_pool.config.clone()),
+            tracer: tx_pool.tracer(),
+            clock: Arc::clone(&tx_pool.clock),
             tx_pool: Arc::new(RwLock::new(tx_pool)),
             orphan: Arc::new(RwLock::new(OrphanPool::new())),
             block_assembler: self.block_assembler,
@@ -487,6 +642,7 @@ impl TxPoolServiceBuilder {
             consensus,
             delay: Arc::new(RwLock::new(LinkedHashMap::new())),
             after_delay: Arc::new(AtomicBool::new(after_delay_window)),
+            peer_rate_limiter: Arc::new(Mutex::new(peer_rate_limiter)),
         };
 
         let signal_receiver = self.signal_receiver.clone();
@@ -584,6 +740,33 @@ impl TxPoolServiceBuilder {
             }
         }
 
+        if service.tx_pool_config.rebroadcast_min_age_secs > 0
+            && service.tx_pool_config.rebroadcast_batch_size > 0
+        {
+            let rebroadcast_service = service.clone();
+            let signal_receiver = self.signal_receiver.clone();
+            // tick at least as often as the min age, so a short min age is honoured
+            let period = REBROADCAST_INTERVAL.min(Duration::from_secs(
+                service.tx_pool_config.rebroadcast_min_age_secs,
+            ));
+            self.handle.spawn(async move {
+                let mut interval = tokio::time::interval(period);
+                interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => {
+                            rebroadcast_service.rebroadcast_local_txs().await;
+                        }
+                        _ = signal_receiver.cancelled() => {
+                            debug!("TxPool received exit signal, exit now");
+                            break
+                        },
+                        else => break,
+                    }
+                }
+            });
+        }
+
         let signal_receiver = self.signal_receiver;
         self.handle.spawn(async move {
             loop {
@@ -600,7 +783,7 @@ impl TxPoolServiceBuilder {
                         ).await;
 
                         let snapshot_clone = Arc::clone(&snapshot);
-                        service
+                        let eviction = service
                         .update_tx_pool_for_reorg(
                             detached_blocks,
                             attached_blocks,
@@ -608,6 +791,16 @@ impl TxPoolServiceBuilder {
                             snapshot_clone,
                         )
                         .await;
+                        if !eviction.is_empty() {
+                            info!(
+                                "reorg to block {} evicted {} txs, {} bytes, {} cycles, in {:?}",
+                                snapshot.tip_number(),
+                                eviction.removed_count,
+                                eviction.removed_bytes,
+                                eviction.removed_cycles,
+                                eviction.duration
+                            );
+                        }
 
                         service.update_block_assembler_after_tx_pool_reorg().await;
                     },
@@ -620,9 +813,15 @@ impl TxPoolServiceBuilder {
             }
         });
         self.started.store(true, Ordering::Relaxed);
+        // the persisted txs are verified again on import, which caches their results in the
+        // background, the prewarm covers the ones not cached yet and skips the others
         if let Err(err) = self.tx_pool_controller.load_persisted_data(txs) {
             error!("Failed to import persisted txs, cause: {}", err);
         }
+        match self.tx_pool_controller.prewarm_verify_cache() {
+            Ok(inserted) => info!("Prewarmed {} verify cache entries from tx-pool", inserted),
+            Err(err) => error!("Failed to prewarm the verify cache, cause: {}", err),
+        }
     }
 }
 
@@ -641,6 +840,10 @@ pub(crate) struct TxPoolService {
     pub(crate) block_assembler_sender: mpsc::Sender<BlockAssemblerMessage>,
     pub(crate) delay: Arc<RwLock<LinkedHashMap<ProposalShortId, TransactionView>>>,
     pub(crate) after_delay: Arc<AtomicBool>,
+    pub(crate) peer_rate_limiter: Arc<Mutex<PeerRateLimiter>>,
+    pub(crate) tracer: Arc<TxTracer>,
+    // the clock of the pool, read without locking it
+    pub(crate) clock: Arc<dyn Clock>,
 }
 
 /// tx verification result
@@ -735,12 +938,15 @@ async fn process(mut service: TxPoolService, message: Message) {
             arguments: hash,
         }) => {
             let id = ProposalShortId::from_tx_hash(&hash);
+            // the chunk lock is released before the pool is locked, a tx leaving the queue
+            // enters the pool, where it's looked up first
+            let verifying = service.chunk.read().await.contains_key(&id);
             let tx_pool = service.tx_pool.read().await;
             let ret = if let Some(PoolEntry {
                 status,
                 inner: entry,
                 ..
-            }) = tx_pool.pool_map.get_by_id(&id)
+            }) = tx_pool.get_entry_by_hash(&hash)
             {
                 let status = if status == &Status::Proposed {
                     TxStatus::Proposed
@@ -748,6 +954,18 @@ async fn process(mut service: TxPoolService, message: Message) {
                     TxStatus::Pending
                 };
                 Ok((status, Some(entry.cycles)))
+            } else if verifying {
+                Ok((TxStatus::Verifying, None))
+            } else if let Some(committed) = tx_pool.committed_entry(&id) {
+                Ok((
+                    TxStatus::Committed(committed.info.block_hash.unpack()),
+                    Some(committed.entry.cycles),
+                ))
+            } else if let Some(info) = tx_pool
+                .committed_info(&id)
+                .filter(|info| info.tx_hash == hash)
+            {
+                Ok((TxStatus::Committed(info.block_hash.unpack()), None))
             } else if let Some(ref recent_reject_db) = tx_pool.recent_reject {
                 let recent_reject_result = recent_reject_db.get(&hash);
                 if let Ok(recent_reject) = recent_reject_result {
@@ -772,12 +990,13 @@ async fn process(mut service: TxPoolService, message: Message) {
             arguments: hash,
         }) => {
             let id = ProposalShortId::from_tx_hash(&hash);
+            let verifying_tx = service.chunk.read().await.get_tx(&id);
             let tx_pool = service.tx_pool.read().await;
             let ret = if let Some(PoolEntry {
                 status,
                 inner: entry,
                 ..
-            }) = tx_pool.pool_map.get_by_id(&id)
+            }) = tx_pool.get_entry_by_hash(&hash)
             {
                 let (tx_status, min_replace_fee) = if status == &Status::Proposed {
                     (TxStatus::Proposed, None)
@@ -792,6 +1011,15 @@ async fn process(mut service: TxPoolService, message: Message) {
                     Some(entry.fee),
                     min_replace_fee,
                 ))
+            } else if let Some(tx) = verifying_tx {
+                Ok(TransactionWithStatus::with_verifying(Some(tx)))
+            } else if let Some(committed) = tx_pool.committed_entry(&id) {
+                Ok(TransactionWithStatus::with_committed(
+                    Some(committed.entry.transaction().clone()),
+                    committed.info.block_hash.unpack(),
+                    Some(committed.entry.cycles),
+                    Some(committed.entry.fee),
+                ))
             } else if let Some(ref recent_reject_db) = tx_pool.recent_reject {
                 match recent_reject_db.get(&hash) {
                     Ok(Some(record)) => Ok(TransactionWithStatus::with_rejected(record)),
@@ -815,7 +1043,7 @@ async fn process(mut service: TxPoolService, message: Message) {
                 .into_iter()
                 .filter_map(|short_id| {
                     tx_pool
-                        .get_tx_from_pool_or_store(&short_id)
+                        .get_tx_from_pool_or_store(&short_id, &service.callbacks)
                         .map(|tx| (short_id, tx))
                 })
                 .collect();
@@ -852,13 +1080,59 @@ async fn process(mut service: TxPoolService, message: Message) {
                 error!("responder send clear_pool failed {:?}", e)
             };
         }
-        Message::GetAllEntryInfo(Request { responder, .. }) => {
+        Message::GetAllEntryInfo(Request {
+            responder,
+            arguments: origin,
+        }) => {
             let tx_pool = service.tx_pool.read().await;
-            let info = tx_pool.get_all_entry_info();
+            let info = tx_pool.get_all_entry_info(origin);
             if let Err(e) = responder.send(info) {
                 error!("responder send get_all_entry_info failed {:?}", e)
             };
         }
+        Message::UpdateThresholds(Request {
+            responder,
+            arguments: thresholds,
+        }) => {
+            let mut tx_pool = service.tx_pool.write().await;
+            let result = tx_pool.update_thresholds(thresholds, &service.callbacks);
+            if let Err(e) = responder.send(result) {
+                error!("responder send update_thresholds failed {:?}", e)
+            };
+        }
+        Message::GetSaturation(Request { responder, .. }) => {
+            let tx_pool = service.tx_pool.read().await;
+            if let Err(e) = responder.send(tx_pool.saturation()) {
+                error!("responder send saturation failed {:?}", e)
+            };
+        }
+        Message::SetReadOnly(Request {
+            responder,
+            arguments: read_only,
+        }) => {
+            service.tx_pool.write().await.set_read_only(read_only);
+            if let Err(e) = responder.send(()) {
+                error!("responder send set_read_only failed {:?}", e)
+            };
+        }
+        Message::GetPeerAdmissionStats(Request { responder, .. }) => {
+            let stats = service.peer_rate_limiter.lock().stats();
+            if let Err(e) = responder.send(stats) {
+                error!("responder send peer_admission_stats failed {:?}", e)
+            };
+        }
+        Message::GetAcceptanceLatency(Request { responder, .. }) => {
+            let histogram = service.tx_pool.read().await.acceptance_latency_histogram();
+            if let Err(e) = responder.send(histogram) {
+                error!("responder send acceptance_latency_histogram failed {:?}", e)
+            };
+        }
+        Message::PendingVerification(Request { responder, .. }) => {
+            let pending = service.chunk.read().await.pending_verification();
+            if let Err(e) = responder.send(pending) {
+                error!("responder send pending_verification failed {:?}", e)
+            };
+        }
         Message::GetAllIds(Request { responder, .. }) => {
             let tx_pool = service.tx_pool.read().await;
             let ids = tx_pool.get_ids();
@@ -866,6 +1140,81 @@ async fn process(mut service: TxPoolService, message: Message) {
                 error!("responder send get_ids failed {:?}", e)
             };
         }
+        Message::GetAllAnnotatedIds(Request { responder, .. }) => {
+            let tx_pool = service.tx_pool.read().await;
+            let ids = tx_pool.get_annotated_ids();
+            if let Err(e) = responder.send(ids) {
+                error!("responder send get_annotated_ids failed {:?}", e)
+            };
+        }
+        Message::GetIdsPaginated(Request {
+            responder,
+            arguments: (filter, offset, limit),
+        }) => {
+            let tx_pool = service.tx_pool.read().await;
+            let ids = tx_pool.get_ids_paginated(filter, offset, limit);
+            if let Err(e) = responder.send(ids) {
+                error!("responder send get_ids_paginated failed {:?}", e)
+            };
+        }
+        Message::GetPoolView(Request {
+            responder,
+            arguments: seq,
+        }) => {
+            let tx_pool = service.tx_pool.read().await;
+            let view = match seq {
+                Some(seq) => tx_pool.view_at(seq),
+                None => Ok(tx_pool.consistent_view()),
+            };
+            if let Err(e) = responder.send(view) {
+                error!("responder send get_pool_view failed {:?}", e)
+            };
+        }
+        Message::CheckRbf(Request {
+            responder,
+            arguments: tx,
+        }) => {
+            let report = service.check_rbf_report(tx).await;
+            if let Err(e) = responder.send(report) {
+                error!("responder send check_rbf failed {:?}", e)
+            };
+        }
+        Message::PrewarmVerifyCache(Request { responder, .. }) => {
+            let inserted = service.prewarm_verify_cache().await;
+            if let Err(e) = responder.send(inserted) {
+                error!("responder send prewarm_verify_cache failed {:?}", e)
+            };
+        }
+        Message::TraceTx(Request {
+            responder,
+            arguments: tx_hash,
+        }) => {
+            service.tracer.watch(tx_hash);
+            if let Err(e) = responder.send(()) {
+                error!("responder send trace_tx failed {:?}", e)
+            };
+        }
+        Message::GetTxTrace(Request {
+            responder,
+            arguments: tx_hash,
+        }) => {
+            let trace = service.tracer.get(&tx_hash);
+            if let Err(e) = responder.send(trace) {
+                error!("responder send get_tx_trace failed {:?}", e)
+            };
+        }
+        Message::GetResubmitList(Request { responder, .. }) => {
+            let entries = service.tx_pool.read().await.resubmit_list();
+            if let Err(e) = responder.send(entries) {
+                error!("responder send get_resubmit_list failed {:?}", e)
+            };
+        }
+        Message::ResubmitExpired(Request { responder, .. }) => {
+            let report = service.resubmit_expired().await;
+            if let Err(e) = responder.send(report) {
+                error!("responder send resubmit_expired failed {:?}", e)
+            };
+        }
         Message::SavePool(Request { responder, .. }) => {
             service.save_pool().await;
             if let Err(e) = responder.send(()) {
@@ -921,6 +1270,12 @@ impl TxPoolService {
             last_txs_updated_at: 0,
             tx_size_limit: TRANSACTION_SIZE_LIMIT,
             max_tx_pool_size: self.tx_pool_config.max_tx_pool_size as u64,
+            max_tx_pool_entries: self.tx_pool_config.max_tx_pool_entries as u64,
+            read_only: tx_pool.is_read_only(),
+            pending_quota: tx_pool.quota_usage(Status::Pending),
+            gap_quota: tx_pool.quota_usage(Status::Gap),
+            proposed_quota: tx_pool.quota_usage(Status::Proposed),
+            seq: tx_pool.seq(),
         }
     }
 
@@ -987,7 +1342,7 @@ impl TxPoolService {
                 }
                 PlugTarget::Proposed => {
                     for entry in entries {
-                        if let Err(err) = tx_pool.add_proposed(entry) {
+                        if let Err(err) = tx_pool.add_proposed(entry, false) {
                             error!("plug entry add_proposed error {}", err);
                         }
                     }
@@ -1,13 +1,22 @@
+use crate::component::entry::TxEntry;
 use crate::error::Reject;
 use crate::pool::TxPool;
+use ckb_app_config::TxPoolConfig;
 use ckb_chain_spec::consensus::Consensus;
 use ckb_dao::DaoCalculator;
 use ckb_snapshot::Snapshot;
 use ckb_store::data_loader_wrapper::AsDataLoader;
 use ckb_store::ChainStore;
-use ckb_types::core::{
-    cell::ResolvedTransaction, tx_pool::TRANSACTION_SIZE_LIMIT, Capacity, Cycle, EpochNumber,
-    TransactionView,
+use ckb_types::{
+    core::{
+        cell::ResolvedTransaction,
+        tx_pool::{
+            get_transaction_weight_with_ratio, Origin, OutputPolicyViolation, ResolveClass,
+            TRANSACTION_SIZE_LIMIT,
+        },
+        Capacity, Cycle, EpochNumber, FeeRate, ScriptHashType, TransactionView,
+    },
+    prelude::*,
 };
 use ckb_verification::{
     cache::{CacheEntry, Completed},
@@ -25,32 +34,140 @@ pub(crate) fn check_txid_collision(tx_pool: &TxPool, tx: &TransactionView) -> Re
     Ok(())
 }
 
+/// Rejects a tx with an output lock or type script whose hash type is not in
+/// `allowed_hash_types`, any hash type passes if it is not set
+pub(crate) fn check_hash_types(config: &TxPoolConfig, tx: &TransactionView) -> Result<(), Reject> {
+    let allowed = match config.allowed_hash_types {
+        Some(ref allowed) => allowed,
+        None => return Ok(()),
+    };
+    for (index, output) in tx.outputs().into_iter().enumerate() {
+        let scripts = std::iter::once(("lock", output.lock()))
+            .chain(output.type_().to_opt().map(|script| ("type", script)));
+        for (kind, script) in scripts {
+            let hash_type: u8 = script.hash_type().into();
+            let is_allowed =
+                ScriptHashType::try_from(script.hash_type()).map_or(false, |hash_type| {
+                    allowed
+                        .iter()
+                        .any(|allowed| ScriptHashType::from(allowed.clone()) == hash_type)
+                });
+            if !is_allowed {
+                return Err(Reject::NonStandard(format!(
+                    "output {index} {kind} script hash type {hash_type} is not allowed"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rejects a relayed tx with more outputs than `max_outputs_per_tx`, or with an output below
+/// `min_output_capacity`, or `min_typed_output_capacity` for the outputs carrying a type script.
+/// Local and re-added txs are exempt.
+pub(crate) fn check_output_policy(
+    config: &TxPoolConfig,
+    tx: &TransactionView,
+    origin: Origin,
+) -> Result<(), Reject> {
+    if origin != Origin::Remote {
+        return Ok(());
+    }
+    if let Some(max) = config.max_outputs_per_tx {
+        let count = tx.outputs().len();
+        if count > max {
+            return Err(Reject::OutputPolicy(
+                OutputPolicyViolation::TooManyOutputs { count, max },
+            ));
+        }
+    }
+    if config.min_output_capacity.is_none() && config.min_typed_output_capacity.is_none() {
+        return Ok(());
+    }
+    for (index, output) in tx.outputs().into_iter().enumerate() {
+        let min_capacity = if output.type_().to_opt().is_some() {
+            config
+                .min_typed_output_capacity
+                .or(config.min_output_capacity)
+        } else {
+            config.min_output_capacity
+        };
+        let capacity: Capacity = output.capacity().unpack();
+        if let Some(min_capacity) = min_capacity.map(Capacity::shannons) {
+            if capacity < min_capacity {
+                return Err(Reject::OutputPolicy(OutputPolicyViolation::LowCapacity {
+                    index,
+                    capacity,
+                    min_capacity,
+                }));
+            }
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn check_tx_fee(
     tx_pool: &TxPool,
     snapshot: &Snapshot,
     rtx: &ResolvedTransaction,
     tx_size: usize,
 ) -> Result<Capacity, Reject> {
-    let fee = DaoCalculator::new(snapshot.consensus(), &snapshot.borrow_as_data_loader())
+    let fee = calculate_tx_fee(snapshot, rtx)?;
+    // the cycles are unknown before the scripts run, the size is the least weight the tx may have
+    let min_fee_rate = tx_pool.dynamic_min_fee_rate();
+    check_min_fee(&tx_pool.config, min_fee_rate, fee, tx_size as u64)?;
+    Ok(fee)
+}
+
+/// Checks the fee of a verified entry against the dynamic min fee rate over its weight
+pub(crate) fn check_entry_min_fee(tx_pool: &TxPool, entry: &TxEntry) -> Result<(), Reject> {
+    let weight = get_transaction_weight_with_ratio(
+        entry.size,
+        entry.cycles,
+        tx_pool.pool_map.bytes_per_cycles,
+    );
+    check_min_fee(
+        &tx_pool.config,
+        tx_pool.dynamic_min_fee_rate(),
+        entry.fee,
+        weight,
+    )
+}
+
+pub(crate) fn calculate_tx_fee(
+    snapshot: &Snapshot,
+    rtx: &ResolvedTransaction,
+) -> Result<Capacity, Reject> {
+    DaoCalculator::new(snapshot.consensus(), &snapshot.borrow_as_data_loader())
         .transaction_fee(rtx)
         .map_err(|err| {
             Reject::Malformed(
                 format!("{err}"),
                 "expect (outputs capacity) <= (inputs capacity)".to_owned(),
             )
-        })?;
-    // Theoretically we cannot use size as weight directly to calculate fee_rate,
-    // here min fee rate is used as a cheap check,
-    // so we will use size to calculate fee_rate directly
-    let min_fee = tx_pool.config.min_fee_rate.fee(tx_size as u64);
+        })
+}
+
+/// Checks `fee` against `min_fee_rate` over `weight`, the configured min fee rate or the floor
+/// raised by evictions, see `TxPool::dynamic_min_fee_rate`
+pub(crate) fn check_min_fee(
+    config: &TxPoolConfig,
+    min_fee_rate: FeeRate,
+    fee: Capacity,
+    weight: u64,
+) -> Result<(), Reject> {
+    let mut min_fee = min_fee_rate.fee(weight);
+    // at least one shannon is required if zero-fee txs are not allowed
+    if !config.allow_zero_fee {
+        min_fee = min_fee.max(Capacity::one());
+    }
     // reject txs which fee lower than min fee rate
     if fee < min_fee {
-        let reject =
-            Reject::LowFeeRate(tx_pool.config.min_fee_rate, min_fee.as_u64(), fee.as_u64());
+        let reject = Reject::LowFeeRate(min_fee_rate, min_fee.as_u64(), fee.as_u64());
         ckb_logger::debug!("reject tx {}", reject);
         return Err(reject);
     }
-    Ok(fee)
+    Ok(())
 }
 
 pub(crate) fn non_contextual_verify(
@@ -94,7 +211,7 @@ pub(crate) fn verify_rtx(
 
     if let Some(ref cached) = cache_entry {
         match cached {
-            CacheEntry::Completed(completed) => {
+            CacheEntry::Completed(completed) | CacheEntry::Prewarmed(completed, _) => {
                 TimeRelativeTransactionVerifier::new(rtx, consensus, data_loader, tx_env)
                     .verify()
                     .map(|_| *completed)
@@ -149,8 +266,32 @@ pub(crate) fn time_relative_verify(
     .map_err(Reject::Verification)
 }
 
-pub(crate) fn is_missing_input(reject: &Reject) -> bool {
-    matches!(reject, Reject::Resolve(out_point_err) if out_point_err.is_unknown())
+/// The tx is kept in the orphan buffer until its unknown inputs or cell deps arrive
+pub(crate) fn is_missing_input(reject: &Reject, tx: &TransactionView) -> bool {
+    matches!(
+        reject.resolve_class(tx),
+        Some(ResolveClass::UnknownInput | ResolveClass::MissingDep)
+    )
+}
+
+/// Permanent rejects are recorded in recent_reject, retryable ones may still be accepted later
+pub(crate) fn is_recorded_reject(reject: &Reject) -> bool {
+    matches!(
+        reject,
+        Reject::Resolve(..)
+            | Reject::Conflict { .. }
+            | Reject::Verification(..)
+            | Reject::RBFRejected(..)
+    ) && !reject.is_retryable()
+}
+
+/// Txs carrying remote peer info are relayed, the others are submitted locally
+pub(crate) fn origin_of<T>(remote: &Option<T>) -> Origin {
+    if remote.is_some() {
+        Origin::Remote
+    } else {
+        Origin::Local
+    }
 }
 
 /// Unwraps a result or propagates its error with snapshot.
@@ -7,10 +7,12 @@ use url::Url;
 
 // The default values are set in the legacy version.
 /// Transaction pool configuration
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TxPoolConfig {
     /// Keep the transaction pool below <max_tx_pool_size> mb
     pub max_tx_pool_size: usize,
+    /// Keep the number of txs in the pool below this
+    pub max_tx_pool_entries: usize,
     /// txs with lower fee rate than this will not be relayed or be mined
     #[serde(with = "FeeRateDef")]
     pub min_fee_rate: FeeRate,
@@ -25,6 +27,9 @@ pub struct TxPoolConfig {
     pub keep_rejected_tx_hashes_days: u8,
     /// rejected tx count limit
     pub keep_rejected_tx_hashes_count: u64,
+    /// rejected tx count limit may grow up to this under reject bursts, and shrink back to
+    /// keep_rejected_tx_hashes_count when calm, fixed if not set
+    pub keep_rejected_tx_hashes_count_max: Option<u64>,
     /// The file to persist the tx pool on the disk when tx pool have been shutdown.
     ///
     /// By default, it is a subdirectory of 'tx-pool' subdirectory under the data directory.
@@ -37,6 +42,150 @@ pub struct TxPoolConfig {
     pub recent_reject: PathBuf,
     /// The expiration time for pool transactions in hours
     pub expiry_hours: u8,
+    /// Local pending txs older than this are re-announced to peers, 0 (the default) to disable
+    pub rebroadcast_min_age_secs: u64,
+    /// Max number of txs re-announced in one round
+    pub rebroadcast_batch_size: usize,
+    /// Stop selecting txs for the block template after this many milliseconds, unbounded if not set
+    pub max_package_duration_millis: Option<u64>,
+    /// Max number of large txs waiting for the chunked verification, more are rejected
+    pub max_chunk_transactions: usize,
+    /// Stop selecting txs for the block template once their cycles reach this, to leave headroom
+    /// below the max block cycles, unbounded if not set
+    pub soft_cycle_target: Option<Cycle>,
+    /// Accept zero-fee txs as long as min_fee_rate allows them, they are packaged last and evicted
+    /// first, reject them whatever the min_fee_rate if false
+    pub allow_zero_fee: bool,
+    /// Max total size of a tx and its in-pool ancestors, capped by the max block bytes
+    pub max_package_size: Option<usize>,
+    /// Max total cycles of a tx and its in-pool ancestors, capped by the max block cycles
+    pub max_package_cycles: Option<Cycle>,
+    /// Max number of header deps of a tx, unbounded if not set
+    pub max_header_deps: Option<usize>,
+    /// Reject txs spending or depending on immature cellbase outputs right after resolving them,
+    /// before running their scripts
+    pub reject_immature_cellbase: bool,
+    /// Max number of txs evicted by the size limit kept aside, the best paying first, to be
+    /// re-admitted once the pool has room again, 0 to disable
+    pub evicted_archive_size: usize,
+    /// Script hash types the outputs of a tx may use, txs with an output lock or type script of
+    /// another hash type are rejected as non-standard, all allowed if not set
+    pub allowed_hash_types: Option<Vec<ScriptHashType>>,
+    /// Share of the txs, from 0 to 1, whose lifecycle is traced, see `TxPool::trace`, 0 to only
+    /// trace the txs registered explicitly
+    pub trace_sample_rate: f64,
+    /// Milliseconds a pinned pool view keeps answering, see `TxPool::consistent_view`
+    pub max_view_age_millis: u64,
+    /// Bytes each cycle weighs in the fee rate of a tx, whose weight is the max of its size and its
+    /// cycles converted to bytes, 0 to rank txs by fee per byte only
+    pub bytes_per_cycles: f64,
+    /// Half-life in seconds of the fee rate floor raised by evictions, 0 to drop it right away
+    pub min_fee_decay_half_life_s: u64,
+    /// Average number of txs per second admitted from a single peer, 0 (the default) to disable
+    /// the limit
+    pub max_peer_tx_rate: u64,
+    /// Number of txs a single peer may send in a burst above the average rate
+    pub max_peer_tx_burst: u64,
+    /// Quota of the pending txs, evicted first when exceeded
+    pub pending_quota: StatusQuota,
+    /// Quota of the gap txs, evicted first when exceeded
+    pub gap_quota: StatusQuota,
+    /// Quota of the proposed txs, evicted first when exceeded
+    pub proposed_quota: StatusQuota,
+    /// How the txs to evict are picked when the pool is over its limits
+    pub eviction_strategy: EvictionStrategy,
+    /// Fee in shannons the txs proposed since the last block template pay above its lowest fee
+    /// rate, once reached the template is rebuilt on the next poll, never if not set
+    pub template_refresh_fee: Option<u64>,
+    /// Keep committed txs queryable with the committed status until this many blocks are built
+    /// on top of their block, 0 removes them as soon as they are committed
+    pub keep_committed_confirmations: u64,
+    /// What to do with a tx spending a cell which pool txs use as cell dep
+    pub consumed_dep_policy: ConsumedDepPolicy,
+    /// Max number of expired local txs kept to be submitted again, 0 to forget them
+    pub resubmit_list_size: usize,
+    /// Expired local txs are dropped from the resubmission list after this many hours
+    pub resubmit_retention_hours: u64,
+    /// Packages larger than this many bytes, a tx and its descendants, are evicted only when no
+    /// smaller package is left, with the `package_size` eviction strategy, never if not set
+    pub max_evict_package_size: Option<usize>,
+    /// Max number of tx resolutions cached until the next block or pool change, 0 to disable
+    pub resolve_cache_size: usize,
+    /// Max number of txs returned by an ancestors or descendants walk of the pool
+    pub max_traversal_entries: usize,
+    /// The pool is cleared instead of reconciled when a reorg detaches more blocks than this,
+    /// never if not set
+    pub reorg_clear_depth: Option<u64>,
+    /// Min capacity in shannons of each output created by a relayed tx, no floor if not set
+    pub min_output_capacity: Option<u64>,
+    /// Min capacity in shannons of the outputs carrying a type script, `min_output_capacity`
+    /// applies to them too if not set
+    pub min_typed_output_capacity: Option<u64>,
+    /// Max number of outputs of a relayed tx, unbounded if not set
+    pub max_outputs_per_tx: Option<usize>,
+    /// Appends the admission attempts, block commits and threshold changes the pool goes through
+    /// to this file, so they can be replayed in a test, `None` disables it
+    pub replay_log: Option<PathBuf>,
+    /// The replay log stops growing once it reaches this many bytes, half of the room left when
+    /// the node starts is kept for the entries the pool holds when the log is closed
+    pub replay_log_max_size: u64,
+}
+
+/// Size and count limits of the txs in one status, within the overall `max_tx_pool_size`.
+///
+/// No limit by default.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatusQuota {
+    /// Max total size in bytes
+    pub max_size: Option<usize>,
+    /// Max number of txs
+    pub max_count: Option<usize>,
+}
+
+impl StatusQuota {
+    /// Whether neither the size nor the count is limited
+    pub fn is_unlimited(&self) -> bool {
+        self.max_size.is_none() && self.max_count.is_none()
+    }
+
+    /// Whether txs of total `size` and `count` go over the quota
+    pub fn is_exceeded(&self, size: usize, count: usize) -> bool {
+        self.max_size.map_or(false, |max| size > max)
+            || self.max_count.map_or(false, |max| count > max)
+    }
+}
+
+/// How the tx-pool picks the txs to evict when it is over its limits, a tx is always evicted
+/// together with its descendants
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictionStrategy {
+    /// The tx with the lowest package fee rate first
+    #[default]
+    PackageFeeRate,
+    /// Among the cheapest txs, the one with the lowest package fee rate whose package frees enough
+    /// room, the lowest package fee rate first if no package is large enough
+    DescendantScore,
+    /// Among the cheapest txs, the one whose package is the smallest still freeing enough room,
+    /// so a large descendant tree is not evicted when a smaller package would do
+    PackageSize,
+}
+
+/// What the tx-pool does with a new tx spending a cell which pool txs use as cell dep, those txs
+/// would be invalidated if the new tx were committed first
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsumedDepPolicy {
+    /// Admit the new tx and keep the txs depending on the cell, they are removed once it is
+    /// committed
+    #[default]
+    Keep,
+    /// Admit the new tx if it pays for the txs depending on the cell and their descendants the
+    /// way a replacement does, and evict them once it is added
+    Evict,
+    /// Reject the new tx
+    Reject,
 }
 
 /// Block assembler config options.
@@ -105,6 +254,11 @@ impl TxPoolConfig {
             &mut self.recent_reject,
             "recent_reject",
         );
+        if let Some(replay_log) = self.replay_log.as_mut() {
+            if replay_log.is_relative() {
+                *replay_log = root_dir.join(&replay_log);
+            }
+        }
     }
 }
 
@@ -1,6 +1,7 @@
+use crate::{ConsumedDepPolicy, EvictionStrategy, StatusQuota};
 use ckb_chain_spec::consensus::TWO_IN_TWO_OUT_CYCLES;
-use ckb_jsonrpc_types::FeeRateDef;
-use ckb_types::core::{Cycle, FeeRate};
+use ckb_jsonrpc_types::{FeeRateDef, ScriptHashType};
+use ckb_types::core::{tx_pool::DEFAULT_BYTES_PER_CYCLES, Cycle, FeeRate};
 use serde::Deserialize;
 use std::cmp;
 use std::path::PathBuf;
@@ -17,6 +18,30 @@ const DEFAULT_MAX_ANCESTORS_COUNT: usize = 125;
 const DEFAULT_EXPIRY_HOURS: u8 = 12;
 // Default max_tx_pool_size 180mb
 const DEFAULT_MAX_TX_POOL_SIZE: usize = 180_000_000;
+// Default max number of txs in the pool
+const DEFAULT_MAX_TX_POOL_ENTRIES: usize = 1_000_000;
+// Default min age of local pending txs before re-broadcast, 0 as re-broadcast is opt-in
+const DEFAULT_REBROADCAST_MIN_AGE_SECS: u64 = 0;
+// Default max txs re-broadcast in one round
+const DEFAULT_REBROADCAST_BATCH_SIZE: usize = 100;
+// Default max large txs waiting for the chunked verification
+const DEFAULT_MAX_CHUNK_TRANSACTIONS: usize = 100;
+// Default half-life of the fee rate floor raised by evictions, 10 minutes
+const DEFAULT_MIN_FEE_DECAY_HALF_LIFE_S: u64 = 10 * 60;
+// Default max expired local txs kept to be submitted again
+const DEFAULT_RESUBMIT_LIST_SIZE: usize = 100;
+// Default retention of the expired local txs, 1 day
+const DEFAULT_RESUBMIT_RETENTION_HOURS: u64 = 24;
+// Default max txs returned by an ancestors or descendants walk
+const DEFAULT_MAX_TRAVERSAL_ENTRIES: usize = 1000;
+// Default max age of a pinned pool view, 10 seconds
+const DEFAULT_MAX_VIEW_AGE_MILLIS: u64 = 10 * 1000;
+// Default max size of the replay log, 100mb
+const DEFAULT_REPLAY_LOG_MAX_SIZE: u64 = 100_000_000;
+// Default txs per second admitted from a single peer, 0 as the peer rate limit is opt-in
+const DEFAULT_MAX_PEER_TX_RATE: u64 = 0;
+// Default burst of txs admitted from a single peer
+const DEFAULT_MAX_PEER_TX_BURST: u64 = 1000;
 
 #[derive(Clone, Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -24,6 +49,8 @@ const DEFAULT_MAX_TX_POOL_SIZE: usize = 180_000_000;
 pub(crate) struct TxPoolConfig {
     #[serde(default = "default_max_tx_pool_size")]
     max_tx_pool_size: usize,
+    #[serde(default = "default_max_tx_pool_entries")]
+    max_tx_pool_entries: usize,
     max_mem_size: Option<usize>,
     max_cycles: Option<Cycle>,
     pub(crate) max_verify_cache_size: Option<usize>,
@@ -33,6 +60,7 @@ pub(crate) struct TxPoolConfig {
     keep_rejected_tx_hashes_days: u8,
     #[serde(default = "default_keep_rejected_tx_hashes_count")]
     keep_rejected_tx_hashes_count: u64,
+    keep_rejected_tx_hashes_count_max: Option<u64>,
     #[serde(with = "FeeRateDef")]
     min_fee_rate: FeeRate,
     #[serde(with = "FeeRateDef", default = "default_min_rbf_rate")]
@@ -45,6 +73,76 @@ pub(crate) struct TxPoolConfig {
     recent_reject: PathBuf,
     #[serde(default = "default_expiry_hours")]
     expiry_hours: u8,
+    #[serde(default = "default_rebroadcast_min_age_secs")]
+    rebroadcast_min_age_secs: u64,
+    #[serde(default = "default_rebroadcast_batch_size")]
+    rebroadcast_batch_size: usize,
+    max_package_duration_millis: Option<u64>,
+    #[serde(default = "default_max_chunk_transactions")]
+    max_chunk_transactions: usize,
+    soft_cycle_target: Option<Cycle>,
+    #[serde(default = "default_allow_zero_fee")]
+    allow_zero_fee: bool,
+    max_package_size: Option<usize>,
+    max_package_cycles: Option<Cycle>,
+    max_header_deps: Option<usize>,
+    #[serde(default = "default_reject_immature_cellbase")]
+    reject_immature_cellbase: bool,
+    #[serde(default)]
+    evicted_archive_size: usize,
+    #[serde(default)]
+    allowed_hash_types: Option<Vec<ScriptHashType>>,
+    #[serde(default)]
+    trace_sample_rate: f64,
+    #[serde(default = "default_max_view_age_millis")]
+    max_view_age_millis: u64,
+    #[serde(
+        default = "default_bytes_per_cycles",
+        deserialize_with = "finite_non_negative"
+    )]
+    bytes_per_cycles: f64,
+    #[serde(default = "default_min_fee_decay_half_life_s")]
+    min_fee_decay_half_life_s: u64,
+    #[serde(default = "default_max_peer_tx_rate")]
+    max_peer_tx_rate: u64,
+    #[serde(default = "default_max_peer_tx_burst")]
+    max_peer_tx_burst: u64,
+    #[serde(default)]
+    pending_quota: StatusQuota,
+    #[serde(default)]
+    gap_quota: StatusQuota,
+    #[serde(default)]
+    proposed_quota: StatusQuota,
+    #[serde(default)]
+    eviction_strategy: EvictionStrategy,
+    #[serde(default)]
+    template_refresh_fee: Option<u64>,
+    #[serde(default)]
+    keep_committed_confirmations: u64,
+    #[serde(default)]
+    consumed_dep_policy: ConsumedDepPolicy,
+    #[serde(default = "default_resubmit_list_size")]
+    resubmit_list_size: usize,
+    #[serde(default = "default_resubmit_retention_hours")]
+    resubmit_retention_hours: u64,
+    #[serde(default)]
+    max_evict_package_size: Option<usize>,
+    #[serde(default)]
+    resolve_cache_size: usize,
+    #[serde(default = "default_max_traversal_entries")]
+    max_traversal_entries: usize,
+    #[serde(default)]
+    reorg_clear_depth: Option<u64>,
+    #[serde(default)]
+    min_output_capacity: Option<u64>,
+    #[serde(default)]
+    min_typed_output_capacity: Option<u64>,
+    #[serde(default)]
+    max_outputs_per_tx: Option<usize>,
+    #[serde(default)]
+    replay_log: Option<PathBuf>,
+    #[serde(default = "default_replay_log_max_size")]
+    replay_log_max_size: u64,
 }
 
 fn default_keep_rejected_tx_hashes_days() -> u8 {
@@ -63,10 +161,84 @@ fn default_max_tx_pool_size() -> usize {
     DEFAULT_MAX_TX_POOL_SIZE
 }
 
+fn default_max_tx_pool_entries() -> usize {
+    DEFAULT_MAX_TX_POOL_ENTRIES
+}
+
 fn default_min_rbf_rate() -> FeeRate {
     DEFAULT_MIN_RBF_RATE
 }
 
+fn default_rebroadcast_min_age_secs() -> u64 {
+    DEFAULT_REBROADCAST_MIN_AGE_SECS
+}
+
+fn default_rebroadcast_batch_size() -> usize {
+    DEFAULT_REBROADCAST_BATCH_SIZE
+}
+
+fn default_max_chunk_transactions() -> usize {
+    DEFAULT_MAX_CHUNK_TRANSACTIONS
+}
+
+fn default_allow_zero_fee() -> bool {
+    true
+}
+
+fn default_resubmit_list_size() -> usize {
+    DEFAULT_RESUBMIT_LIST_SIZE
+}
+
+fn default_resubmit_retention_hours() -> u64 {
+    DEFAULT_RESUBMIT_RETENTION_HOURS
+}
+
+fn default_max_traversal_entries() -> usize {
+    DEFAULT_MAX_TRAVERSAL_ENTRIES
+}
+
+fn default_replay_log_max_size() -> u64 {
+    DEFAULT_REPLAY_LOG_MAX_SIZE
+}
+
+fn default_max_view_age_millis() -> u64 {
+    DEFAULT_MAX_VIEW_AGE_MILLIS
+}
+
+fn default_reject_immature_cellbase() -> bool {
+    true
+}
+
+fn default_bytes_per_cycles() -> f64 {
+    DEFAULT_BYTES_PER_CYCLES
+}
+
+fn finite_non_negative<'de, D>(d: D) -> Result<f64, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+{
+    let value = f64::deserialize(d)?;
+    if !value.is_finite() || value < 0.0 {
+        return Err(serde::de::Error::invalid_value(
+            serde::de::Unexpected::Float(value),
+            &"a finite non-negative number",
+        ));
+    }
+    Ok(value)
+}
+
+fn default_min_fee_decay_half_life_s() -> u64 {
+    DEFAULT_MIN_FEE_DECAY_HALF_LIFE_S
+}
+
+fn default_max_peer_tx_rate() -> u64 {
+    DEFAULT_MAX_PEER_TX_RATE
+}
+
+fn default_max_peer_tx_burst() -> u64 {
+    DEFAULT_MAX_PEER_TX_BURST
+}
+
 impl Default for crate::TxPoolConfig {
     fn default() -> Self {
         TxPoolConfig::default().into()
@@ -78,12 +250,14 @@ impl Default for TxPoolConfig {
         Self {
             max_mem_size: None,
             max_tx_pool_size: DEFAULT_MAX_TX_POOL_SIZE,
+            max_tx_pool_entries: DEFAULT_MAX_TX_POOL_ENTRIES,
             max_cycles: None,
             max_verify_cache_size: None,
             max_conflict_cache_size: None,
             max_committed_txs_hash_cache_size: None,
             keep_rejected_tx_hashes_days: default_keep_rejected_tx_hashes_days(),
             keep_rejected_tx_hashes_count: default_keep_rejected_tx_hashes_count(),
+            keep_rejected_tx_hashes_count_max: None,
             min_fee_rate: DEFAULT_MIN_FEE_RATE,
             min_rbf_rate: DEFAULT_MIN_RBF_RATE,
             max_tx_verify_cycles: DEFAULT_MAX_TX_VERIFY_CYCLES,
@@ -91,6 +265,42 @@ impl Default for TxPoolConfig {
             persisted_data: Default::default(),
             recent_reject: Default::default(),
             expiry_hours: DEFAULT_EXPIRY_HOURS,
+            rebroadcast_min_age_secs: DEFAULT_REBROADCAST_MIN_AGE_SECS,
+            rebroadcast_batch_size: DEFAULT_REBROADCAST_BATCH_SIZE,
+            max_package_duration_millis: None,
+            max_chunk_transactions: DEFAULT_MAX_CHUNK_TRANSACTIONS,
+            soft_cycle_target: None,
+            allow_zero_fee: default_allow_zero_fee(),
+            max_package_size: None,
+            max_package_cycles: None,
+            max_header_deps: None,
+            reject_immature_cellbase: default_reject_immature_cellbase(),
+            evicted_archive_size: 0,
+            allowed_hash_types: None,
+            trace_sample_rate: 0.0,
+            max_view_age_millis: DEFAULT_MAX_VIEW_AGE_MILLIS,
+            bytes_per_cycles: DEFAULT_BYTES_PER_CYCLES,
+            min_fee_decay_half_life_s: DEFAULT_MIN_FEE_DECAY_HALF_LIFE_S,
+            max_peer_tx_rate: DEFAULT_MAX_PEER_TX_RATE,
+            max_peer_tx_burst: DEFAULT_MAX_PEER_TX_BURST,
+            pending_quota: StatusQuota::default(),
+            gap_quota: StatusQuota::default(),
+            proposed_quota: StatusQuota::default(),
+            eviction_strategy: EvictionStrategy::default(),
+            template_refresh_fee: None,
+            keep_committed_confirmations: 0,
+            consumed_dep_policy: ConsumedDepPolicy::default(),
+            resubmit_list_size: DEFAULT_RESUBMIT_LIST_SIZE,
+            resubmit_retention_hours: DEFAULT_RESUBMIT_RETENTION_HOURS,
+            max_evict_package_size: None,
+            resolve_cache_size: 0,
+            max_traversal_entries: DEFAULT_MAX_TRAVERSAL_ENTRIES,
+            reorg_clear_depth: None,
+            min_output_capacity: None,
+            min_typed_output_capacity: None,
+            max_outputs_per_tx: None,
+            replay_log: None,
+            replay_log_max_size: DEFAULT_REPLAY_LOG_MAX_SIZE,
         }
     }
 }
@@ -100,12 +310,14 @@ impl From<TxPoolConfig> for crate::TxPoolConfig {
         let TxPoolConfig {
             max_mem_size: _,
             max_tx_pool_size,
+            max_tx_pool_entries,
             max_cycles: _,
             max_verify_cache_size: _,
             max_conflict_cache_size: _,
             max_committed_txs_hash_cache_size: _,
             keep_rejected_tx_hashes_days,
             keep_rejected_tx_hashes_count,
+            keep_rejected_tx_hashes_count_max,
             min_fee_rate,
             min_rbf_rate,
             max_tx_verify_cycles,
@@ -113,19 +325,118 @@ impl From<TxPoolConfig> for crate::TxPoolConfig {
             persisted_data,
             recent_reject,
             expiry_hours,
+            rebroadcast_min_age_secs,
+            rebroadcast_batch_size,
+            max_package_duration_millis,
+            max_chunk_transactions,
+            soft_cycle_target,
+            allow_zero_fee,
+            max_package_size,
+            max_package_cycles,
+            max_header_deps,
+            reject_immature_cellbase,
+            evicted_archive_size,
+            allowed_hash_types,
+            trace_sample_rate,
+            max_view_age_millis,
+            bytes_per_cycles,
+            min_fee_decay_half_life_s,
+            max_peer_tx_rate,
+            max_peer_tx_burst,
+            pending_quota,
+            gap_quota,
+            proposed_quota,
+            eviction_strategy,
+            template_refresh_fee,
+            keep_committed_confirmations,
+            consumed_dep_policy,
+            resubmit_list_size,
+            resubmit_retention_hours,
+            max_evict_package_size,
+            resolve_cache_size,
+            max_traversal_entries,
+            reorg_clear_depth,
+            min_output_capacity,
+            min_typed_output_capacity,
+            max_outputs_per_tx,
+            replay_log,
+            replay_log_max_size,
         } = input;
 
         Self {
             max_tx_pool_size,
+            max_tx_pool_entries,
             min_fee_rate,
             min_rbf_rate,
             max_tx_verify_cycles,
             max_ancestors_count: cmp::max(DEFAULT_MAX_ANCESTORS_COUNT, max_ancestors_count),
             keep_rejected_tx_hashes_days,
             keep_rejected_tx_hashes_count,
+            keep_rejected_tx_hashes_count_max,
             persisted_data,
             recent_reject,
             expiry_hours,
+            rebroadcast_min_age_secs,
+            rebroadcast_batch_size,
+            max_package_duration_millis,
+            max_chunk_transactions,
+            soft_cycle_target,
+            allow_zero_fee,
+            max_package_size,
+            max_package_cycles,
+            max_header_deps,
+            reject_immature_cellbase,
+            evicted_archive_size,
+            allowed_hash_types,
+            trace_sample_rate,
+            max_view_age_millis,
+            bytes_per_cycles,
+            min_fee_decay_half_life_s,
+            max_peer_tx_rate,
+            max_peer_tx_burst,
+            pending_quota,
+            gap_quota,
+            proposed_quota,
+            eviction_strategy,
+            template_refresh_fee,
+            keep_committed_confirmations,
+            consumed_dep_policy,
+            resubmit_list_size,
+            resubmit_retention_hours,
+            max_evict_package_size,
+            resolve_cache_size,
+            max_traversal_entries,
+            reorg_clear_depth,
+            min_output_capacity,
+            min_typed_output_capacity,
+            max_outputs_per_tx,
+            replay_log,
+            replay_log_max_size,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUIRED: &str = r#"
+        min_fee_rate = 1000
+        max_tx_verify_cycles = 70000000
+        max_ancestors_count = 25
+    "#;
+
+    #[test]
+    fn test_deserialize_bytes_per_cycles() {
+        for value in ["-0.1", "nan", "inf"] {
+            let s = format!("{REQUIRED}bytes_per_cycles = {value}");
+            assert!(toml::from_str::<TxPoolConfig>(&s).is_err(), "{value}");
+        }
+
+        let s = format!("{REQUIRED}bytes_per_cycles = 0.0");
+        let config = toml::from_str::<TxPoolConfig>(&s).unwrap();
+        assert_eq!(config.bytes_per_cycles, 0.0);
+        let config = toml::from_str::<TxPoolConfig>(REQUIRED).unwrap();
+        assert_eq!(config.bytes_per_cycles, DEFAULT_BYTES_PER_CYCLES);
+    }
+}
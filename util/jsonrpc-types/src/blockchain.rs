@@ -581,12 +581,15 @@ pub enum Status {
     /// Status "rejected". The transaction has been recently removed from the pool.
     /// Due to storage limitations, the node can only hold the most recently removed transactions.
     Rejected,
+    /// Status "verifying". The transaction is accepted and its scripts are being verified in
+    /// chunks, it enters the pool once the verification completes.
+    Verifying,
 }
 
 /// Transaction status and the block hash if it is committed.
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
 pub struct TxStatus {
-    /// The transaction status, allowed values: "pending", "proposed" "committed" "unknown" and "rejected".
+    /// The transaction status, allowed values: "pending", "proposed" "committed" "unknown" "rejected" and "verifying".
     pub status: Status,
     /// The block hash of the block which has committed this transaction in the canonical chain.
     pub block_hash: Option<H256>,
@@ -602,6 +605,7 @@ impl From<tx_pool::TxStatus> for TxStatus {
             tx_pool::TxStatus::Committed(hash) => TxStatus::committed(hash),
             tx_pool::TxStatus::Rejected(reason) => TxStatus::rejected(reason),
             tx_pool::TxStatus::Unknown => TxStatus::unknown(),
+            tx_pool::TxStatus::Verifying => TxStatus::verifying(),
         }
     }
 }
@@ -660,6 +664,15 @@ impl TxStatus {
         }
     }
 
+    /// Transaction which is accepted and still being verified.
+    pub fn verifying() -> Self {
+        Self {
+            status: Status::Verifying,
+            block_hash: None,
+            reason: None,
+        }
+    }
+
     /// Returns true if the status is Unknown.
     pub fn is_unknown(&self) -> bool {
         matches!(self.status, Status::Unknown)
@@ -44,7 +44,8 @@ pub use self::net::{
     RemoteNodeProtocol, SyncState,
 };
 pub use self::pool::{
-    OutputsValidator, PoolTransactionEntry, PoolTransactionReject, RawTxPool, TxPoolEntries,
+    AnnotatedTxId, OutputsValidator, PoolTransactionEntry, PoolTransactionReject, PoolTxFilter,
+    RBFRule, RBFRuleCheck, RawTxPool, StatusQuotaUsage, TxPoolAnnotatedIds, TxPoolEntries,
     TxPoolEntry, TxPoolIds, TxPoolInfo,
 };
 pub use self::proposal_short_id::ProposalShortId;
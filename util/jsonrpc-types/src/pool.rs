@@ -1,7 +1,10 @@
 use crate::{BlockNumber, Capacity, Cycle, Timestamp, TransactionView, Uint64};
 use ckb_types::core::service::PoolTransactionEntry as CorePoolTransactionEntry;
 use ckb_types::core::tx_pool::{
-    Reject, TxEntryInfo, TxPoolEntryInfo, TxPoolIds as CoreTxPoolIds, TxPoolInfo as CoreTxPoolInfo,
+    AnnotatedTxId as CoreAnnotatedTxId, PoolTxFilter as CorePoolTxFilter, RBFRule as CoreRBFRule,
+    Reject, StatusQuotaUsage as CoreStatusQuotaUsage, TxEntryInfo,
+    TxPoolAnnotatedIds as CoreTxPoolAnnotatedIds, TxPoolEntryInfo, TxPoolIds as CoreTxPoolIds,
+    TxPoolInfo as CoreTxPoolInfo,
 };
 use ckb_types::prelude::Unpack;
 use ckb_types::H256;
@@ -55,6 +58,43 @@ pub struct TxPoolInfo {
     pub tx_size_limit: Uint64,
     /// Total limit on the size of transactions in the tx-pool
     pub max_tx_pool_size: Uint64,
+    /// Limit on the number of transactions in the tx-pool, compared with `pending` plus `proposed`
+    pub max_tx_pool_entries: Uint64,
+    /// Whether the tx-pool is read-only for maintenance, rejecting new transactions
+    pub read_only: bool,
+    /// Usage and quota of the pending transactions
+    pub pending_quota: StatusQuotaUsage,
+    /// Usage and quota of the transactions in the gap state, proposed but not yet committable
+    pub gap_quota: StatusQuotaUsage,
+    /// Usage and quota of the proposed transactions
+    pub proposed_quota: StatusQuotaUsage,
+    /// Sequence number of the last pool membership change the info was read at
+    #[serde(default)]
+    pub seq: Uint64,
+}
+
+/// Total size and count of the transactions in one state of the tx-pool, and the quota on them.
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+pub struct StatusQuotaUsage {
+    /// Total serialized size of the transactions in bytes
+    pub size: Uint64,
+    /// Count of the transactions
+    pub count: Uint64,
+    /// Limit on `size`, null for unlimited
+    pub max_size: Option<Uint64>,
+    /// Limit on `count`, null for unlimited
+    pub max_count: Option<Uint64>,
+}
+
+impl From<CoreStatusQuotaUsage> for StatusQuotaUsage {
+    fn from(usage: CoreStatusQuotaUsage) -> Self {
+        StatusQuotaUsage {
+            size: (usage.size as u64).into(),
+            count: (usage.count as u64).into(),
+            max_size: usage.max_size.map(|size| (size as u64).into()),
+            max_count: usage.max_count.map(|count| (count as u64).into()),
+        }
+    }
 }
 
 impl From<CoreTxPoolInfo> for TxPoolInfo {
@@ -72,6 +112,12 @@ impl From<CoreTxPoolInfo> for TxPoolInfo {
             last_txs_updated_at: tx_pool_info.last_txs_updated_at.into(),
             tx_size_limit: tx_pool_info.tx_size_limit.into(),
             max_tx_pool_size: tx_pool_info.max_tx_pool_size.into(),
+            max_tx_pool_entries: tx_pool_info.max_tx_pool_entries.into(),
+            read_only: tx_pool_info.read_only,
+            pending_quota: tx_pool_info.pending_quota.into(),
+            gap_quota: tx_pool_info.gap_quota.into(),
+            proposed_quota: tx_pool_info.proposed_quota.into(),
+            seq: tx_pool_info.seq.into(),
         }
     }
 }
@@ -121,6 +167,83 @@ impl OutputsValidator {
     }
 }
 
+/// Which transactions in the pool a page of ids is taken from.
+#[derive(Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolTxFilter {
+    /// "all": both pending and proposed transactions.
+    #[default]
+    All,
+    /// "pending": transactions not proposed yet, including those proposed in the gap.
+    Pending,
+    /// "proposed": proposed transactions.
+    Proposed,
+}
+
+impl From<PoolTxFilter> for CorePoolTxFilter {
+    fn from(filter: PoolTxFilter) -> Self {
+        match filter {
+            PoolTxFilter::All => CorePoolTxFilter::All,
+            PoolTxFilter::Pending => CorePoolTxFilter::Pending,
+            PoolTxFilter::Proposed => CorePoolTxFilter::Proposed,
+        }
+    }
+}
+
+/// A replace-by-fee rule a transaction has to pass to replace the transactions it conflicts
+/// with in the pool, in the order they are checked.
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RBFRule {
+    /// "no_new_unconfirmed_inputs": the transaction spends no unconfirmed input the replaced
+    /// transactions did not, and depends on none of their outputs.
+    NoNewUnconfirmedInputs,
+    /// "pays_replaced_fees": the transaction pays at least the fees of the replaced transactions.
+    PaysReplacedFees,
+    /// "pays_min_rbf_fee": the transaction pays at least the fees of the replaced transactions
+    /// plus its size at `min_rbf_rate`.
+    PaysMinRbfFee,
+    /// "limited_replacement": the transaction replaces at most 100 transactions, none of them
+    /// among its ancestors or spent by it.
+    LimitedReplacement,
+    /// "replaces_pending_only": every replaced transaction is pending.
+    ReplacesPendingOnly,
+    /// "within_ancestor_limits": once the replaced transactions are removed, the transaction
+    /// stays within the ancestors count and size limits.
+    WithinAncestorLimits,
+}
+
+impl From<CoreRBFRule> for RBFRule {
+    fn from(rule: CoreRBFRule) -> Self {
+        match rule {
+            CoreRBFRule::NoNewUnconfirmedInputs => RBFRule::NoNewUnconfirmedInputs,
+            CoreRBFRule::PaysReplacedFees => RBFRule::PaysReplacedFees,
+            CoreRBFRule::PaysMinRbfFee => RBFRule::PaysMinRbfFee,
+            CoreRBFRule::LimitedReplacement => RBFRule::LimitedReplacement,
+            CoreRBFRule::ReplacesPendingOnly => RBFRule::ReplacesPendingOnly,
+            CoreRBFRule::WithinAncestorLimits => RBFRule::WithinAncestorLimits,
+        }
+    }
+}
+
+/// A replace-by-fee rule checked for a transaction, with whether it passed
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+pub struct RBFRuleCheck {
+    /// The rule checked
+    pub rule: RBFRule,
+    /// Whether the transaction passed it
+    pub passed: bool,
+}
+
+impl From<(CoreRBFRule, bool)> for RBFRuleCheck {
+    fn from((rule, passed): (CoreRBFRule, bool)) -> Self {
+        RBFRuleCheck {
+            rule: rule.into(),
+            passed,
+        }
+    }
+}
+
 /// Array of transaction ids
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
 pub struct TxPoolIds {
@@ -128,14 +251,74 @@ pub struct TxPoolIds {
     pub pending: Vec<H256>,
     /// Proposed transaction ids
     pub proposed: Vec<H256>,
+    /// Sequence number of the last pool membership change the ids were read at, see
+    /// `get_pool_entries_at`
+    #[serde(default)]
+    pub seq: Uint64,
 }
 
 impl From<CoreTxPoolIds> for TxPoolIds {
     fn from(ids: CoreTxPoolIds) -> Self {
-        let CoreTxPoolIds { pending, proposed } = ids;
+        let CoreTxPoolIds {
+            pending,
+            proposed,
+            seq,
+        } = ids;
         TxPoolIds {
             pending: pending.iter().map(Unpack::unpack).collect(),
             proposed: proposed.iter().map(Unpack::unpack).collect(),
+            seq: seq.into(),
+        }
+    }
+}
+
+/// Transaction id annotated with the fee info of the transaction
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+pub struct AnnotatedTxId {
+    /// Transaction hash
+    pub hash: H256,
+    /// The transaction fee.
+    pub fee: Capacity,
+    /// The transaction serialized size in block.
+    pub size: Uint64,
+    /// The transaction fee rate, in shannons per KB.
+    pub fee_rate: Uint64,
+}
+
+impl From<CoreAnnotatedTxId> for AnnotatedTxId {
+    fn from(id: CoreAnnotatedTxId) -> Self {
+        AnnotatedTxId {
+            hash: id.hash.unpack(),
+            fee: id.fee.into(),
+            size: id.size.into(),
+            fee_rate: id.fee_rate.as_u64().into(),
+        }
+    }
+}
+
+/// Array of transaction ids annotated with their fee info
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+pub struct TxPoolAnnotatedIds {
+    /// Pending transactions
+    pub pending: Vec<AnnotatedTxId>,
+    /// Proposed transactions
+    pub proposed: Vec<AnnotatedTxId>,
+    /// Sequence number of the last pool membership change the ids were read at
+    #[serde(default)]
+    pub seq: Uint64,
+}
+
+impl From<CoreTxPoolAnnotatedIds> for TxPoolAnnotatedIds {
+    fn from(ids: CoreTxPoolAnnotatedIds) -> Self {
+        let CoreTxPoolAnnotatedIds {
+            pending,
+            proposed,
+            seq,
+        } = ids;
+        TxPoolAnnotatedIds {
+            pending: pending.into_iter().map(Into::into).collect(),
+            proposed: proposed.into_iter().map(Into::into).collect(),
+            seq: seq.into(),
         }
     }
 }
@@ -149,6 +332,9 @@ pub struct TxPoolEntry {
     pub size: Uint64,
     /// The transaction fee.
     pub fee: Capacity,
+    /// The weight the fee rate is computed with, the max of the size and the cycles converted to
+    /// bytes.
+    pub weight: Uint64,
     /// Size of in-tx-pool ancestor transactions
     pub ancestors_size: Uint64,
     /// Cycles of in-tx-pool ancestor transactions
@@ -157,6 +343,10 @@ pub struct TxPoolEntry {
     pub ancestors_count: Uint64,
     /// The unix timestamp when entering the Txpool, unit: Millisecond
     pub timestamp: Uint64,
+    /// Number of rejected transactions which tried to double spend this one
+    pub double_spend_attempts: Uint64,
+    /// Hashes of the most recent transactions which tried to double spend this one
+    pub double_spend_tx_hashes: Vec<H256>,
 }
 
 impl From<TxEntryInfo> for TxPoolEntry {
@@ -165,10 +355,17 @@ impl From<TxEntryInfo> for TxPoolEntry {
             cycles: info.cycles.into(),
             size: info.size.into(),
             fee: info.fee.into(),
+            weight: info.weight.into(),
             ancestors_size: info.ancestors_size.into(),
             ancestors_cycles: info.ancestors_cycles.into(),
             ancestors_count: info.ancestors_count.into(),
             timestamp: info.timestamp.into(),
+            double_spend_attempts: info.double_spend_attempts.into(),
+            double_spend_tx_hashes: info
+                .double_spend_tx_hashes
+                .iter()
+                .map(Unpack::unpack)
+                .collect(),
         }
     }
 }
@@ -180,11 +377,23 @@ pub struct TxPoolEntries {
     pub pending: HashMap<H256, TxPoolEntry>,
     /// Proposed tx verbose info
     pub proposed: HashMap<H256, TxPoolEntry>,
+    /// Hash of the chain tip the entries were read against, missing from the entries dumped by
+    /// older nodes
+    #[serde(default)]
+    pub tip_hash: Option<H256>,
+    /// Sequence number of the last pool membership change the entries were read at
+    #[serde(default)]
+    pub seq: Uint64,
 }
 
 impl From<TxPoolEntryInfo> for TxPoolEntries {
     fn from(info: TxPoolEntryInfo) -> Self {
-        let TxPoolEntryInfo { pending, proposed } = info;
+        let TxPoolEntryInfo {
+            pending,
+            proposed,
+            tip_hash,
+            seq,
+        } = info;
 
         TxPoolEntries {
             pending: pending
@@ -195,16 +404,22 @@ impl From<TxPoolEntryInfo> for TxPoolEntries {
                 .into_iter()
                 .map(|(hash, entry)| (hash.unpack(), entry.into()))
                 .collect(),
+            tip_hash: Some(tip_hash.unpack()),
+            seq: seq.into(),
         }
     }
 }
 
 /// All transactions in tx-pool.
 ///
-/// `RawTxPool` is equivalent to [`TxPoolIds`][] `|` [`TxPoolEntries`][].
+/// `RawTxPool` is equivalent to [`TxPoolIds`][] `|` [`TxPoolEntries`][] `|`
+/// `{ "annotated": ` [`TxPoolAnnotatedIds`][] `}`.
+///
+/// The annotated ids are wrapped, an empty pool would read the same as [`TxPoolIds`][] otherwise.
 ///
 /// [`TxPoolIds`]: struct.TxPoolIds.html
 /// [`TxPoolEntries`]: struct.TxPoolEntries.html
+/// [`TxPoolAnnotatedIds`]: struct.TxPoolAnnotatedIds.html
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(untagged)]
 pub enum RawTxPool {
@@ -212,6 +427,11 @@ pub enum RawTxPool {
     Ids(TxPoolIds),
     /// verbose = true
     Verbose(TxPoolEntries),
+    /// verbose = false, annotated = true
+    Annotated {
+        /// Transaction ids annotated with their fee info
+        annotated: TxPoolAnnotatedIds,
+    },
 }
 
 /// TX reject message
@@ -224,6 +444,12 @@ pub enum PoolTransactionReject {
     /// Transaction exceeded maximum ancestors count limit
     ExceededMaximumAncestorsCount(String),
 
+    /// Transaction package exceeded maximum size or cycles limit
+    ExceededPackageLimit(String),
+
+    /// Transaction exceeded maximum header deps limit
+    ExceededMaximumHeaderDeps(String),
+
     /// Transaction exceeded maximum size limit
     ExceededTransactionSizeLimit(String),
 
@@ -242,6 +468,9 @@ pub enum PoolTransactionReject {
     /// Resolve failed
     Resolve(String),
 
+    /// The transaction spends inputs already spent by pool transactions
+    Conflict(String),
+
     /// Verification failed
     Verification(String),
 
@@ -250,6 +479,30 @@ pub enum PoolTransactionReject {
 
     /// RBF rejected
     RBFRejected(String),
+
+    /// Transaction is not proposed
+    NotProposed(String),
+
+    /// The verification queue is full
+    VerificationQueueFull(String),
+
+    /// The sending peer exceeded its admission rate
+    RateLimited(String),
+
+    /// The transaction does not meet the local standardness policy
+    NonStandard(String),
+
+    /// The transaction spends a cell used as cell dep by pool transactions
+    DepConsumed(String),
+
+    /// The transaction pool is read-only for maintenance
+    Maintenance(String),
+
+    /// The transaction pool was cleared by a deep reorg
+    ReorgCleared(String),
+
+    /// The transaction outputs violate the output policy of the pool
+    OutputPolicy(String),
 }
 
 impl From<Reject> for PoolTransactionReject {
@@ -259,6 +512,10 @@ impl From<Reject> for PoolTransactionReject {
             Reject::ExceededMaximumAncestorsCount => {
                 Self::ExceededMaximumAncestorsCount(format!("{reject}"))
             }
+            Reject::ExceededPackageLimit(..) => Self::ExceededPackageLimit(format!("{reject}")),
+            Reject::ExceededMaximumHeaderDeps(..) => {
+                Self::ExceededMaximumHeaderDeps(format!("{reject}"))
+            }
             Reject::ExceededTransactionSizeLimit(..) => {
                 Self::ExceededTransactionSizeLimit(format!("{reject}"))
             }
@@ -267,9 +524,18 @@ impl From<Reject> for PoolTransactionReject {
             Reject::Malformed(_, _) => Self::Malformed(format!("{reject}")),
             Reject::DeclaredWrongCycles(..) => Self::DeclaredWrongCycles(format!("{reject}")),
             Reject::Resolve(_) => Self::Resolve(format!("{reject}")),
+            Reject::Conflict { .. } => Self::Conflict(format!("{reject}")),
             Reject::Verification(_) => Self::Verification(format!("{reject}")),
             Reject::Expiry(_) => Self::Expiry(format!("{reject}")),
             Reject::RBFRejected(_) => Self::RBFRejected(format!("{reject}")),
+            Reject::NotProposed(_) => Self::NotProposed(format!("{reject}")),
+            Reject::VerificationQueueFull(_) => Self::VerificationQueueFull(format!("{reject}")),
+            Reject::RateLimited(_) => Self::RateLimited(format!("{reject}")),
+            Reject::NonStandard(_) => Self::NonStandard(format!("{reject}")),
+            Reject::DepConsumed(_) => Self::DepConsumed(format!("{reject}")),
+            Reject::Maintenance => Self::Maintenance(format!("{reject}")),
+            Reject::ReorgCleared(_) => Self::ReorgCleared(format!("{reject}")),
+            Reject::OutputPolicy(_) => Self::OutputPolicy(format!("{reject}")),
         }
     }
 }
@@ -1,4 +1,4 @@
-use crate::pool::OutputsValidator;
+use crate::pool::{OutputsValidator, RawTxPool, TxPoolAnnotatedIds, TxPoolIds};
 
 #[test]
 fn test_outputs_validator_json_display() {
@@ -8,3 +8,24 @@ fn test_outputs_validator_json_display() {
     );
     assert_eq!("passthrough", OutputsValidator::Passthrough.json_display());
 }
+
+#[test]
+fn test_raw_tx_pool_round_trip() {
+    let ids = RawTxPool::Ids(TxPoolIds {
+        pending: vec![],
+        proposed: vec![],
+        seq: 0.into(),
+    });
+    let annotated = RawTxPool::Annotated {
+        annotated: TxPoolAnnotatedIds {
+            pending: vec![],
+            proposed: vec![],
+            seq: 0.into(),
+        },
+    };
+    // empty pools still deserialize into the variant they were serialized from
+    for raw in [ids, annotated] {
+        let json = serde_json::to_string(&raw).unwrap();
+        assert_eq!(serde_json::from_str::<RawTxPool>(&json).unwrap(), raw);
+    }
+}
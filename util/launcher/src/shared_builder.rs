@@ -28,6 +28,8 @@ use ckb_tx_pool::{
 };
 use ckb_types::core::EpochExt;
 use ckb_types::core::HeaderView;
+use ckb_types::core::{Capacity, TransactionView};
+use ckb_types::prelude::*;
 use ckb_verification::cache::init_cache;
 use std::cmp::Ordering;
 use std::collections::HashSet;
@@ -455,7 +457,7 @@ fn register_tx_pool_callback(tx_pool_builder: &mut TxPoolServiceBuilder, notify:
         tx_pool.update_statics_for_remove_tx(entry.size, entry.cycles);
     }));
 
-    let notify_reject = notify;
+    let notify_reject = notify.clone();
     tx_pool_builder.register_reject(Box::new(
         move |tx_pool: &mut TxPool, entry: &TxEntry, reject: Reject| {
             // update statics
@@ -464,11 +466,7 @@ fn register_tx_pool_callback(tx_pool_builder: &mut TxPoolServiceBuilder, notify:
             let tx_hash = entry.transaction().hash();
             // record recent reject
             if matches!(reject, Reject::Resolve(..) | Reject::RBFRejected(..)) {
-                if let Some(ref mut recent_reject) = tx_pool.recent_reject {
-                    if let Err(e) = recent_reject.put(&tx_hash, reject.clone()) {
-                        error!("record recent_reject failed {} {} {}", tx_hash, reject, e);
-                    }
-                }
+                tx_pool.put_recent_reject(&tx_hash, &reject);
             }
 
             if reject.is_allowed_relay() {
@@ -482,4 +480,19 @@ fn register_tx_pool_callback(tx_pool_builder: &mut TxPoolServiceBuilder, notify:
             notify_reject.notify_reject_transaction(notify_tx_entry, reject);
         },
     ));
+
+    // a losing double spend is rejected before its scripts run, its cycles and fee are unknown
+    let notify_double_spend = notify;
+    tx_pool_builder.register_double_spend(Box::new(
+        move |tx: &TransactionView, reject: Reject, now_ms: u64| {
+            let notify_tx_entry = PoolTransactionEntry {
+                transaction: tx.clone(),
+                cycles: 0,
+                size: tx.data().serialized_size_in_block(),
+                fee: Capacity::zero(),
+                timestamp: now_ms,
+            };
+            notify_double_spend.notify_reject_transaction(notify_tx_entry, reject);
+        },
+    ));
 }
@@ -7,8 +7,9 @@
 //! [`ckb-metrics-service`]: ../ckb_metrics_service/index.html
 
 use prometheus::{
-    register_histogram, register_histogram_vec, register_int_counter, register_int_gauge,
-    register_int_gauge_vec, Histogram, HistogramVec, IntCounter, IntGauge, IntGaugeVec,
+    register_histogram, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge, register_int_gauge_vec, Histogram, HistogramVec, IntCounter, IntGauge,
+    IntGaugeVec,
 };
 use prometheus_static_metric::make_static_metric;
 use std::cell::Cell;
@@ -37,6 +38,28 @@ make_static_metric! {
             metadata,
         },
     }
+
+    // Struct for the CKB tx-pool fee flow outcome label
+    struct CkbTxPoolFeeFlow: IntCounter{
+        "outcome" => {
+            committed,
+            evicted,
+            cleared,
+            expired,
+            replaced,
+            conflicted,
+            readmitted,
+        },
+    }
+
+    // Struct for the CKB verify cache prewarm outcome label
+    struct CkbVerifyCachePrewarm: IntCounter{
+        "outcome" => {
+            inserted,
+            mismatched,
+            hit,
+        },
+    }
 }
 
 pub struct Metrics {
@@ -70,9 +93,18 @@ pub struct Metrics {
     pub ckb_sys_mem_rocksdb: IntGaugeVec,
     /// Counter for CKB network ban peers
     pub ckb_network_ban_peer: IntCounter,
+    /// Counter for the fees in shannons of the txs leaving the tx-pool, by outcome
+    pub ckb_tx_pool_fee_flow: CkbTxPoolFeeFlow,
+    /// Counter for the txs evicted by the tx-pool size limit
+    pub ckb_tx_pool_evicted_txs: IntCounter,
+    /// Counter for the bytes of the txs evicted by the tx-pool size limit
+    pub ckb_tx_pool_evicted_bytes: IntCounter,
+    /// Counter for the verify cache entries prewarmed from the tx-pool, by outcome
+    pub ckb_verify_cache_prewarm: CkbVerifyCachePrewarm,
 }
 
-static METRICS: once_cell::sync::Lazy<Metrics> = once_cell::sync::Lazy::new(|| Metrics {
+static METRICS: once_cell::sync::Lazy<Metrics> = once_cell::sync::Lazy::new(|| {
+    Metrics {
     ckb_chain_tip: register_int_gauge!("ckb_chain_tip", "The CKB chain tip header number").unwrap(),
     ckb_freezer_size: register_int_gauge!("ckb_freezer_size", "The CKB freezer size").unwrap(),
     ckb_freezer_read: register_int_counter!("ckb_freezer_read", "The CKB freezer read").unwrap(),
@@ -148,6 +180,35 @@ static METRICS: once_cell::sync::Lazy<Metrics> = once_cell::sync::Lazy::new(|| M
         "CKB network baned peer count"
     )
     .unwrap(),
+    ckb_tx_pool_fee_flow: CkbTxPoolFeeFlow::from(
+        &register_int_counter_vec!(
+            "ckb_tx_pool_fee_flow",
+            "The fees in shannons of the txs leaving the CKB tx-pool, by outcome, readmitted counts \
+            the evicted fees which came back from the archive",
+            &["outcome"]
+        )
+        .unwrap(),
+    ),
+    ckb_tx_pool_evicted_txs: register_int_counter!(
+        "ckb_tx_pool_evicted_txs",
+        "The txs evicted by the CKB tx-pool size limit"
+    )
+    .unwrap(),
+    ckb_tx_pool_evicted_bytes: register_int_counter!(
+        "ckb_tx_pool_evicted_bytes",
+        "The bytes of the txs evicted by the CKB tx-pool size limit"
+    )
+    .unwrap(),
+    ckb_verify_cache_prewarm: CkbVerifyCachePrewarm::from(
+        &register_int_counter_vec!(
+            "ckb_verify_cache_prewarm",
+            "The verify cache entries prewarmed from the CKB tx-pool, by outcome: inserted, \
+             ignored as verified under other rules, or used by a block",
+            &["outcome"]
+        )
+        .unwrap(),
+    ),
+}
 });
 
 /// Indicate whether the metrics service is enabled.
@@ -1,8 +1,14 @@
 use ckb_error::{ErrorKind, InternalErrorKind, SilentError as DefaultError};
 
-use crate::core::{
-    error::{OutPointError, TransactionError, TransactionErrorSource},
-    tx_pool::Reject,
+use crate::{
+    core::{
+        error::{OutPointError, TransactionError, TransactionErrorSource},
+        tx_pool::{Reject, ResolveClass},
+        TransactionBuilder,
+    },
+    h256,
+    packed::{CellDep, CellInput, OutPoint},
+    prelude::*,
 };
 
 #[test]
@@ -115,3 +121,72 @@ fn test_if_is_malformed_tx() {
         assert_eq!(reject.is_malformed_tx(), is_malformed);
     }
 }
+
+#[test]
+fn test_resolve_class() {
+    let input = OutPoint::new(h256!("0x1").pack(), 0);
+    let dep = OutPoint::new(h256!("0x2").pack(), 0);
+    let tx = TransactionBuilder::default()
+        .input(CellInput::new(input.clone(), 0))
+        .cell_dep(CellDep::new_builder().out_point(dep.clone()).build())
+        .build();
+
+    for (error, class, retryable) in vec![
+        (
+            OutPointError::Unknown(input.clone()),
+            ResolveClass::UnknownInput,
+            true,
+        ),
+        (OutPointError::Unknown(dep), ResolveClass::MissingDep, true),
+        (
+            OutPointError::InvalidHeader(Default::default()),
+            ResolveClass::MissingDep,
+            true,
+        ),
+        (
+            OutPointError::Dead(input.clone()),
+            ResolveClass::Dead,
+            false,
+        ),
+        (
+            OutPointError::OutOfOrder(input.clone()),
+            ResolveClass::Invalid,
+            false,
+        ),
+        (
+            OutPointError::InvalidDepGroup(input),
+            ResolveClass::Invalid,
+            false,
+        ),
+        (
+            OutPointError::OverMaxDepExpansionLimit,
+            ResolveClass::Invalid,
+            false,
+        ),
+    ] {
+        let reject = Reject::Resolve(error);
+        assert_eq!(reject.resolve_class(&tx), Some(class));
+        assert_eq!(reject.is_retryable(), retryable);
+    }
+
+    let reject = Reject::Duplicated(Default::default());
+    assert_eq!(reject.resolve_class(&tx), None);
+    assert!(!reject.is_retryable());
+
+    let reject = Reject::RateLimited(Default::default());
+    assert_eq!(reject.resolve_class(&tx), None);
+    assert!(reject.is_retryable());
+
+    let reject = Reject::Maintenance;
+    assert_eq!(reject.resolve_class(&tx), None);
+    assert!(reject.is_retryable());
+
+    // a cellbase output matures as the chain grows, other verification errors are permanent
+    let immature = TransactionError::CellbaseImmaturity {
+        inner: TransactionErrorSource::Inputs,
+        index: 0,
+    };
+    assert!(Reject::Verification(immature.into()).is_retryable());
+    let invalid_since = TransactionError::InvalidSince { index: 0 };
+    assert!(!Reject::Verification(invalid_since.into()).is_retryable());
+}
@@ -3,9 +3,9 @@ use crate::{
     core::{
         self,
         error::{OutPointError, TransactionError},
-        BlockNumber, Capacity, Cycle, FeeRate,
+        BlockNumber, Capacity, Cycle, FeeRate, TransactionView,
     },
-    packed::Byte32,
+    packed::{Byte32, ProposalShortId},
     H256,
 };
 use ckb_error::{
@@ -24,6 +24,14 @@ pub enum Reject {
     #[error("Transaction exceeded maximum ancestors count limit, try send it later")]
     ExceededMaximumAncestorsCount,
 
+    /// The total size or cycles of the tx and its in-pool ancestors exceeded the package limit
+    #[error("Transaction package {0} {1} exceeded maximum limit {2}, try send it later")]
+    ExceededPackageLimit(String, u64, u64),
+
+    /// Transaction exceeded maximum header deps limit
+    #[error("Transaction has {0} header deps, exceeded maximum limit {1}")]
+    ExceededMaximumHeaderDeps(usize, usize),
+
     /// Transaction exceeded maximum size limit
     #[error("Transaction size {0} exceeded maximum limit {1}")]
     ExceededTransactionSizeLimit(u64, u64),
@@ -48,6 +56,13 @@ pub enum Reject {
     #[error("Resolve failed {0}")]
     Resolve(OutPointError),
 
+    /// The tx spends inputs already spent by in-pool txs
+    #[error("Conflict with in-pool transactions {conflicting_ids:?}")]
+    Conflict {
+        /// The in-pool txs spending the same inputs, in the order of the inputs
+        conflicting_ids: Vec<ProposalShortId>,
+    },
+
     /// Verification failed
     #[error("Verification failed {0}")]
     Verification(Error),
@@ -59,6 +74,74 @@ pub enum Reject {
     /// RBF rejected
     #[error("RBF rejected: {0}")]
     RBFRejected(String),
+
+    /// Transaction is not proposed
+    #[error("Transaction({0}) is not proposed")]
+    NotProposed(Byte32),
+
+    /// The chunked verification queue is full
+    #[error("The verification queue is full, limit {0}, try send it later")]
+    VerificationQueueFull(usize),
+
+    /// The sending peer exceeded its admission rate
+    #[error("Peer {0} exceeded the transaction admission rate, try send it later")]
+    RateLimited(String),
+
+    /// The tx does not meet the local standardness policy
+    #[error("Non-standard transaction, {0}")]
+    NonStandard(String),
+
+    /// The tx spends a cell which pool txs use as cell dep
+    #[error("Cell dep consumed, {0}")]
+    DepConsumed(String),
+
+    /// The tx-pool is read-only for maintenance
+    #[error("The tx-pool is in maintenance mode, try send it later")]
+    Maintenance,
+
+    /// The tx-pool was cleared by a reorg detaching more blocks than `reorg_clear_depth`
+    #[error("The tx-pool was cleared by a reorg detaching {0} blocks")]
+    ReorgCleared(usize),
+
+    /// The outputs of the tx violate the output policy of the pool
+    #[error("Output policy violated, {0}")]
+    OutputPolicy(OutputPolicyViolation),
+}
+
+/// How the outputs of a tx violate the output policy of the pool
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum OutputPolicyViolation {
+    /// An output holds less capacity than the policy floor
+    #[error("output {index} capacity {capacity} is below the minimum {min_capacity}")]
+    LowCapacity {
+        /// Index of the output
+        index: usize,
+        /// Capacity of the output
+        capacity: Capacity,
+        /// The policy floor for this output
+        min_capacity: Capacity,
+    },
+    /// The tx creates more outputs than allowed
+    #[error("{count} outputs exceed the maximum {max}")]
+    TooManyOutputs {
+        /// Number of outputs of the tx
+        count: usize,
+        /// Max number of outputs allowed
+        max: usize,
+    },
+}
+
+/// What kind of resolve failure a `Reject::Resolve` is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolveClass {
+    /// An input is unknown, its parent tx may arrive soon
+    UnknownInput,
+    /// A cell dep or header dep is unknown, it may arrive soon
+    MissingDep,
+    /// An input or cell dep is already spent, permanently
+    Dead,
+    /// The tx references cells in a way that can never resolve
+    Invalid,
 }
 
 fn is_malformed_from_verification(error: &Error) -> bool {
@@ -79,6 +162,15 @@ fn is_malformed_from_verification(error: &Error) -> bool {
     }
 }
 
+// a cellbase output spent or depended on before it matures, it does once the chain grows
+fn is_immature_cellbase(error: &Error) -> bool {
+    error.kind() == ErrorKind::Transaction
+        && matches!(
+            error.downcast_ref::<TransactionError>(),
+            Some(TransactionError::CellbaseImmaturity { .. })
+        )
+}
+
 impl Reject {
     /// Returns true if the reject reason is malformed tx.
     pub fn is_malformed_tx(&self) -> bool {
@@ -101,6 +193,37 @@ impl Reject {
         matches!(self, Reject::DeclaredWrongCycles(..))
             || (!matches!(self, Reject::LowFeeRate(..)) && !self.is_malformed_tx())
     }
+
+    /// Classifies a resolve failure of `tx`, returns None for other rejects.
+    pub fn resolve_class(&self, tx: &TransactionView) -> Option<ResolveClass> {
+        let class = match self {
+            Reject::Resolve(OutPointError::Unknown(out_point)) => {
+                if tx.input_pts_iter().any(|input| &input == out_point) {
+                    ResolveClass::UnknownInput
+                } else {
+                    ResolveClass::MissingDep
+                }
+            }
+            Reject::Resolve(OutPointError::InvalidHeader(_)) => ResolveClass::MissingDep,
+            Reject::Resolve(OutPointError::Dead(_)) => ResolveClass::Dead,
+            Reject::Resolve(_) => ResolveClass::Invalid,
+            _ => return None,
+        };
+        Some(class)
+    }
+
+    /// Returns true if the tx failed to resolve only because an input or dep is not known yet,
+    /// spends a cellbase output not mature yet, or was throttled by the admission rate limit or
+    /// the maintenance mode, so the same tx may be accepted later
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Reject::Resolve(OutPointError::Unknown(_) | OutPointError::InvalidHeader(_))
+            | Reject::RateLimited(_)
+            | Reject::Maintenance => true,
+            Reject::Verification(err) => is_immature_cellbase(err),
+            _ => false,
+        }
+    }
 }
 
 impl_error_conversion_with_kind!(Reject, ErrorKind::SubmitTransaction, Error);
@@ -120,6 +243,55 @@ pub enum TxStatus {
     /// Status "rejected". The transaction has been recently removed from the pool.
     /// Due to storage limitations, the node can only hold the most recently removed transactions.
     Rejected(String),
+    /// Status "verifying". The transaction is accepted and its scripts are being verified in
+    /// chunks, it enters the pool once the verification completes.
+    Verifying,
+}
+
+/// Where a tx-pool entry was submitted from
+///
+/// Defaults to `Remote`, the privileges of local txs are only granted when set explicitly.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Origin {
+    /// Submitted through local RPC
+    Local,
+    /// Relayed by a remote peer
+    #[default]
+    Remote,
+    /// Re-added from a detached block during chain reorganization
+    Reorg,
+}
+
+/// Which tx-pool entries a page of ids is taken from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PoolTxFilter {
+    /// Every entry, pending or proposed
+    #[default]
+    All,
+    /// Entries not proposed yet, including those proposed in the gap
+    Pending,
+    /// Proposed entries
+    Proposed,
+}
+
+/// A replace-by-fee rule a tx has to pass to replace its conflicting entries, in the order they
+/// are checked
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RBFRule {
+    /// Rule #2, the tx spends no unconfirmed input the replaced txs did not, and depends on none of
+    /// their outputs
+    NoNewUnconfirmedInputs,
+    /// Rule #3, the tx pays at least the fees of the replaced txs
+    PaysReplacedFees,
+    /// Rule #4, the tx pays at least the fees of the replaced txs plus its size at `min_rbf_rate`
+    PaysMinRbfFee,
+    /// Rule #5, the tx replaces at most 100 txs, none of them among its ancestors or spent by it
+    LimitedReplacement,
+    /// Rule #6, every replaced tx is pending or gap
+    ReplacesPendingOnly,
+    /// Rule #7, once the replaced txs are removed, the tx has at most `max_ancestors_count`
+    /// in-pool ancestors, itself included, and their size stays within the package limit
+    WithinAncestorLimits,
 }
 
 /// Tx-pool entry info
@@ -131,6 +303,8 @@ pub struct TxEntryInfo {
     pub size: u64,
     /// The transaction fee.
     pub fee: Capacity,
+    /// The weight the fee rate is computed with, see `get_transaction_weight`.
+    pub weight: u64,
     /// Size of in-tx-pool ancestor transactions
     pub ancestors_size: u64,
     /// Cycles of in-tx-pool ancestor transactions
@@ -143,15 +317,47 @@ pub struct TxEntryInfo {
     pub ancestors_count: u64,
     /// The unix timestamp when entering the Txpool, unit: Millisecond
     pub timestamp: u64,
+    /// Where the transaction was submitted from
+    pub origin: Origin,
+    /// Number of rejected transactions which tried to double spend this one
+    pub double_spend_attempts: u64,
+    /// Hashes of the most recent transactions which tried to double spend this one
+    pub double_spend_tx_hashes: Vec<Byte32>,
 }
 
 /// Array of transaction ids
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TxPoolIds {
     /// Pending transaction ids
     pub pending: Vec<Byte32>,
     /// Proposed transaction ids
     pub proposed: Vec<Byte32>,
+    /// Sequence number of the last pool membership change the ids were read at
+    pub seq: u64,
+}
+
+/// Transaction id annotated with the fee info of the transaction
+#[derive(Debug, PartialEq, Eq)]
+pub struct AnnotatedTxId {
+    /// Transaction hash
+    pub hash: Byte32,
+    /// The transaction fee
+    pub fee: Capacity,
+    /// The transaction serialized size in block
+    pub size: u64,
+    /// The transaction fee rate
+    pub fee_rate: FeeRate,
+}
+
+/// Array of transaction ids annotated with their fee info, in the same order as `TxPoolIds`
+#[derive(Debug, PartialEq, Eq)]
+pub struct TxPoolAnnotatedIds {
+    /// Pending transactions
+    pub pending: Vec<AnnotatedTxId>,
+    /// Proposed transactions
+    pub proposed: Vec<AnnotatedTxId>,
+    /// Sequence number of the last pool membership change the ids were read at
+    pub seq: u64,
 }
 
 /// All in-pool transaction entry info
@@ -161,6 +367,10 @@ pub struct TxPoolEntryInfo {
     pub pending: HashMap<Byte32, TxEntryInfo>,
     /// Proposed transaction entry info
     pub proposed: HashMap<Byte32, TxEntryInfo>,
+    /// Hash of the snapshot tip the info was taken against
+    pub tip_hash: Byte32,
+    /// Sequence number of the last pool membership change the info was read at
+    pub seq: u64,
 }
 
 /// The JSON view of a transaction as well as its status.
@@ -229,6 +439,18 @@ impl TransactionWithStatus {
         }
     }
 
+    /// Build with verifying status
+    pub fn with_verifying(tx: Option<core::TransactionView>) -> Self {
+        Self {
+            tx_status: TxStatus::Verifying,
+            transaction: tx,
+            cycles: None,
+            fee: None,
+            min_replace_fee: None,
+            time_added_to_pool: None,
+        }
+    }
+
     /// Build with rejected status
     pub fn with_unknown() -> Self {
         Self {
@@ -282,10 +504,17 @@ pub fn get_transaction_virtual_bytes(tx_size: usize, cycles: u64) -> u64 {
 /// Introducing the transaction weight converts the multi-dimensional knapsack to a typical knapsack problem,
 /// which has a simple greedy algorithm.
 pub fn get_transaction_weight(tx_size: usize, cycles: u64) -> u64 {
-    std::cmp::max(
-        tx_size as u64,
-        (cycles as f64 * DEFAULT_BYTES_PER_CYCLES) as u64,
-    )
+    get_transaction_weight_with_ratio(tx_size, cycles, DEFAULT_BYTES_PER_CYCLES)
+}
+
+/// Same as `get_transaction_weight` with a custom number of bytes each cycle weighs, the weight
+/// is the size alone if `bytes_per_cycles` is 0.
+pub fn get_transaction_weight_with_ratio(
+    tx_size: usize,
+    cycles: u64,
+    bytes_per_cycles: f64,
+) -> u64 {
+    std::cmp::max(tx_size as u64, (cycles as f64 * bytes_per_cycles) as u64)
 }
 
 /// The maximum size of the tx-pool to accept transactions
@@ -343,4 +572,30 @@ pub struct TxPoolInfo {
     pub tx_size_limit: u64,
     /// Total limit on the size of transactions in the tx-pool
     pub max_tx_pool_size: u64,
+    /// Limit on the number of transactions in the tx-pool, compared with `pending_size` plus
+    /// `proposed_size`
+    pub max_tx_pool_entries: u64,
+    /// Whether the tx-pool is read-only for maintenance, rejecting new transactions
+    pub read_only: bool,
+    /// Usage and quota of the pending transactions
+    pub pending_quota: StatusQuotaUsage,
+    /// Usage and quota of the transactions in the gap state
+    pub gap_quota: StatusQuotaUsage,
+    /// Usage and quota of the proposed transactions
+    pub proposed_quota: StatusQuotaUsage,
+    /// Sequence number of the last pool membership change the info was read at
+    pub seq: u64,
+}
+
+/// Total size and count of the transactions in one state of the tx-pool, and the quota on them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StatusQuotaUsage {
+    /// Total serialized size of the transactions
+    pub size: usize,
+    /// Count of the transactions
+    pub count: usize,
+    /// Limit on `size`, `None` for unlimited
+    pub max_size: Option<usize>,
+    /// Limit on `count`, `None` for unlimited
+    pub max_count: Option<usize>,
 }
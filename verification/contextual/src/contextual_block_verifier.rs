@@ -23,7 +23,7 @@ use ckb_types::{
     utilities::merkle_mountain_range::ChainRootMMR,
 };
 use ckb_verification::cache::{
-    TxVerificationCache, {CacheEntry, Completed},
+    TxVerificationCache, {CacheEntry, Completed, ForkContext},
 };
 use ckb_verification::{
     BlockErrorKind, CellbaseError, CommitError, ContextualTransactionVerifier,
@@ -327,7 +327,7 @@ impl<'a, 'b, 'c, CS: ChainStore + VersionbitsIndexer> DaoHeaderVerifier<'a, 'b,
     }
 }
 
-struct BlockTxsVerifier<'a, 'b, CS> {
+pub(crate) struct BlockTxsVerifier<'a, 'b, CS> {
     context: VerifyContext<CS>,
     header: HeaderView,
     handle: &'a Handle,
@@ -404,6 +404,7 @@ impl<'a, 'b, CS: ChainStore + VersionbitsIndexer + 'static> BlockTxsVerifier<'a,
         };
 
         let tx_env = Arc::new(TxVerifyEnv::new_commit(&self.header));
+        let fork_context = ForkContext::new(&self.context.consensus, &tx_env);
 
         // make verifiers orthogonal
         let ret = resolved
@@ -412,23 +413,37 @@ impl<'a, 'b, CS: ChainStore + VersionbitsIndexer + 'static> BlockTxsVerifier<'a,
             .map(|(index, tx)| {
                 let tx_hash = tx.transaction.hash();
 
-                if let Some(cache_entry) = fetched_cache.get(&tx_hash) {
+                // verified by the tx-pool under other rules than the block's, the scripts run again
+                let cache_entry = fetched_cache.get(&tx_hash).filter(|entry| match entry {
+                    CacheEntry::Prewarmed(_, context) => *context == fork_context,
+                    _ => true,
+                });
+                if let Some(cache_entry) = cache_entry {
                     match cache_entry {
-                        CacheEntry::Completed(completed) => TimeRelativeTransactionVerifier::new(
-                            Arc::clone(tx),
-                            Arc::clone(&self.context.consensus),
-                            self.context.store.as_data_loader(),
-                            Arc::clone(&tx_env),
-                        )
-                        .verify()
-                        .map_err(|error| {
-                            BlockTransactionsError {
-                                index: index as u32,
-                                error,
-                            }
-                            .into()
-                        })
-                        .map(|_| (tx_hash, *completed)),
+                        CacheEntry::Completed(completed) | CacheEntry::Prewarmed(completed, _) => {
+                            TimeRelativeTransactionVerifier::new(
+                                Arc::clone(tx),
+                                Arc::clone(&self.context.consensus),
+                                self.context.store.as_data_loader(),
+                                Arc::clone(&tx_env),
+                            )
+                            .verify()
+                            .map_err(|error| {
+                                BlockTransactionsError {
+                                    index: index as u32,
+                                    error,
+                                }
+                                .into()
+                            })
+                            .map(|_| {
+                                if let CacheEntry::Prewarmed(..) = cache_entry {
+                                    if let Some(metrics) = ckb_metrics::handle() {
+                                        metrics.ckb_verify_cache_prewarm.hit.inc();
+                                    }
+                                }
+                                (tx_hash, *completed)
+                            })
+                        }
                         CacheEntry::Suspended(suspended) => ContextualTransactionVerifier::new(
                             Arc::clone(tx),
                             Arc::clone(&self.context.consensus),
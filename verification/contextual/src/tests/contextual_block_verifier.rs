@@ -1,4 +1,6 @@
-use super::super::contextual_block_verifier::{EpochVerifier, TwoPhaseCommitVerifier};
+use super::super::contextual_block_verifier::{
+    BlockTxsVerifier, EpochVerifier, TwoPhaseCommitVerifier,
+};
 use crate::contextual_block_verifier::{RewardVerifier, VerifyContext};
 use ckb_chain::chain::{ChainController, ChainService};
 use ckb_chain_spec::consensus::{Consensus, ConsensusBuilder};
@@ -10,17 +12,24 @@ use ckb_test_chain_utils::always_success_cell;
 use ckb_types::{
     bytes::Bytes,
     core::{
-        capacity_bytes, cell::ResolvedTransaction, BlockBuilder, BlockNumber, BlockView, Capacity,
-        EpochExt, EpochNumberWithFraction, HeaderBuilder, HeaderView, TransactionBuilder,
-        TransactionView, UncleBlockView,
+        capacity_bytes,
+        cell::{CellMetaBuilder, ResolvedTransaction},
+        BlockBuilder, BlockNumber, BlockView, Capacity, EpochExt, EpochNumberWithFraction,
+        HeaderBuilder, HeaderView, TransactionBuilder, TransactionView, UncleBlockView,
     },
+    h256,
     packed::{Byte32, CellDep, CellInput, CellOutputBuilder, OutPoint, ProposalShortId, Script},
     prelude::*,
     utilities::DIFF_TWO,
 };
-use ckb_verification::{CellbaseError, CommitError, EpochError};
+use ckb_verification::cache::{
+    init_cache, prewarm, CacheEntry, Completed, ForkContext, PrewarmEntry, PrewarmStats,
+    TxVerificationCache,
+};
+use ckb_verification::{CellbaseError, CommitError, EpochError, TxVerifyEnv};
 use ckb_verification_traits::Switch;
 use std::sync::Arc;
+use tokio::sync::RwLock;
 
 fn gen_block(
     parent_header: &HeaderView,
@@ -351,3 +360,121 @@ fn test_uncle_proposal() {
     let verifier = TwoPhaseCommitVerifier::new(&context, &block);
     assert!(verifier.verify().is_ok());
 }
+
+#[test]
+fn test_prewarmed_cache_skips_script_verification() {
+    let (_chain, shared) = start_chain(None);
+    let context = dummy_context(&shared);
+    let parent = shared.snapshot().tip_header().clone();
+    let header = HeaderBuilder::default()
+        .parent_hash(parent.hash())
+        .number(1u64.pack())
+        .epoch(EpochNumberWithFraction::new(0, 1, 1000).pack())
+        .build();
+
+    let cellbase = TransactionBuilder::default()
+        .input(CellInput::new_cellbase_input(1))
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(100).pack())
+                .build(),
+        )
+        .output_data(Bytes::new().pack())
+        .build();
+    // the input is locked by a script whose code is nowhere, running it fails
+    let input = CellMetaBuilder::from_cell_output(
+        CellOutputBuilder::default()
+            .capacity(capacity_bytes!(100).pack())
+            .lock(Script::new_builder().code_hash(h256!("0x1").pack()).build())
+            .build(),
+        Bytes::new(),
+    )
+    .out_point(OutPoint::new(h256!("0x2").pack(), 0))
+    .build();
+    let tx = TransactionBuilder::default()
+        .input(CellInput::new(input.out_point.clone(), 0))
+        .output(
+            CellOutputBuilder::default()
+                .capacity(capacity_bytes!(100).pack())
+                .build(),
+        )
+        .output_data(Bytes::new().pack())
+        .build();
+    let resolved = vec![
+        Arc::new(ResolvedTransaction {
+            transaction: cellbase,
+            resolved_cell_deps: vec![],
+            resolved_inputs: vec![],
+            resolved_dep_groups: vec![],
+        }),
+        Arc::new(ResolvedTransaction {
+            transaction: tx.clone(),
+            resolved_cell_deps: vec![],
+            resolved_inputs: vec![input],
+            resolved_dep_groups: vec![],
+        }),
+    ];
+
+    let fork_context = ForkContext::new(&shared.consensus(), &TxVerifyEnv::new_commit(&header));
+    let prewarmed = PrewarmEntry {
+        tx_hash: tx.hash(),
+        completed: Completed {
+            cycles: 1000,
+            fee: Capacity::zero(),
+        },
+        fork_context,
+    };
+    let verify_with = |cache: TxVerificationCache| {
+        BlockTxsVerifier::new(
+            context.clone(),
+            header.clone(),
+            shared.async_handle(),
+            &Arc::new(RwLock::new(cache)),
+            &parent,
+        )
+        .verify(&resolved, false)
+    };
+    let verify = |context_of_entry: ForkContext| {
+        let mut cache = init_cache();
+        let entry = PrewarmEntry {
+            fork_context: context_of_entry,
+            ..prewarmed.clone()
+        };
+        let stats = prewarm(&mut cache, vec![entry], fork_context);
+        (stats, verify_with(cache))
+    };
+
+    let (stats, ret) = verify(fork_context);
+    assert_eq!(
+        stats,
+        PrewarmStats {
+            inserted: 1,
+            mismatched: 0
+        }
+    );
+    let (cycles, completed) = ret.expect("the prewarmed result is trusted");
+    assert_eq!(cycles, 1000);
+    assert_eq!(completed, vec![prewarmed.completed]);
+
+    // verified under other rules, the scripts run and fail
+    let other_context = ForkContext {
+        vm_version_2_and_syscalls_3: !fork_context.vm_version_2_and_syscalls_3,
+    };
+    let (stats, ret) = verify(other_context);
+    assert_eq!(
+        stats,
+        PrewarmStats {
+            inserted: 0,
+            mismatched: 1
+        }
+    );
+    assert!(ret.is_err());
+
+    // prewarmed under other rules than the block's, the entry is not trusted
+    let mut cache = init_cache();
+    cache.put(
+        tx.hash(),
+        CacheEntry::Prewarmed(prewarmed.completed, other_context),
+    );
+    assert!(verify_with(cache).is_err());
+}
@@ -1,6 +1,7 @@
 //! TX verification cache
 
-use ckb_script::TransactionSnapshot;
+use ckb_chain_spec::consensus::Consensus;
+use ckb_script::{TransactionSnapshot, TxVerifyEnv};
 use ckb_types::{
     core::{Capacity, Cycle},
     packed::Byte32,
@@ -24,6 +25,9 @@ pub enum CacheEntry {
     Completed(Completed),
     /// Suspended
     Suspended(Suspended),
+    /// Completed by the tx-pool under the fork context and fed to the cache by `prewarm`, not used
+    /// by a block yet
+    Prewarmed(Completed, ForkContext),
 }
 
 /// Suspended state
@@ -55,3 +59,68 @@ impl CacheEntry {
         CacheEntry::Suspended(Suspended { snap, fee })
     }
 }
+
+/// The script execution rules a verification result depends on, a cached result can only be
+/// trusted under the same rules
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ForkContext {
+    /// Whether the CKB2023 VM version 2 and syscalls 3 are enabled
+    pub vm_version_2_and_syscalls_3: bool,
+}
+
+impl ForkContext {
+    /// The rules the scripts of a tx run under in the environment `tx_env`
+    pub fn new(consensus: &Consensus, tx_env: &TxVerifyEnv) -> Self {
+        let epoch = tx_env.epoch_number(consensus.tx_proposal_window());
+        ForkContext {
+            vm_version_2_and_syscalls_3: consensus
+                .hardfork_switch()
+                .ckb2023
+                .is_vm_version_2_and_syscalls_3_enabled(epoch),
+        }
+    }
+}
+
+/// A verification result exported by the tx-pool, see `prewarm`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrewarmEntry {
+    /// Transaction hash
+    pub tx_hash: Byte32,
+    /// Cycles and fee of the verified tx
+    pub completed: Completed,
+    /// The rules the tx was verified under
+    pub fork_context: ForkContext,
+}
+
+/// What a `prewarm` call did with the entries it was fed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PrewarmStats {
+    /// Entries inserted into the cache
+    pub inserted: usize,
+    /// Entries ignored since they were verified under other rules
+    pub mismatched: usize,
+}
+
+/// Feeds verification results exported by the tx-pool into `cache`, the ones verified under
+/// another fork context than `context` are ignored, and so are the txs already cached.
+pub fn prewarm<I>(cache: &mut TxVerificationCache, entries: I, context: ForkContext) -> PrewarmStats
+where
+    I: IntoIterator<Item = PrewarmEntry>,
+{
+    let mut stats = PrewarmStats::default();
+    for entry in entries {
+        if entry.fork_context != context {
+            stats.mismatched += 1;
+            continue;
+        }
+        if cache.contains(&entry.tx_hash) {
+            continue;
+        }
+        cache.put(
+            entry.tx_hash,
+            CacheEntry::Prewarmed(entry.completed, entry.fork_context),
+        );
+        stats.inserted += 1;
+    }
+    stats
+}
@@ -22,8 +22,8 @@ pub use crate::genesis_verifier::GenesisVerifier;
 pub use crate::header_verifier::HeaderVerifier;
 pub use crate::transaction_verifier::{
     CapacityVerifier, ContextualTransactionVerifier, ContextualWithoutScriptTransactionVerifier,
-    DaoScriptSizeVerifier, NonContextualTransactionVerifier, ScriptVerifier, Since, SinceMetric,
-    TimeRelativeTransactionVerifier,
+    DaoScriptSizeVerifier, MaturityVerifier, NonContextualTransactionVerifier, ScriptVerifier,
+    Since, SinceMetric, TimeRelativeTransactionVerifier,
 };
 pub use ckb_script::{
     ScriptError, ScriptGroupType, TransactionSnapshot, TransactionState as ScriptVerifyState,
@@ -430,6 +430,7 @@ pub struct MaturityVerifier {
 }
 
 impl MaturityVerifier {
+    /// Create a new `MaturityVerifier`
     pub fn new(
         transaction: Arc<ResolvedTransaction>,
         epoch: EpochNumberWithFraction,
@@ -442,6 +443,7 @@ impl MaturityVerifier {
         }
     }
 
+    /// Verify the cellbase outputs spent or depended on are mature at `epoch`
     pub fn verify(&self) -> Result<(), Error> {
         let cellbase_immature = |meta: &CellMeta| -> bool {
             meta.transaction_info